@@ -0,0 +1,357 @@
+use std::fmt;
+use std::ops::{Add, AddAssign};
+
+use cranelift_entity::SecondaryMap;
+use miden_hir::FunctionIdent;
+use rustc_hash::FxHashMap;
+
+use crate::masm::{BlockId, Function, Op, Program};
+
+/// An estimate of the number of VM cycles needed to execute some code
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Cycles {
+    /// The number of cycles needed if the cheapest path through the code is taken
+    pub best: u64,
+    /// The number of cycles needed if the most expensive path through the code is taken,
+    /// or `None` if there is no known bound, i.e. the code contains a `while.true` loop,
+    /// or calls a function whose cost is unknown
+    pub worst: Option<u64>,
+}
+impl Default for Cycles {
+    fn default() -> Self {
+        Self::fixed(0)
+    }
+}
+impl Cycles {
+    /// Code which always takes `n` cycles
+    pub const fn fixed(n: u64) -> Self {
+        Self {
+            best: n,
+            worst: Some(n),
+        }
+    }
+
+    /// Code whose cost is not known, e.g. a call to a function defined outside the program
+    pub const fn unknown() -> Self {
+        Self {
+            best: 0,
+            worst: None,
+        }
+    }
+
+    /// Returns true if this cost has an upper bound
+    pub fn is_bounded(&self) -> bool {
+        self.worst.is_some()
+    }
+
+    /// The cost of executing either this code, or `other`, e.g. the branches of an `if.true`
+    pub fn either(self, other: Self) -> Self {
+        Self {
+            best: self.best.min(other.best),
+            worst: self.worst.zip(other.worst).map(|(a, b)| a.max(b)),
+        }
+    }
+
+    /// The cost of executing this code `n` times in a row
+    pub fn repeat(self, n: u64) -> Self {
+        Self {
+            best: self.best * n,
+            worst: self.worst.map(|worst| worst * n),
+        }
+    }
+}
+impl Add for Cycles {
+    type Output = Cycles;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self {
+            best: self.best + rhs.best,
+            worst: self.worst.zip(rhs.worst).map(|(a, b)| a + b),
+        }
+    }
+}
+impl AddAssign for Cycles {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+impl fmt::Display for Cycles {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.worst {
+            Some(worst) => write!(f, "best {}, worst {}", self.best, worst),
+            None => write!(f, "best {}, worst unbounded", self.best),
+        }
+    }
+}
+
+/// This structure holds the estimated cycle cost of a [Function], as computed by [op_cycles]
+/// for each of its instructions, plus the cost of each function it calls.
+///
+/// The overhead of the basic blocks the assembler groups instructions into depends on how it
+/// packs them, and is not included.
+pub struct FunctionCost {
+    /// The name of the function
+    pub name: FunctionIdent,
+    /// The cost of executing the body of the function, including the functions it calls
+    pub total: Cycles,
+    /// The cost of executing each block of the function once, including any blocks nested in it
+    pub blocks: SecondaryMap<BlockId, Cycles>,
+}
+impl FunctionCost {
+    /// Compute the cost of `function`, where `callees` holds the cost of the functions it calls.
+    ///
+    /// The cost of a call to a function which is not in `callees` is unknown, which makes the
+    /// worst case of any code containing that call unbounded.
+    pub fn compute(function: &Function, callees: &FxHashMap<FunctionIdent, Cycles>) -> Self {
+        let mut blocks = SecondaryMap::new();
+        let total = block_cycles(function, function.body, callees, &mut blocks);
+        Self {
+            name: function.name,
+            total,
+            blocks,
+        }
+    }
+
+    /// Compute the cost of every function in `program`, in the order they appear in it.
+    ///
+    /// Callees are visited before their callers, so that the cost of each call includes the
+    /// cost of the function it calls. Calls to functions outside of `program`, e.g. the
+    /// standard library or kernel procedures, have an unknown cost.
+    pub fn compute_program(program: &Program) -> Vec<Self> {
+        let functions = program
+            .modules
+            .iter()
+            .flat_map(|module| module.functions.iter())
+            .map(|function| (function.name, function))
+            .collect::<FxHashMap<_, _>>();
+
+        let mut costs = FxHashMap::<FunctionIdent, FunctionCost>::default();
+        let mut totals = FxHashMap::<FunctionIdent, Cycles>::default();
+        for module in program.modules.iter() {
+            for function in module.functions.iter() {
+                compute_postorder(function, &functions, &mut costs, &mut totals);
+            }
+        }
+
+        program
+            .modules
+            .iter()
+            .flat_map(|module| module.functions.iter())
+            .map(|function| costs.remove(&function.name).unwrap())
+            .collect()
+    }
+}
+
+/// Compute the cost of `function` after that of every function it calls.
+///
+/// Miden Assembly does not permit recursion, but should a call cycle be present, the cost of
+/// the call which closes it is unknown.
+fn compute_postorder(
+    function: &Function,
+    functions: &FxHashMap<FunctionIdent, &Function>,
+    costs: &mut FxHashMap<FunctionIdent, FunctionCost>,
+    totals: &mut FxHashMap<FunctionIdent, Cycles>,
+) {
+    if costs.contains_key(&function.name) {
+        return;
+    }
+    // Mark this function as in progress, so that a call cycle terminates
+    costs.insert(
+        function.name,
+        FunctionCost {
+            name: function.name,
+            total: Cycles::unknown(),
+            blocks: SecondaryMap::new(),
+        },
+    );
+    for block in function.blocks.values() {
+        for op in block.ops.iter() {
+            if let Op::Exec(callee) = op {
+                if let Some(callee) = functions.get(callee) {
+                    compute_postorder(callee, functions, costs, totals);
+                }
+            }
+        }
+    }
+    let cost = FunctionCost::compute(function, totals);
+    totals.insert(function.name, cost.total);
+    costs.insert(function.name, cost);
+}
+impl fmt::Display for FunctionCost {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "cycles for {}: {}", &self.name, &self.total)?;
+        for (block, cycles) in self.blocks.iter() {
+            writeln!(f, "  {block}: {cycles}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Compute the cost of executing `block` once, recording the cost of it,
+/// and of every block nested within it, in `blocks`
+fn block_cycles(
+    function: &Function,
+    block: BlockId,
+    callees: &FxHashMap<FunctionIdent, Cycles>,
+    blocks: &mut SecondaryMap<BlockId, Cycles>,
+) -> Cycles {
+    let mut cycles = Cycles::default();
+    for op in function.block(block).ops.iter() {
+        cycles += Cycles::fixed(op_cycles(op));
+        match op {
+            Op::If(then_blk, else_blk) => {
+                let then_cycles = block_cycles(function, *then_blk, callees, blocks);
+                let else_cycles = block_cycles(function, *else_blk, callees, blocks);
+                cycles += then_cycles.either(else_cycles);
+            }
+            Op::While(body) => {
+                // The body may not execute at all, or may execute any number of times
+                block_cycles(function, *body, callees, blocks);
+                cycles += Cycles::unknown();
+            }
+            Op::Repeat(n, body) => {
+                cycles += block_cycles(function, *body, callees, blocks).repeat(*n as u64);
+            }
+            Op::Exec(callee) | Op::Syscall(callee) => {
+                cycles += callees.get(callee).copied().unwrap_or_else(Cycles::unknown);
+            }
+            _ => (),
+        }
+    }
+    blocks[block] = cycles;
+    cycles
+}
+
+/// The cost of pushing a constant, which takes a second cycle for some values
+const PUSH: u64 = 2;
+
+/// Get the number of cycles the Miden VM needs to execute `op`, as documented in the
+/// Miden Assembly instruction reference.
+///
+/// Where the documented cost is a range, because it depends on the value of an immediate or
+/// of the operands, the upper bound is used. Instructions with an immediate which are emitted
+/// as a push of the immediate followed by the instruction itself, e.g. `gt.b`, cost as much
+/// as that pair of instructions.
+///
+/// For control flow instructions, only the cost of the instruction itself is returned, not that
+/// of the blocks or functions it executes: `if.true` and `while.true` cost 2 cycles for entering and exiting
+/// their blocks, and `while.true` costs one further cycle for each iteration after the first,
+/// which this function does not account for. `repeat.n` is unrolled by the assembler, so has no
+/// cost of its own.
+pub fn op_cycles(op: &Op) -> u64 {
+    match op {
+        Op::Padw => 4,
+        Op::Push(_) | Op::PushU8(_) | Op::PushU16(_) | Op::PushU32(_) => PUSH,
+        Op::Push2(_) => 2 * PUSH,
+        Op::Pushw(_) => 4 * PUSH,
+        Op::Drop => 1,
+        Op::Dropw => 4,
+        // These are emitted as a pair of instructions by the assembler
+        Op::Dup(8 | 10 | 12 | 14) => 3,
+        Op::Dup(_) => 1,
+        Op::Dupw(_) => 4,
+        Op::Swap(1) => 1,
+        Op::Swap(_) => 6,
+        Op::Swapw(_) => 1,
+        Op::Movup(2..=8) | Op::Movdn(2..=8) => 1,
+        Op::Movup(_) | Op::Movdn(_) => 4,
+        Op::Movupw(2) | Op::Movdnw(2) => 2,
+        Op::Movupw(_) | Op::Movdnw(_) => 3,
+        Op::Cswap | Op::Cswapw => 1,
+        Op::Cdrop => 2,
+        Op::Cdropw => 5,
+        Op::Assert => 1,
+        Op::Assertz | Op::AssertEq => 2,
+        Op::AssertEqw => 11,
+        Op::LocAddr(_) => 2,
+        // The experimental offset forms are assumed to cost as much as the regular forms
+        Op::MemLoad | Op::MemLoadOffset | Op::MemLoadw => 1,
+        Op::MemLoadImm(_) | Op::MemLoadOffsetImm(_, _) | Op::MemLoadwImm(_) => 2,
+        Op::MemStore | Op::MemStoreOffset => 2,
+        Op::MemStoreImm(_) | Op::MemStoreOffsetImm(_, _) => 4,
+        Op::MemStorew => 1,
+        Op::MemStorewImm(_) => 3,
+        Op::If(_, _) | Op::While(_) => 2,
+        Op::Repeat(_, _) => 0,
+        Op::Exec(_) => 0,
+        Op::Syscall(_) => 2,
+        Op::Add | Op::Mul | Op::Neg | Op::Inv | Op::Incr => 1,
+        Op::AddImm(_) | Op::MulImm(_) => 2,
+        Op::Sub | Op::SubImm(_) | Op::Div | Op::DivImm(_) => 2,
+        Op::Pow2 => 16,
+        // `exp` is `exp.u64`, i.e. for an exponent of up to 64 bits
+        Op::Exp => 9 + 64,
+        Op::ExpImm(bits) => 9 + *bits as u64,
+        Op::Not | Op::And | Op::Or => 1,
+        Op::AndImm(_) | Op::OrImm(_) => PUSH + 1,
+        Op::Xor => 7,
+        Op::XorImm(_) => PUSH + 7,
+        Op::Eq => 1,
+        Op::EqImm(_) | Op::Neq => 2,
+        Op::NeqImm(_) => 3,
+        Op::Gt | Op::Lt => 17,
+        Op::Gte | Op::Lte => 18,
+        Op::GtImm(_) | Op::LtImm(_) => PUSH + 17,
+        Op::GteImm(_) | Op::LteImm(_) => PUSH + 18,
+        Op::IsOdd => 5,
+        Op::Eqw => 15,
+        Op::Clk => 1,
+        Op::U32Test => 5,
+        Op::U32Testw => 23,
+        Op::U32Assert => 3,
+        Op::U32Assert2 => 1,
+        Op::U32Assertw => 6,
+        Op::U32Cast => 2,
+        Op::U32Split => 1,
+        Op::U32CheckedAdd | Op::U32CheckedSub | Op::U32CheckedMul => 4,
+        Op::U32CheckedAddImm(_) | Op::U32CheckedSubImm(_) | Op::U32CheckedMulImm(_) => 6,
+        Op::U32OverflowingAdd | Op::U32OverflowingSub | Op::U32OverflowingMul => 1,
+        Op::U32OverflowingAddImm(_) | Op::U32OverflowingSubImm(_) | Op::U32OverflowingMulImm(_) => {
+            3
+        }
+        Op::U32WrappingAdd | Op::U32WrappingSub | Op::U32WrappingMul => 2,
+        Op::U32WrappingAddImm(_) => 3,
+        Op::U32WrappingSubImm(_) | Op::U32WrappingMulImm(_) => 4,
+        Op::U32OverflowingAdd3 | Op::U32OverflowingMadd => 1,
+        Op::U32WrappingAdd3 | Op::U32WrappingMadd => 2,
+        Op::U32CheckedDiv => 3,
+        Op::U32CheckedDivImm(_) => 5,
+        Op::U32UncheckedDiv => 2,
+        Op::U32UncheckedDivImm(_) => 3,
+        Op::U32CheckedMod => 4,
+        Op::U32CheckedModImm(_) => 6,
+        Op::U32UncheckedMod => 3,
+        Op::U32UncheckedModImm(_) => 4,
+        Op::U32CheckedDivMod => 2,
+        Op::U32CheckedDivModImm(_) => 4,
+        Op::U32UncheckedDivMod => 1,
+        Op::U32UncheckedDivModImm(_) => 3,
+        Op::U32And | Op::U32Xor => 1,
+        Op::U32Or => 6,
+        Op::U32Not => 5,
+        Op::U32CheckedShl | Op::U32CheckedShr | Op::U32CheckedRotl => 47,
+        Op::U32CheckedShlImm(_) | Op::U32CheckedShrImm(_) | Op::U32CheckedRotlImm(_) => 4,
+        Op::U32UncheckedShl | Op::U32UncheckedShr | Op::U32UncheckedRotl => 40,
+        Op::U32UncheckedShlImm(_) | Op::U32UncheckedShrImm(_) | Op::U32UncheckedRotlImm(_) => 3,
+        Op::U32CheckedRotr => 59,
+        Op::U32CheckedRotrImm(_) => 6,
+        Op::U32UncheckedRotr => 44,
+        Op::U32UncheckedRotrImm(_) => 3,
+        Op::U32CheckedPopcnt => 36,
+        Op::U32UncheckedPopcnt => 33,
+        Op::U32Eq => 2,
+        Op::U32EqImm(_) => 4,
+        Op::U32Neq => 3,
+        Op::U32NeqImm(_) => 5,
+        Op::U32CheckedLt => 6,
+        Op::U32UncheckedLt => 5,
+        Op::U32CheckedLte => 8,
+        Op::U32UncheckedLte => 7,
+        Op::U32CheckedGt | Op::U32CheckedGte => 7,
+        Op::U32UncheckedGt | Op::U32UncheckedGte => 6,
+        Op::U32CheckedMin => 9,
+        Op::U32UncheckedMin => 8,
+        Op::U32CheckedMax => 10,
+        Op::U32UncheckedMax => 9,
+    }
+}
@@ -337,6 +337,11 @@ impl Emulator {
         self.clk_limit = max;
     }
 
+    /// Returns the number of cycles executed so far
+    pub fn cycles(&self) -> usize {
+        self.clk
+    }
+
     /// Sets the next breakpoint for the emulator
     pub fn set_breakpoint(&mut self, bp: Breakpoint) {
         self.bp = Some(bp);
@@ -508,6 +513,18 @@ impl Emulator {
         self.memory[addr][ptr.index as usize] = value;
     }
 
+    /// Read the element at the word at `addr`, and element `index`
+    pub fn load(&self, addr: usize) -> Felt {
+        use crate::NativePtr;
+
+        let ptr = NativePtr::from_ptr(addr.try_into().expect("invalid address"));
+        let addr = ptr.waddr as usize;
+        assert_eq!(ptr.offset, 0, "invalid load: unaligned address {addr:#?}");
+        assert!(addr < self.memory.len(), "invalid address");
+
+        self.memory[addr][ptr.index as usize]
+    }
+
     /// Run the emulator by invoking `callee` with `args` placed on the
     /// operand stack in FIFO order.
     ///
@@ -546,7 +563,22 @@ impl Emulator {
         args: &[Felt],
     ) -> Result<OperandStack<Felt>, EmulationError> {
         // Place the arguments on the operand stack
-        assert_eq!(args.len(), function.arity());
+        //
+        // Arguments larger than 32 bits are passed as multiple field elements
+        let expected_args = function
+            .signature
+            .params()
+            .iter()
+            .map(|param| {
+                param
+                    .ty
+                    .clone()
+                    .to_raw_parts()
+                    .map(|parts| parts.len())
+                    .unwrap_or(0)
+            })
+            .sum::<usize>();
+        assert_eq!(args.len(), expected_args);
         for arg in args.iter().copied().rev() {
             self.stack.push(arg);
         }
@@ -1440,6 +1472,8 @@ impl Emulator {
                 Op::Eqw => {
                     let b = popw!(self);
                     let a = popw!(self);
+                    self.stack.pushw(a);
+                    self.stack.pushw(b);
                     self.stack.push_u8((a == b) as u8);
                 }
                 Op::Clk => {
@@ -1490,8 +1524,23 @@ impl Emulator {
                 Op::U32OverflowingAddImm(imm) => binop_overflowing_u32!(self, add, imm),
                 Op::U32WrappingAdd => binop_wrapping_u32!(self, add),
                 Op::U32WrappingAddImm(imm) => binop_wrapping_u32!(self, add, imm),
-                Op::U32OverflowingAdd3 => todo!(),
-                Op::U32WrappingAdd3 => todo!(),
+                Op::U32OverflowingAdd3 => {
+                    let c = pop_u32!(self) as u64;
+                    let b = pop_u32!(self) as u64;
+                    let a = pop_u32!(self) as u64;
+                    let result = a + b + c;
+                    let d = result % 2u64.pow(32);
+                    let e = result / 2u64.pow(32);
+                    self.stack.push(Felt::new(d));
+                    self.stack.push(Felt::new(e));
+                }
+                Op::U32WrappingAdd3 => {
+                    let c = pop_u32!(self) as u64;
+                    let b = pop_u32!(self) as u64;
+                    let a = pop_u32!(self) as u64;
+                    let d = (a + b + c) % 2u64.pow(32);
+                    self.stack.push(Felt::new(d));
+                }
                 Op::U32CheckedSub => binop_checked_u32!(self, sub),
                 Op::U32CheckedSubImm(imm) => binop_checked_u32!(self, sub, imm),
                 Op::U32OverflowingSub => binop_overflowing_u32!(self, sub),
@@ -0,0 +1,60 @@
+use miden_diagnostics::{DiagnosticsHandler, Severity, SourceSpan};
+use miden_hir::{diagnostic, Opcode, Type};
+
+/// This type represents the errors which can occur when lowering Miden IR to MASM IR.
+///
+/// Invalid IR is a bug in whatever produced it, and is caught by validation before code
+/// generation, so it is not represented here. These errors are raised for valid IR which
+/// the code generator does not (yet) know how to lower.
+#[derive(Debug, thiserror::Error)]
+pub enum CodegenError {
+    /// The instruction at `span` applies `op` to a value of type `ty`, which is not supported
+    #[error("unsupported operation: '{op}' is not supported for values of type '{ty}'")]
+    Unsupported {
+        op: Opcode,
+        ty: Type,
+        span: SourceSpan,
+    },
+    /// The instruction at `span` has opcode `op`, which the code generator does not know
+    /// how to lower for that instruction format
+    #[error("unsupported operation: '{op}' is not supported by the code generator")]
+    UnsupportedOpcode { op: Opcode, span: SourceSpan },
+    /// The inline assembly at `span` contains `op`, which cannot be used in inline assembly
+    #[error("unsupported operation: '{op}' is not supported in inline assembly")]
+    UnsupportedInlineAsm { op: &'static str, span: SourceSpan },
+}
+impl CodegenError {
+    /// Emit a diagnostic for this error to `diagnostics`, pointing at the offending instruction
+    /// if its location is known
+    pub fn emit(&self, diagnostics: &DiagnosticsHandler) {
+        match self {
+            Self::Unsupported { op, ty, span } => {
+                diagnostic!(
+                    diagnostics,
+                    Severity::Error,
+                    "unsupported operation",
+                    *span,
+                    format!("'{op}' is not supported for values of type '{ty}'")
+                );
+            }
+            Self::UnsupportedOpcode { op, span } => {
+                diagnostic!(
+                    diagnostics,
+                    Severity::Error,
+                    "unsupported operation",
+                    *span,
+                    format!("'{op}' is not supported by the code generator")
+                );
+            }
+            Self::UnsupportedInlineAsm { op, span } => {
+                diagnostic!(
+                    diagnostics,
+                    Severity::Error,
+                    "unsupported operation",
+                    *span,
+                    format!("'{op}' is not supported in inline assembly")
+                );
+            }
+        }
+    }
+}
@@ -1,16 +1,27 @@
+mod cost;
 mod emulator;
+mod error;
 mod masm;
+mod peephole;
+mod pipeline;
 mod stackify;
 #[cfg(test)]
 mod tests;
 
+pub use self::cost::{op_cycles, Cycles, FunctionCost};
 pub use self::emulator::{Breakpoint, DebugInfo, EmulationError, Emulator};
+pub use self::error::CodegenError;
 pub use self::masm::*;
+pub use self::peephole::Peephole;
+pub use self::pipeline::{OptLevel, Pipeline};
 pub use self::stackify::Stackify;
 
+use std::time::Instant;
+
 use miden_diagnostics::DiagnosticsHandler;
 use miden_hir as hir;
 use miden_hir_analysis::FunctionAnalysis;
+use miden_hir_transform::{Inliner, PassManager, PrintOptions, Snapshot, Statistics};
 
 /// This struct implements a compiler pass that emits an intermediate form
 /// of Miden Assembly corresponding to a given [miden_hir::Program].
@@ -48,17 +59,90 @@ impl<'p> miden_hir_pass::Pass for HirToMasm<'p> {
 /// can be used to take a linked [miden_hir::Program] and
 /// compile it to MASM IR, an intermediate representation of Miden Assembly
 /// used within the compiler.
+///
+/// The rewrites applied to each function before it is lowered are described by a [Pipeline],
+/// which by default only contains the rewrites required for lowering, see
+/// [MasmCompiler::with_pipeline].
+///
+/// Compilation can be instrumented to print the IR of functions around each pass, including
+/// `stackify` and `peephole`, see [MasmCompiler::with_print_options], and to collect
+/// statistics about each pass, see [MasmCompiler::with_statistics].
+///
+/// In debug builds, each function is also validated after every rewrite, so that a rewrite
+/// which breaks the invariants of the IR is reported by name, see
+/// [MasmCompiler::with_validation].
 pub struct MasmCompiler<'a> {
     diagnostics: &'a DiagnosticsHandler,
+    inliner: Option<Inliner>,
+    rewrites: PassManager,
+    print: PrintOptions,
+    collect_statistics: bool,
+    validate: bool,
 }
 impl<'a> MasmCompiler<'a> {
     pub fn new(diagnostics: &'a DiagnosticsHandler) -> Self {
-        Self { diagnostics }
+        let (inliner, rewrites) = Pipeline::default().into_parts();
+        Self {
+            diagnostics,
+            inliner,
+            rewrites,
+            print: PrintOptions::default(),
+            collect_statistics: false,
+            validate: cfg!(debug_assertions),
+        }
+    }
+
+    /// Use `pipeline` to rewrite each function before it is lowered to MASM IR
+    pub fn with_pipeline(mut self, pipeline: Pipeline) -> Self {
+        let (inliner, rewrites) = pipeline.into_parts();
+        self.inliner = inliner;
+        self.rewrites = rewrites;
+        self
+    }
+
+    /// Print the IR of functions around the passes selected by `options`
+    pub fn with_print_options(mut self, options: PrintOptions) -> Self {
+        self.print = options;
+        self
+    }
+
+    /// Collect [Statistics] about each pass run during compilation
+    pub fn with_statistics(mut self, collect: bool) -> Self {
+        self.collect_statistics = collect;
+        self
+    }
+
+    /// Set whether each function is validated after every rewrite applied to it
+    ///
+    /// If a rewrite produces invalid IR, compilation fails with an error naming that rewrite,
+    /// and the diagnostics describing the problem are emitted.
+    pub fn with_validation(mut self, validate: bool) -> Self {
+        self.validate = validate;
+        self
+    }
+
+    /// Get the statistics collected so far, if enabled, see [MasmCompiler::with_statistics]
+    pub fn statistics(&self) -> Option<&Statistics> {
+        self.rewrites.statistics()
     }
 
     /// Compile an [hir::Program] that has been linked and is ready to be compiled.
+    ///
+    /// If the program uses an operation which cannot be lowered to Miden Assembly, compilation
+    /// fails with a [CodegenError], and a diagnostic pointing at the offending instruction
+    /// is emitted.
     pub fn compile(&mut self, input: &mut hir::Program) -> anyhow::Result<Program> {
-        ProgramCompiler::new(input, self.diagnostics).compile()
+        if let Some(inliner) = self.inliner.as_mut() {
+            use miden_hir_pass::Pass;
+
+            inliner.run(input)?;
+        }
+        self.rewrites
+            .print_options(self.print.clone())
+            .collect_statistics(self.collect_statistics);
+        ProgramCompiler::new(input, self.diagnostics, &mut self.rewrites, &self.print)
+            .with_validation(self.validate)
+            .compile()
     }
 
     /// Compile a single [hir::Module] as a program.
@@ -87,21 +171,36 @@ impl<'a> MasmCompiler<'a> {
 }
 
 struct ProgramCompiler<'a> {
-    #[allow(unused)]
     diagnostics: &'a DiagnosticsHandler,
     input: &'a mut hir::Program,
     output: Program,
+    rewrites: &'a mut PassManager,
+    print: &'a PrintOptions,
+    validate: bool,
 }
 impl<'a> ProgramCompiler<'a> {
-    pub fn new(input: &'a mut hir::Program, diagnostics: &'a DiagnosticsHandler) -> Self {
+    pub fn new(
+        input: &'a mut hir::Program,
+        diagnostics: &'a DiagnosticsHandler,
+        rewrites: &'a mut PassManager,
+        print: &'a PrintOptions,
+    ) -> Self {
         let output = Program::from(input as &hir::Program);
         Self {
             diagnostics,
             input,
             output,
+            rewrites,
+            print,
+            validate: false,
         }
     }
 
+    pub fn with_validation(mut self, validate: bool) -> Self {
+        self.validate = validate;
+        self
+    }
+
     pub fn compile(mut self) -> anyhow::Result<Program> {
         // Remove the set of modules to compile from the program
         let mut modules = self.input.modules_mut().take();
@@ -130,12 +229,11 @@ impl<'a> ProgramCompiler<'a> {
     /// 3. Prepare the function by applying the rewrite pipeline
     /// 4. Re-attach the function to the module
     /// 5. Run the stackification pass to lower to MASM IR
-    /// 6. Add the MASM IR function to the MASM IR module
+    /// 6. Run the peephole optimizer over the MASM IR function
+    /// 7. Add the MASM IR function to the MASM IR module
     ///
     /// Once all functions are compiled from this module, the MASM IR module itself is returned.
     fn compile_module(&mut self, module: &mut hir::Module) -> anyhow::Result<Module> {
-        use miden_hir_pass::Pass;
-
         let mut output = Module::new(module.name);
 
         // Compute import information for this module
@@ -170,9 +268,8 @@ impl<'a> ProgramCompiler<'a> {
             // Rather than move the cursor, we use the ability to
             // get a temporary read-only cursor to the previous item
             let function = cursor.peek_prev().get().unwrap();
-            // Lower the function
-            let mut pass = Stackify::new(self.input, &analysis);
-            let masm_function = pass.run(function)?;
+            // Lower the function, and clean up the resulting code
+            let masm_function = self.lower_function(function, &analysis, &output.imports)?;
             // Attach to MASM module
             output.functions.push_back(masm_function);
         }
@@ -180,16 +277,112 @@ impl<'a> ProgramCompiler<'a> {
         Ok(output)
     }
 
+    /// Run the stackification pass on `function`, followed by the peephole optimizer,
+    /// instrumenting both in the same way as the rewrite pipeline
+    fn lower_function(
+        &mut self,
+        function: &hir::Function,
+        analysis: &FunctionAnalysis,
+        imports: &hir::ModuleImportInfo,
+    ) -> anyhow::Result<Box<Function>> {
+        use miden_hir_pass::Pass;
+
+        let print = self.print;
+        let id = &function.id;
+        if print.print_before("stackify", id) {
+            print.print(self.diagnostics, "before", "stackify", id, function);
+        }
+        let start = Instant::now();
+        let masm_function = Stackify::new(self.input, analysis)
+            .run(function)
+            .map_err(|err| {
+                if let Some(err) = err.downcast_ref::<CodegenError>() {
+                    err.emit(self.diagnostics);
+                }
+                err
+            })?;
+        if let Some(statistics) = self.rewrites.statistics_mut() {
+            // Every instruction and block is replaced by its lowered form
+            let stats = statistics.pass_mut("stackify");
+            stats.runs += 1;
+            stats.time += start.elapsed();
+            stats.record_counts(Snapshot::new(function).counts(), (0, 0));
+            stats.record_counts((0, 0), masm_size(&masm_function));
+        }
+        if print.print_after("stackify", id) {
+            print.print(
+                self.diagnostics,
+                "after",
+                "stackify",
+                id,
+                masm_function.display(imports),
+            );
+        }
+
+        if print.print_before("peephole", id) {
+            print.print(
+                self.diagnostics,
+                "before",
+                "peephole",
+                id,
+                masm_function.display(imports),
+            );
+        }
+        let before = masm_size(&masm_function);
+        let start = Instant::now();
+        let masm_function = Peephole.run(masm_function)?;
+        if let Some(statistics) = self.rewrites.statistics_mut() {
+            let stats = statistics.pass_mut("peephole");
+            stats.runs += 1;
+            stats.time += start.elapsed();
+            stats.record_counts(before, masm_size(&masm_function));
+        }
+        if print.print_after("peephole", id) {
+            print.print(
+                self.diagnostics,
+                "after",
+                "peephole",
+                id,
+                masm_function.display(imports),
+            );
+        }
+
+        Ok(masm_function)
+    }
+
     fn rewrite_function(
-        &self,
+        &mut self,
         function: &mut hir::Function,
         analysis: &mut FunctionAnalysis,
     ) -> anyhow::Result<()> {
-        use miden_hir_transform::{self as transform, RewritePass};
+        if self.validate {
+            self.rewrites
+                .run_validated(function, analysis, self.diagnostics)
+                .map(|_| ())
+        } else {
+            self.rewrites
+                .run_instrumented(function, analysis, self.diagnostics)
+                .map(|_| ())
+        }
+    }
+}
 
-        let mut rewrites = transform::SplitCriticalEdges
-            .chain(transform::Treeify)
-            .chain(transform::InlineBlocks);
-        rewrites.run(function, analysis)
+/// Get the number of instructions and blocks in `function`, not counting blocks which are
+/// unreachable from its body
+fn masm_size(function: &Function) -> (usize, usize) {
+    let mut insts = 0;
+    let mut blocks = 0;
+    let mut worklist = vec![function.body];
+    while let Some(block) = worklist.pop() {
+        blocks += 1;
+        for op in function.block(block).ops.iter() {
+            insts += 1;
+            match op {
+                Op::If(then_blk, else_blk) => worklist.extend([*then_blk, *else_blk]),
+                Op::While(body) | Op::Repeat(_, body) => worklist.push(*body),
+                _ => (),
+            }
+        }
     }
+    (insts, blocks)
 }
@@ -1,9 +1,11 @@
 mod function;
 mod module;
+mod parser;
 mod program;
 
 pub use self::function::{Function, FunctionListAdapter};
 pub use self::module::Module;
+pub use self::parser::ParseError;
 pub use self::program::Program;
 pub use miden_hir::{
     Local, LocalId, MasmBlock as Block, MasmBlockId as BlockId, MasmImport as Import, MasmOp as Op,
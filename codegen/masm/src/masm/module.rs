@@ -108,6 +108,9 @@ impl Module {
             path.push(component);
         }
         assert!(path.set_extension("masm"));
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
 
         let mut out = File::create(&path)?;
         self.emit(codemap, &mut out)
@@ -281,12 +284,35 @@ impl Module {
 
         module.functions.push_back(f);
 
+        // # Advance the native pointer triplet on top of the stack to the next element,
+        // # which may be in the next word.
+        // #
+        // # [waddr, index, offset] => [waddr', (index + 1) % 4, offset]
+        let next_element = [
+            // # [index + 1, waddr, offset]
+            Op::Swap(1),
+            Op::U32CheckedAddImm(1),
+            // # [waddr + (index + 1 == 4), index + 1, offset]
+            Op::Dup(0),
+            Op::EqImm(Felt::new(4)),
+            Op::Movup(2),
+            Op::Add,
+            // # [waddr', (index + 1) % 4, offset]
+            Op::Swap(1),
+            Op::U32CheckedModImm(4),
+            Op::Swap(1),
+        ];
+
         // # Load a single 32-bit machine word from the given native pointer triplet.
         // #
         // # A native pointer triplet consists of a word address which contains the
         // # start of the data; an element index, which indicates which element of
         // # the word the data starts in; and a byte offset, which indicates which
         // # byte is the start of the data.
+        // #
+        // # If the byte offset is non-zero, the value spans two elements: the low
+        // # bits of the value are the high bits of the first element, and the high
+        // # bits of the value are the low bits of the next element.
         // export.load_sw # [waddr, index, offset]
         let load_sw = "intrinsics::mem::load_sw".parse().unwrap();
         let mut f = Box::new(Function::new(
@@ -297,27 +323,21 @@ impl Module {
                     AbiParam::new(Type::U8),
                     AbiParam::new(Type::U8),
                 ],
-                [AbiParam::new(Type::Felt)],
+                [AbiParam::new(Type::U32)],
             ),
         ));
         {
             let is_aligned = f.create_block();
             let is_unaligned = f.create_block();
-            let is_first_element = f.create_block();
-            let is_not_first_element = f.create_block();
-            let is_second_element = f.create_block();
-            let is_not_second_element = f.create_block();
-            let is_third_element = f.create_block();
-            let is_fourth_element = f.create_block();
             let body = f.block_mut(f.body);
             body.extend_from_slice(&[
                 // # check for alignment and offset validity
                 Op::Dup(2),
                 Op::EqImm(Felt::ZERO),
                 Op::Dup(3),
-                Op::PushU8(8),
+                Op::PushU8(4),
                 Op::U32CheckedLt,
-                // # offset must be < 8
+                // # offset must be < 4
                 Op::Assert,
                 // # if the pointer is naturally aligned..
                 Op::If(is_aligned, is_unaligned),
@@ -334,172 +354,529 @@ impl Module {
 
             let is_unaligned = f.block_mut(is_unaligned);
             is_unaligned.extend_from_slice(&[
-                // # check if the load starts in the first element
+                // # convert the byte offset to a bit offset
+                // # [waddr, index, shift]
+                Op::Movup(2),
+                Op::U32CheckedMulImm(8),
+                Op::Movdn(2),
+                // # load the first element and shift out the bits preceding the value
+                // # [lo, waddr, index, shift]
+                Op::Dup(1),
                 Op::Dup(1),
+                Op::Exec(load_felt_unchecked),
+                Op::Dup(3),
+                Op::U32CheckedShr,
+                // # [waddr, index, shift, lo]
+                Op::Movdn(3),
+            ]);
+            is_unaligned.extend_from_slice(&next_element);
+            is_unaligned.extend_from_slice(&[
+                // # load the next element
+                // # [elem, shift, lo]
+                Op::Exec(load_felt_unchecked),
+                // # shift the low bits of the element into the high bits of the value
+                // # [32 - shift, elem, lo]
+                Op::PushU8(32),
+                Op::Movup(2),
+                Op::U32CheckedSub,
+                // # [hi, lo]
+                Op::U32CheckedShl,
+                // # combine the two halves
+                // # [result]
+                Op::U32Or,
+            ]);
+        }
+
+        module.functions.push_back(f);
+
+        // # Load a pair of 32-bit machine words from the given native pointer triplet.
+        // #
+        // # The word at the lower address is placed on top of the stack.
+        // export.load_dw # [waddr, index, offset]
+        let load_dw = "intrinsics::mem::load_dw".parse().unwrap();
+        let mut f = Box::new(Function::new(
+            load_dw,
+            Signature::new(
+                [
+                    AbiParam::new(Type::U32),
+                    AbiParam::new(Type::U8),
+                    AbiParam::new(Type::U8),
+                ],
+                [AbiParam::new(Type::U32), AbiParam::new(Type::U32)],
+            ),
+        ));
+        {
+            let body = f.block_mut(f.body);
+            body.extend_from_slice(&[
+                // # load the first word, and move it below the pointer
+                // # [waddr, index, offset, value0]
+                Op::Dup(2),
+                Op::Dup(2),
+                Op::Dup(2),
+                Op::Exec(load_sw),
+                Op::Movdn(3),
+            ]);
+            body.extend_from_slice(&next_element);
+            body.extend_from_slice(&[
+                // # [value0, value1]
+                Op::Exec(load_sw),
+                Op::Swap(1),
+            ]);
+        }
+
+        module.functions.push_back(f);
+
+        // # Load four 32-bit machine words from the given native pointer triplet.
+        // #
+        // # The words are placed on the stack such that the word at the lowest
+        // # address is on top.
+        // export.load_qw # [waddr, index, offset]
+        let load_qw = "intrinsics::mem::load_qw".parse().unwrap();
+        let mut f = Box::new(Function::new(
+            load_qw,
+            Signature::new(
+                [
+                    AbiParam::new(Type::U32),
+                    AbiParam::new(Type::U8),
+                    AbiParam::new(Type::U8),
+                ],
+                [
+                    AbiParam::new(Type::U32),
+                    AbiParam::new(Type::U32),
+                    AbiParam::new(Type::U32),
+                    AbiParam::new(Type::U32),
+                ],
+            ),
+        ));
+        {
+            let body = f.block_mut(f.body);
+            for i in 0..3 {
+                body.extend_from_slice(&[
+                    // # load the next word, and move it below the pointer and
+                    // # any words loaded before it
+                    // # [waddr, index, offset, value0, .., value_i]
+                    Op::Dup(2),
+                    Op::Dup(2),
+                    Op::Dup(2),
+                    Op::Exec(load_sw),
+                    Op::Movdn(3 + i),
+                ]);
+                body.extend_from_slice(&next_element);
+            }
+            body.extend_from_slice(&[
+                // # [value0, value1, value2, value3]
+                Op::Exec(load_sw),
+                Op::Movdn(3),
+            ]);
+        }
+
+        module.functions.push_back(f);
+
+        // # See `store_felt` for safe usage
+        // proc.store_felt_unchecked # [waddr, index, value]
+        let store_felt_unchecked = "intrinsics::mem::store_felt_unchecked".parse().unwrap();
+        let mut signature = Signature::new(
+            [
+                AbiParam::new(Type::U32),
+                AbiParam::new(Type::U8),
+                AbiParam::new(Type::Felt),
+            ],
+            [],
+        );
+        signature.linkage = Linkage::Internal;
+        let mut f = Box::new(Function::new(store_felt_unchecked, signature));
+        {
+            let is_first_element = f.create_block();
+            let is_not_first_element = f.create_block();
+            let is_second_element = f.create_block();
+            let is_not_second_element = f.create_block();
+            let is_third_element = f.create_block();
+            let is_fourth_element = f.create_block();
+            let body = f.block_mut(f.body);
+            body.extend_from_slice(&[
+                // # load the word which contains the element we're replacing
+                // # [w0, w1, w2, w3, waddr, index, value]
+                Op::Padw,
+                Op::Dup(4),
+                Op::MemLoadw,
+                // # [index, w0, w1, w2, w3, waddr, value]
+                Op::Movup(5),
+                Op::Dup(0),
                 Op::EqImm(Felt::ZERO),
                 Op::If(is_first_element, is_not_first_element),
             ]);
 
             let is_first_element = f.block_mut(is_first_element);
             is_first_element.extend_from_slice(&[
-                // # the load is across both the first and second elements
-                // # drop the element index
-                Op::Swap(1),
+                // # drop the index and the element being replaced
+                // # [w1, w2, w3, waddr, value]
                 Op::Drop,
-                // # load a word
-                // # [w0, w1, w2, w3, offset]
-                Op::Padw,
-                Op::Movup(4),
-                Op::MemLoadw,
-                // # drop the unused elements
-                Op::Movup(3),
-                Op::Movup(3),
                 Op::Drop,
-                Op::Drop,
-                // # shift high bits left by the offset
-                // # [hi, w1, offset]
-                Op::Dup(2),
-                Op::U32CheckedShl,
-                // # move the low bits to the top and shift them as well
-                // # [offset, 32, w1, hi]
-                Op::Swap(1),
-                Op::PushU8(32),
-                Op::Movup(3),
-                // # [32 - offset, w1, hi]
-                Op::U32CheckedSub,
-                // # [lo, hi]
-                Op::U32CheckedShr,
-                // # combine the two halves
-                // # [result]
-                Op::U32Or,
+                // # [waddr, value, w1, w2, w3]
+                Op::Movup(4),
+                Op::Movup(4),
+                Op::MemStorew,
+                Op::Dropw,
             ]);
 
             let is_not_first_element = f.block_mut(is_not_first_element);
             is_not_first_element.extend_from_slice(&[
-                // # check if the load starts in the second element
-                Op::Dup(1),
+                Op::Dup(0),
                 Op::EqImm(Felt::ONE),
                 Op::If(is_second_element, is_not_second_element),
             ]);
 
             let is_second_element = f.block_mut(is_second_element);
             is_second_element.extend_from_slice(&[
-                // # the load is across both the second and third elements
-                // # drop the element idnex
+                // # drop the index and the element being replaced
+                // # [w0, w2, w3, waddr, value]
+                Op::Drop,
                 Op::Swap(1),
                 Op::Drop,
-                // # load
-                // # [w0, w1, w2, w3, offset]
-                Op::Padw,
+                // # [waddr, w0, value, w2, w3]
                 Op::Movup(4),
-                Op::MemLoadw,
-                // # drop the unused elements
-                // # [w1, w2, offset]
-                Op::Drop,
-                Op::Movdn(2),
-                Op::Movdn(2),
-                Op::Drop,
-                // # shift the high bits
-                // # [hi, w2, offset]
-                Op::Dup(2),
-                Op::U32CheckedShl,
-                // # shift the low bits
-                // # [offset, 32, w2, hi]
                 Op::Swap(1),
-                Op::PushU8(32),
-                Op::Movup(3),
-                // # [32 - offset, w2, hi]
-                Op::U32CheckedSub,
-                // # [lo, hi]
-                Op::U32CheckedShr,
-                // # combine the two halves
-                // # [result]
-                Op::U32Or,
+                Op::Movup(4),
+                Op::MemStorew,
+                Op::Dropw,
             ]);
 
             let is_not_second_element = f.block_mut(is_not_second_element);
             is_not_second_element.extend_from_slice(&[
-                // # check if the load starts in the third element
-                Op::Swap(1),
                 Op::EqImm(Felt::new(2)),
                 Op::If(is_third_element, is_fourth_element),
             ]);
 
             let is_third_element = f.block_mut(is_third_element);
             is_third_element.extend_from_slice(&[
-                // # the load is across both the third and fourth elements
-                // # [w0, w1, w2, w3, offset]
-                Op::Padw,
-                Op::Movup(4),
-                Op::MemLoadw,
-                // # drop first two unused
-                // # [w2, w3, offset]
+                // # drop the element being replaced
+                // # [w0, w1, w3, waddr, value]
+                Op::Movup(2),
                 Op::Drop,
+                // # [waddr, w0, w1, value, w3]
+                Op::Movup(4),
+                Op::Movdn(2),
+                Op::Movup(4),
+                Op::MemStorew,
+                Op::Dropw,
+            ]);
+
+            let is_fourth_element = f.block_mut(is_fourth_element);
+            is_fourth_element.extend_from_slice(&[
+                // # drop the element being replaced
+                // # [w0, w1, w2, waddr, value]
+                Op::Movup(3),
                 Op::Drop,
-                // # shift the high bits
-                // # [hi, w3, offset]
-                Op::Dup(2),
-                Op::U32CheckedShl,
-                // # shift the low bits
-                // # [offset, 32, w3, hi]
-                Op::Swap(1),
-                Op::PushU8(32),
+                // # [waddr, w0, w1, w2, value]
+                Op::Movup(4),
+                Op::Movdn(3),
+                Op::Movup(4),
+                Op::MemStorew,
+                Op::Dropw,
+            ]);
+        }
+
+        module.functions.push_back(f);
+
+        // # Store a field element to the given native pointer triplet.
+        // #
+        // # A field element must be naturally aligned, i.e. it's byte offset must be zero.
+        // export.store_felt # [waddr, index, offset, value]
+        let store_felt = "intrinsics::mem::store_felt".parse().unwrap();
+        let mut f = Box::new(Function::new(
+            store_felt,
+            Signature::new(
+                [
+                    AbiParam::new(Type::U32),
+                    AbiParam::new(Type::U8),
+                    AbiParam::new(Type::U8),
+                    AbiParam::new(Type::Felt),
+                ],
+                [],
+            ),
+        ));
+        {
+            let body = f.block_mut(f.body);
+            body.extend_from_slice(&[
+                // # assert the pointer is felt-aligned, then store
+                Op::Movup(2),
+                Op::Assertz,
+                Op::Exec(store_felt_unchecked),
+            ]);
+        }
+
+        module.functions.push_back(f);
+
+        // # Replace the bits selected by `mask` in the element at the given word address
+        // # and element index, with `bits`, which must only have bits set that are also
+        // # set in `mask`.
+        // proc.update_element # [waddr, index, mask, bits]
+        let update_element = "intrinsics::mem::update_element".parse().unwrap();
+        let mut signature = Signature::new(
+            [
+                AbiParam::new(Type::U32),
+                AbiParam::new(Type::U8),
+                AbiParam::new(Type::U32),
+                AbiParam::new(Type::U32),
+            ],
+            [],
+        );
+        signature.linkage = Linkage::Internal;
+        let mut f = Box::new(Function::new(update_element, signature));
+        {
+            let body = f.block_mut(f.body);
+            body.extend_from_slice(&[
+                // # load the current value of the element
+                // # [elem, waddr, index, mask, bits]
+                Op::Dup(1),
+                Op::Dup(1),
+                Op::Exec(load_felt_unchecked),
+                // # clear the bits we're replacing
+                // # [elem & !mask, waddr, index, bits]
+                Op::Movup(3),
+                Op::U32Not,
+                Op::U32And,
+                // # set the new bits
+                // # [elem', waddr, index]
                 Op::Movup(3),
-                // # [32 - offset, w3, hi]
-                Op::U32CheckedSub,
-                // # [lo, hi]
-                Op::U32CheckedShr,
-                // # combine the two halves
-                // # [result]
                 Op::U32Or,
+                // # write the element back
+                // # [waddr, index, elem']
+                Op::Movdn(2),
+                Op::Exec(store_felt_unchecked),
             ]);
+        }
 
-            let is_fourth_element = f.block_mut(is_fourth_element);
-            is_fourth_element.extend_from_slice(&[
-                // # the load crosses a word boundary
-                // # start with the word containing the low bits
-                // # [waddr, waddr, offset]
-                Op::Dup(0),
-                // # [waddr + 1, waddr, offset]
-                Op::U32CheckedAddImm(1),
-                // # load the word and drop the unused elements
-                // # [w0, waddr, offset]
-                Op::Padw,
+        module.functions.push_back(f);
+
+        // # Store the low bits of `value` selected by `mask` to the given native pointer triplet.
+        // #
+        // # The mask must be of the form `2^n - 1`, where `n` is the size in bits of the value
+        // # being stored, i.e. 8, 16 or 32. If the value does not fit in the element at which it
+        // # starts, the remaining high bits are written to the low bits of the next element, which
+        // # may be in the next word.
+        // export.store_masked # [waddr, index, offset, mask, value]
+        let store_masked = "intrinsics::mem::store_masked".parse().unwrap();
+        let mut f = Box::new(Function::new(
+            store_masked,
+            Signature::new(
+                [
+                    AbiParam::new(Type::U32),
+                    AbiParam::new(Type::U8),
+                    AbiParam::new(Type::U8),
+                    AbiParam::new(Type::U32),
+                    AbiParam::new(Type::U32),
+                ],
+                [],
+            ),
+        ));
+        {
+            let may_spill = f.create_block();
+            let no_spill = f.create_block();
+            let spills = f.create_block();
+            let spill_is_empty = f.create_block();
+            let body = f.block_mut(f.body);
+            body.extend_from_slice(&[
+                // # discard any bits of the value not selected by the mask
+                // # [waddr, index, offset, mask, value & mask]
                 Op::Movup(4),
-                Op::MemLoadw,
+                Op::Dup(4),
+                Op::U32And,
                 Op::Movdn(4),
-                Op::Drop,
-                Op::Drop,
-                Op::Drop,
-                // # shift the low bits
-                // # [offset, 32, w0, waddr, offset]
+                // # convert the byte offset to a bit offset
+                // # [shift, waddr, index, mask, value]
+                Op::Movup(2),
+                Op::U32CheckedMulImm(8),
+                // # compute the bits and mask for the first element
+                // # [value << shift, shift, waddr, index, mask, value]
+                Op::Dup(4),
+                Op::Dup(1),
+                Op::U32CheckedShl,
+                // # [mask << shift, value << shift, shift, waddr, index, mask, value]
+                Op::Dup(4),
+                Op::Dup(2),
+                Op::U32CheckedShl,
+                // # [waddr, index, mask << shift, value << shift, shift, waddr, index, mask, value]
+                Op::Dup(4),
+                Op::Dup(4),
+                // # [shift, waddr, index, mask, value]
+                Op::Exec(update_element),
+                // # an aligned value never spills into the next element
+                Op::Dup(0),
+                Op::NeqImm(Felt::ZERO),
+                Op::If(may_spill, no_spill),
+            ]);
+
+            let no_spill = f.block_mut(no_spill);
+            no_spill.extend_from_slice(&[Op::Dropw, Op::Drop]);
+
+            let may_spill = f.block_mut(may_spill);
+            may_spill.extend_from_slice(&[
+                // # [32 - shift, waddr, index, mask, value]
                 Op::PushU8(32),
-                Op::Dup(3),
-                // # [32 - offset, w0, waddr, offset]
+                Op::Swap(1),
                 Op::U32CheckedSub,
-                // # [lo, waddr, offset]
+                // # compute the mask and bits for the next element
+                // # [mask >> (32 - shift), 32 - shift, waddr, index, value]
+                Op::Movup(3),
+                Op::Dup(1),
                 Op::U32CheckedShr,
-                // # load the word with the high bits, drop unused elements
-                // # [w3, lo, offset]
-                Op::Swap(1),
-                Op::Padw,
+                // # [value >> (32 - shift), mask >> (32 - shift), waddr, index]
                 Op::Movup(4),
-                Op::MemLoadw,
-                Op::Drop,
-                Op::Drop,
-                Op::Drop,
-                // # shift high bits
-                // # [hi, lo]
                 Op::Movup(2),
-                Op::U32CheckedShl,
-                // # combine the two halves
-                // # [result]
-                Op::U32Or,
+                Op::U32CheckedShr,
+                // # if the mask is empty, the value fit in the first element
+                Op::Dup(1),
+                Op::NeqImm(Felt::ZERO),
+                Op::If(spills, spill_is_empty),
+            ]);
+
+            let spill_is_empty = f.block_mut(spill_is_empty);
+            spill_is_empty.push(Op::Dropw);
+
+            let spills = f.block_mut(spills);
+            spills.extend_from_slice(&[
+                // # advance to the next element, which may be in the next word
+                // # [index + 1, bits, mask, waddr]
+                Op::Movup(3),
+                Op::U32CheckedAddImm(1),
+                // # [waddr + (index + 1 == 4), index + 1, bits, mask]
+                Op::Dup(0),
+                Op::EqImm(Felt::new(4)),
+                Op::Movup(4),
+                Op::Add,
+                // # [waddr', (index + 1) % 4, bits, mask]
+                Op::Swap(1),
+                Op::U32CheckedModImm(4),
+                Op::Swap(1),
+                // # [waddr', index', mask, bits]
+                Op::Movup(3),
+                Op::Movdn(2),
+                Op::Exec(update_element),
             ]);
         }
 
         module.functions.push_back(f);
 
+        // # Store a single 32-bit machine word to the given native pointer triplet.
+        // #
+        // # A native pointer triplet consists of a word address which contains the
+        // # start of the data; an element index, which indicates which element of
+        // # the word the data starts in; and a byte offset, which indicates which
+        // # byte is the start of the data.
+        // export.store_sw # [waddr, index, offset, value]
+        let store_sw = "intrinsics::mem::store_sw".parse().unwrap();
+        let mut f = Box::new(Function::new(
+            store_sw,
+            Signature::new(
+                [
+                    AbiParam::new(Type::U32),
+                    AbiParam::new(Type::U8),
+                    AbiParam::new(Type::U8),
+                    AbiParam::new(Type::U32),
+                ],
+                [],
+            ),
+        ));
+        {
+            let body = f.block_mut(f.body);
+            body.extend_from_slice(&[
+                // # [waddr, index, offset, 0xffffffff, value]
+                Op::PushU32(u32::MAX),
+                Op::Movdn(3),
+                Op::Exec(store_masked),
+            ]);
+        }
+
+        module.functions.push_back(f);
+
+        // # Copy the native pointer triplet on top of the stack, then move the value following
+        // # the original triplet to the top, and store it to the copied pointer.
+        // #
+        // # [waddr, index, offset, value, ..] => [waddr, index, offset, ..]
+        let store_sw_preserving_ptr = [
+            // # [waddr, index, offset, value, waddr, index, offset]
+            Op::Dup(2),
+            Op::Dup(2),
+            Op::Dup(2),
+            Op::Movup(6),
+            Op::Movdn(3),
+            Op::Exec(store_sw),
+        ];
+
+        // # Store a pair of 32-bit machine words to the given native pointer triplet.
+        // #
+        // # The first word of the pair is the one on top of the stack, and is stored
+        // # at the lower address.
+        // export.store_dw # [waddr, index, offset, value_lo, value_hi]
+        let store_dw = "intrinsics::mem::store_dw".parse().unwrap();
+        let mut f = Box::new(Function::new(
+            store_dw,
+            Signature::new(
+                [
+                    AbiParam::new(Type::U32),
+                    AbiParam::new(Type::U8),
+                    AbiParam::new(Type::U8),
+                    AbiParam::new(Type::U32),
+                    AbiParam::new(Type::U32),
+                ],
+                [],
+            ),
+        ));
+        {
+            let body = f.block_mut(f.body);
+            body.extend_from_slice(&store_sw_preserving_ptr);
+            body.extend_from_slice(&next_element);
+            body.push(Op::Exec(store_sw));
+        }
+
+        module.functions.push_back(f);
+
+        // # Store four 32-bit machine words to the given native pointer triplet.
+        // #
+        // # The words are stored in the order they appear on the stack, i.e. the word
+        // # on top of the stack is stored at the lowest address.
+        // export.store_qw # [waddr, index, offset, value0, value1, value2, value3]
+        let store_qw = "intrinsics::mem::store_qw".parse().unwrap();
+        let mut f = Box::new(Function::new(
+            store_qw,
+            Signature::new(
+                [
+                    AbiParam::new(Type::U32),
+                    AbiParam::new(Type::U8),
+                    AbiParam::new(Type::U8),
+                    AbiParam::new(Type::U32),
+                    AbiParam::new(Type::U32),
+                    AbiParam::new(Type::U32),
+                    AbiParam::new(Type::U32),
+                ],
+                [],
+            ),
+        ));
+        {
+            let body = f.block_mut(f.body);
+            for _ in 0..3 {
+                body.extend_from_slice(&store_sw_preserving_ptr);
+                body.extend_from_slice(&next_element);
+            }
+            body.push(Op::Exec(store_sw));
+        }
+
+        module.functions.push_back(f);
+
         module
     }
+
+    /// This is a helper that parses and returns the predefined `std::math::u64` module
+    ///
+    /// Only the procedures called by the code generated for 64-bit integer operations are
+    /// defined, see `intrinsics/u64.masm`. This is used in place of the standard library,
+    /// which is not available to the [crate::Emulator].
+    pub fn std_math_u64() -> Self {
+        Self::parse_str(
+            "std::math::u64".parse().unwrap(),
+            include_str!("../../intrinsics/u64.masm"),
+        )
+        .expect("invalid std::math::u64 module")
+    }
 }
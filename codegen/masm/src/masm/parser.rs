@@ -0,0 +1,422 @@
+use std::path::Path;
+
+use miden_assembly::{
+    ast::{CodeBody, Instruction, ModuleAst, Node, ProcedureAst},
+    ProcedureId,
+};
+use miden_hir::{FunctionIdent, Ident, Linkage, Signature, Symbol, Type};
+
+use super::{BlockId, Function, LocalId, Module, ModuleImportInfo, Op};
+
+/// This error is returned when Miden Assembly source code could not be
+/// translated to MASM IR.
+#[derive(Debug, thiserror::Error)]
+pub enum ParseError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// The source code is not syntactically valid Miden Assembly
+    #[error("invalid syntax: {0}")]
+    Syntax(String),
+    /// The source code uses an instruction which has no equivalent in MASM IR
+    #[error("unable to parse '{function}': unsupported instruction '{instruction}'")]
+    Unsupported {
+        function: FunctionIdent,
+        instruction: String,
+    },
+    /// The source code is valid, but cannot be represented in MASM IR
+    #[error("unable to parse '{function}': {reason}")]
+    Invalid {
+        function: FunctionIdent,
+        reason: String,
+    },
+}
+
+impl Module {
+    /// Parse the Miden Assembly module in the file at `path`, giving it the name `name`
+    ///
+    /// See [Module::parse_str] for details.
+    pub fn parse_file<P: AsRef<Path>>(name: Ident, path: P) -> Result<Self, ParseError> {
+        let source = std::fs::read_to_string(path)?;
+        Self::parse_str(name, &source)
+    }
+
+    /// Parse `source` as a Miden Assembly module named `name`, e.g. `std::math::u64`
+    ///
+    /// This is the inverse of [Module::to_module_ast], and can be used to load hand-written
+    /// Miden Assembly, such as the standard library, so that it can be executed in the
+    /// [crate::Emulator] alongside code produced by the compiler.
+    ///
+    /// Miden Assembly is untyped, so the functions of the parsed module are given a signature
+    /// with no parameters or results. Only the linkage of the signature is meaningful, i.e.
+    /// exported procedures are public, all others are internal.
+    pub fn parse_str(name: Ident, source: &str) -> Result<Self, ParseError> {
+        let ast = ModuleAst::parse(source).map_err(|err| ParseError::Syntax(err.to_string()))?;
+
+        let mut module = Module::new(name);
+
+        // Procedures local to the module are referenced by their index in the module
+        let local_ids = ast
+            .procs()
+            .iter()
+            .map(|procedure| FunctionIdent {
+                module: name,
+                function: Ident::with_empty_span(Symbol::intern(procedure.name.as_ref())),
+            })
+            .collect::<Vec<_>>();
+
+        for (procedure, id) in ast.procs().iter().zip(local_ids.iter().copied()) {
+            let function = FunctionParser {
+                ast: &ast,
+                local_ids: &local_ids,
+                imports: &mut module.imports,
+            }
+            .parse(id, procedure)?;
+            module.functions.push_back(function);
+        }
+
+        Ok(module)
+    }
+}
+
+/// This struct holds the module-level context needed to translate a single procedure
+struct FunctionParser<'a> {
+    ast: &'a ModuleAst,
+    local_ids: &'a [FunctionIdent],
+    imports: &'a mut ModuleImportInfo,
+}
+impl<'a> FunctionParser<'a> {
+    fn parse(
+        &mut self,
+        id: FunctionIdent,
+        procedure: &ProcedureAst,
+    ) -> Result<Box<Function>, ParseError> {
+        let mut signature = Signature::new([], []);
+        if !procedure.is_export {
+            signature.linkage = Linkage::Internal;
+        }
+
+        let mut function = Box::new(Function::new(id, signature));
+        // Each procedure local is a single word of memory
+        for _ in 0..procedure.num_locals {
+            function.alloc_local(Type::Felt);
+        }
+
+        let body = function.body;
+        self.parse_block(&mut function, body, &procedure.body)?;
+
+        Ok(function)
+    }
+
+    fn parse_block(
+        &mut self,
+        function: &mut Function,
+        block: BlockId,
+        code: &CodeBody,
+    ) -> Result<(), ParseError> {
+        for node in code.nodes() {
+            match node {
+                Node::Instruction(inst) => {
+                    let ops = self.parse_instruction(function.name, inst)?;
+                    function.block_mut(block).ops.extend(ops);
+                }
+                Node::IfElse {
+                    true_case,
+                    false_case,
+                } => {
+                    let then_blk = function.create_block();
+                    let else_blk = function.create_block();
+                    self.parse_block(function, then_blk, true_case)?;
+                    self.parse_block(function, else_blk, false_case)?;
+                    function.block_mut(block).push(Op::If(then_blk, else_blk));
+                }
+                Node::While { body } => {
+                    let body_blk = function.create_block();
+                    self.parse_block(function, body_blk, body)?;
+                    function.block_mut(block).push(Op::While(body_blk));
+                }
+                Node::Repeat { times, body } => {
+                    let times = u8::try_from(*times).map_err(|_| ParseError::Invalid {
+                        function: function.name,
+                        reason: format!("repeat count of {times} is larger than 255"),
+                    })?;
+                    let body_blk = function.create_block();
+                    self.parse_block(function, body_blk, body)?;
+                    function.block_mut(block).push(Op::Repeat(times, body_blk));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Translate `inst` to its equivalent sequence of MASM IR ops
+    ///
+    /// This is the inverse of [Op::into_node]
+    fn parse_instruction(
+        &mut self,
+        current_function: FunctionIdent,
+        inst: &Instruction,
+    ) -> Result<Vec<Op>, ParseError> {
+        let op = match inst {
+            Instruction::PadW => Op::Padw,
+            Instruction::PushU8(imm) => Op::PushU8(*imm),
+            Instruction::PushU16(imm) => Op::PushU16(*imm),
+            Instruction::PushU32(imm) => Op::PushU32(*imm),
+            Instruction::PushFelt(imm) => Op::Push(*imm),
+            Instruction::PushWord(word) => Op::Pushw(*word),
+            Instruction::PushU8List(imms) => {
+                return Ok(imms.iter().copied().map(Op::PushU8).collect())
+            }
+            Instruction::PushU16List(imms) => {
+                return Ok(imms.iter().copied().map(Op::PushU16).collect())
+            }
+            Instruction::PushU32List(imms) => {
+                return Ok(imms.iter().copied().map(Op::PushU32).collect())
+            }
+            Instruction::PushFeltList(imms) => {
+                return Ok(imms.iter().copied().map(Op::Push).collect())
+            }
+            Instruction::Drop => Op::Drop,
+            Instruction::DropW => Op::Dropw,
+            Instruction::Dup0 => Op::Dup(0),
+            Instruction::Dup1 => Op::Dup(1),
+            Instruction::Dup2 => Op::Dup(2),
+            Instruction::Dup3 => Op::Dup(3),
+            Instruction::Dup4 => Op::Dup(4),
+            Instruction::Dup5 => Op::Dup(5),
+            Instruction::Dup6 => Op::Dup(6),
+            Instruction::Dup7 => Op::Dup(7),
+            Instruction::Dup8 => Op::Dup(8),
+            Instruction::Dup9 => Op::Dup(9),
+            Instruction::Dup10 => Op::Dup(10),
+            Instruction::Dup11 => Op::Dup(11),
+            Instruction::Dup12 => Op::Dup(12),
+            Instruction::Dup13 => Op::Dup(13),
+            Instruction::Dup14 => Op::Dup(14),
+            Instruction::Dup15 => Op::Dup(15),
+            Instruction::DupW0 => Op::Dupw(0),
+            Instruction::DupW1 => Op::Dupw(1),
+            Instruction::DupW2 => Op::Dupw(2),
+            Instruction::DupW3 => Op::Dupw(3),
+            Instruction::Swap1 => Op::Swap(1),
+            Instruction::Swap2 => Op::Swap(2),
+            Instruction::Swap3 => Op::Swap(3),
+            Instruction::Swap4 => Op::Swap(4),
+            Instruction::Swap5 => Op::Swap(5),
+            Instruction::Swap6 => Op::Swap(6),
+            Instruction::Swap7 => Op::Swap(7),
+            Instruction::Swap8 => Op::Swap(8),
+            Instruction::Swap9 => Op::Swap(9),
+            Instruction::Swap10 => Op::Swap(10),
+            Instruction::Swap11 => Op::Swap(11),
+            Instruction::Swap12 => Op::Swap(12),
+            Instruction::Swap13 => Op::Swap(13),
+            Instruction::Swap14 => Op::Swap(14),
+            Instruction::Swap15 => Op::Swap(15),
+            Instruction::SwapW1 => Op::Swapw(1),
+            Instruction::SwapW2 => Op::Swapw(2),
+            Instruction::SwapW3 => Op::Swapw(3),
+            Instruction::MovUp2 => Op::Movup(2),
+            Instruction::MovUp3 => Op::Movup(3),
+            Instruction::MovUp4 => Op::Movup(4),
+            Instruction::MovUp5 => Op::Movup(5),
+            Instruction::MovUp6 => Op::Movup(6),
+            Instruction::MovUp7 => Op::Movup(7),
+            Instruction::MovUp8 => Op::Movup(8),
+            Instruction::MovUp9 => Op::Movup(9),
+            Instruction::MovUp10 => Op::Movup(10),
+            Instruction::MovUp11 => Op::Movup(11),
+            Instruction::MovUp12 => Op::Movup(12),
+            Instruction::MovUp13 => Op::Movup(13),
+            Instruction::MovUp14 => Op::Movup(14),
+            Instruction::MovUp15 => Op::Movup(15),
+            Instruction::MovUpW2 => Op::Movupw(2),
+            Instruction::MovUpW3 => Op::Movupw(3),
+            Instruction::MovDn2 => Op::Movdn(2),
+            Instruction::MovDn3 => Op::Movdn(3),
+            Instruction::MovDn4 => Op::Movdn(4),
+            Instruction::MovDn5 => Op::Movdn(5),
+            Instruction::MovDn6 => Op::Movdn(6),
+            Instruction::MovDn7 => Op::Movdn(7),
+            Instruction::MovDn8 => Op::Movdn(8),
+            Instruction::MovDn9 => Op::Movdn(9),
+            Instruction::MovDn10 => Op::Movdn(10),
+            Instruction::MovDn11 => Op::Movdn(11),
+            Instruction::MovDn12 => Op::Movdn(12),
+            Instruction::MovDn13 => Op::Movdn(13),
+            Instruction::MovDn14 => Op::Movdn(14),
+            Instruction::MovDn15 => Op::Movdn(15),
+            Instruction::MovDnW2 => Op::Movdnw(2),
+            Instruction::MovDnW3 => Op::Movdnw(3),
+            Instruction::CSwap => Op::Cswap,
+            Instruction::CSwapW => Op::Cswapw,
+            Instruction::CDrop => Op::Cdrop,
+            Instruction::CDropW => Op::Cdropw,
+            Instruction::Assert => Op::Assert,
+            Instruction::Assertz => Op::Assertz,
+            Instruction::AssertEq => Op::AssertEq,
+            Instruction::AssertEqw => Op::AssertEqw,
+            Instruction::Locaddr(idx) => Op::LocAddr(LocalId::new(*idx as usize)),
+            Instruction::MemLoad => Op::MemLoad,
+            Instruction::MemLoadImm(addr) => Op::MemLoadImm(*addr),
+            Instruction::MemLoadW => Op::MemLoadw,
+            Instruction::MemLoadWImm(addr) => Op::MemLoadwImm(*addr),
+            Instruction::MemStore => Op::MemStore,
+            Instruction::MemStoreImm(addr) => Op::MemStoreImm(*addr),
+            Instruction::MemStoreW => Op::MemStorew,
+            Instruction::MemStoreWImm(addr) => Op::MemStorewImm(*addr),
+            Instruction::ExecLocal(idx) => {
+                let callee = self.local_ids.get(*idx as usize).copied().ok_or_else(|| {
+                    ParseError::Invalid {
+                        function: current_function,
+                        reason: format!("reference to undefined local procedure {idx}"),
+                    }
+                })?;
+                Op::Exec(callee)
+            }
+            Instruction::ExecImported(id) => Op::Exec(self.resolve_import(current_function, id)?),
+            Instruction::SysCall(id) => Op::Syscall(self.resolve_import(current_function, id)?),
+            Instruction::Add => Op::Add,
+            Instruction::AddImm(imm) => Op::AddImm(*imm),
+            Instruction::Sub => Op::Sub,
+            Instruction::SubImm(imm) => Op::SubImm(*imm),
+            Instruction::Mul => Op::Mul,
+            Instruction::MulImm(imm) => Op::MulImm(*imm),
+            Instruction::Div => Op::Div,
+            Instruction::DivImm(imm) => Op::DivImm(*imm),
+            Instruction::Neg => Op::Neg,
+            Instruction::Inv => Op::Inv,
+            Instruction::Incr => Op::Incr,
+            Instruction::Pow2 => Op::Pow2,
+            Instruction::Exp => Op::Exp,
+            Instruction::ExpBitLength(imm) => Op::ExpImm(*imm),
+            Instruction::Not => Op::Not,
+            Instruction::And => Op::And,
+            Instruction::Or => Op::Or,
+            Instruction::Xor => Op::Xor,
+            Instruction::Eq => Op::Eq,
+            Instruction::EqImm(imm) => Op::EqImm(*imm),
+            Instruction::Neq => Op::Neq,
+            Instruction::NeqImm(imm) => Op::NeqImm(*imm),
+            Instruction::Gt => Op::Gt,
+            Instruction::Gte => Op::Gte,
+            Instruction::Lt => Op::Lt,
+            Instruction::Lte => Op::Lte,
+            Instruction::IsOdd => Op::IsOdd,
+            Instruction::Eqw => Op::Eqw,
+            Instruction::Clk => Op::Clk,
+            Instruction::U32Test => Op::U32Test,
+            Instruction::U32TestW => Op::U32Testw,
+            Instruction::U32Assert => Op::U32Assert,
+            Instruction::U32Assert2 => Op::U32Assert2,
+            Instruction::U32AssertW => Op::U32Assertw,
+            Instruction::U32Cast => Op::U32Cast,
+            Instruction::U32Split => Op::U32Split,
+            Instruction::U32CheckedAdd => Op::U32CheckedAdd,
+            Instruction::U32CheckedAddImm(imm) => Op::U32CheckedAddImm(*imm),
+            Instruction::U32OverflowingAdd => Op::U32OverflowingAdd,
+            Instruction::U32OverflowingAddImm(imm) => Op::U32OverflowingAddImm(*imm),
+            Instruction::U32WrappingAdd => Op::U32WrappingAdd,
+            Instruction::U32WrappingAddImm(imm) => Op::U32WrappingAddImm(*imm),
+            Instruction::U32OverflowingAdd3 => Op::U32OverflowingAdd3,
+            Instruction::U32WrappingAdd3 => Op::U32WrappingAdd3,
+            Instruction::U32CheckedSub => Op::U32CheckedSub,
+            Instruction::U32CheckedSubImm(imm) => Op::U32CheckedSubImm(*imm),
+            Instruction::U32OverflowingSub => Op::U32OverflowingSub,
+            Instruction::U32OverflowingSubImm(imm) => Op::U32OverflowingSubImm(*imm),
+            Instruction::U32WrappingSub => Op::U32WrappingSub,
+            Instruction::U32WrappingSubImm(imm) => Op::U32WrappingSubImm(*imm),
+            Instruction::U32CheckedMul => Op::U32CheckedMul,
+            Instruction::U32CheckedMulImm(imm) => Op::U32CheckedMulImm(*imm),
+            Instruction::U32OverflowingMul => Op::U32OverflowingMul,
+            Instruction::U32OverflowingMulImm(imm) => Op::U32OverflowingMulImm(*imm),
+            Instruction::U32WrappingMul => Op::U32WrappingMul,
+            Instruction::U32WrappingMulImm(imm) => Op::U32WrappingMulImm(*imm),
+            Instruction::U32OverflowingMadd => Op::U32OverflowingMadd,
+            Instruction::U32WrappingMadd => Op::U32WrappingMadd,
+            Instruction::U32CheckedDiv => Op::U32CheckedDiv,
+            Instruction::U32CheckedDivImm(imm) => Op::U32CheckedDivImm(*imm),
+            Instruction::U32UncheckedDiv => Op::U32UncheckedDiv,
+            Instruction::U32UncheckedDivImm(imm) => Op::U32UncheckedDivImm(*imm),
+            Instruction::U32CheckedMod => Op::U32CheckedMod,
+            Instruction::U32CheckedModImm(imm) => Op::U32CheckedModImm(*imm),
+            Instruction::U32UncheckedMod => Op::U32UncheckedMod,
+            Instruction::U32UncheckedModImm(imm) => Op::U32UncheckedModImm(*imm),
+            Instruction::U32CheckedDivMod => Op::U32CheckedDivMod,
+            Instruction::U32CheckedDivModImm(imm) => Op::U32CheckedDivModImm(*imm),
+            Instruction::U32UncheckedDivMod => Op::U32UncheckedDivMod,
+            Instruction::U32UncheckedDivModImm(imm) => Op::U32UncheckedDivModImm(*imm),
+            Instruction::U32CheckedAnd => Op::U32And,
+            Instruction::U32CheckedOr => Op::U32Or,
+            Instruction::U32CheckedXor => Op::U32Xor,
+            Instruction::U32CheckedNot => Op::U32Not,
+            Instruction::U32CheckedShl => Op::U32CheckedShl,
+            Instruction::U32CheckedShlImm(imm) => Op::U32CheckedShlImm(u32::from(*imm)),
+            Instruction::U32UncheckedShl => Op::U32UncheckedShl,
+            Instruction::U32UncheckedShlImm(imm) => Op::U32UncheckedShlImm(u32::from(*imm)),
+            Instruction::U32CheckedShr => Op::U32CheckedShr,
+            Instruction::U32CheckedShrImm(imm) => Op::U32CheckedShrImm(u32::from(*imm)),
+            Instruction::U32UncheckedShr => Op::U32UncheckedShr,
+            Instruction::U32UncheckedShrImm(imm) => Op::U32UncheckedShrImm(u32::from(*imm)),
+            Instruction::U32CheckedRotl => Op::U32CheckedRotl,
+            Instruction::U32CheckedRotlImm(imm) => Op::U32CheckedRotlImm(u32::from(*imm)),
+            Instruction::U32UncheckedRotl => Op::U32UncheckedRotl,
+            Instruction::U32UncheckedRotlImm(imm) => Op::U32UncheckedRotlImm(u32::from(*imm)),
+            Instruction::U32CheckedRotr => Op::U32CheckedRotr,
+            Instruction::U32CheckedRotrImm(imm) => Op::U32CheckedRotrImm(u32::from(*imm)),
+            Instruction::U32UncheckedRotr => Op::U32UncheckedRotr,
+            Instruction::U32UncheckedRotrImm(imm) => Op::U32UncheckedRotrImm(u32::from(*imm)),
+            Instruction::U32CheckedPopcnt => Op::U32CheckedPopcnt,
+            Instruction::U32UncheckedPopcnt => Op::U32UncheckedPopcnt,
+            Instruction::U32CheckedEq => Op::U32Eq,
+            Instruction::U32CheckedEqImm(imm) => Op::U32EqImm(*imm),
+            Instruction::U32CheckedNeq => Op::U32Neq,
+            Instruction::U32CheckedNeqImm(imm) => Op::U32NeqImm(*imm),
+            Instruction::U32CheckedLt => Op::U32CheckedLt,
+            Instruction::U32UncheckedLt => Op::U32UncheckedLt,
+            Instruction::U32CheckedLte => Op::U32CheckedLte,
+            Instruction::U32UncheckedLte => Op::U32UncheckedLte,
+            Instruction::U32CheckedGt => Op::U32CheckedGt,
+            Instruction::U32UncheckedGt => Op::U32UncheckedGt,
+            Instruction::U32CheckedGte => Op::U32CheckedGte,
+            Instruction::U32UncheckedGte => Op::U32UncheckedGte,
+            Instruction::U32CheckedMin => Op::U32CheckedMin,
+            Instruction::U32UncheckedMin => Op::U32UncheckedMin,
+            Instruction::U32CheckedMax => Op::U32CheckedMax,
+            Instruction::U32UncheckedMax => Op::U32UncheckedMax,
+            other => {
+                return Err(ParseError::Unsupported {
+                    function: current_function,
+                    instruction: other.to_string(),
+                })
+            }
+        };
+
+        Ok(vec![op])
+    }
+
+    /// Resolve the procedure identified by `id` to the fully-qualified name of the
+    /// imported function, and record the import in the module import table.
+    fn resolve_import(
+        &mut self,
+        current_function: FunctionIdent,
+        id: &ProcedureId,
+    ) -> Result<FunctionIdent, ParseError> {
+        let (name, path) = self
+            .ast
+            .import_info()
+            .invoked_procs()
+            .get(id)
+            .ok_or_else(|| ParseError::Invalid {
+                function: current_function,
+                reason: format!("reference to unknown imported procedure {id:?}"),
+            })?;
+        let callee = FunctionIdent {
+            module: Ident::with_empty_span(Symbol::intern(path.as_ref())),
+            function: Ident::with_empty_span(Symbol::intern(name.as_ref())),
+        };
+        self.imports.add(callee);
+
+        Ok(callee)
+    }
+}
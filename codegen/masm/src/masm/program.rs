@@ -32,6 +32,9 @@ impl Program {
 
     /// Write this [Program] to the given output directory.
     ///
+    /// If this program is executable, the program entrypoint is written to `exec.masm`, and
+    /// each module is written to a file whose path is derived from the module name.
+    ///
     /// The provided [miden_diagnostics::CodeMap] is used for computing source locations.
     pub fn write_to_directory<P: AsRef<Path>>(
         &self,
@@ -43,8 +46,11 @@ impl Program {
         let path = path.as_ref();
         assert!(path.is_dir());
 
-        let program = self.to_program_ast();
-        program.write_to_file(path.join(masm::LibraryPath::EXEC_PATH))?;
+        // Libraries have no entrypoint, so there is no executable module to emit
+        if self.is_executable() {
+            let program = self.to_program_ast();
+            program.write_to_file(path.join(masm::LibraryPath::EXEC_PATH))?;
+        }
 
         for module in self.modules.iter() {
             module.write_to_directory(codemap, path)?;
@@ -0,0 +1,252 @@
+use miden_hir::{Felt, FieldElement, StarkField};
+use rustc_hash::FxHashMap;
+use smallvec::SmallVec;
+
+use crate::masm::{self, BlockId, Op};
+
+/// This pass performs peephole optimization of a [masm::Function] after stackification,
+/// rewriting short sequences of instructions to cheaper equivalents.
+///
+/// Every rewrite preserves the stack effect of the sequence it replaces, i.e. given the same
+/// operand stack, the original and the rewritten sequence leave the same operand stack and
+/// the same memory behind, and trap under the same conditions. The rules are:
+///
+/// * Pairs of instructions which undo each other are removed: `swap.n swap.n`,
+/// `swapw.n swapw.n`, `movup.n movdn.n`, `movdn.n movup.n`, and their word-sized equivalents.
+/// * An instruction which only pushes a value, i.e. `dup.n` or a constant, followed by `drop`
+/// is removed, as is `dupw.n`, `padw` or `push` of a word, followed by `dropw`.
+/// * A constant pushed immediately before an instruction with an immediate form, e.g.
+/// `push.c eq`, is folded into that instruction, e.g. `eq.c`, when `c` is a valid immediate
+/// for it, and the immediate form traps under the same conditions.
+/// * Field arithmetic with an identity immediate, i.e. `add.0`, `sub.0`, `mul.1`, and `div.1`,
+/// is removed, and `add.1` is replaced with `incr`.
+/// * Instructions which end both branches of an `if.true` are hoisted out of it, to follow the
+/// `if.true` instead.
+///
+/// Rules are applied repeatedly until no further changes can be made.
+pub struct Peephole;
+impl miden_hir_pass::Pass for Peephole {
+    type Input<'a> = Box<masm::Function>;
+    type Output<'a> = Box<masm::Function>;
+    type Error = anyhow::Error;
+
+    fn run<'a>(&mut self, mut function: Self::Input<'a>) -> Result<Self::Output<'a>, Self::Error> {
+        loop {
+            let mut changed = false;
+            for block in function.blocks.values_mut() {
+                changed |= simplify(&mut block.ops);
+            }
+            changed |= hoist_if_tails(&mut function);
+            if !changed {
+                break;
+            }
+        }
+
+        Ok(function)
+    }
+}
+
+/// Rewrite `ops` by applying the local rules of this pass, returning true if anything changed.
+///
+/// The rewritten block is built up one instruction at a time, and after each instruction is
+/// appended, rules are applied to the end of the rewritten block until none match. As the
+/// result of one rewrite can enable another with the instructions preceding it, e.g. removing
+/// `swap.1 swap.1` from `dup.0 swap.1 swap.1 drop`, this handles cascading rewrites in one pass.
+fn simplify(ops: &mut SmallVec<[Op; 4]>) -> bool {
+    let mut changed = false;
+    let mut output = SmallVec::<[Op; 4]>::with_capacity(ops.len());
+    for op in ops.drain(..) {
+        output.push(op);
+        while let Some((len, replacement)) = rewrite_tail(&output) {
+            output.truncate(output.len() - len);
+            output.extend(replacement);
+            changed = true;
+        }
+    }
+    *ops = output;
+    changed
+}
+
+/// If a rule matches the end of `ops`, return the number of instructions it replaces,
+/// and the instruction they are replaced with, if any
+fn rewrite_tail(ops: &[Op]) -> Option<(usize, Option<Op>)> {
+    match ops {
+        // `swap.n` exchanges the top of the stack with the element at depth `n`,
+        // so applying it twice restores the original order
+        [.., Op::Swap(a), Op::Swap(b)] | [.., Op::Swapw(a), Op::Swapw(b)] if a == b => {
+            Some((2, None))
+        }
+        // `movup.n` removes the element at depth `n` and places it on top, and `movdn.n`
+        // removes the top element and places it at depth `n`, so each undoes the other
+        [.., Op::Movup(a), Op::Movdn(b)]
+        | [.., Op::Movdn(a), Op::Movup(b)]
+        | [.., Op::Movupw(a), Op::Movdnw(b)]
+        | [.., Op::Movdnw(a), Op::Movupw(b)]
+            if a == b =>
+        {
+            Some((2, None))
+        }
+        // These push a copy of an element, or a constant, without reading anything else,
+        // so dropping the pushed value leaves the stack as it was
+        [.., Op::Dup(_), Op::Drop] | [.., Op::Dupw(_) | Op::Padw | Op::Pushw(_), Op::Dropw] => {
+            Some((2, None))
+        }
+        [.., push, Op::Drop] if push_value(push).is_some() => Some((2, None)),
+        // `add.0` and `sub.0` leave the top of the stack unchanged, as do `mul.1` and `div.1`
+        [.., Op::AddImm(imm) | Op::SubImm(imm)] if *imm == Felt::ZERO => Some((1, None)),
+        [.., Op::MulImm(imm) | Op::DivImm(imm)] if *imm == Felt::ONE => Some((1, None)),
+        // `incr` is the dedicated form of `add.1`
+        [.., Op::AddImm(imm)] if *imm == Felt::ONE => Some((1, Some(Op::Incr))),
+        // Pushing `c` and then popping it as the operand of an instruction is equivalent to
+        // the immediate form of that instruction, which takes `c` as its operand instead
+        [.., push, op] => {
+            let imm = push_value(push)?;
+            fold_immediate(imm, op).map(|op| (2, Some(op)))
+        }
+        _ => None,
+    }
+}
+
+/// If `op` pushes a single constant field element on the stack, return its value
+fn push_value(op: &Op) -> Option<Felt> {
+    match op {
+        Op::Push(imm) => Some(*imm),
+        Op::PushU8(imm) => Some(Felt::new(*imm as u64)),
+        Op::PushU16(imm) => Some(Felt::new(*imm as u64)),
+        Op::PushU32(imm) => Some(Felt::new(*imm as u64)),
+        _ => None,
+    }
+}
+
+/// Get the immediate form of `op` with `imm` as its topmost operand, if `op` has one, and `imm`
+/// is valid for it.
+///
+/// An immediate which would trap at runtime when pushed, e.g. a zero divisor, or a value that
+/// does not fit in 32 bits for a u32 operation, is never folded, as the immediate form would
+/// instead be rejected by the assembler.
+fn fold_immediate(imm: Felt, op: &Op) -> Option<Op> {
+    let is_nonzero = imm != Felt::ZERO;
+    let bool_imm = match imm.as_int() {
+        0 => Some(false),
+        1 => Some(true),
+        _ => None,
+    };
+    let u32_imm = u32::try_from(imm.as_int()).ok();
+    let shift_imm = u32_imm.filter(|imm| *imm < 32);
+    let divisor_imm = u32_imm.filter(|imm| *imm != 0);
+    match op {
+        Op::Add => Some(Op::AddImm(imm)),
+        Op::Sub => Some(Op::SubImm(imm)),
+        Op::Mul => Some(Op::MulImm(imm)),
+        Op::Div if is_nonzero => Some(Op::DivImm(imm)),
+        Op::And => bool_imm.map(Op::AndImm),
+        Op::Or => bool_imm.map(Op::OrImm),
+        Op::Xor => bool_imm.map(Op::XorImm),
+        Op::Eq => Some(Op::EqImm(imm)),
+        Op::Neq => Some(Op::NeqImm(imm)),
+        Op::Gt => Some(Op::GtImm(imm)),
+        Op::Gte => Some(Op::GteImm(imm)),
+        Op::Lt => Some(Op::LtImm(imm)),
+        Op::Lte => Some(Op::LteImm(imm)),
+        Op::MemLoad => u32_imm.map(Op::MemLoadImm),
+        Op::MemLoadw => u32_imm.map(Op::MemLoadwImm),
+        Op::MemStore => u32_imm.map(Op::MemStoreImm),
+        Op::MemStorew => u32_imm.map(Op::MemStorewImm),
+        Op::U32CheckedAdd => u32_imm.map(Op::U32CheckedAddImm),
+        Op::U32OverflowingAdd => u32_imm.map(Op::U32OverflowingAddImm),
+        Op::U32WrappingAdd => u32_imm.map(Op::U32WrappingAddImm),
+        Op::U32CheckedSub => u32_imm.map(Op::U32CheckedSubImm),
+        Op::U32OverflowingSub => u32_imm.map(Op::U32OverflowingSubImm),
+        Op::U32WrappingSub => u32_imm.map(Op::U32WrappingSubImm),
+        Op::U32CheckedMul => u32_imm.map(Op::U32CheckedMulImm),
+        Op::U32OverflowingMul => u32_imm.map(Op::U32OverflowingMulImm),
+        Op::U32WrappingMul => u32_imm.map(Op::U32WrappingMulImm),
+        Op::U32CheckedDiv => divisor_imm.map(Op::U32CheckedDivImm),
+        Op::U32UncheckedDiv => divisor_imm.map(Op::U32UncheckedDivImm),
+        Op::U32CheckedMod => divisor_imm.map(Op::U32CheckedModImm),
+        Op::U32UncheckedMod => divisor_imm.map(Op::U32UncheckedModImm),
+        Op::U32CheckedDivMod => divisor_imm.map(Op::U32CheckedDivModImm),
+        Op::U32UncheckedDivMod => divisor_imm.map(Op::U32UncheckedDivModImm),
+        Op::U32CheckedShl => shift_imm.map(Op::U32CheckedShlImm),
+        Op::U32UncheckedShl => shift_imm.map(Op::U32UncheckedShlImm),
+        Op::U32CheckedShr => shift_imm.map(Op::U32CheckedShrImm),
+        Op::U32UncheckedShr => shift_imm.map(Op::U32UncheckedShrImm),
+        Op::U32CheckedRotl => shift_imm.map(Op::U32CheckedRotlImm),
+        Op::U32UncheckedRotl => shift_imm.map(Op::U32UncheckedRotlImm),
+        Op::U32CheckedRotr => shift_imm.map(Op::U32CheckedRotrImm),
+        Op::U32UncheckedRotr => shift_imm.map(Op::U32UncheckedRotrImm),
+        Op::U32Eq => u32_imm.map(Op::U32EqImm),
+        Op::U32Neq => u32_imm.map(Op::U32NeqImm),
+        _ => None,
+    }
+}
+
+/// Move the instructions which end both branches of an `if.true` out of it, placing them
+/// immediately after it, returning true if anything changed.
+///
+/// Both branches begin with the same operand stack, and each must leave the stack in the same
+/// shape for the code following the `if.true`, so executing a common suffix of both branches
+/// after the branch is taken has the same effect as executing it at the end of either one.
+///
+/// Only blocks referenced by a single instruction are modified, and control flow instructions
+/// are never hoisted, as they would introduce new references to the blocks they contain.
+fn hoist_if_tails(function: &mut masm::Function) -> bool {
+    let mut refs = FxHashMap::<BlockId, usize>::default();
+    for block in function.blocks.values() {
+        for op in block.ops.iter() {
+            match op {
+                Op::If(then_blk, else_blk) => {
+                    *refs.entry(*then_blk).or_default() += 1;
+                    *refs.entry(*else_blk).or_default() += 1;
+                }
+                Op::While(blk) | Op::Repeat(_, blk) => {
+                    *refs.entry(*blk).or_default() += 1;
+                }
+                _ => (),
+            }
+        }
+    }
+
+    let mut changed = false;
+    let blocks = function.blocks.keys().collect::<SmallVec<[BlockId; 8]>>();
+    for block in blocks.into_iter() {
+        let mut index = 0;
+        while index < function.blocks[block].ops.len() {
+            let op = function.blocks[block].ops[index];
+            index += 1;
+            let Op::If(then_blk, else_blk) = op else {
+                continue;
+            };
+            if then_blk == else_blk || refs[&then_blk] != 1 || refs[&else_blk] != 1 {
+                continue;
+            }
+
+            let then_ops = &function.blocks[then_blk].ops;
+            let else_ops = &function.blocks[else_blk].ops;
+            let len = then_ops
+                .iter()
+                .rev()
+                .zip(else_ops.iter().rev())
+                .take_while(|(a, b)| a == b && !is_control_flow(a))
+                .count();
+            if len == 0 {
+                continue;
+            }
+
+            let tail = SmallVec::<[Op; 4]>::from_slice(&then_ops[(then_ops.len() - len)..]);
+            for blk in [then_blk, else_blk] {
+                let ops = &mut function.blocks[blk].ops;
+                ops.truncate(ops.len() - len);
+            }
+            function.blocks[block].ops.insert_many(index, tail);
+            changed = true;
+        }
+    }
+
+    changed
+}
+
+#[inline]
+fn is_control_flow(op: &Op) -> bool {
+    matches!(op, Op::If(_, _) | Op::While(_) | Op::Repeat(_, _))
+}
@@ -0,0 +1,188 @@
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::bail;
+use miden_hir_transform::{self as transform, PassManager, RewritePass};
+
+/// The names of the rewrites which can be added to a [Pipeline] by name
+const NAMED_PASSES: &[&str] = &["inline", "mem2reg", "sccp", "gvn", "dce", "simplify-cfg"];
+
+/// The optimization level to compile at, which determines the rewrites in the
+/// default [Pipeline], see [Pipeline::with_opt_level]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum OptLevel {
+    /// No optimizations are applied
+    #[default]
+    O0,
+    /// Promote stack slots to SSA values, propagate constants, and remove dead code
+    O1,
+    /// As `O1`, but also inline small functions into their callers, and eliminate
+    /// redundant computations
+    O2,
+    /// As `O2`, but only inline functions hinted to always be inlined, and private functions
+    /// with a single call site, which are removed once inlined and so do not grow the program.
+    /// The program is also cleaned up again once the control flow graph has been simplified,
+    /// as every block left at that point may be duplicated by [transform::Treeify]
+    Os,
+}
+impl FromStr for OptLevel {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "0" => Ok(Self::O0),
+            "1" => Ok(Self::O1),
+            "2" => Ok(Self::O2),
+            "s" => Ok(Self::Os),
+            _ => Err(()),
+        }
+    }
+}
+impl fmt::Display for OptLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::O0 => f.write_str("-O0"),
+            Self::O1 => f.write_str("-O1"),
+            Self::O2 => f.write_str("-O2"),
+            Self::Os => f.write_str("-Os"),
+        }
+    }
+}
+
+/// A [Pipeline] describes the rewrites applied to each function by [crate::MasmCompiler],
+/// in the order they are applied.
+///
+/// Rewrites are added either by name, see [Pipeline::add_named] for the names recognized,
+/// or directly, which allows frontends to apply their own rewrites before stackification.
+///
+/// A pipeline may also inline calls between the functions of the program, see
+/// [Pipeline::inline], which is done before any of the rewrites are applied.
+///
+/// The rewrites needed to lower a function to a form which can be stackified, i.e.
+/// [transform::LowerSwitch], [transform::SplitCriticalEdges], [transform::Treeify] and
+/// [transform::InlineBlocks], are always applied after those in the pipeline.
+#[derive(Default)]
+pub struct Pipeline {
+    inliner: Option<transform::Inliner>,
+    rewrites: PassManager,
+    names: Vec<&'static str>,
+}
+impl Pipeline {
+    /// Create an empty pipeline, i.e. one which only applies the required rewrites
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create the default pipeline for `level`
+    pub fn with_opt_level(level: OptLevel) -> Self {
+        let mut pipeline = Self::new();
+        match level {
+            OptLevel::O0 => (),
+            OptLevel::O1 => {
+                pipeline
+                    .add(transform::Mem2Reg)
+                    .add(transform::Sccp)
+                    .add(transform::DeadCodeElimination)
+                    .add(transform::SimplifyCfg);
+            }
+            OptLevel::O2 => {
+                pipeline
+                    .inline(transform::Inliner::default())
+                    .add(transform::Mem2Reg)
+                    .add(transform::Sccp)
+                    .add(transform::GlobalValueNumbering)
+                    .add(transform::DeadCodeElimination)
+                    .add(transform::SimplifyCfg);
+            }
+            OptLevel::Os => {
+                pipeline
+                    .inline(transform::Inliner { threshold: 0 })
+                    .add(transform::Mem2Reg)
+                    .add(transform::Sccp)
+                    .add(transform::GlobalValueNumbering)
+                    .add(transform::DeadCodeElimination)
+                    .add(transform::SimplifyCfg)
+                    .add(transform::DeadCodeElimination)
+                    .add(transform::SimplifyCfg);
+            }
+        }
+        pipeline
+    }
+
+    /// Inline calls between the functions of the program using `inliner`, before any
+    /// rewrites are applied to the functions themselves
+    pub fn inline(&mut self, inliner: transform::Inliner) -> &mut Self {
+        if self.inliner.replace(inliner).is_none() {
+            self.names.insert(0, "inline");
+        }
+        self
+    }
+
+    /// Append `pass` to the pipeline
+    pub fn add<P>(&mut self, pass: P) -> &mut Self
+    where
+        P: RewritePass<Error = anyhow::Error> + 'static,
+    {
+        self.names.push(pass.name());
+        self.rewrites.add(pass);
+        self
+    }
+
+    /// Append the rewrite called `name` to the pipeline, one of:
+    ///
+    /// * `inline`, see [transform::Inliner], which is always applied first
+    /// * `mem2reg`, see [transform::Mem2Reg]
+    /// * `sccp`, see [transform::Sccp]
+    /// * `gvn`, see [transform::GlobalValueNumbering]
+    /// * `dce`, see [transform::DeadCodeElimination]
+    /// * `simplify-cfg`, see [transform::SimplifyCfg]
+    pub fn add_named(&mut self, name: &str) -> anyhow::Result<&mut Self> {
+        match name {
+            "inline" => Ok(self.inline(transform::Inliner::default())),
+            "mem2reg" => Ok(self.add(transform::Mem2Reg)),
+            "sccp" => Ok(self.add(transform::Sccp)),
+            "gvn" => Ok(self.add(transform::GlobalValueNumbering)),
+            "dce" => Ok(self.add(transform::DeadCodeElimination)),
+            "simplify-cfg" => Ok(self.add(transform::SimplifyCfg)),
+            _ => bail!(
+                "unknown pass '{}', expected one of [{}]",
+                name,
+                NAMED_PASSES.join(", ")
+            ),
+        }
+    }
+
+    /// Get the names of the rewrites in this pipeline, in the order they are applied
+    pub fn passes(&self) -> &[&'static str] {
+        self.names.as_slice()
+    }
+
+    /// Split this pipeline into the [transform::Inliner] to run on the program, if any,
+    /// and a [PassManager] which applies the rewrites in it, followed by the rewrites
+    /// required for stackification
+    pub(crate) fn into_parts(self) -> (Option<transform::Inliner>, PassManager) {
+        let mut rewrites = self.rewrites;
+        rewrites
+            .add(transform::LowerSwitch)
+            .add(transform::SplitCriticalEdges)
+            .add(transform::Treeify)
+            .add(transform::InlineBlocks);
+        (self.inliner, rewrites)
+    }
+}
+impl fmt::Debug for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.names.iter()).finish()
+    }
+}
+impl FromStr for Pipeline {
+    type Err = anyhow::Error;
+
+    /// Parse a comma-separated list of rewrite names, see [Pipeline::add_named]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut pipeline = Self::new();
+        for name in s.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            pipeline.add_named(name)?;
+        }
+        Ok(pipeline)
+    }
+}
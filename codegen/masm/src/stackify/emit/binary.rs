@@ -1,11 +1,11 @@
-use miden_hir::{assert_matches, Immediate, Overflow, Type};
+use miden_hir::{assert_matches, Immediate, Opcode, Overflow, Type};
 
-use crate::masm::Op;
+use crate::{masm::Op, CodegenError};
 
 use super::OpEmitter;
 
 impl<'a> OpEmitter<'a> {
-    pub fn eq(&mut self) {
+    pub fn eq(&mut self) -> Result<(), CodegenError> {
         let lhs = self.pop().expect("operand stack is empty");
         let rhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
@@ -15,7 +15,7 @@ impl<'a> OpEmitter<'a> {
                 self.eq_i128();
             }
             Type::I64 | Type::U64 => {
-                self.emit(Op::Eqw);
+                self.eq_int64();
             }
             Type::Felt => {
                 self.emit(Op::Eq);
@@ -30,23 +30,24 @@ impl<'a> OpEmitter<'a> {
             | Type::I1 => {
                 self.emit(Op::U32Eq);
             }
-            ty => unimplemented!("eq is not yet implemented for {ty}"),
+            ty => return Err(self.unsupported(Opcode::Eq, ty.clone())),
         }
         self.push(Type::I1);
+        Ok(())
     }
 
-    pub fn eq_imm(&mut self, imm: Immediate) {
+    pub fn eq_imm(&mut self, imm: Immediate) -> Result<(), CodegenError> {
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
         assert_eq!(ty, imm.ty(), "expected eq operands to be the same type");
         match &ty {
             Type::I128 => {
-                self.push_immediate(imm);
+                self.push_immediate(imm)?;
                 self.eq_i128();
             }
             Type::I64 | Type::U64 => {
-                self.push_immediate(imm);
-                self.emit(Op::Eqw);
+                self.push_immediate(imm)?;
+                self.eq_int64();
             }
             Type::Felt => {
                 self.emit(Op::EqImm(imm.as_felt().unwrap()));
@@ -57,12 +58,13 @@ impl<'a> OpEmitter<'a> {
             Type::I32 | Type::I16 | Type::I8 => {
                 self.emit(Op::U32EqImm(imm.as_i64().unwrap() as u64 as u32));
             }
-            ty => unimplemented!("eq is not yet implemented for {ty}"),
+            ty => return Err(self.unsupported(Opcode::Eq, ty.clone())),
         }
         self.push(Type::I1);
+        Ok(())
     }
 
-    pub fn neq(&mut self) {
+    pub fn neq(&mut self) -> Result<(), CodegenError> {
         let lhs = self.pop().expect("operand stack is empty");
         let rhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
@@ -72,7 +74,7 @@ impl<'a> OpEmitter<'a> {
                 self.eq_i128();
             }
             Type::I64 | Type::U64 => {
-                self.emit_all(&[Op::Eqw, Op::Not]);
+                self.neq_int64();
             }
             Type::Felt => {
                 self.emit(Op::Neq);
@@ -87,23 +89,24 @@ impl<'a> OpEmitter<'a> {
             | Type::I1 => {
                 self.emit(Op::U32Neq);
             }
-            ty => unimplemented!("neq is not yet implemented for {ty}"),
+            ty => return Err(self.unsupported(Opcode::Neq, ty.clone())),
         }
         self.push(Type::I1);
+        Ok(())
     }
 
-    pub fn neq_imm(&mut self, imm: Immediate) {
+    pub fn neq_imm(&mut self, imm: Immediate) -> Result<(), CodegenError> {
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
         assert_eq!(ty, imm.ty(), "expected neq operands to be the same type");
         match &ty {
             Type::I128 => {
-                self.push_immediate(imm);
+                self.push_immediate(imm)?;
                 self.neq_i128();
             }
             Type::I64 | Type::U64 => {
-                self.push_immediate(imm);
-                self.emit_all(&[Op::Eqw, Op::Not]);
+                self.push_immediate(imm)?;
+                self.neq_int64();
             }
             Type::Felt => {
                 self.emit(Op::NeqImm(imm.as_felt().unwrap()));
@@ -114,12 +117,13 @@ impl<'a> OpEmitter<'a> {
             Type::I32 | Type::I16 | Type::I8 => {
                 self.emit(Op::U32NeqImm(imm.as_i64().unwrap() as u64 as u32));
             }
-            ty => unimplemented!("neq is not yet implemented for {ty}"),
+            ty => return Err(self.unsupported(Opcode::Neq, ty.clone())),
         }
         self.push(Type::I1);
+        Ok(())
     }
 
-    pub fn gt(&mut self) {
+    pub fn gt(&mut self) -> Result<(), CodegenError> {
         let lhs = self.pop().expect("operand stack is empty");
         let rhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
@@ -131,12 +135,13 @@ impl<'a> OpEmitter<'a> {
             Type::U32 | Type::U16 | Type::U8 | Type::I1 => {
                 self.emit(Op::U32CheckedGt);
             }
-            ty => unimplemented!("gt is not yet implemented for {ty}"),
+            ty => return Err(self.unsupported(Opcode::Gt, ty.clone())),
         }
         self.push(Type::I1);
+        Ok(())
     }
 
-    pub fn gt_imm(&mut self, imm: Immediate) {
+    pub fn gt_imm(&mut self, imm: Immediate) -> Result<(), CodegenError> {
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
         assert_eq!(ty, imm.ty(), "expected gt operands to be the same type");
@@ -147,12 +152,13 @@ impl<'a> OpEmitter<'a> {
             Type::U32 | Type::U16 | Type::U8 | Type::I1 => {
                 self.emit_all(&[Op::PushU32(imm.as_u32().unwrap()), Op::U32CheckedGt]);
             }
-            ty => unimplemented!("gt is not yet implemented for {ty}"),
+            ty => return Err(self.unsupported(Opcode::Gt, ty.clone())),
         }
         self.push(Type::I1);
+        Ok(())
     }
 
-    pub fn gte(&mut self) {
+    pub fn gte(&mut self) -> Result<(), CodegenError> {
         let lhs = self.pop().expect("operand stack is empty");
         let rhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
@@ -164,12 +170,13 @@ impl<'a> OpEmitter<'a> {
             Type::U32 | Type::U16 | Type::U8 | Type::I1 => {
                 self.emit(Op::U32CheckedGte);
             }
-            ty => unimplemented!("gte is not yet implemented for {ty}"),
+            ty => return Err(self.unsupported(Opcode::Gte, ty.clone())),
         }
         self.push(Type::I1);
+        Ok(())
     }
 
-    pub fn gte_imm(&mut self, imm: Immediate) {
+    pub fn gte_imm(&mut self, imm: Immediate) -> Result<(), CodegenError> {
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
         assert_eq!(ty, imm.ty(), "expected gte operands to be the same type");
@@ -180,12 +187,13 @@ impl<'a> OpEmitter<'a> {
             Type::U32 | Type::U16 | Type::U8 | Type::I1 => {
                 self.emit_all(&[Op::PushU32(imm.as_u32().unwrap()), Op::U32CheckedGte]);
             }
-            ty => unimplemented!("gte is not yet implemented for {ty}"),
+            ty => return Err(self.unsupported(Opcode::Gte, ty.clone())),
         }
         self.push(Type::I1);
+        Ok(())
     }
 
-    pub fn lt(&mut self) {
+    pub fn lt(&mut self) -> Result<(), CodegenError> {
         let lhs = self.pop().expect("operand stack is empty");
         let rhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
@@ -197,12 +205,13 @@ impl<'a> OpEmitter<'a> {
             Type::U32 | Type::U16 | Type::U8 | Type::I1 => {
                 self.emit(Op::U32CheckedLt);
             }
-            ty => unimplemented!("lt is not yet implemented for {ty}"),
+            ty => return Err(self.unsupported(Opcode::Lt, ty.clone())),
         }
         self.push(Type::I1);
+        Ok(())
     }
 
-    pub fn lt_imm(&mut self, imm: Immediate) {
+    pub fn lt_imm(&mut self, imm: Immediate) -> Result<(), CodegenError> {
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
         assert_eq!(ty, imm.ty(), "expected lt operands to be the same type");
@@ -213,12 +222,13 @@ impl<'a> OpEmitter<'a> {
             Type::U32 | Type::U16 | Type::U8 | Type::I1 => {
                 self.emit_all(&[Op::PushU32(imm.as_u32().unwrap()), Op::U32CheckedLt]);
             }
-            ty => unimplemented!("lt is not yet implemented for {ty}"),
+            ty => return Err(self.unsupported(Opcode::Lt, ty.clone())),
         }
         self.push(Type::I1);
+        Ok(())
     }
 
-    pub fn lte(&mut self) {
+    pub fn lte(&mut self) -> Result<(), CodegenError> {
         let lhs = self.pop().expect("operand stack is empty");
         let rhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
@@ -230,12 +240,13 @@ impl<'a> OpEmitter<'a> {
             Type::U32 | Type::U16 | Type::U8 | Type::I1 => {
                 self.emit(Op::U32CheckedLte);
             }
-            ty => unimplemented!("lte is not yet implemented for {ty}"),
+            ty => return Err(self.unsupported(Opcode::Lte, ty.clone())),
         }
         self.push(Type::I1);
+        Ok(())
     }
 
-    pub fn lte_imm(&mut self, imm: Immediate) {
+    pub fn lte_imm(&mut self, imm: Immediate) -> Result<(), CodegenError> {
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
         assert_eq!(ty, imm.ty(), "expected lte operands to be the same type");
@@ -246,12 +257,13 @@ impl<'a> OpEmitter<'a> {
             Type::U32 | Type::U16 | Type::U8 | Type::I1 => {
                 self.emit_all(&[Op::PushU32(imm.as_u32().unwrap()), Op::U32CheckedLte]);
             }
-            ty => unimplemented!("lte is not yet implemented for {ty}"),
+            ty => return Err(self.unsupported(Opcode::Lte, ty.clone())),
         }
         self.push(Type::I1);
+        Ok(())
     }
 
-    pub fn add(&mut self, overflow: Overflow) {
+    pub fn add(&mut self, overflow: Overflow) -> Result<(), CodegenError> {
         let lhs = self.pop().expect("operand stack is empty");
         let rhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
@@ -269,15 +281,16 @@ impl<'a> OpEmitter<'a> {
             ty @ (Type::U16 | Type::U8 | Type::I1) => {
                 self.add_uint(ty.size_in_bits() as u32, overflow);
             }
-            ty => unimplemented!("add is not yet implemented for {ty}"),
+            ty => return Err(self.unsupported(Opcode::Add, ty.clone())),
         }
         self.push(ty);
         if overflow.is_overflowing() {
             self.push(Type::I1);
         }
+        Ok(())
     }
 
-    pub fn add_imm(&mut self, imm: Immediate, overflow: Overflow) {
+    pub fn add_imm(&mut self, imm: Immediate, overflow: Overflow) -> Result<(), CodegenError> {
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
         assert_eq!(ty, imm.ty(), "expected add operands to be the same type");
@@ -286,7 +299,7 @@ impl<'a> OpEmitter<'a> {
                 self.emit(Op::AddImm(imm.as_felt().unwrap()));
             }
             Type::U64 => {
-                self.push_immediate(imm);
+                self.push_immediate(imm)?;
                 self.add_u64(overflow);
             }
             Type::U32 => {
@@ -295,15 +308,16 @@ impl<'a> OpEmitter<'a> {
             ty @ (Type::U16 | Type::U8 | Type::I1) => {
                 self.add_imm_uint(imm.as_u32().unwrap(), ty.size_in_bits() as u32, overflow);
             }
-            ty => unimplemented!("add is not yet implemented for {ty}"),
+            ty => return Err(self.unsupported(Opcode::Add, ty.clone())),
         }
         self.push(ty);
         if overflow.is_overflowing() {
             self.push(Type::I1);
         }
+        Ok(())
     }
 
-    pub fn sub(&mut self, overflow: Overflow) {
+    pub fn sub(&mut self, overflow: Overflow) -> Result<(), CodegenError> {
         let lhs = self.pop().expect("operand stack is empty");
         let rhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
@@ -321,15 +335,16 @@ impl<'a> OpEmitter<'a> {
             ty @ (Type::U16 | Type::U8 | Type::I1) => {
                 self.sub_uint(ty.size_in_bits() as u32, overflow);
             }
-            ty => unimplemented!("sub is not yet implemented for {ty}"),
+            ty => return Err(self.unsupported(Opcode::Sub, ty.clone())),
         }
         self.push(ty);
         if overflow.is_overflowing() {
             self.push(Type::I1);
         }
+        Ok(())
     }
 
-    pub fn sub_imm(&mut self, imm: Immediate, overflow: Overflow) {
+    pub fn sub_imm(&mut self, imm: Immediate, overflow: Overflow) -> Result<(), CodegenError> {
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
         assert_eq!(ty, imm.ty(), "expected sub operands to be the same type");
@@ -338,7 +353,7 @@ impl<'a> OpEmitter<'a> {
                 self.emit(Op::SubImm(imm.as_felt().unwrap()));
             }
             Type::U64 => {
-                self.push_immediate(imm);
+                self.push_immediate(imm)?;
                 self.sub_u64(overflow);
             }
             Type::U32 => {
@@ -347,15 +362,16 @@ impl<'a> OpEmitter<'a> {
             ty @ (Type::U16 | Type::U8 | Type::I1) => {
                 self.sub_imm_uint(imm.as_u32().unwrap(), ty.size_in_bits() as u32, overflow);
             }
-            ty => unimplemented!("sub is not yet implemented for {ty}"),
+            ty => return Err(self.unsupported(Opcode::Sub, ty.clone())),
         }
         self.push(ty);
         if overflow.is_overflowing() {
             self.push(Type::I1);
         }
+        Ok(())
     }
 
-    pub fn mul(&mut self, overflow: Overflow) {
+    pub fn mul(&mut self, overflow: Overflow) -> Result<(), CodegenError> {
         let rhs = self.pop().expect("operand stack is empty");
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
@@ -374,7 +390,7 @@ impl<'a> OpEmitter<'a> {
                 // z = z2 * (2^63)^2 + z1 * 2^63 + z0
                 //
                 // We assume the stack holds two words representing x and y, with y on top of the stack
-                todo!()
+                return Err(self.unsupported(Opcode::Mul, Type::I128));
             }
             Type::U64 => self.mul_u64(overflow),
             Type::Felt => {
@@ -392,21 +408,22 @@ impl<'a> OpEmitter<'a> {
             ty if !ty.is_integer() => {
                 panic!("invalid binary operand: mul expects integer operands, got {ty}")
             }
-            ty => unimplemented!("mul for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::Mul, ty.clone())),
         }
         self.push(ty);
         if overflow.is_overflowing() {
             self.push(Type::I1);
         }
+        Ok(())
     }
 
-    pub fn mul_imm(&mut self, imm: Immediate, overflow: Overflow) {
+    pub fn mul_imm(&mut self, imm: Immediate, overflow: Overflow) -> Result<(), CodegenError> {
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
         assert_eq!(ty, imm.ty(), "expected mul operands to be the same type");
         match &ty {
             Type::U64 => {
-                self.push_immediate(imm);
+                self.push_immediate(imm)?;
                 self.mul_u64(overflow);
             }
             Type::Felt => {
@@ -424,15 +441,16 @@ impl<'a> OpEmitter<'a> {
             ty if !ty.is_integer() => {
                 panic!("invalid binary operand: mul expects integer operands, got {ty}")
             }
-            ty => unimplemented!("mul for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::Mul, ty.clone())),
         }
         self.push(ty);
         if overflow.is_overflowing() {
             self.push(Type::I1);
         }
+        Ok(())
     }
 
-    pub fn checked_div(&mut self) {
+    pub fn checked_div(&mut self) -> Result<(), CodegenError> {
         let rhs = self.pop().expect("operand stack is empty");
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
@@ -449,19 +467,20 @@ impl<'a> OpEmitter<'a> {
             ty if !ty.is_integer() => {
                 panic!("invalid binary operand: div expects integer operands, got {ty}")
             }
-            ty => unimplemented!("div for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::Div, ty.clone())),
         }
         self.push(ty);
+        Ok(())
     }
 
-    pub fn checked_div_imm(&mut self, imm: Immediate) {
+    pub fn checked_div_imm(&mut self, imm: Immediate) -> Result<(), CodegenError> {
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
         assert_eq!(ty, imm.ty(), "expected div operands to be the same type");
         match &ty {
             Type::U64 => {
                 assert_ne!(imm.as_u64().unwrap(), 0, "invalid division by zero");
-                self.push_immediate(imm);
+                self.push_immediate(imm)?;
                 self.checked_div_u64();
             }
             Type::Felt => {
@@ -474,12 +493,13 @@ impl<'a> OpEmitter<'a> {
             ty if !ty.is_integer() => {
                 panic!("invalid binary operand: div expects integer operands, got {ty}")
             }
-            ty => unimplemented!("div for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::Div, ty.clone())),
         }
         self.push(ty);
+        Ok(())
     }
 
-    pub fn unchecked_div(&mut self) {
+    pub fn unchecked_div(&mut self) -> Result<(), CodegenError> {
         let rhs = self.pop().expect("operand stack is empty");
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
@@ -493,19 +513,20 @@ impl<'a> OpEmitter<'a> {
             ty if !ty.is_integer() => {
                 panic!("invalid binary operand: div expects integer operands, got {ty}")
             }
-            ty => unimplemented!("div for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::Div, ty.clone())),
         }
         self.push(ty);
+        Ok(())
     }
 
-    pub fn unchecked_div_imm(&mut self, imm: Immediate) {
+    pub fn unchecked_div_imm(&mut self, imm: Immediate) -> Result<(), CodegenError> {
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
         assert_eq!(ty, imm.ty(), "expected div operands to be the same type");
         match &ty {
             Type::U64 => {
                 assert_ne!(imm.as_u64().unwrap(), 0, "invalid division by zero");
-                self.push_immediate(imm);
+                self.push_immediate(imm)?;
                 self.unchecked_div_u64();
             }
             Type::Felt => {
@@ -518,12 +539,13 @@ impl<'a> OpEmitter<'a> {
             ty if !ty.is_integer() => {
                 panic!("invalid binary operand: div expects integer operands, got {ty}")
             }
-            ty => unimplemented!("div for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::Div, ty.clone())),
         }
         self.push(ty);
+        Ok(())
     }
 
-    pub fn checked_mod(&mut self) {
+    pub fn checked_mod(&mut self) -> Result<(), CodegenError> {
         let rhs = self.pop().expect("operand stack is empty");
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
@@ -537,19 +559,20 @@ impl<'a> OpEmitter<'a> {
             ty if !ty.is_integer() => {
                 panic!("invalid binary operand: mod expects integer operands, got {ty}")
             }
-            ty => unimplemented!("mod for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::Mod, ty.clone())),
         }
         self.push(ty);
+        Ok(())
     }
 
-    pub fn checked_mod_imm(&mut self, imm: Immediate) {
+    pub fn checked_mod_imm(&mut self, imm: Immediate) -> Result<(), CodegenError> {
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
         assert_eq!(ty, imm.ty(), "expected mod operands to be the same type");
         match &ty {
             Type::U64 => {
                 assert_ne!(imm.as_u64().unwrap(), 0, "invalid division by zero");
-                self.push_immediate(imm);
+                self.push_immediate(imm)?;
                 self.checked_mod_u64();
             }
             Type::U32 => self.checked_mod_imm_u32(imm.as_u32().unwrap()),
@@ -559,12 +582,13 @@ impl<'a> OpEmitter<'a> {
             ty if !ty.is_integer() => {
                 panic!("invalid binary operand: mod expects integer operands, got {ty}")
             }
-            ty => unimplemented!("mod for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::Mod, ty.clone())),
         }
         self.push(ty);
+        Ok(())
     }
 
-    pub fn unchecked_mod(&mut self) {
+    pub fn unchecked_mod(&mut self) -> Result<(), CodegenError> {
         let rhs = self.pop().expect("operand stack is empty");
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
@@ -578,19 +602,20 @@ impl<'a> OpEmitter<'a> {
             ty if !ty.is_integer() => {
                 panic!("invalid binary operand: mod expects integer operands, got {ty}")
             }
-            ty => unimplemented!("mod for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::Mod, ty.clone())),
         }
         self.push(ty);
+        Ok(())
     }
 
-    pub fn unchecked_mod_imm(&mut self, imm: Immediate) {
+    pub fn unchecked_mod_imm(&mut self, imm: Immediate) -> Result<(), CodegenError> {
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
         assert_eq!(ty, imm.ty(), "expected mod operands to be the same type");
         match &ty {
             Type::U64 => {
                 assert_ne!(imm.as_u64().unwrap(), 0, "invalid division by zero");
-                self.push_immediate(imm);
+                self.push_immediate(imm)?;
                 self.unchecked_mod_u64();
             }
             Type::U32 => self.unchecked_mod_imm_u32(imm.as_u32().unwrap()),
@@ -600,12 +625,13 @@ impl<'a> OpEmitter<'a> {
             ty if !ty.is_integer() => {
                 panic!("invalid binary operand: mod expects integer operands, got {ty}")
             }
-            ty => unimplemented!("mod for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::Mod, ty.clone())),
         }
         self.push(ty);
+        Ok(())
     }
 
-    pub fn checked_divmod(&mut self) {
+    pub fn checked_divmod(&mut self) -> Result<(), CodegenError> {
         let rhs = self.pop().expect("operand stack is empty");
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
@@ -619,20 +645,21 @@ impl<'a> OpEmitter<'a> {
             ty if !ty.is_integer() => {
                 panic!("invalid binary operand: divmod expects integer operands, got {ty}")
             }
-            ty => unimplemented!("divmod for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::DivMod, ty.clone())),
         }
         self.push(ty.clone());
         self.push(ty);
+        Ok(())
     }
 
-    pub fn checked_divmod_imm(&mut self, imm: Immediate) {
+    pub fn checked_divmod_imm(&mut self, imm: Immediate) -> Result<(), CodegenError> {
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
         assert_eq!(ty, imm.ty(), "expected divmod operands to be the same type");
         match &ty {
             Type::U64 => {
                 assert_ne!(imm.as_u64().unwrap(), 0, "invalid division by zero");
-                self.push_immediate(imm);
+                self.push_immediate(imm)?;
                 self.checked_divmod_u64();
             }
             Type::U32 => self.checked_divmod_imm_u32(imm.as_u32().unwrap()),
@@ -642,13 +669,14 @@ impl<'a> OpEmitter<'a> {
             ty if !ty.is_integer() => {
                 panic!("invalid binary operand: divmod expects integer operands, got {ty}")
             }
-            ty => unimplemented!("divmod for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::DivMod, ty.clone())),
         }
         self.push(ty.clone());
         self.push(ty);
+        Ok(())
     }
 
-    pub fn unchecked_divmod(&mut self) {
+    pub fn unchecked_divmod(&mut self) -> Result<(), CodegenError> {
         let rhs = self.pop().expect("operand stack is empty");
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
@@ -662,20 +690,21 @@ impl<'a> OpEmitter<'a> {
             ty if !ty.is_integer() => {
                 panic!("invalid binary operand: divmod expects integer operands, got {ty}")
             }
-            ty => unimplemented!("divmod for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::DivMod, ty.clone())),
         }
         self.push(ty.clone());
         self.push(ty);
+        Ok(())
     }
 
-    pub fn unchecked_divmod_imm(&mut self, imm: Immediate) {
+    pub fn unchecked_divmod_imm(&mut self, imm: Immediate) -> Result<(), CodegenError> {
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
         assert_eq!(ty, imm.ty(), "expected divmod operands to be the same type");
         match &ty {
             Type::U64 => {
                 assert_ne!(imm.as_u64().unwrap(), 0, "invalid division by zero");
-                self.push_immediate(imm);
+                self.push_immediate(imm)?;
                 self.unchecked_divmod_u64();
             }
             Type::U32 => self.unchecked_divmod_imm_u32(imm.as_u32().unwrap()),
@@ -685,19 +714,20 @@ impl<'a> OpEmitter<'a> {
             ty if !ty.is_integer() => {
                 panic!("invalid binary operand: divmod expects integer operands, got {ty}")
             }
-            ty => unimplemented!("divmod for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::DivMod, ty.clone())),
         }
         self.push(ty.clone());
         self.push(ty);
+        Ok(())
     }
 
-    pub fn exp(&mut self) {
+    pub fn exp(&mut self) -> Result<(), CodegenError> {
         let rhs = self.pop().expect("operand stack is empty");
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
         assert_eq!(ty, rhs.ty(), "expected exp operands to be the same type");
         match &ty {
-            Type::U64 => todo!("exponentiation by squaring"),
+            Type::U64 => return Err(self.unsupported(Opcode::Exp, Type::U64)),
             Type::Felt => {
                 self.emit(Op::Exp);
             }
@@ -711,12 +741,13 @@ impl<'a> OpEmitter<'a> {
             ty if !ty.is_integer() => {
                 panic!("invalid binary operand: exp expects integer operands, got {ty}")
             }
-            ty => unimplemented!("mod for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::Exp, ty.clone())),
         }
         self.push(ty);
+        Ok(())
     }
 
-    pub fn exp_imm(&mut self, imm: Immediate) {
+    pub fn exp_imm(&mut self, imm: Immediate) -> Result<(), CodegenError> {
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
         assert_eq!(ty, imm.ty(), "expected exp operands to be the same type");
@@ -726,7 +757,7 @@ impl<'a> OpEmitter<'a> {
             .try_into()
             .expect("invalid exponent: must be value < 64");
         match &ty {
-            Type::U64 => todo!("exponentiation by squaring"),
+            Type::U64 => return Err(self.unsupported(Opcode::Exp, Type::U64)),
             Type::Felt => {
                 self.emit(Op::ExpImm(exp));
             }
@@ -740,9 +771,10 @@ impl<'a> OpEmitter<'a> {
             ty if !ty.is_integer() => {
                 panic!("invalid binary operand: exp expects integer operands, got {ty}")
             }
-            ty => unimplemented!("mod for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::Exp, ty.clone())),
         }
         self.push(ty);
+        Ok(())
     }
 
     pub fn and(&mut self) {
@@ -802,7 +834,7 @@ impl<'a> OpEmitter<'a> {
         self.push(ty);
     }
 
-    pub fn band(&mut self) {
+    pub fn band(&mut self) -> Result<(), CodegenError> {
         let rhs = self.pop().expect("operand stack is empty");
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
@@ -814,18 +846,19 @@ impl<'a> OpEmitter<'a> {
             ty if !ty.is_integer() => {
                 panic!("invalid binary operand: band expects integer operands, got {ty}")
             }
-            ty => unimplemented!("band for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::Band, ty.clone())),
         }
         self.push(ty);
+        Ok(())
     }
 
-    pub fn band_imm(&mut self, imm: Immediate) {
+    pub fn band_imm(&mut self, imm: Immediate) -> Result<(), CodegenError> {
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
         assert_eq!(ty, imm.ty(), "expected band operands to be the same type");
         match &ty {
             Type::U64 | Type::I64 => {
-                self.push_immediate(imm);
+                self.push_immediate(imm)?;
                 self.band_int64();
             }
             Type::U32 | Type::U16 | Type::U8 => self.band_imm_u32(imm.as_u32().unwrap()),
@@ -836,12 +869,13 @@ impl<'a> OpEmitter<'a> {
             ty if !ty.is_integer() => {
                 panic!("invalid binary operand: band expects integer operands, got {ty}")
             }
-            ty => unimplemented!("band for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::Band, ty.clone())),
         }
         self.push(ty);
+        Ok(())
     }
 
-    pub fn bor(&mut self) {
+    pub fn bor(&mut self) -> Result<(), CodegenError> {
         let rhs = self.pop().expect("operand stack is empty");
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
@@ -853,18 +887,19 @@ impl<'a> OpEmitter<'a> {
             ty if !ty.is_integer() => {
                 panic!("invalid binary operand: bor expects integer operands, got {ty}")
             }
-            ty => unimplemented!("bor for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::Bor, ty.clone())),
         }
         self.push(ty);
+        Ok(())
     }
 
-    pub fn bor_imm(&mut self, imm: Immediate) {
+    pub fn bor_imm(&mut self, imm: Immediate) -> Result<(), CodegenError> {
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
         assert_eq!(ty, imm.ty(), "expected bor operands to be the same type");
         match &ty {
             Type::U64 | Type::I64 => {
-                self.push_immediate(imm);
+                self.push_immediate(imm)?;
                 self.bor_int64();
             }
             Type::U32 | Type::U16 | Type::U8 => self.bor_imm_u32(imm.as_u32().unwrap()),
@@ -875,12 +910,13 @@ impl<'a> OpEmitter<'a> {
             ty if !ty.is_integer() => {
                 panic!("invalid binary operand: bor expects integer operands, got {ty}")
             }
-            ty => unimplemented!("bor for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::Bor, ty.clone())),
         }
         self.push(ty);
+        Ok(())
     }
 
-    pub fn bxor(&mut self) {
+    pub fn bxor(&mut self) -> Result<(), CodegenError> {
         let rhs = self.pop().expect("operand stack is empty");
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
@@ -896,18 +932,19 @@ impl<'a> OpEmitter<'a> {
             ty if !ty.is_integer() => {
                 panic!("invalid binary operand: bxor expects integer operands, got {ty}")
             }
-            ty => unimplemented!("bxor for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::Bxor, ty.clone())),
         }
         self.push(ty);
+        Ok(())
     }
 
-    pub fn bxor_imm(&mut self, imm: Immediate) {
+    pub fn bxor_imm(&mut self, imm: Immediate) -> Result<(), CodegenError> {
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
         assert_eq!(ty, imm.ty(), "expected bxor operands to be the same type");
         match &ty {
             Type::U64 | Type::I64 => {
-                self.push_immediate(imm);
+                self.push_immediate(imm)?;
                 self.bxor_int64();
             }
             Type::U32 => self.bxor_imm_u32(imm.as_u32().unwrap()),
@@ -924,12 +961,13 @@ impl<'a> OpEmitter<'a> {
             ty if !ty.is_integer() => {
                 panic!("invalid binary operand: bxor expects integer operands, got {ty}")
             }
-            ty => unimplemented!("bxor for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::Bxor, ty.clone())),
         }
         self.push(ty);
+        Ok(())
     }
 
-    pub fn shl(&mut self) {
+    pub fn shl(&mut self) -> Result<(), CodegenError> {
         let rhs = self.pop().expect("operand stack is empty");
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
@@ -944,12 +982,13 @@ impl<'a> OpEmitter<'a> {
             ty if !ty.is_integer() => {
                 panic!("invalid binary operand: shl expects integer operands, got {ty}")
             }
-            ty => unimplemented!("shl for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::Shl, ty.clone())),
         }
         self.push(ty);
+        Ok(())
     }
 
-    pub fn shl_imm(&mut self, imm: Immediate) {
+    pub fn shl_imm(&mut self, imm: Immediate) -> Result<(), CodegenError> {
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
         assert_eq!(ty, imm.ty(), "expected shl operands to be the same type");
@@ -959,7 +998,7 @@ impl<'a> OpEmitter<'a> {
                     imm.as_u64().unwrap() < 64,
                     "invalid shift value: must be < 64"
                 );
-                self.push_immediate(imm);
+                self.push_immediate(imm)?;
                 self.shl_u64();
             }
             Type::U32 => self.shl_imm_u32(imm.as_u32().unwrap()),
@@ -970,12 +1009,13 @@ impl<'a> OpEmitter<'a> {
             ty if !ty.is_integer() => {
                 panic!("invalid binary operand: shl expects integer operands, got {ty}")
             }
-            ty => unimplemented!("shl for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::Shl, ty.clone())),
         }
         self.push(ty);
+        Ok(())
     }
 
-    pub fn shr(&mut self) {
+    pub fn shr(&mut self) -> Result<(), CodegenError> {
         let rhs = self.pop().expect("operand stack is empty");
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
@@ -986,12 +1026,13 @@ impl<'a> OpEmitter<'a> {
             ty if !ty.is_integer() => {
                 panic!("invalid binary operand: shr expects integer operands, got {ty}")
             }
-            ty => unimplemented!("shr for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::Shr, ty.clone())),
         }
         self.push(ty);
+        Ok(())
     }
 
-    pub fn shr_imm(&mut self, imm: Immediate) {
+    pub fn shr_imm(&mut self, imm: Immediate) -> Result<(), CodegenError> {
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
         assert_eq!(ty, imm.ty(), "expected shr operands to be the same type");
@@ -999,19 +1040,20 @@ impl<'a> OpEmitter<'a> {
             Type::U64 => {
                 let shift = imm.as_u64().unwrap();
                 assert!(shift < 64, "invalid shift value: must be < 64, got {shift}");
-                self.push_immediate(imm);
+                self.push_immediate(imm)?;
                 self.shr_u64();
             }
             Type::U32 | Type::U16 | Type::U8 => self.shr_imm_u32(imm.as_u32().unwrap()),
             ty if !ty.is_integer() => {
                 panic!("invalid binary operand: shr expects integer operands, got {ty}")
             }
-            ty => unimplemented!("shr for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::Shr, ty.clone())),
         }
         self.push(ty);
+        Ok(())
     }
 
-    pub fn rotl(&mut self) {
+    pub fn rotl(&mut self) -> Result<(), CodegenError> {
         let rhs = self.pop().expect("operand stack is empty");
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
@@ -1022,30 +1064,32 @@ impl<'a> OpEmitter<'a> {
             ty if !ty.is_integer() => {
                 panic!("invalid binary operand: rotl expects integer operands, got {ty}")
             }
-            ty => unimplemented!("rotl for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::Rotl, ty.clone())),
         }
         self.push(ty);
+        Ok(())
     }
 
-    pub fn rotl_imm(&mut self, imm: Immediate) {
+    pub fn rotl_imm(&mut self, imm: Immediate) -> Result<(), CodegenError> {
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
         assert_eq!(ty, imm.ty(), "expected rotl operands to be the same type");
         match &ty {
             Type::U64 => {
-                self.push_immediate(imm);
+                self.push_immediate(imm)?;
                 self.rotl_u64();
             }
             Type::U32 => self.rotl_imm_u32(imm.as_u32().unwrap()),
             ty if !ty.is_integer() => {
                 panic!("invalid binary operand: rotl expects integer operands, got {ty}")
             }
-            ty => unimplemented!("rotl for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::Rotl, ty.clone())),
         }
         self.push(ty);
+        Ok(())
     }
 
-    pub fn rotr(&mut self) {
+    pub fn rotr(&mut self) -> Result<(), CodegenError> {
         let rhs = self.pop().expect("operand stack is empty");
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
@@ -1056,30 +1100,32 @@ impl<'a> OpEmitter<'a> {
             ty if !ty.is_integer() => {
                 panic!("invalid binary operand: rotr expects integer operands, got {ty}")
             }
-            ty => unimplemented!("rotr for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::Rotr, ty.clone())),
         }
         self.push(ty);
+        Ok(())
     }
 
-    pub fn rotr_imm(&mut self, imm: Immediate) {
+    pub fn rotr_imm(&mut self, imm: Immediate) -> Result<(), CodegenError> {
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
         assert_eq!(ty, imm.ty(), "expected rotr operands to be the same type");
         match &ty {
             Type::U64 => {
-                self.push_immediate(imm);
+                self.push_immediate(imm)?;
                 self.rotr_u64();
             }
             Type::U32 => self.rotr_imm_u32(imm.as_u32().unwrap()),
             ty if !ty.is_integer() => {
                 panic!("invalid binary operand: rotr expects integer operands, got {ty}")
             }
-            ty => unimplemented!("rotr for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::Rotr, ty.clone())),
         }
         self.push(ty);
+        Ok(())
     }
 
-    pub fn min(&mut self) {
+    pub fn min(&mut self) -> Result<(), CodegenError> {
         let rhs = self.pop().expect("operand stack is empty");
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
@@ -1090,30 +1136,32 @@ impl<'a> OpEmitter<'a> {
             ty if !ty.is_integer() => {
                 panic!("invalid binary operand: min expects integer operands, got {ty}")
             }
-            ty => unimplemented!("min for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::Min, ty.clone())),
         }
         self.push(ty);
+        Ok(())
     }
 
-    pub fn min_imm(&mut self, imm: Immediate) {
+    pub fn min_imm(&mut self, imm: Immediate) -> Result<(), CodegenError> {
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
         assert_eq!(ty, imm.ty(), "expected min operands to be the same type");
         match &ty {
             Type::U64 => {
-                self.push_immediate(imm);
+                self.push_immediate(imm)?;
                 self.min_u64();
             }
             Type::U32 | Type::U16 | Type::U8 | Type::I1 => self.min_imm_u32(imm.as_u32().unwrap()),
             ty if !ty.is_integer() => {
                 panic!("invalid binary operand: min expects integer operands, got {ty}")
             }
-            ty => unimplemented!("min for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::Min, ty.clone())),
         }
         self.push(ty);
+        Ok(())
     }
 
-    pub fn max(&mut self) {
+    pub fn max(&mut self) -> Result<(), CodegenError> {
         let rhs = self.pop().expect("operand stack is empty");
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
@@ -1124,26 +1172,28 @@ impl<'a> OpEmitter<'a> {
             ty if !ty.is_integer() => {
                 panic!("invalid binary operand: max expects integer operands, got {ty}")
             }
-            ty => unimplemented!("max for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::Max, ty.clone())),
         }
         self.push(ty);
+        Ok(())
     }
 
-    pub fn max_imm(&mut self, imm: Immediate) {
+    pub fn max_imm(&mut self, imm: Immediate) -> Result<(), CodegenError> {
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
         assert_eq!(ty, imm.ty(), "expected max operands to be the same type");
         match &ty {
             Type::U64 => {
-                self.push_immediate(imm);
+                self.push_immediate(imm)?;
                 self.max_u64();
             }
             Type::U32 | Type::U16 | Type::U8 | Type::I1 => self.max_imm_u32(imm.as_u32().unwrap()),
             ty if !ty.is_integer() => {
                 panic!("invalid binary operand: max expects integer operands, got {ty}")
             }
-            ty => unimplemented!("max for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::Max, ty.clone())),
         }
         self.push(ty);
+        Ok(())
     }
 }
@@ -311,7 +311,7 @@ impl<'a> OpEmitter<'a> {
         // Mask out any bits between N and 32.
         let unused_bits = 32 - n;
         if unused_bits > 0 {
-            self.const_mask_u32(1 << ((32 - unused_bits) - 1));
+            self.const_mask_u32(u32::MAX >> unused_bits);
         }
     }
 
@@ -1,9 +1,20 @@
-use miden_hir::{StructType, Type};
+use miden_hir::{Felt, FieldElement, Opcode, Type};
 
-use crate::masm::{NativePtr, Op};
+use crate::{
+    masm::{LocalId, NativePtr, Op},
+    stackify::TypedValue,
+    CodegenError,
+};
 
 use super::OpEmitter;
 
+/// The maximum number of parts, see [Type::to_raw_parts], of an aggregate which can be loaded
+/// from a pointer which is not known at compile-time.
+///
+/// Each part is moved below the native pointer triplet and the parts loaded before it, which
+/// `movdn` can only do while they are all within the first 16 elements of the operand stack.
+const MAX_DYNAMIC_LOAD_PARTS: usize = 14;
+
 /// Allocation
 impl<'a> OpEmitter<'a> {
     /// Allocate a procedure-local memory slot of sufficient size to store a value
@@ -23,6 +34,40 @@ impl<'a> OpEmitter<'a> {
     }
 }
 
+/// Spills
+impl<'a> OpEmitter<'a> {
+    /// Spill the operand at index `n` on the operand stack to the given procedure locals,
+    /// removing it from the operand stack.
+    ///
+    /// Each element of the operand is stored in its own local, the element on top of the
+    /// stack being stored in the first of `locals`.
+    pub fn spill(&mut self, n: usize, locals: &[LocalId]) {
+        if n > 0 {
+            self.movup(n as u8);
+        }
+        let operand = self.stack.pop().expect("operand stack is empty");
+        assert_eq!(
+            operand.size(),
+            locals.len(),
+            "expected a local for each element of the spilled operand"
+        );
+        for local in locals.iter().copied() {
+            // The address of a local is in the IR's byte-addressable address space,
+            // so it must be converted to a native address before it is stored to
+            self.emit_all(&[Op::LocAddr(local), Op::U32CheckedDivImm(16), Op::MemStore]);
+        }
+    }
+
+    /// Reload `value` from the procedure locals it was spilled to via [Self::spill],
+    /// placing it on top of the operand stack.
+    pub fn reload(&mut self, value: TypedValue, locals: &[LocalId]) {
+        for local in locals.iter().rev().copied() {
+            self.emit_all(&[Op::LocAddr(local), Op::U32CheckedDivImm(16), Op::MemLoad]);
+        }
+        self.stack.push(value);
+    }
+}
+
 /// Loads
 impl<'a> OpEmitter<'a> {
     /// Load a value of corresponding to the pointee type of a pointer operand on the stack.
@@ -30,7 +75,7 @@ impl<'a> OpEmitter<'a> {
     /// The type of the pointer determines what address space the pointer value represents;
     /// either the Miden-native address space (word-addressable), or the IR's byte-addressable
     /// address space.
-    pub fn load(&mut self, ty: Type) {
+    pub fn load(&mut self, ty: Type) -> Result<(), CodegenError> {
         let ptr = self.stack.pop().expect("operand stack is empty");
         match ptr.ty() {
             Type::Ptr(_) => {
@@ -40,39 +85,37 @@ impl<'a> OpEmitter<'a> {
                     Type::I128 => self.load_quad_word(None),
                     Type::I64 | Type::U64 => self.load_double_word(None),
                     Type::Felt => self.load_felt(None),
-                    Type::I32 | Type::U32 => self.load_word(None),
-                    ty @ (Type::I16 | Type::U16 | Type::U8 | Type::I8 | Type::I1) => {
-                        self.load_word(None);
-                        self.trunc_int32(ty.size_in_bits() as u32);
-                    }
-                    ty => todo!("support for loading {ty} is not yet implemented"),
+                    Type::I32 | Type::U32 | Type::Ptr(_) => self.load_word(None),
+                    ty if ty.size_in_bytes() <= 4 => self.load_small(ty, None),
+                    ty @ (Type::Array(..) | Type::Struct(_)) => self.load_aggregate(ty, None)?,
+                    ty => return Err(self.unsupported(Opcode::Load, ty.clone())),
                 }
                 self.stack.push(ty);
             }
             ty if !ty.is_pointer() => {
                 panic!("invalid operand to load: expected pointer, got {ty}")
             }
-            ty => unimplemented!("load support for pointers of type {ty} is not implemented"),
+            ty => return Err(self.unsupported(Opcode::Load, ty)),
         }
+        Ok(())
     }
 
     /// Load a value of type `ty` from `addr`.
     ///
     /// NOTE: The address represented by `addr` is in the IR's byte-addressable address space.
-    pub fn load_imm(&mut self, addr: u32, ty: Type) {
+    pub fn load_imm(&mut self, addr: u32, ty: Type) -> Result<(), CodegenError> {
         let ptr = NativePtr::from_ptr(addr);
         match &ty {
             Type::I128 => self.load_quad_word(Some(ptr)),
             Type::I64 | Type::U64 => self.load_double_word(Some(ptr)),
             Type::Felt => self.load_felt(Some(ptr)),
-            Type::I32 | Type::U32 => self.load_word(Some(ptr)),
-            Type::I16 | Type::U16 | Type::U8 | Type::I8 | Type::I1 => {
-                self.load_word(Some(ptr));
-                self.trunc_int32(ty.size_in_bits() as u32);
-            }
-            ty => todo!("support for loading {ty} is not yet implemented"),
+            Type::I32 | Type::U32 | Type::Ptr(_) => self.load_word(Some(ptr)),
+            ty if ty.size_in_bytes() <= 4 => self.load_small(ty, Some(ptr)),
+            ty @ (Type::Array(..) | Type::Struct(_)) => self.load_aggregate(ty, Some(ptr))?,
+            ty => return Err(self.unsupported(Opcode::Load, ty.clone())),
         }
         self.stack.push(ty);
+        Ok(())
     }
 
     /// Emit a sequence of instructions to translate a raw pointer value to
@@ -129,40 +172,7 @@ impl<'a> OpEmitter<'a> {
             ptr.is_element_aligned(),
             "felt values must be naturally aligned"
         );
-        match ptr.index {
-            0 => self.emit(Op::MemLoadImm(ptr.waddr)),
-            1 => {
-                self.emit_all(&[
-                    Op::Padw,
-                    Op::MemLoadwImm(ptr.waddr),
-                    Op::Movup(4),
-                    Op::Movup(4),
-                    Op::Drop,
-                    Op::Drop,
-                    Op::Drop,
-                ]);
-            }
-            2 => {
-                self.emit_all(&[
-                    Op::Padw,
-                    Op::MemLoadwImm(ptr.waddr),
-                    Op::Drop,
-                    Op::Drop,
-                    Op::Swap(1),
-                    Op::Drop,
-                ]);
-            }
-            3 => {
-                self.emit_all(&[
-                    Op::Padw,
-                    Op::MemLoadwImm(ptr.waddr),
-                    Op::Drop,
-                    Op::Drop,
-                    Op::Drop,
-                ]);
-            }
-            _ => unreachable!(),
-        }
+        self.load_element_imm(ptr.waddr, ptr.index);
     }
 
     /// Loads a single 32-bit machine word, i.e. a single field element, not the Miden notion of a word
@@ -178,130 +188,23 @@ impl<'a> OpEmitter<'a> {
 
     /// Loads a single 32-bit machine word from the given immediate address.
     fn load_word_imm(&mut self, ptr: NativePtr) {
-        let is_aligned = ptr.is_element_aligned();
-        let rshift = 32 - ptr.offset as u32;
-        match ptr.index {
-            0 if is_aligned => self.emit(Op::MemLoadImm(ptr.waddr)),
-            0 => {
-                self.emit_all(&[
-                    // Load a quad-word
-                    Op::Padw,
-                    Op::MemLoadwImm(ptr.waddr),
-                    // Move the two elements across which the desired machine word spans
-                    // to the bottom of the stack temporarily
-                    Op::Movdn(4),
-                    Op::Movdn(4),
-                    // Drop the unused elements
-                    Op::Drop,
-                    Op::Drop,
-                    // Shift the high bits left by the offset
-                    Op::U32CheckedShlImm(ptr.offset as u32),
-                    // Move the low bits to the top and shift them right
-                    Op::Swap(1),
-                    Op::U32CheckedShrImm(rshift),
-                    // OR the high and low bits together
-                    Op::U32Or,
-                ]);
-            }
-            1 if is_aligned => self.emit_all(&[
-                // Load a quad-word
-                Op::Padw,
-                Op::MemLoadwImm(ptr.waddr),
-                // Drop the first unused element
-                Op::Drop,
-                // Move the desired element past the last two unused
-                Op::Movdn(3),
-                // Drop the remaining unused elements
-                Op::Drop,
-                Op::Drop,
-            ]),
-            1 => {
-                self.emit_all(&[
-                    // Load a quad-word
-                    Op::Padw,
-                    Op::MemLoadwImm(ptr.waddr),
-                    // Drop the first unused element
-                    Op::Drop,
-                    // Move the two elements across which the desired machine word spans
-                    // to the bottom of the stack temporarily
-                    Op::Movdn(3),
-                    Op::Movdn(3),
-                    // Drop the remaining unused element
-                    Op::Drop,
-                    // Shift the high bits left by the offset
-                    Op::U32CheckedShlImm(ptr.offset as u32),
-                    // Move the low bits to the top and shift them right
-                    Op::Swap(1),
-                    Op::U32CheckedShrImm(rshift),
-                    // OR the high and low bits together
-                    Op::U32Or,
-                ]);
-            }
-            2 if is_aligned => self.emit_all(&[
-                // Load a quad-word
-                Op::Padw,
-                Op::MemLoadwImm(ptr.waddr),
-                // Drop the first two unused elements
-                Op::Drop,
-                Op::Drop,
-                // Swap the last remaining unused element to the top and drop it
-                Op::Swap(1),
-                Op::Drop,
-            ]),
-            2 => {
-                self.emit_all(&[
-                    // Load a quad-word
-                    Op::Padw,
-                    Op::MemLoadwImm(ptr.waddr),
-                    // Drop the first two unused elements
-                    Op::Drop,
-                    Op::Drop,
-                    // Shift the high bits left by the offset
-                    Op::U32CheckedShlImm(ptr.offset as u32),
-                    // Move the low bits to the top and shift them right
-                    Op::Swap(1),
-                    Op::U32CheckedShrImm(rshift),
-                    // OR the high and low bits together
-                    Op::U32Or,
-                ]);
-            }
-            3 if is_aligned => self.emit_all(&[
-                // Load a quad-word
-                Op::Padw,
-                Op::MemLoadwImm(ptr.waddr),
-                // Drop the three unused elements
-                Op::Drop,
-                Op::Drop,
-                Op::Drop,
-            ]),
-            3 => {
-                self.emit_all(&[
-                    // Load the quad-word containing the low bits
-                    Op::Padw,
-                    Op::MemLoadwImm(ptr.waddr + 1),
-                    // Move the element we need to the bottom temporarily
-                    Op::Movdn(4),
-                    // Drop the unused elements
-                    Op::Drop,
-                    Op::Drop,
-                    Op::Drop,
-                    // Shift the low bits right by the offset
-                    Op::U32CheckedShrImm(rshift),
-                    // Load the quad-word containing the high bits
-                    Op::Padw,
-                    Op::MemLoadwImm(ptr.waddr),
-                    // Drop the unused elements
-                    Op::Drop,
-                    Op::Drop,
-                    Op::Drop,
-                    // Shift the high bits left by the offset
-                    Op::U32CheckedShlImm(ptr.offset as u32),
-                    // OR the high and low bits together
-                    Op::U32Or,
-                ]);
-            }
-            _ => unreachable!(),
+        if ptr.is_element_aligned() {
+            return self.load_element_imm(ptr.waddr, ptr.index);
         }
+
+        // An unaligned machine word spans two elements, the low bits of the value are in
+        // the high bits of the first element, and the high bits of the value are in the low
+        // bits of the second element, which may be in the next word.
+        let shift = ptr.offset as u32 * 8;
+        let (next_waddr, next_index) = if ptr.index == 3 {
+            (ptr.waddr + 1, 0)
+        } else {
+            (ptr.waddr, ptr.index + 1)
+        };
+        self.load_element_imm(ptr.waddr, ptr.index);
+        self.emit(Op::U32CheckedShrImm(shift));
+        self.load_element_imm(next_waddr, next_index);
+        self.emit_all(&[Op::U32CheckedShlImm(32 - shift), Op::U32Or]);
     }
 
     /// Load a pair of machine words (32-bit elements) to the operand stack
@@ -314,124 +217,11 @@ impl<'a> OpEmitter<'a> {
     }
 
     fn load_double_word_imm(&mut self, ptr: NativePtr) {
-        let aligned = ptr.is_element_aligned();
-        match ptr.index {
-            0 if aligned => {
-                self.emit_all(&[
-                    // Load quad-word
-                    Op::Padw,
-                    Op::MemLoadwImm(ptr.waddr),
-                    // Move the two elements we need to the bottom temporarily
-                    Op::Movdn(4),
-                    Op::Movdn(4),
-                    // Drop the unused elements
-                    Op::Drop,
-                    Op::Drop,
-                ]);
-            }
-            0 => {
-                // An unaligned double-word load spans three elements
-                self.emit_all(&[
-                    // Load quad-word
-                    Op::Padw,
-                    Op::MemLoadwImm(ptr.waddr),
-                    // Move the unused element to the top and drop it
-                    Op::Movup(4),
-                    Op::Drop,
-                ]);
-                self.realign_double_word(ptr);
-            }
-            1 if aligned => {
-                self.emit_all(&[
-                    // Load quad-word
-                    Op::Padw,
-                    Op::MemLoadwImm(ptr.waddr),
-                    // Drop the first word, its unused
-                    Op::Drop,
-                    // Move the last word up and drop it, also unused
-                    Op::Movup(3),
-                    Op::Drop,
-                ]);
-            }
-            1 => {
-                // An unaligned double-word load spans three elements
-                self.emit_all(&[
-                    // Load a quad-word
-                    Op::Padw,
-                    Op::MemLoadwImm(ptr.waddr),
-                    // Drop the unused element
-                    Op::Drop,
-                ]);
-                self.realign_double_word(ptr);
-            }
-            2 if aligned => {
-                self.emit_all(&[
-                    // Load quad-word
-                    Op::Padw,
-                    Op::MemLoadwImm(ptr.waddr),
-                    // Drop unused words
-                    Op::Drop,
-                    Op::Drop,
-                ]);
-            }
-            2 => {
-                // An unaligned double-word load spans three elements,
-                // and in this case, two quad-words, because the last
-                // element is across a quad-word boundary
-                self.emit_all(&[
-                    // Load the second quad-word first
-                    Op::Padw,
-                    Op::MemLoadwImm(ptr.waddr + 1),
-                    // Move the element we need to the bottom temporarily
-                    Op::Movdn(4),
-                    // Drop the three unused elements of this word
-                    Op::Drop,
-                    Op::Drop,
-                    Op::Drop,
-                    // Load the first quad-word
-                    Op::Padw,
-                    Op::MemLoadwImm(ptr.waddr),
-                    // Drop the two unused elements
-                    Op::Drop,
-                    Op::Drop,
-                ]);
-                self.realign_double_word(ptr);
-            }
-            3 if aligned => {
-                self.emit_all(&[
-                    // Load second word, drop unused elements
-                    Op::Padw,
-                    Op::MemLoadwImm(ptr.waddr + 1),
-                    Op::Movup(4),
-                    Op::Drop,
-                    Op::Movup(3),
-                    Op::Drop,
-                    // Load first word, drop unused elements
-                    Op::Padw,
-                    Op::MemLoadwImm(ptr.waddr),
-                    Op::Drop,
-                    Op::Drop,
-                    Op::Drop,
-                ]);
-            }
-            3 => {
-                self.emit_all(&[
-                    // Load second word, drop unused element
-                    Op::Padw,
-                    Op::MemLoadwImm(ptr.waddr + 1),
-                    Op::Movup(4),
-                    Op::Drop,
-                    // Load first word, drop unused elements
-                    Op::Padw,
-                    Op::MemLoadwImm(ptr.waddr),
-                    Op::Drop,
-                    Op::Drop,
-                    Op::Drop,
-                ]);
-                self.realign_double_word(ptr);
-            }
-            _ => unimplemented!("unaligned loads are not yet implemented: {ptr:#?}"),
-        }
+        // Load the part at the highest address first, so that the
+        // part at the lowest address ends up on top of the stack
+        let addr = ptr.as_ptr();
+        self.load_word_imm(NativePtr::from_ptr(addr + 4));
+        self.load_word_imm(ptr);
     }
 
     /// Load a quartet of machine words (32-bit elements) to the operand stack
@@ -443,377 +233,146 @@ impl<'a> OpEmitter<'a> {
     }
 
     fn load_quad_word_imm(&mut self, ptr: NativePtr) {
-        // For all other cases, more complicated loads are required
-        let aligned = ptr.is_element_aligned();
-        match ptr.index {
-            // Naturally-aligned
-            0 if aligned => self.emit_all(&[Op::Padw, Op::MemLoadwImm(ptr.waddr)]),
-            0 => {
-                // An unaligned quad-word load spans five elements
-                self.emit_all(&[
-                    // Load second quad-word
-                    Op::Padw,
-                    Op::MemLoadwImm(ptr.waddr + 1),
-                    // Drop all but the first element
-                    Op::Movdn(4),
-                    Op::Drop,
-                    Op::Drop,
-                    Op::Drop,
-                    // Load first quad-word
-                    Op::Padw,
-                    Op::MemLoadwImm(ptr.waddr),
-                ]);
-                self.realign_quad_word(ptr);
-            }
-            1 if aligned => {
-                self.emit_all(&[
-                    // Load second quad-word
-                    Op::Padw,
-                    Op::MemLoadwImm(ptr.waddr + 1),
-                    // Drop last element
-                    Op::Movup(4),
-                    Op::Drop,
-                    // Load first quad-word
-                    Op::Padw,
-                    Op::MemLoadwImm(ptr.waddr),
-                    // Drop first element
-                    Op::Drop,
-                ]);
-            }
-            1 => {
-                // An unaligned double-word load spans five elements
-                self.emit_all(&[
-                    // Load second quad-word
-                    Op::Padw,
-                    Op::MemLoadwImm(ptr.waddr + 1),
-                    // Drop all but the first two elements
-                    Op::Movdn(4),
-                    Op::Movdn(4),
-                    Op::Drop,
-                    Op::Drop,
-                    // Load first quad-word
-                    Op::Padw,
-                    Op::MemLoadwImm(ptr.waddr),
-                    // Drop the first word
-                    Op::Drop,
-                ]);
-                self.realign_quad_word(ptr);
+        // A word-aligned value can be read in a single instruction
+        if ptr.is_word_aligned() {
+            self.emit_all(&[Op::Padw, Op::MemLoadwImm(ptr.waddr)]);
+            return;
+        }
+
+        // Otherwise, load each 32-bit part of the value in reverse order, so
+        // that the part at the lowest address ends up on top of the stack
+        let addr = ptr.as_ptr();
+        for i in (0..4).rev() {
+            self.load_word_imm(NativePtr::from_ptr(addr + (i * 4)));
+        }
+    }
+
+    /// Load a value smaller than 32 bits, zero-extended to 32 bits.
+    ///
+    /// Expects a native pointer triplet on the stack if an immediate address is not given.
+    fn load_small(&mut self, ty: &Type, ptr: Option<NativePtr>) {
+        let bits = ty.size_in_bits() as u32;
+        match ptr {
+            // If the value is contained entirely in one element, there is no need to
+            // touch the next element at all
+            Some(imm) if (imm.offset as u32 * 8) + bits <= 32 => {
+                self.load_element_imm(imm.waddr, imm.index);
+                if imm.offset > 0 {
+                    self.emit(Op::U32CheckedShrImm(imm.offset as u32 * 8));
+                }
             }
-            2 if aligned => {
-                self.emit_all(&[
-                    // Load second quad-word
-                    Op::Padw,
-                    Op::MemLoadwImm(ptr.waddr),
-                    // Drop last two elements
-                    Op::Movup(4),
-                    Op::Movup(4),
-                    Op::Drop,
-                    Op::Drop,
-                    // Load first quad-word
-                    Op::Padw,
-                    Op::MemLoadwImm(ptr.waddr),
-                    // Drop first two elements
-                    Op::Drop,
-                    Op::Drop,
-                ]);
+            ptr => self.load_word(ptr),
+        }
+        self.trunc_int32(bits);
+    }
+
+    /// Load an array or struct, by loading each of its parts, as given by [Type::to_raw_parts],
+    /// such that the first part, i.e. the one at the lowest address, is on top of the stack.
+    ///
+    /// A native pointer triplet is expected on the stack if an immediate is not given. In that
+    /// case, the aggregate may have at most [MAX_DYNAMIC_LOAD_PARTS] parts, otherwise an error
+    /// is returned.
+    fn load_aggregate(&mut self, ty: &Type, ptr: Option<NativePtr>) -> Result<(), CodegenError> {
+        let parts = ty
+            .clone()
+            .to_raw_parts()
+            .expect("invalid load: unknown type");
+
+        if let Some(imm) = ptr {
+            let mut addr = imm.as_ptr();
+            let mut addrs = Vec::with_capacity(parts.len());
+            for part in parts.iter() {
+                addrs.push(addr);
+                addr += part.size_in_bytes() as u32;
             }
-            2 => {
-                // An unaligned double-word load spans five elements
-                self.emit_all(&[
-                    // Load the second quad-word
-                    Op::Padw,
-                    Op::MemLoadwImm(ptr.waddr + 1),
-                    // Drop the last element
-                    Op::Movup(4),
-                    Op::Drop,
-                    // Load the first quad-word
-                    Op::Padw,
-                    Op::MemLoadwImm(ptr.waddr),
-                    // Drop the two unused elements
-                    Op::Drop,
-                    Op::Drop,
-                ]);
-                self.realign_quad_word(ptr);
+            for (part, addr) in parts.iter().zip(addrs).rev() {
+                self.load_part(part, Some(NativePtr::from_ptr(addr)));
             }
-            3 if aligned => {
-                self.emit_all(&[
-                    // Load second word, drop last element
-                    Op::Padw,
-                    Op::MemLoadwImm(ptr.waddr + 1),
-                    Op::Movup(4),
-                    Op::Drop,
-                    // Load first word
-                    Op::Padw,
-                    Op::MemLoadwImm(ptr.waddr),
-                    // Drop first three elements
-                    Op::Drop,
-                    Op::Drop,
-                    Op::Drop,
-                ]);
-            }
-            3 => {
-                // An unaligned quad-word load spans five elements,
-                self.emit_all(&[
-                    // Load second word
-                    Op::Padw,
-                    Op::MemLoadwImm(ptr.waddr + 1),
-                    // Load first word
-                    Op::Padw,
-                    Op::MemLoadwImm(ptr.waddr),
-                    // Drop unused elements
-                    Op::Drop,
-                    Op::Drop,
-                    Op::Drop,
-                ]);
-                self.realign_quad_word(ptr);
+            return Ok(());
+        }
+
+        if parts.len() > MAX_DYNAMIC_LOAD_PARTS {
+            return Err(self.unsupported(Opcode::Load, ty.clone()));
+        }
+
+        let last = parts.len() - 1;
+        for (i, part) in parts.iter().enumerate() {
+            if i == last {
+                self.load_part(part, None);
+                // Move the last part below the others
+                if last > 0 {
+                    self.emit(Op::Movdn(last as u8));
+                }
+                break;
             }
-            _ => unimplemented!("unaligned loads are not yet implemented: {ptr:#?}"),
+
+            let size = part.size_in_bytes() as u32;
+            self.emit_all(&[
+                // Copy the pointer
+                //
+                // [waddr, index, offset, waddr, index, offset]
+                Op::Dup(2),
+                Op::Dup(2),
+                Op::Dup(2),
+            ]);
+            self.load_part(part, None);
+            // Move the part below the pointer, and any parts loaded before it
+            //
+            // [waddr, index, offset, parts..]
+            self.emit(Op::Movdn(3 + i as u8));
+            self.advance_native_ptr(size);
         }
+
+        Ok(())
     }
 
-    /// This handles emitting code that handles aligning an unaligned double machine-word value
-    /// which is split across three machine words (field elements).
-    ///
-    /// To recap:
-    ///
-    /// * A machine word is a 32-bit chunk stored in a single field element
-    /// * A double word is a pair of 32-bit chunks
-    /// * A quad word is a quartet of 32-bit chunks (i.e. a Miden "word")
-    /// * An unaligned double-word requires three 32-bit chunks to represent,
-    /// since the first chunk does not contain a full 32-bits, so an extra is
-    /// needed to hold those bits.
-    ///
-    /// As an example, assume the pointer we are dereferencing is a u64 value,
-    /// which has 8-byte alignment, and the value is stored 40 bytes from the
-    /// nearest quad-word-aligned boundary. To load the value, we must fetch
-    /// the full quad-word from the aligned address, drop the first word, as
-    /// it is unused, and then recombine the 64 bits we need spread across
-    /// the remaining three words to obtain the double-word value we actually want.
-    ///
-    /// The data, on the stack, is shown below:
-    ///
-    /// ```text,ignore
-    /// # If we visualize which bytes are contained in each 32-bit chunk on the stack, we get:
-    /// [0..=4, 5..=8, 9..=12]
-    ///
-    /// # These byte indices are relative to the nearest word-aligned address, in the same order
-    /// # as they would occur in a byte-addressable address space. The significance of each byte
-    /// # depends on the value being dereferenced, but Miden is a little-endian machine, so typically
-    /// # the most significant bytes come first (i.e. also commonly referred to as "high" vs "low" bits).
-    /// #
-    /// # If we visualize the layout of the bits of our u64 value spread across the three chunks, we get:
-    /// [00000000111111111111111111111111, 111111111111111111111111111111, 11111111111111111111111100000000]
-    /// ```
-    ///
-    /// As illustrated above, what should be a double-word value is occupying three words. To "realign" the
-    /// value, i.e. ensure that it is naturally aligned and fits in two words, we have to perform a sequence
-    /// of shifts and masks to get the bits where they belong. This function performs those steps, with the
-    /// assumption that the caller has three values on the operand stack representing any unaligned double-word
-    /// value
-    fn realign_double_word(&mut self, ptr: NativePtr) {
-        // The stack starts as: [chunk_hi, chunk_mid, chunk_lo]
-        //
-        // We will refer to the parts of our desired double-word value
-        // as two parts, `x_hi` and `x_lo`.
-        self.emit_all(&[
-            // Re-align the high bits by shifting out the offset
-            //
-            // This gives us the first half of the first word.
-            //
-            // [x_hi_hi, chunk_mid, chunk__lo]
-            Op::U32CheckedShlImm(ptr.offset as u32),
-            // Move the value below the other chunks temporarily
-            //
-            // [chunk_mid, chunk_lo, x_hi_hi]
-            Op::Movdn(3),
-            // We must split the middle chunk into two parts,
-            // one containing the bits to be combined with the
-            // first machine word; the other to be combined with
-            // the second machine word.
-            //
-            // First, we duplicate the chunk, since we need two
-            // copies of it:
-            //
-            // [chunk_mid, chunk_mid, chunk_lo, x_hi_hi]
-            Op::Dup(0),
-            // Then, we shift the chunk right by 32 - offset bits,
-            // re-aligning the low bits of the first word, and
-            // isolating them.
-            //
-            // [x_hi_lo, chunk_mid, chunk_lo, x_hi_hi]
-            Op::U32CheckedShrImm(32 - ptr.offset as u32),
-            // Move the high bits back to the top
-            //
-            // [x_hi_hi, x_hi_lo, chunk_mid, chunk_lo]
-            Op::Movup(3),
-            // OR the two parts of the `x_hi` chunk together
-            //
-            // [x_hi, chunk_mid, chunk_lo]
-            Op::U32Or,
-            // Move `x_hi` to the bottom for later
-            Op::Movdn(2),
-            // Now, we need to re-align the high bits of the second word
-            // by shifting the remaining copy of the middle chunk, similar
-            // to what we did at the very beginning.
-            //
-            // This gives us the first half of the second word.
-            //
-            // [x_lo_hi, chunk_lo, x_hi]
-            Op::U32CheckedShlImm(ptr.offset as u32),
-            // Next, swap the low bit chunk to the top temporarily
-            Op::Swap(1),
-            // Shift the value right, as done previously for the middle chunk
-            Op::U32CheckedShrImm(32 - ptr.offset as u32),
-            // OR the two halves together, giving us our second word, `x_lo`
-            //
-            // [x_lo, x_hi]
-            Op::U32Or,
-            // Swap the words so they are in the correct order
-            //
-            // [x_hi, x_lo]
-            Op::Swap(1),
-        ]);
+    /// Load a single part of a larger value, see [Type::to_raw_parts]
+    fn load_part(&mut self, ty: &Type, ptr: Option<NativePtr>) {
+        match ty {
+            Type::Felt => self.load_felt(ptr),
+            ty if ty.size_in_bytes() == 4 => self.load_word(ptr),
+            ty => self.load_small(ty, ptr),
+        }
     }
 
-    /// This handles emitting code that handles aligning an unaligned quad machine-word value
-    /// which is split across five machine words (field elements).
-    ///
-    /// To recap:
-    ///
-    /// * A machine word is a 32-bit chunk stored in a single field element
-    /// * A double word is a pair of 32-bit chunks
-    /// * A quad word is a quartet of 32-bit chunks (i.e. a Miden "word")
-    /// * An unaligned quad-word requires five 32-bit chunks to represent,
-    /// since the first chunk does not contain a full 32-bits, so an extra is
-    /// needed to hold those bits.
-    ///
-    /// See the example in [OpEmitter::realign_quad_word] for more details on how bits are
-    /// laid out in each word, and what is required to realign unaligned words.
-    fn realign_quad_word(&mut self, ptr: NativePtr) {
-        // The stack starts as: [chunk_hi, chunk_mid_hi, chunk_mid_mid, chunk_mid_lo, chunk_lo]
-        //
-        // We will refer to the parts of our desired quad-word value
-        // as four parts, `x_hi2`, `x_hi1`, `x_lo2`, and `x_lo1`, where
-        // the integer suffix should appear in decreasing order on the
-        // stack when we're done.
-        self.emit_all(&[
-            // Re-align the high bits by shifting out the offset
-            //
-            // This gives us the first half of `x_hi2`.
-            //
-            // [x_hi2_hi, chunk_mid_hi, chunk_mid_mid, chunk_mid_lo, chunk__lo]
-            Op::U32CheckedShlImm(ptr.offset as u32),
-            // Move the value below the other chunks temporarily
-            //
-            // [chunk_mid_hi, chunk_mid_mid, chunk_mid_lo, chunk__lo, x_hi2_hi]
-            Op::Movdn(5),
-            // We must split the `chunk_mid_hi` chunk into two parts,
-            // one containing the bits to be combined with `x_hi2_hi`;
-            // the other to be combined with `x_hi1_hi`.
-            //
-            // First, we duplicate the chunk, since we need two
-            // copies of it:
-            //
-            // [chunk_mid_hi, chunk_mid_hi, chunk_mid_mid, chunk_mid_lo, chunk_lo, x_hi2_hi]
-            Op::Dup(0),
-            // Then, we shift the chunk right by 32 - offset bits,
-            // re-aligning the low bits of `x_hi2`, and isolating them.
-            //
-            // [x_hi2_lo, chunk_mid_hi, chunk_mid_mid, chunk_mid_lo, chunk_lo, x_hi2_hi]
-            Op::U32CheckedShrImm(32 - ptr.offset as u32),
-            // Move the high bits of `x_hi2` back to the top
-            //
-            // [x_hi2_hi, x_hi2_lo, chunk_mid_hi, chunk_mid_mid, chunk_mid_lo, chunk_lo]
-            Op::Movup(3),
-            // OR the two parts of the `x_hi2` chunk together
-            //
-            // [x_hi2, chunk_mid_hi, chunk_mid_mid, chunk_mid_lo, chunk_lo]
-            Op::U32Or,
-            // Move `x_hi2` to the bottom for later
-            //
-            // [chunk_mid_hi, chunk_mid_mid, chunk_mid_lo, chunk_lo, x_hi2]
-            Op::Movdn(5),
-            // Now, we need to re-align the high bits of `x_hi1` by shifting
-            // the remaining copy of `chunk_mid_hi`, similar to what we did for `x_hi2`
-            //
-            // This gives us the first half of `x_hi1`
-            //
-            // [x_hi1_hi, chunk_mid_mid, chunk_mid_lo, chunk_lo, x_hi2]
-            Op::U32CheckedShlImm(ptr.offset as u32),
-            // Next, move the chunk containing the low bits of `x_hi1` to the top temporarily
-            //
-            // [chunk_mid_mid, chunk_mid_lo, chunk_lo, x_hi2, x_hi1_hi]
-            Op::Movdn(5),
-            // Duplicate it, as we need two copies
-            //
-            // [chunk_mid_mid, chunk_mid_mid, chunk_mid_lo, chunk_lo, x_hi2, x_hi1_hi]
-            Op::Dup(0),
-            // Shift the value right, as done previously for the low bits of `x_hi2`
-            //
-            // [x_hi1_lo, chunk_mid_mid, chunk_mid_lo, chunk_lo, x_hi2, x_hi1_hi]
-            Op::U32CheckedShrImm(32 - ptr.offset as u32),
-            // Move the high bits of `x_hi1` to the top
-            Op::Movup(5),
-            // OR the two halves together, giving us our second word, `x_hi1`
-            //
-            // [x_hi1, chunk_mid_mid, chunk_mid_lo, chunk_lo, x_hi2]
-            Op::U32Or,
-            // Move the word to the bottom of the stack
-            //
-            // [chunk_mid_mid, chunk_mid_lo, chunk_lo, x_hi2, x_hi1]
-            Op::Movdn(5),
-            // Now, we need to re-align the high bits of `x_lo2` by shifting
-            // the remaining copy of `chunk_mid_mid`, as done previously.
-            //
-            // [x_lo2_hi, chunk_mid_lo, chunk_lo, x_hi2, x_hi1]
-            Op::U32CheckedShlImm(ptr.offset as u32),
-            // Next, move the chunk containing the low bits of `x_lo2` to the top temporarily
-            //
-            // [chunk_mid_lo, chunk_lo, x_hi2, x_hi1, x_lo2_hi]
-            Op::Movdn(5),
-            // Duplicate it, as done previously
-            //
-            // [chunk_mid_lo, chunk_mid_lo, chunk_lo, x_hi2, x_hi1, x_lo2_hi]
-            Op::Dup(0),
-            // Shift the value right to get the low bits of `x_lo2`
-            //
-            // [x_lo2_lo, chunk_mid_lo, chunk_lo, x_hi2, x_hi1, x_lo2_hi]
-            Op::U32CheckedShrImm(32 - ptr.offset as u32),
-            // Move the high bits of `x_lo2` to the top
-            //
-            // [x_lo2_hi, x_lo2_lo, chunk_mid_lo, chunk_lo, x_hi2, x_hi1]
-            Op::Movup(6),
-            // OR the two halves together, giving us our third word, `x_lo2`
-            //
-            // [x_lo2, chunk_mid_lo, chunk_lo, x_hi2, x_hi1]
-            Op::U32Or,
-            // Move to the bottom of the stack
-            //
-            // [chunk_mid_lo, chunk_lo, x_hi2, x_hi1, x_lo2]
-            Op::Movdn(5),
-            // Re-align the high bits of `x_lo1`
-            //
-            // [x_lo1_hi, chunk_lo, x_hi2, x_hi1, x_lo2]
-            Op::U32CheckedShlImm(ptr.offset as u32),
-            // Move the chunk containing the low bits to the top
-            //
-            // [chunk_lo, x_hi2, x_hi1, x_lo2, x_lo1_hi]
-            Op::Movdn(5),
-            // Shift the value right to get the low bits of `x_lo1`
-            Op::U32CheckedShrImm(32 - ptr.offset as u32),
-            // Move the high bits of `x_lo1` to the top
-            //
-            // [x_lo1_hi, x_lo1_lo, x_hi2, x_hi1, x_lo2]
-            Op::Movup(5),
-            // OR the two halves together, giving us our fourth word, `x_lo1`
-            //
-            // [x_lo1, x_hi2, x_hi1, x_lo2]
-            Op::U32Or,
-            // Move to the bottom
-            //
-            // [x_hi2, x_hi1, x_lo2, x_lo1]
-            Op::Movdn(5),
-        ]);
+    /// Load the element at the given word address and element index
+    fn load_element_imm(&mut self, waddr: u32, index: u8) {
+        match index {
+            0 => self.emit(Op::MemLoadImm(waddr)),
+            1 => self.emit_all(&[
+                // Load a quad-word
+                Op::Padw,
+                Op::MemLoadwImm(waddr),
+                // Drop the first unused element
+                Op::Drop,
+                // Move the desired element past the last two unused
+                Op::Movdn(2),
+                // Drop the remaining unused elements
+                Op::Drop,
+                Op::Drop,
+            ]),
+            2 => self.emit_all(&[
+                // Load a quad-word
+                Op::Padw,
+                Op::MemLoadwImm(waddr),
+                // Drop the first two unused elements
+                Op::Drop,
+                Op::Drop,
+                // Swap the last remaining unused element to the top and drop it
+                Op::Swap(1),
+                Op::Drop,
+            ]),
+            3 => self.emit_all(&[
+                // Load a quad-word
+                Op::Padw,
+                Op::MemLoadwImm(waddr),
+                // Drop the three unused elements
+                Op::Drop,
+                Op::Drop,
+                Op::Drop,
+            ]),
+            _ => unreachable!(),
+        }
     }
 }
 
@@ -824,7 +383,7 @@ impl<'a> OpEmitter<'a> {
     ///
     /// The type of the pointer is given as `ptr`, and can be used for both validation and
     /// determining alignment.
-    pub fn store(&mut self) {
+    pub fn store(&mut self) -> Result<(), CodegenError> {
         let ptr = self.stack.pop().expect("operand stack is empty");
         let value = self.stack.pop().expect("operand stack is empty");
         let ptr_ty = ptr.ty();
@@ -847,24 +406,22 @@ impl<'a> OpEmitter<'a> {
                     Type::Felt => self.store_felt(None),
                     Type::I32 | Type::U32 => self.store_word(None),
                     ref ty if ty.size_in_bytes() <= 4 => self.store_small(ty, None),
-                    Type::Array(ref elem_ty, _) => self.store_array(elem_ty, None),
-                    Type::Struct(ref struct_ty) => self.store_struct(struct_ty, None),
-                    ty => unimplemented!(
-                        "invalid store: support for storing {ty} has not been implemented"
-                    ),
+                    ref ty @ (Type::Array(..) | Type::Struct(_)) => self.store_aggregate(ty, None),
+                    ty => return Err(self.unsupported(Opcode::Store, ty)),
                 }
             }
             ty if !ty.is_pointer() => {
                 panic!("invalid operand to store: expected pointer, got {ty}")
             }
-            ty => unimplemented!("store support for pointers of type {ty} is not implemented"),
+            ty => return Err(self.unsupported(Opcode::Store, ty)),
         }
+        Ok(())
     }
 
     /// Store a value of type `ty` to `addr`.
     ///
     /// NOTE: The address represented by `addr` is in the IR's byte-addressable address space.
-    pub fn store_imm(&mut self, addr: u32) {
+    pub fn store_imm(&mut self, addr: u32) -> Result<(), CodegenError> {
         let value = self.stack.pop().expect("operand stack is empty");
         let value_ty = value.ty();
         assert!(
@@ -878,12 +435,10 @@ impl<'a> OpEmitter<'a> {
             Type::Felt => self.store_felt(Some(ptr)),
             Type::I32 | Type::U32 => self.store_word(Some(ptr)),
             ref ty if ty.size_in_bytes() <= 4 => self.store_small(ty, Some(ptr)),
-            Type::Array(ref elem_ty, _) => self.store_array(elem_ty, Some(ptr)),
-            Type::Struct(ref struct_ty) => self.store_struct(struct_ty, Some(ptr)),
-            ty => {
-                unimplemented!("invalid store: support for storing {ty} has not been implemented")
-            }
+            ref ty @ (Type::Array(..) | Type::Struct(_)) => self.store_aggregate(ty, Some(ptr)),
+            ty => return Err(self.unsupported(Opcode::Store, ty)),
         }
+        Ok(())
     }
 
     /// Copy `count * sizeof(*ty)` from a source address to a destination address.
@@ -896,8 +451,12 @@ impl<'a> OpEmitter<'a> {
     ///
     /// The semantics of this instruction are as follows:
     ///
-    /// * The ``
-    pub fn memcpy(&mut self) {
+    /// * The `count * sizeof(*ty)` bytes starting at `src` are copied to `dst`, one byte at a
+    /// time, in order of increasing address.
+    /// * Neither pointer is required to be aligned.
+    /// * The regions are not expected to overlap, if they do, the result is as if a forward
+    /// byte-wise copy was performed.
+    pub fn memcpy(&mut self) -> Result<(), CodegenError> {
         let src = self.stack.pop().expect("operand stack is empty");
         let dst = self.stack.pop().expect("operand stack is empty");
         let count = self.stack.pop().expect("operand stack is empty");
@@ -909,41 +468,316 @@ impl<'a> OpEmitter<'a> {
             "expected src and dst operands to have the same type"
         );
         match ty {
-            Type::Ptr(ref _pointee) => {
-                todo!()
+            Type::Ptr(ref pointee) => {
+                let size = pointee.size_in_bytes() as u32;
+                let body = self.function.create_block();
+                self.emit_all(&[
+                    // Convert the count to a number of bytes
+                    //
+                    // [src, dst, count * size]
+                    Op::Movup(2),
+                    Op::U32CheckedMulImm(size),
+                    Op::Movdn(2),
+                    // Enter the loop if there is anything to copy
+                    Op::Dup(2),
+                    Op::NeqImm(Felt::ZERO),
+                    Op::While(body),
+                ]);
+
+                let prev = self.switch_to_block(body);
+                // Load the byte at `src`
+                //
+                // [waddr, index, offset, src, dst, n]
+                self.emit(Op::Dup(0));
+                self.emit_native_ptr();
+                self.emit_all(&[
+                    // [waddr, index, 0, offset * 8, src, dst, n]
+                    Op::Movup(2),
+                    Op::U32CheckedMulImm(8),
+                    Op::Movdn(2),
+                    Op::PushU8(0),
+                    Op::Movdn(2),
+                    // [elem, offset * 8, src, dst, n]
+                    Op::Exec("intrinsics::mem::load_felt".parse().unwrap()),
+                    // [byte, src, dst, n]
+                    Op::Swap(1),
+                    Op::U32CheckedShr,
+                    Op::PushU32(0xff),
+                    Op::U32And,
+                    // Store it to `dst`
+                    //
+                    // [waddr, index, offset, byte, src, dst, n]
+                    Op::Dup(2),
+                ]);
+                self.emit_native_ptr();
+                self.emit_all(&[
+                    // [waddr, index, offset, 0xff, byte, src, dst, n]
+                    Op::PushU32(0xff),
+                    Op::Movdn(3),
+                    Op::Exec("intrinsics::mem::store_masked".parse().unwrap()),
+                    // Advance both pointers, and decrement the number of bytes remaining
+                    //
+                    // [src + 1, dst + 1, n - 1]
+                    Op::U32CheckedAddImm(1),
+                    Op::Swap(1),
+                    Op::U32CheckedAddImm(1),
+                    Op::Swap(1),
+                    Op::Movup(2),
+                    Op::U32CheckedSubImm(1),
+                    Op::Movdn(2),
+                    // Continue looping if there are bytes remaining
+                    Op::Dup(2),
+                    Op::NeqImm(Felt::ZERO),
+                ]);
+                self.switch_to_block(prev);
+
+                // Clean up the operands
+                self.emit_all(&[Op::Drop, Op::Drop, Op::Drop]);
             }
             ty if !ty.is_pointer() => {
                 panic!("invalid operand to memcpy: expected pointer, got {ty}")
             }
-            ty => unimplemented!("memcpy support for pointers of type {ty} is not implemented"),
+            ty => return Err(self.unsupported(Opcode::MemCpy, ty)),
         }
+        Ok(())
     }
 
-    fn store_quad_word(&mut self, _ptr: Option<NativePtr>) {
-        todo!()
+    fn store_quad_word(&mut self, ptr: Option<NativePtr>) {
+        if let Some(imm) = ptr {
+            return self.store_quad_word_imm(imm);
+        }
+
+        self.emit(Op::Exec("intrinsics::mem::store_qw".parse().unwrap()));
     }
 
-    fn store_double_word(&mut self, _ptr: Option<NativePtr>) {
-        todo!()
+    fn store_quad_word_imm(&mut self, ptr: NativePtr) {
+        // A word-aligned value can be written in a single instruction
+        if ptr.is_word_aligned() {
+            self.emit_all(&[Op::MemStorewImm(ptr.waddr), Op::Dropw]);
+            return;
+        }
+
+        // Otherwise, store each 32-bit part of the value in turn
+        let addr = ptr.as_ptr();
+        for i in 0..4 {
+            self.store_word_imm(NativePtr::from_ptr(addr + (i * 4)));
+        }
     }
 
-    fn store_word(&mut self, _ptr: Option<NativePtr>) {
-        todo!()
+    fn store_double_word(&mut self, ptr: Option<NativePtr>) {
+        if let Some(imm) = ptr {
+            return self.store_double_word_imm(imm);
+        }
+
+        self.emit(Op::Exec("intrinsics::mem::store_dw".parse().unwrap()));
     }
 
-    fn store_felt(&mut self, _ptr: Option<NativePtr>) {
-        todo!()
+    fn store_double_word_imm(&mut self, ptr: NativePtr) {
+        // Store the part at the lowest address first, as it is on top of the stack
+        let addr = ptr.as_ptr();
+        self.store_word_imm(ptr);
+        self.store_word_imm(NativePtr::from_ptr(addr + 4));
     }
 
-    fn store_small(&mut self, _ty: &Type, _ptr: Option<NativePtr>) {
-        todo!()
+    /// Stores a single 32-bit machine word, i.e. a single field element, not the Miden notion of a word
+    ///
+    /// Expects a native pointer triplet on the stack if an immediate address is not given.
+    fn store_word(&mut self, ptr: Option<NativePtr>) {
+        if let Some(imm) = ptr {
+            return self.store_word_imm(imm);
+        }
+
+        self.emit(Op::Exec("intrinsics::mem::store_sw".parse().unwrap()));
+    }
+
+    /// Stores a single 32-bit machine word to the given immediate address.
+    fn store_word_imm(&mut self, ptr: NativePtr) {
+        if ptr.is_element_aligned() {
+            return self.replace_element_imm(ptr);
+        }
+
+        // An unaligned store must merge the value with the two elements it spans
+        self.push_native_ptr(ptr);
+        self.emit(Op::Exec("intrinsics::mem::store_sw".parse().unwrap()));
+    }
+
+    /// Store a field element to a naturally aligned address, either immediate or dynamic
+    ///
+    /// A native pointer triplet is expected on the stack if an immediate is not given.
+    fn store_felt(&mut self, ptr: Option<NativePtr>) {
+        if let Some(imm) = ptr {
+            return self.store_felt_imm(imm);
+        }
+
+        self.emit(Op::Exec("intrinsics::mem::store_felt".parse().unwrap()));
+    }
+
+    fn store_felt_imm(&mut self, ptr: NativePtr) {
+        assert!(
+            ptr.is_element_aligned(),
+            "felt values must be naturally aligned"
+        );
+        self.replace_element_imm(ptr);
+    }
+
+    /// Store a value smaller than 32 bits, i.e. without disturbing the other bytes of the
+    /// element(s) it is written to.
+    ///
+    /// A native pointer triplet is expected on the stack if an immediate is not given.
+    fn store_small(&mut self, ty: &Type, ptr: Option<NativePtr>) {
+        let mask = u32::MAX >> (32 - (ty.size_in_bytes() as u32 * 8));
+        match ptr {
+            Some(imm) => {
+                self.emit(Op::PushU32(mask));
+                self.push_native_ptr(imm);
+            }
+            None => {
+                self.emit_all(&[Op::PushU32(mask), Op::Movdn(3)]);
+            }
+        }
+        self.emit(Op::Exec("intrinsics::mem::store_masked".parse().unwrap()));
+    }
+
+    /// Store an array or struct, by storing each of its parts, as given by [Type::to_raw_parts],
+    /// in the order they appear on the operand stack, i.e. the first part is at the lowest address.
+    ///
+    /// A native pointer triplet is expected on the stack if an immediate is not given.
+    fn store_aggregate(&mut self, ty: &Type, ptr: Option<NativePtr>) {
+        let parts = ty
+            .clone()
+            .to_raw_parts()
+            .expect("invalid store: unknown type");
+
+        if let Some(imm) = ptr {
+            let mut addr = imm.as_ptr();
+            for part in parts.into_iter() {
+                let size = part.size_in_bytes() as u32;
+                self.store_part(&part, Some(NativePtr::from_ptr(addr)));
+                addr += size;
+            }
+            return;
+        }
+
+        let last = parts.len() - 1;
+        for (i, part) in parts.into_iter().enumerate() {
+            if i == last {
+                self.store_part(&part, None);
+                break;
+            }
+
+            let size = part.size_in_bytes() as u32;
+            self.emit_all(&[
+                // Copy the pointer, and move the part to be stored after it
+                //
+                // [waddr, index, offset, part, waddr, index, offset]
+                Op::Dup(2),
+                Op::Dup(2),
+                Op::Dup(2),
+                Op::Movup(6),
+                Op::Movdn(3),
+            ]);
+            self.store_part(&part, None);
+            self.advance_native_ptr(size);
+        }
+    }
+
+    /// Store a single part of a larger value, see [Type::to_raw_parts]
+    fn store_part(&mut self, ty: &Type, ptr: Option<NativePtr>) {
+        match ty {
+            Type::Felt => self.store_felt(ptr),
+            ty if ty.size_in_bytes() == 4 => self.store_word(ptr),
+            ty => self.store_small(ty, ptr),
+        }
     }
 
-    fn store_array(&mut self, _element_ty: &Type, _ptr: Option<NativePtr>) {
-        todo!()
+    /// Replace the element at the given element-aligned address with the value on top of the stack
+    fn replace_element_imm(&mut self, ptr: NativePtr) {
+        assert!(ptr.is_element_aligned());
+        match ptr.index {
+            0 => self.emit(Op::MemStoreImm(ptr.waddr)),
+            1 => self.emit_all(&[
+                // Load a quad-word
+                Op::Padw,
+                Op::MemLoadwImm(ptr.waddr),
+                // Drop the element being replaced
+                Op::Swap(1),
+                Op::Drop,
+                // Move the value into place
+                Op::Movup(3),
+                Op::Swap(1),
+                // Write back the updated quad-word
+                Op::MemStorewImm(ptr.waddr),
+                Op::Dropw,
+            ]),
+            2 => self.emit_all(&[
+                // Load a quad-word
+                Op::Padw,
+                Op::MemLoadwImm(ptr.waddr),
+                // Drop the element being replaced
+                Op::Movup(2),
+                Op::Drop,
+                // Move the value into place
+                Op::Movup(3),
+                Op::Movdn(2),
+                // Write back the updated quad-word
+                Op::MemStorewImm(ptr.waddr),
+                Op::Dropw,
+            ]),
+            3 => self.emit_all(&[
+                // Load a quad-word
+                Op::Padw,
+                Op::MemLoadwImm(ptr.waddr),
+                // Drop the element being replaced
+                Op::Movup(3),
+                Op::Drop,
+                // Move the value into place
+                Op::Movup(3),
+                Op::Movdn(3),
+                // Write back the updated quad-word
+                Op::MemStorewImm(ptr.waddr),
+                Op::Dropw,
+            ]),
+            _ => unreachable!(),
+        }
     }
 
-    fn store_struct(&mut self, _ty: &StructType, _ptr: Option<NativePtr>) {
-        todo!()
+    /// Push the native pointer triplet corresponding to `ptr` on the operand stack
+    fn push_native_ptr(&mut self, ptr: NativePtr) {
+        self.emit_all(&[
+            Op::PushU8(ptr.offset),
+            Op::PushU8(ptr.index),
+            Op::PushU32(ptr.waddr),
+        ]);
+    }
+
+    /// Advance the native pointer triplet on top of the stack by `n` bytes
+    fn advance_native_ptr(&mut self, n: u32) {
+        self.emit_all(&[
+            // Compute the new byte offset, and the number of elements it overflowed by
+            //
+            // [offset + n, waddr, index]
+            Op::Movup(2),
+            Op::U32CheckedAddImm(n),
+            // [index + ((offset + n) / 4), offset + n, waddr]
+            Op::Dup(0),
+            Op::U32CheckedDivImm(4),
+            Op::Movup(3),
+            Op::U32CheckedAdd,
+            // [index + ((offset + n) / 4), waddr, offset']
+            Op::Swap(1),
+            Op::U32CheckedModImm(4),
+            Op::Movdn(2),
+            // Compute the new element index, and the number of words it overflowed by
+            //
+            // [waddr', index + ((offset + n) / 4), offset']
+            Op::Dup(0),
+            Op::U32CheckedDivImm(4),
+            Op::Movup(2),
+            Op::U32CheckedAdd,
+            // [waddr', index', offset']
+            Op::Swap(1),
+            Op::U32CheckedModImm(4),
+            Op::Swap(1),
+        ]);
     }
 }
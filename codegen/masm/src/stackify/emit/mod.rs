@@ -87,9 +87,12 @@ pub mod smallint;
 pub mod unary;
 
 use core::ops::{Deref, DerefMut};
-use miden_hir::{self as hir, Immediate, Type};
+use miden_hir::{self as hir, Immediate, SourceSpan, Type};
 
-use crate::masm::{self as masm, Op};
+use crate::{
+    masm::{self as masm, Op},
+    CodegenError,
+};
 
 use super::{Operand, OperandStack};
 
@@ -111,21 +114,19 @@ impl<'a> InstOpEmitter<'a> {
         block: masm::BlockId,
         stack: &'a mut OperandStack,
     ) -> Self {
-        Self {
-            dfg,
-            inst,
-            emitter: OpEmitter::new(function, block, stack),
-        }
+        let mut emitter = OpEmitter::new(function, block, stack);
+        emitter.span = dfg.inst_span(inst);
+        Self { dfg, inst, emitter }
     }
 
-    pub fn exec(&mut self, callee: hir::FunctionIdent) {
+    pub fn exec(&mut self, callee: hir::FunctionIdent) -> Result<(), CodegenError> {
         let import = self.dfg.get_import(&callee).unwrap();
-        self.emitter.exec(import);
+        self.emitter.exec(import)
     }
 
-    pub fn syscall(&mut self, callee: hir::FunctionIdent) {
+    pub fn syscall(&mut self, callee: hir::FunctionIdent) -> Result<(), CodegenError> {
         let import = self.dfg.get_import(&callee).unwrap();
-        self.emitter.syscall(import);
+        self.emitter.syscall(import)
     }
 
     #[inline(always)]
@@ -165,10 +166,14 @@ impl<'a> Drop for InstOpEmitter<'a> {
 ///
 /// The [OpEmitter] carries limited context of its own, and expects to receive arguments
 /// to it's various builder functions to provide necessary context for specific constructs.
+///
+/// Operations which are not supported for the types of their operands return a
+/// [CodegenError], located at the instruction being emitted when created via [InstOpEmitter].
 pub struct OpEmitter<'a> {
     stack: &'a mut OperandStack,
     function: &'a mut masm::Function,
     current_block: masm::BlockId,
+    span: SourceSpan,
 }
 impl<'a> OpEmitter<'a> {
     #[inline(always)]
@@ -181,6 +186,7 @@ impl<'a> OpEmitter<'a> {
             stack,
             function,
             current_block: block,
+            span: SourceSpan::UNKNOWN,
         }
     }
 
@@ -234,11 +240,20 @@ impl<'a> OpEmitter<'a> {
         }
     }
 
+    /// Construct the error raised when `op` is not supported for values of type `ty`
+    pub fn unsupported(&self, op: hir::Opcode, ty: Type) -> CodegenError {
+        CodegenError::Unsupported {
+            op,
+            ty,
+            span: self.span,
+        }
+    }
+
     /// Push an immediate value on the operand stack
     ///
     /// This has no effect on the state of the emulated operand stack
     #[inline]
-    pub fn push_immediate(&mut self, imm: Immediate) {
+    pub fn push_immediate(&mut self, imm: Immediate) -> Result<(), CodegenError> {
         match imm {
             Immediate::I1(i) => self.emit(Op::PushU8(i as u8)),
             Immediate::I8(i) => self.emit(Op::PushU8(i as u8)),
@@ -251,15 +266,17 @@ impl<'a> OpEmitter<'a> {
             Immediate::I64(i) => self.push_i64(i),
             Immediate::I128(i) => self.push_i128(i),
             Immediate::Felt(i) => self.emit(Op::Push(i)),
-            Immediate::F64(_) => unimplemented!("floating-point immediates are not supported"),
+            Immediate::F64(_) => return Err(self.unsupported(hir::Opcode::ImmF64, Type::F64)),
         }
+        Ok(())
     }
 
     /// Push a literal on the operand stack, and update the emulated stack accordingly
-    pub fn literal<I: Into<Immediate>>(&mut self, imm: I) {
+    pub fn literal<I: Into<Immediate>>(&mut self, imm: I) -> Result<(), CodegenError> {
         let imm = imm.into();
-        self.push_immediate(imm);
+        self.push_immediate(imm)?;
         self.stack.push(imm);
+        Ok(())
     }
 
     #[inline(always)]
@@ -420,7 +437,7 @@ impl<'a> OpEmitter<'a> {
             let raw_size = self.stack.raw_len();
             self.stack.dropn(num_to_drop);
             self.emit_n(raw_size / 4, Op::Dropw);
-            self.emit_n(raw_size % 4, Op::Dropw);
+            self.emit_n(raw_size % 4, Op::Drop);
             return;
         }
 
@@ -527,7 +544,6 @@ impl<'a> OpEmitter<'a> {
         &mut self.function.blocks[self.current_block]
     }
 
-    #[allow(unused)]
     #[inline]
     pub fn switch_to_block(&mut self, block: masm::BlockId) -> masm::BlockId {
         let prev = self.current_block;
@@ -565,11 +581,11 @@ mod tests {
         let four = Immediate::U64(2u64.pow(32));
         let five = Immediate::U64(2u64.pow(32) | 2u64.pow(33) | u32::MAX as u64);
 
-        emitter.literal(one);
-        emitter.literal(two);
-        emitter.literal(three);
-        emitter.literal(four);
-        emitter.literal(five);
+        emitter.literal(one).unwrap();
+        emitter.literal(two).unwrap();
+        emitter.literal(three).unwrap();
+        emitter.literal(four).unwrap();
+        emitter.literal(five).unwrap();
 
         {
             let block = emitter.current_block();
@@ -811,26 +827,26 @@ mod tests {
         let one = Immediate::U32(1);
         let two = Immediate::U32(2);
 
-        emitter.literal(one);
-        emitter.literal(two);
+        emitter.literal(one).unwrap();
+        emitter.literal(two).unwrap();
 
-        emitter.add_imm(one, Overflow::Checked);
+        emitter.add_imm(one, Overflow::Checked).unwrap();
         assert_eq!(emitter.stack_len(), 2);
         assert_eq!(emitter.stack()[0], Type::U32);
         assert_eq!(emitter.stack()[1], one);
 
-        emitter.add(Overflow::Checked);
+        emitter.add(Overflow::Checked).unwrap();
         assert_eq!(emitter.stack_len(), 1);
         assert_eq!(emitter.stack()[0], Type::U32);
 
-        emitter.add_imm(one, Overflow::Overflowing);
+        emitter.add_imm(one, Overflow::Overflowing).unwrap();
         assert_eq!(emitter.stack_len(), 2);
         assert_eq!(emitter.stack()[0], Type::I1);
         assert_eq!(emitter.stack()[1], Type::U32);
 
         emitter.drop();
         emitter.dup(0);
-        emitter.add(Overflow::Overflowing);
+        emitter.add(Overflow::Overflowing).unwrap();
         assert_eq!(emitter.stack_len(), 2);
         assert_eq!(emitter.stack()[0], Type::I1);
         assert_eq!(emitter.stack()[1], Type::U32);
@@ -846,26 +862,26 @@ mod tests {
         let one = Immediate::U32(1);
         let two = Immediate::U32(2);
 
-        emitter.literal(one);
-        emitter.literal(two);
+        emitter.literal(one).unwrap();
+        emitter.literal(two).unwrap();
 
-        emitter.sub_imm(one, Overflow::Checked);
+        emitter.sub_imm(one, Overflow::Checked).unwrap();
         assert_eq!(emitter.stack_len(), 2);
         assert_eq!(emitter.stack()[0], Type::U32);
         assert_eq!(emitter.stack()[1], one);
 
-        emitter.sub(Overflow::Checked);
+        emitter.sub(Overflow::Checked).unwrap();
         assert_eq!(emitter.stack_len(), 1);
         assert_eq!(emitter.stack()[0], Type::U32);
 
-        emitter.sub_imm(one, Overflow::Overflowing);
+        emitter.sub_imm(one, Overflow::Overflowing).unwrap();
         assert_eq!(emitter.stack_len(), 2);
         assert_eq!(emitter.stack()[0], Type::I1);
         assert_eq!(emitter.stack()[1], Type::U32);
 
         emitter.drop();
         emitter.dup(0);
-        emitter.sub(Overflow::Overflowing);
+        emitter.sub(Overflow::Overflowing).unwrap();
         assert_eq!(emitter.stack_len(), 2);
         assert_eq!(emitter.stack()[0], Type::I1);
         assert_eq!(emitter.stack()[1], Type::U32);
@@ -881,26 +897,26 @@ mod tests {
         let one = Immediate::U32(1);
         let two = Immediate::U32(2);
 
-        emitter.literal(one);
-        emitter.literal(two);
+        emitter.literal(one).unwrap();
+        emitter.literal(two).unwrap();
 
-        emitter.mul_imm(one, Overflow::Checked);
+        emitter.mul_imm(one, Overflow::Checked).unwrap();
         assert_eq!(emitter.stack_len(), 2);
         assert_eq!(emitter.stack()[0], Type::U32);
         assert_eq!(emitter.stack()[1], one);
 
-        emitter.mul(Overflow::Checked);
+        emitter.mul(Overflow::Checked).unwrap();
         assert_eq!(emitter.stack_len(), 1);
         assert_eq!(emitter.stack()[0], Type::U32);
 
-        emitter.mul_imm(one, Overflow::Overflowing);
+        emitter.mul_imm(one, Overflow::Overflowing).unwrap();
         assert_eq!(emitter.stack_len(), 2);
         assert_eq!(emitter.stack()[0], Type::I1);
         assert_eq!(emitter.stack()[1], Type::U32);
 
         emitter.drop();
         emitter.dup(0);
-        emitter.mul(Overflow::Overflowing);
+        emitter.mul(Overflow::Overflowing).unwrap();
         assert_eq!(emitter.stack_len(), 2);
         assert_eq!(emitter.stack()[0], Type::I1);
         assert_eq!(emitter.stack()[1], Type::U32);
@@ -916,20 +932,20 @@ mod tests {
         let one = Immediate::U32(1);
         let two = Immediate::U32(2);
 
-        emitter.literal(one);
-        emitter.literal(two);
+        emitter.literal(one).unwrap();
+        emitter.literal(two).unwrap();
 
-        emitter.eq_imm(two);
+        emitter.eq_imm(two).unwrap();
         assert_eq!(emitter.stack_len(), 2);
         assert_eq!(emitter.stack()[0], Type::I1);
         assert_eq!(emitter.stack()[1], one);
 
-        emitter.assert();
+        emitter.assert().unwrap();
         assert_eq!(emitter.stack_len(), 1);
         assert_eq!(emitter.stack()[0], one);
 
         emitter.dup(0);
-        emitter.eq();
+        emitter.eq().unwrap();
         assert_eq!(emitter.stack_len(), 1);
         assert_eq!(emitter.stack()[0], Type::I1);
     }
@@ -944,20 +960,20 @@ mod tests {
         let one = Immediate::U32(1);
         let two = Immediate::U32(2);
 
-        emitter.literal(one);
-        emitter.literal(two);
+        emitter.literal(one).unwrap();
+        emitter.literal(two).unwrap();
 
-        emitter.neq_imm(two);
+        emitter.neq_imm(two).unwrap();
         assert_eq!(emitter.stack_len(), 2);
         assert_eq!(emitter.stack()[0], Type::I1);
         assert_eq!(emitter.stack()[1], one);
 
-        emitter.assertz();
+        emitter.assertz().unwrap();
         assert_eq!(emitter.stack_len(), 1);
         assert_eq!(emitter.stack()[0], one);
 
         emitter.dup(0);
-        emitter.neq();
+        emitter.neq().unwrap();
         assert_eq!(emitter.stack_len(), 1);
         assert_eq!(emitter.stack()[0], Type::I1);
     }
@@ -972,8 +988,8 @@ mod tests {
         let t = Immediate::I1(true);
         let f = Immediate::I1(false);
 
-        emitter.literal(t);
-        emitter.literal(f);
+        emitter.literal(t).unwrap();
+        emitter.literal(f).unwrap();
 
         emitter.and_imm(t);
         assert_eq!(emitter.stack_len(), 2);
@@ -995,8 +1011,8 @@ mod tests {
         let t = Immediate::I1(true);
         let f = Immediate::I1(false);
 
-        emitter.literal(t);
-        emitter.literal(f);
+        emitter.literal(t).unwrap();
+        emitter.literal(f).unwrap();
 
         emitter.or_imm(t);
         assert_eq!(emitter.stack_len(), 2);
@@ -1018,8 +1034,8 @@ mod tests {
         let t = Immediate::I1(true);
         let f = Immediate::I1(false);
 
-        emitter.literal(t);
-        emitter.literal(f);
+        emitter.literal(t).unwrap();
+        emitter.literal(f).unwrap();
 
         emitter.xor_imm(t);
         assert_eq!(emitter.stack_len(), 2);
@@ -1040,7 +1056,7 @@ mod tests {
 
         let t = Immediate::I1(true);
 
-        emitter.literal(t);
+        emitter.literal(t).unwrap();
 
         emitter.not();
         assert_eq!(emitter.stack_len(), 1);
@@ -1057,17 +1073,17 @@ mod tests {
         let one = Immediate::U32(1);
         let two = Immediate::U32(2);
 
-        emitter.literal(one);
-        emitter.literal(two);
+        emitter.literal(one).unwrap();
+        emitter.literal(two).unwrap();
 
-        emitter.gt_imm(two);
+        emitter.gt_imm(two).unwrap();
         assert_eq!(emitter.stack_len(), 2);
         assert_eq!(emitter.stack()[0], Type::I1);
         assert_eq!(emitter.stack()[1], one);
 
         emitter.drop();
         emitter.dup(0);
-        emitter.gt();
+        emitter.gt().unwrap();
         assert_eq!(emitter.stack_len(), 1);
         assert_eq!(emitter.stack()[0], Type::I1);
     }
@@ -1082,17 +1098,17 @@ mod tests {
         let one = Immediate::U32(1);
         let two = Immediate::U32(2);
 
-        emitter.literal(one);
-        emitter.literal(two);
+        emitter.literal(one).unwrap();
+        emitter.literal(two).unwrap();
 
-        emitter.gte_imm(two);
+        emitter.gte_imm(two).unwrap();
         assert_eq!(emitter.stack_len(), 2);
         assert_eq!(emitter.stack()[0], Type::I1);
         assert_eq!(emitter.stack()[1], one);
 
         emitter.drop();
         emitter.dup(0);
-        emitter.gte();
+        emitter.gte().unwrap();
         assert_eq!(emitter.stack_len(), 1);
         assert_eq!(emitter.stack()[0], Type::I1);
     }
@@ -1107,17 +1123,17 @@ mod tests {
         let one = Immediate::U32(1);
         let two = Immediate::U32(2);
 
-        emitter.literal(one);
-        emitter.literal(two);
+        emitter.literal(one).unwrap();
+        emitter.literal(two).unwrap();
 
-        emitter.lt_imm(two);
+        emitter.lt_imm(two).unwrap();
         assert_eq!(emitter.stack_len(), 2);
         assert_eq!(emitter.stack()[0], Type::I1);
         assert_eq!(emitter.stack()[1], one);
 
         emitter.drop();
         emitter.dup(0);
-        emitter.lt();
+        emitter.lt().unwrap();
         assert_eq!(emitter.stack_len(), 1);
         assert_eq!(emitter.stack()[0], Type::I1);
     }
@@ -1132,17 +1148,17 @@ mod tests {
         let one = Immediate::U32(1);
         let two = Immediate::U32(2);
 
-        emitter.literal(one);
-        emitter.literal(two);
+        emitter.literal(one).unwrap();
+        emitter.literal(two).unwrap();
 
-        emitter.lte_imm(two);
+        emitter.lte_imm(two).unwrap();
         assert_eq!(emitter.stack_len(), 2);
         assert_eq!(emitter.stack()[0], Type::I1);
         assert_eq!(emitter.stack()[1], one);
 
         emitter.drop();
         emitter.dup(0);
-        emitter.lte();
+        emitter.lte().unwrap();
         assert_eq!(emitter.stack_len(), 1);
         assert_eq!(emitter.stack()[0], Type::I1);
     }
@@ -1157,15 +1173,15 @@ mod tests {
         let one = Immediate::U32(1);
         let two = Immediate::U32(2);
 
-        emitter.literal(one);
-        emitter.literal(two);
+        emitter.literal(one).unwrap();
+        emitter.literal(two).unwrap();
 
-        emitter.checked_div_imm(two);
+        emitter.checked_div_imm(two).unwrap();
         assert_eq!(emitter.stack_len(), 2);
         assert_eq!(emitter.stack()[0], Type::U32);
         assert_eq!(emitter.stack()[1], one);
 
-        emitter.checked_div();
+        emitter.checked_div().unwrap();
         assert_eq!(emitter.stack_len(), 1);
         assert_eq!(emitter.stack()[0], Type::U32);
     }
@@ -1180,15 +1196,15 @@ mod tests {
         let one = Immediate::U32(1);
         let two = Immediate::U32(2);
 
-        emitter.literal(one);
-        emitter.literal(two);
+        emitter.literal(one).unwrap();
+        emitter.literal(two).unwrap();
 
-        emitter.unchecked_div_imm(two);
+        emitter.unchecked_div_imm(two).unwrap();
         assert_eq!(emitter.stack_len(), 2);
         assert_eq!(emitter.stack()[0], Type::U32);
         assert_eq!(emitter.stack()[1], one);
 
-        emitter.unchecked_div();
+        emitter.unchecked_div().unwrap();
         assert_eq!(emitter.stack_len(), 1);
         assert_eq!(emitter.stack()[0], Type::U32);
     }
@@ -1203,15 +1219,15 @@ mod tests {
         let one = Immediate::U32(1);
         let two = Immediate::U32(2);
 
-        emitter.literal(one);
-        emitter.literal(two);
+        emitter.literal(one).unwrap();
+        emitter.literal(two).unwrap();
 
-        emitter.checked_mod_imm(two);
+        emitter.checked_mod_imm(two).unwrap();
         assert_eq!(emitter.stack_len(), 2);
         assert_eq!(emitter.stack()[0], Type::U32);
         assert_eq!(emitter.stack()[1], one);
 
-        emitter.checked_mod();
+        emitter.checked_mod().unwrap();
         assert_eq!(emitter.stack_len(), 1);
         assert_eq!(emitter.stack()[0], Type::U32);
     }
@@ -1226,15 +1242,15 @@ mod tests {
         let one = Immediate::U32(1);
         let two = Immediate::U32(2);
 
-        emitter.literal(one);
-        emitter.literal(two);
+        emitter.literal(one).unwrap();
+        emitter.literal(two).unwrap();
 
-        emitter.unchecked_mod_imm(two);
+        emitter.unchecked_mod_imm(two).unwrap();
         assert_eq!(emitter.stack_len(), 2);
         assert_eq!(emitter.stack()[0], Type::U32);
         assert_eq!(emitter.stack()[1], one);
 
-        emitter.unchecked_mod();
+        emitter.unchecked_mod().unwrap();
         assert_eq!(emitter.stack_len(), 1);
         assert_eq!(emitter.stack()[0], Type::U32);
     }
@@ -1249,16 +1265,16 @@ mod tests {
         let one = Immediate::U32(1);
         let two = Immediate::U32(2);
 
-        emitter.literal(one);
-        emitter.literal(two);
+        emitter.literal(one).unwrap();
+        emitter.literal(two).unwrap();
 
-        emitter.checked_divmod_imm(two);
+        emitter.checked_divmod_imm(two).unwrap();
         assert_eq!(emitter.stack_len(), 3);
         assert_eq!(emitter.stack()[0], Type::U32);
         assert_eq!(emitter.stack()[1], Type::U32);
         assert_eq!(emitter.stack()[2], one);
 
-        emitter.checked_divmod();
+        emitter.checked_divmod().unwrap();
         assert_eq!(emitter.stack_len(), 3);
         assert_eq!(emitter.stack()[0], Type::U32);
         assert_eq!(emitter.stack()[1], Type::U32);
@@ -1275,16 +1291,16 @@ mod tests {
         let one = Immediate::U32(1);
         let two = Immediate::U32(2);
 
-        emitter.literal(one);
-        emitter.literal(two);
+        emitter.literal(one).unwrap();
+        emitter.literal(two).unwrap();
 
-        emitter.unchecked_divmod_imm(two);
+        emitter.unchecked_divmod_imm(two).unwrap();
         assert_eq!(emitter.stack_len(), 3);
         assert_eq!(emitter.stack()[0], Type::U32);
         assert_eq!(emitter.stack()[1], Type::U32);
         assert_eq!(emitter.stack()[2], one);
 
-        emitter.unchecked_divmod();
+        emitter.unchecked_divmod().unwrap();
         assert_eq!(emitter.stack_len(), 3);
         assert_eq!(emitter.stack()[0], Type::U32);
         assert_eq!(emitter.stack()[1], Type::U32);
@@ -1301,15 +1317,15 @@ mod tests {
         let one = Immediate::U32(1);
         let two = Immediate::U32(2);
 
-        emitter.literal(one);
-        emitter.literal(two);
+        emitter.literal(one).unwrap();
+        emitter.literal(two).unwrap();
 
-        emitter.exp_imm(two);
+        emitter.exp_imm(two).unwrap();
         assert_eq!(emitter.stack_len(), 2);
         assert_eq!(emitter.stack()[0], Type::U32);
         assert_eq!(emitter.stack()[1], one);
 
-        emitter.exp();
+        emitter.exp().unwrap();
         assert_eq!(emitter.stack_len(), 1);
         assert_eq!(emitter.stack()[0], Type::U32);
     }
@@ -1324,15 +1340,15 @@ mod tests {
         let one = Immediate::U32(1);
         let two = Immediate::U32(2);
 
-        emitter.literal(one);
-        emitter.literal(two);
+        emitter.literal(one).unwrap();
+        emitter.literal(two).unwrap();
 
-        emitter.band_imm(one);
+        emitter.band_imm(one).unwrap();
         assert_eq!(emitter.stack_len(), 2);
         assert_eq!(emitter.stack()[0], Type::U32);
         assert_eq!(emitter.stack()[1], one);
 
-        emitter.band();
+        emitter.band().unwrap();
         assert_eq!(emitter.stack_len(), 1);
         assert_eq!(emitter.stack()[0], Type::U32);
     }
@@ -1347,15 +1363,15 @@ mod tests {
         let one = Immediate::U32(1);
         let two = Immediate::U32(2);
 
-        emitter.literal(one);
-        emitter.literal(two);
+        emitter.literal(one).unwrap();
+        emitter.literal(two).unwrap();
 
-        emitter.bor_imm(one);
+        emitter.bor_imm(one).unwrap();
         assert_eq!(emitter.stack_len(), 2);
         assert_eq!(emitter.stack()[0], Type::U32);
         assert_eq!(emitter.stack()[1], one);
 
-        emitter.bor();
+        emitter.bor().unwrap();
         assert_eq!(emitter.stack_len(), 1);
         assert_eq!(emitter.stack()[0], Type::U32);
     }
@@ -1370,15 +1386,15 @@ mod tests {
         let one = Immediate::U32(1);
         let two = Immediate::U32(2);
 
-        emitter.literal(one);
-        emitter.literal(two);
+        emitter.literal(one).unwrap();
+        emitter.literal(two).unwrap();
 
-        emitter.bxor_imm(one);
+        emitter.bxor_imm(one).unwrap();
         assert_eq!(emitter.stack_len(), 2);
         assert_eq!(emitter.stack()[0], Type::U32);
         assert_eq!(emitter.stack()[1], one);
 
-        emitter.bxor();
+        emitter.bxor().unwrap();
         assert_eq!(emitter.stack_len(), 1);
         assert_eq!(emitter.stack()[0], Type::U32);
     }
@@ -1393,15 +1409,15 @@ mod tests {
         let one = Immediate::U32(1);
         let two = Immediate::U32(2);
 
-        emitter.literal(one);
-        emitter.literal(two);
+        emitter.literal(one).unwrap();
+        emitter.literal(two).unwrap();
 
-        emitter.shl_imm(one);
+        emitter.shl_imm(one).unwrap();
         assert_eq!(emitter.stack_len(), 2);
         assert_eq!(emitter.stack()[0], Type::U32);
         assert_eq!(emitter.stack()[1], one);
 
-        emitter.shl();
+        emitter.shl().unwrap();
         assert_eq!(emitter.stack_len(), 1);
         assert_eq!(emitter.stack()[0], Type::U32);
     }
@@ -1416,15 +1432,15 @@ mod tests {
         let one = Immediate::U32(1);
         let two = Immediate::U32(2);
 
-        emitter.literal(one);
-        emitter.literal(two);
+        emitter.literal(one).unwrap();
+        emitter.literal(two).unwrap();
 
-        emitter.shr_imm(one);
+        emitter.shr_imm(one).unwrap();
         assert_eq!(emitter.stack_len(), 2);
         assert_eq!(emitter.stack()[0], Type::U32);
         assert_eq!(emitter.stack()[1], one);
 
-        emitter.shr();
+        emitter.shr().unwrap();
         assert_eq!(emitter.stack_len(), 1);
         assert_eq!(emitter.stack()[0], Type::U32);
     }
@@ -1439,15 +1455,15 @@ mod tests {
         let one = Immediate::U32(1);
         let two = Immediate::U32(2);
 
-        emitter.literal(one);
-        emitter.literal(two);
+        emitter.literal(one).unwrap();
+        emitter.literal(two).unwrap();
 
-        emitter.rotl_imm(one);
+        emitter.rotl_imm(one).unwrap();
         assert_eq!(emitter.stack_len(), 2);
         assert_eq!(emitter.stack()[0], Type::U32);
         assert_eq!(emitter.stack()[1], one);
 
-        emitter.rotl();
+        emitter.rotl().unwrap();
         assert_eq!(emitter.stack_len(), 1);
         assert_eq!(emitter.stack()[0], Type::U32);
     }
@@ -1462,15 +1478,15 @@ mod tests {
         let one = Immediate::U32(1);
         let two = Immediate::U32(2);
 
-        emitter.literal(one);
-        emitter.literal(two);
+        emitter.literal(one).unwrap();
+        emitter.literal(two).unwrap();
 
-        emitter.rotr_imm(one);
+        emitter.rotr_imm(one).unwrap();
         assert_eq!(emitter.stack_len(), 2);
         assert_eq!(emitter.stack()[0], Type::U32);
         assert_eq!(emitter.stack()[1], one);
 
-        emitter.rotr();
+        emitter.rotr().unwrap();
         assert_eq!(emitter.stack_len(), 1);
         assert_eq!(emitter.stack()[0], Type::U32);
     }
@@ -1485,15 +1501,15 @@ mod tests {
         let one = Immediate::U32(1);
         let two = Immediate::U32(2);
 
-        emitter.literal(one);
-        emitter.literal(two);
+        emitter.literal(one).unwrap();
+        emitter.literal(two).unwrap();
 
-        emitter.min_imm(one);
+        emitter.min_imm(one).unwrap();
         assert_eq!(emitter.stack_len(), 2);
         assert_eq!(emitter.stack()[0], Type::U32);
         assert_eq!(emitter.stack()[1], one);
 
-        emitter.min();
+        emitter.min().unwrap();
         assert_eq!(emitter.stack_len(), 1);
         assert_eq!(emitter.stack()[0], Type::U32);
     }
@@ -1508,15 +1524,15 @@ mod tests {
         let one = Immediate::U32(1);
         let two = Immediate::U32(2);
 
-        emitter.literal(one);
-        emitter.literal(two);
+        emitter.literal(one).unwrap();
+        emitter.literal(two).unwrap();
 
-        emitter.max_imm(one);
+        emitter.max_imm(one).unwrap();
         assert_eq!(emitter.stack_len(), 2);
         assert_eq!(emitter.stack()[0], Type::U32);
         assert_eq!(emitter.stack()[1], one);
 
-        emitter.max();
+        emitter.max().unwrap();
         assert_eq!(emitter.stack_len(), 1);
         assert_eq!(emitter.stack()[0], Type::U32);
     }
@@ -1530,9 +1546,9 @@ mod tests {
 
         let max = Immediate::U32(u32::MAX);
 
-        emitter.literal(max);
+        emitter.literal(max).unwrap();
 
-        emitter.trunc(&Type::U16);
+        emitter.trunc(&Type::U16).unwrap();
         assert_eq!(emitter.stack_len(), 1);
         assert_eq!(emitter.stack()[0], Type::U16);
     }
@@ -1546,9 +1562,9 @@ mod tests {
 
         let one = Immediate::U16(1);
 
-        emitter.literal(one);
+        emitter.literal(one).unwrap();
 
-        emitter.zext(&Type::U32);
+        emitter.zext(&Type::U32).unwrap();
         assert_eq!(emitter.stack_len(), 1);
         assert_eq!(emitter.stack()[0], Type::U32);
     }
@@ -1562,9 +1578,9 @@ mod tests {
 
         let num = Immediate::I16(-128);
 
-        emitter.literal(num);
+        emitter.literal(num).unwrap();
 
-        emitter.sext(&Type::I32);
+        emitter.sext(&Type::I32).unwrap();
         assert_eq!(emitter.stack_len(), 1);
         assert_eq!(emitter.stack()[0], Type::I32);
     }
@@ -1578,9 +1594,9 @@ mod tests {
 
         let num = Immediate::U32(128);
 
-        emitter.literal(num);
+        emitter.literal(num).unwrap();
 
-        emitter.cast(&Type::I32);
+        emitter.cast(&Type::I32).unwrap();
         assert_eq!(emitter.stack_len(), 1);
         assert_eq!(emitter.stack()[0], Type::I32);
     }
@@ -1595,7 +1611,7 @@ mod tests {
         let addr = Immediate::U32(128);
         let ptr = Type::Ptr(Box::new(Type::Array(Box::new(Type::U64), 8)));
 
-        emitter.literal(addr);
+        emitter.literal(addr).unwrap();
 
         emitter.inttoptr(&ptr);
         assert_eq!(emitter.stack_len(), 1);
@@ -1611,9 +1627,9 @@ mod tests {
 
         let num = Immediate::U32(128);
 
-        emitter.literal(num);
+        emitter.literal(num).unwrap();
 
-        emitter.is_odd();
+        emitter.is_odd().unwrap();
         assert_eq!(emitter.stack_len(), 1);
         assert_eq!(emitter.stack()[0], Type::I1);
     }
@@ -1627,9 +1643,9 @@ mod tests {
 
         let num = Immediate::U32(128);
 
-        emitter.literal(num);
+        emitter.literal(num).unwrap();
 
-        emitter.popcnt();
+        emitter.popcnt().unwrap();
         assert_eq!(emitter.stack_len(), 1);
         assert_eq!(emitter.stack()[0], Type::U32);
     }
@@ -1643,9 +1659,9 @@ mod tests {
 
         let num = Immediate::U32(128);
 
-        emitter.literal(num);
+        emitter.literal(num).unwrap();
 
-        emitter.bnot();
+        emitter.bnot().unwrap();
         assert_eq!(emitter.stack_len(), 1);
         assert_eq!(emitter.stack()[0], Type::U32);
     }
@@ -1659,9 +1675,9 @@ mod tests {
 
         let ten = Immediate::U32(10);
 
-        emitter.literal(ten);
+        emitter.literal(ten).unwrap();
 
-        emitter.pow2();
+        emitter.pow2().unwrap();
         assert_eq!(emitter.stack_len(), 1);
         assert_eq!(emitter.stack()[0], Type::U32);
     }
@@ -1675,9 +1691,9 @@ mod tests {
 
         let ten = Immediate::U32(10);
 
-        emitter.literal(ten);
+        emitter.literal(ten).unwrap();
 
-        emitter.incr();
+        emitter.incr().unwrap();
         assert_eq!(emitter.stack_len(), 1);
         assert_eq!(emitter.stack()[0], Type::U32);
     }
@@ -1691,9 +1707,9 @@ mod tests {
 
         let ten = Immediate::Felt(Felt::new(10));
 
-        emitter.literal(ten);
+        emitter.literal(ten).unwrap();
 
-        emitter.inv();
+        emitter.inv().unwrap();
         assert_eq!(emitter.stack_len(), 1);
         assert_eq!(emitter.stack()[0], Type::Felt);
     }
@@ -1707,9 +1723,9 @@ mod tests {
 
         let ten = Immediate::Felt(Felt::new(10));
 
-        emitter.literal(ten);
+        emitter.literal(ten).unwrap();
 
-        emitter.neg();
+        emitter.neg().unwrap();
         assert_eq!(emitter.stack_len(), 1);
         assert_eq!(emitter.stack()[0], Type::Felt);
     }
@@ -1723,10 +1739,10 @@ mod tests {
 
         let ten = Immediate::U32(10);
 
-        emitter.literal(ten);
+        emitter.literal(ten).unwrap();
         assert_eq!(emitter.stack_len(), 1);
 
-        emitter.assert();
+        emitter.assert().unwrap();
         assert_eq!(emitter.stack_len(), 0);
     }
 
@@ -1739,10 +1755,10 @@ mod tests {
 
         let ten = Immediate::U32(10);
 
-        emitter.literal(ten);
+        emitter.literal(ten).unwrap();
         assert_eq!(emitter.stack_len(), 1);
 
-        emitter.assertz();
+        emitter.assertz().unwrap();
         assert_eq!(emitter.stack_len(), 0);
     }
 
@@ -1755,15 +1771,15 @@ mod tests {
 
         let ten = Immediate::U32(10);
 
-        emitter.literal(ten);
-        emitter.literal(ten);
-        emitter.literal(ten);
+        emitter.literal(ten).unwrap();
+        emitter.literal(ten).unwrap();
+        emitter.literal(ten).unwrap();
         assert_eq!(emitter.stack_len(), 3);
 
-        emitter.assert_eq_imm(ten);
+        emitter.assert_eq_imm(ten).unwrap();
         assert_eq!(emitter.stack_len(), 2);
 
-        emitter.assert_eq();
+        emitter.assert_eq().unwrap();
         assert_eq!(emitter.stack_len(), 0);
     }
 
@@ -1778,12 +1794,12 @@ mod tests {
         let one = Immediate::U32(1);
         let two = Immediate::U32(2);
 
-        emitter.literal(one);
-        emitter.literal(two);
-        emitter.literal(t);
+        emitter.literal(one).unwrap();
+        emitter.literal(two).unwrap();
+        emitter.literal(t).unwrap();
         assert_eq!(emitter.stack_len(), 3);
 
-        emitter.select();
+        emitter.select().unwrap();
         assert_eq!(emitter.stack_len(), 1);
         assert_eq!(emitter.stack()[0], Type::U32);
     }
@@ -1809,11 +1825,11 @@ mod tests {
         let t = Immediate::I1(true);
         let one = Immediate::U32(1);
 
-        emitter.literal(t);
-        emitter.literal(one);
+        emitter.literal(t).unwrap();
+        emitter.literal(one).unwrap();
         assert_eq!(emitter.stack_len(), 2);
 
-        emitter.exec(&callee);
+        emitter.exec(&callee).unwrap();
         assert_eq!(emitter.stack_len(), 1);
         assert_eq!(emitter.stack()[0], return_ty);
     }
@@ -1830,11 +1846,11 @@ mod tests {
         emitter.push(addr);
         assert_eq!(emitter.stack_len(), 1);
 
-        emitter.load(Type::U32);
+        emitter.load(Type::U32).unwrap();
         assert_eq!(emitter.stack_len(), 1);
         assert_eq!(emitter.stack()[0], Type::U32);
 
-        emitter.load_imm(128, Type::I32);
+        emitter.load_imm(128, Type::I32).unwrap();
         assert_eq!(emitter.stack_len(), 2);
         assert_eq!(emitter.stack()[0], Type::I32);
         assert_eq!(emitter.stack()[1], Type::U32);
@@ -1,6 +1,6 @@
-use miden_hir::{self as hir, ArgumentExtension, ArgumentPurpose, Felt, Immediate, Type};
+use miden_hir::{self as hir, ArgumentExtension, ArgumentPurpose, Felt, Immediate, Opcode, Type};
 
-use crate::masm::Op;
+use crate::{masm::Op, CodegenError};
 
 use super::{int64, OpEmitter};
 
@@ -8,7 +8,7 @@ impl<'a> OpEmitter<'a> {
     /// Assert that an integer value on the stack has the value 1
     ///
     /// This operation consumes the input value.
-    pub fn assert(&mut self) {
+    pub fn assert(&mut self) -> Result<(), CodegenError> {
         let arg = self.stack.pop().expect("operand stack is empty");
         match arg.ty() {
             Type::Felt
@@ -30,14 +30,15 @@ impl<'a> OpEmitter<'a> {
             ty if !ty.is_integer() => {
                 panic!("invalid argument to assert: expected integer, got {ty}")
             }
-            ty => unimplemented!("support for assert on {ty} is not implemented"),
+            ty => return Err(self.unsupported(Opcode::Assert, ty)),
         }
+        Ok(())
     }
 
     /// Assert that an integer value on the stack has the value 0
     ///
     /// This operation consumes the input value.
-    pub fn assertz(&mut self) {
+    pub fn assertz(&mut self) -> Result<(), CodegenError> {
         let arg = self.stack.pop().expect("operand stack is empty");
         match arg.ty() {
             Type::Felt
@@ -56,14 +57,15 @@ impl<'a> OpEmitter<'a> {
             ty if !ty.is_integer() => {
                 panic!("invalid argument to assertz: expected integer, got {ty}")
             }
-            ty => unimplemented!("support for assertz on {ty} is not implemented"),
+            ty => return Err(self.unsupported(Opcode::Assertz, ty)),
         }
+        Ok(())
     }
 
     /// Assert that the top two integer values on the stack have the same value
     ///
     /// This operation consumes the input values.
-    pub fn assert_eq(&mut self) {
+    pub fn assert_eq(&mut self) -> Result<(), CodegenError> {
         let rhs = self.pop().expect("operand stack is empty");
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
@@ -96,15 +98,16 @@ impl<'a> OpEmitter<'a> {
             ty if !ty.is_integer() => {
                 panic!("invalid argument to assert_eq: expected integer, got {ty}")
             }
-            ty => unimplemented!("support for assert_eq on {ty} is not implemented"),
+            ty => return Err(self.unsupported(Opcode::AssertEq, ty)),
         }
+        Ok(())
     }
 
     /// Emit code to assert that an integer value on the stack has the same value
     /// as the provided immediate.
     ///
     /// This operation consumes the input value.
-    pub fn assert_eq_imm(&mut self, imm: Immediate) {
+    pub fn assert_eq_imm(&mut self, imm: Immediate) -> Result<(), CodegenError> {
         let lhs = self.pop().expect("operand stack is empty");
         let ty = lhs.ty();
         assert_eq!(
@@ -124,7 +127,7 @@ impl<'a> OpEmitter<'a> {
                 self.emit_all(&[Op::EqImm(imm.as_felt().unwrap()), Op::Assert]);
             }
             Type::I128 => {
-                self.push_immediate(imm);
+                self.push_immediate(imm)?;
                 self.emit(Op::AssertEqw)
             }
             Type::I64 | Type::U64 => {
@@ -144,8 +147,9 @@ impl<'a> OpEmitter<'a> {
             ty if !ty.is_integer() => {
                 panic!("invalid argument to assert_eq: expected integer, got {ty}")
             }
-            ty => unimplemented!("support for assert_eq on {ty} is not implemented"),
+            ty => return Err(self.unsupported(Opcode::AssertEq, ty)),
         }
+        Ok(())
     }
 
     /// Emit code to select between two values of the same type, based on a boolean condition.
@@ -158,7 +162,7 @@ impl<'a> OpEmitter<'a> {
     /// * Pop `b` and `a` from the stack, and push back `b` if `c` is true, or `a` if `c` is false.
     ///
     /// This operation will assert that the selected value is a valid value for the given type.
-    pub fn select(&mut self) {
+    pub fn select(&mut self) -> Result<(), CodegenError> {
         let c = self.stack.pop().expect("operand stack is empty");
         let b = self.stack.pop().expect("operand stack is empty");
         let a = self.stack.pop().expect("operand stack is empty");
@@ -194,15 +198,33 @@ impl<'a> OpEmitter<'a> {
             ty if !ty.is_integer() => {
                 panic!("invalid argument to assert_eq: expected integer, got {ty}")
             }
-            ty => unimplemented!("support for assert_eq on {ty} is not implemented"),
+            ty => return Err(self.unsupported(Opcode::Select, ty.clone())),
         }
         self.stack.push(ty);
+        Ok(())
     }
 
     /// Execute the given procedure.
     ///
     /// A function called using this operation is invoked in the same memory context as the caller.
-    pub fn exec(&mut self, callee: &hir::ExternalFunction) {
+    pub fn exec(&mut self, callee: &hir::ExternalFunction) -> Result<(), CodegenError> {
+        self.prepare_call(callee)?;
+        self.emit(Op::Exec(callee.id));
+        Ok(())
+    }
+
+    /// Execute the given procedure as a syscall.
+    ///
+    /// A function called using this operation is invoked in the root (kernel) context.
+    pub fn syscall(&mut self, callee: &hir::ExternalFunction) -> Result<(), CodegenError> {
+        self.prepare_call(callee)?;
+        self.emit(Op::Syscall(callee.id));
+        Ok(())
+    }
+
+    /// Validate and extend the arguments to `callee` on the operand stack, replacing them
+    /// with its results.
+    fn prepare_call(&mut self, callee: &hir::ExternalFunction) -> Result<(), CodegenError> {
         let import = callee;
         let callee = import.id;
         let signature = &import.signature;
@@ -236,7 +258,7 @@ impl<'a> OpEmitter<'a> {
                     assert!(provided_size <= expected_size, "invalid call to {callee}: invalid argument type for parameter at index {i}, expected integer width to be <= {expected_size} bits");
                     // Zero-extend this argument
                     self.stack.push(arg);
-                    self.zext(&param.ty);
+                    self.zext(&param.ty)?;
                     self.stack.drop();
                 }
                 // Caller can provide a smaller type which will be sign-extended to the expected type
@@ -254,7 +276,7 @@ impl<'a> OpEmitter<'a> {
                     }
                     // Push the operand back on the stack for `sext`
                     self.stack.push(arg);
-                    self.sext(&param.ty);
+                    self.sext(&param.ty)?;
                     self.stack.drop();
                 }
                 ArgumentExtension::Zext | ArgumentExtension::Sext => (),
@@ -265,13 +287,6 @@ impl<'a> OpEmitter<'a> {
             self.stack.push(result.ty.clone());
         }
 
-        self.emit(Op::Exec(callee));
-    }
-
-    /// Execute the given procedure as a syscall.
-    ///
-    /// A function called using this operation is invoked in the same memory context as the caller.
-    pub fn syscall(&mut self, _callee: &hir::ExternalFunction) {
-        todo!()
+        Ok(())
     }
 }
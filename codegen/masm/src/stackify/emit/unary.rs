@@ -1,4 +1,4 @@
-use miden_hir::{Overflow, Type};
+use miden_hir::{Opcode, Overflow, Type};
 
 use crate::masm::Op;
 
@@ -26,7 +26,7 @@ impl<'a> OpEmitter<'a> {
     /// This function assumes that an integer value of type `src` is on top of the operand stack,
     /// and will ensure a value of type `dst` is on the operand stack after truncation, or that
     /// execution traps.
-    pub fn trunc(&mut self, dst: &Type) {
+    pub fn trunc(&mut self, dst: &Type) -> Result<(), CodegenError> {
         let arg = self.stack.pop().expect("operand stack is empty");
         let src = arg.ty();
         assert!(
@@ -54,9 +54,10 @@ impl<'a> OpEmitter<'a> {
             (Type::I16 | Type::U16, _) if n <= 16 => self.trunc_int32(n),
             // Truncating an i8/u8 to smaller than 8 bits
             (Type::I8 | Type::U8, _) if n <= 8 => self.trunc_int32(n),
-            (src, dst) => unimplemented!("unsupported truncation of {src} to {dst}"),
+            (src, _) => return Err(self.unsupported(Opcode::Trunc, src.clone())),
         }
         self.stack.push(dst.clone());
+        Ok(())
     }
 
     /// Zero-extend an unsigned integral value of type `src` to type `dst`
@@ -85,7 +86,7 @@ impl<'a> OpEmitter<'a> {
     /// This function assumes that an integer value of type `src` is on top of the operand stack,
     /// and will ensure a value of type `dst` is on the operand stack after truncation, or that
     /// execution traps.
-    pub fn zext(&mut self, dst: &Type) {
+    pub fn zext(&mut self, dst: &Type) -> Result<(), CodegenError> {
         let arg = self.stack.pop().expect("operand stack is empty");
         let src = arg.ty();
         assert!(
@@ -111,9 +112,10 @@ impl<'a> OpEmitter<'a> {
             // Zero-extending to felt, from types that fit in felt, is a no-op
             (Type::I1 | Type::U8 | Type::U16 | Type::U32, Type::Felt) => (),
             (src, dst) if dst.is_signed_integer() => panic!("invalid zero-extension from {src} to {dst}: value may not fit in range, use explicit cast instead"),
-            (src, dst) => panic!("unsupported zero-extension from {src} to {dst}"),
+            (src, _) => return Err(self.unsupported(Opcode::Zext, src.clone())),
         }
         self.stack.push(dst.clone());
+        Ok(())
     }
 
     /// Sign-extend an integral value of type `src` to type `dst`
@@ -142,7 +144,7 @@ impl<'a> OpEmitter<'a> {
     /// This function assumes that an integer value of type `src` is on top of the operand stack,
     /// and will ensure a value of type `dst` is on the operand stack after truncation, or that
     /// execution traps.
-    pub fn sext(&mut self, dst: &Type) {
+    pub fn sext(&mut self, dst: &Type) -> Result<(), CodegenError> {
         let arg = self.stack.pop().expect("operand stack is empty");
         let src = arg.ty();
         assert!(src.is_integer() && dst.is_signed_integer(), "invalid sign-extension of {src} to {dst}: only integer-to-signed-integer casts are supported");
@@ -162,9 +164,10 @@ impl<'a> OpEmitter<'a> {
                 Type::I1 | Type::I8 | Type::U8 | Type::I16 | Type::U16,
                 Type::I32 | Type::I64 | Type::I128,
             ) => self.sext_smallint(src_bits, dst_bits),
-            (src, dst) => panic!("unsupported sign-extension from {src} to {dst}"),
+            (src, _) => return Err(self.unsupported(Opcode::Sext, src.clone())),
         }
         self.stack.push(dst.clone());
+        Ok(())
     }
 
     /// Convert between two integral types, given as `src` and `dst`,
@@ -191,7 +194,7 @@ impl<'a> OpEmitter<'a> {
     /// This function assumes that an integer value of type `src` is on top of the operand stack,
     /// and will ensure a value of type `dst` is on the operand stack after truncation, or that
     /// execution traps.
-    pub fn cast(&mut self, dst: &Type) {
+    pub fn cast(&mut self, dst: &Type) -> Result<(), CodegenError> {
         let arg = self.stack.pop().expect("operand stack is empty");
         let src = arg.ty();
         assert!(
@@ -293,9 +296,10 @@ impl<'a> OpEmitter<'a> {
             }
             // i1
             (Type::I1, _) => self.zext_smallint(src_bits, dst_bits),
-            (src, dst) => unimplemented!("unsupported cast from {src} to {dst}"),
+            (src, _) => return Err(self.unsupported(Opcode::Cast, src.clone())),
         }
         self.stack.push(dst.clone());
+        Ok(())
     }
 
     /// Cast `arg` to a pointer value
@@ -320,7 +324,7 @@ impl<'a> OpEmitter<'a> {
     /// The result is placed on the stack as a boolean value.
     ///
     /// This operation consumes the input operand.
-    pub fn is_odd(&mut self) {
+    pub fn is_odd(&mut self) -> Result<(), CodegenError> {
         let arg = self.stack.pop().expect("operand stack is empty");
         match arg.ty() {
             // For both signed and unsigned types,
@@ -348,19 +352,18 @@ impl<'a> OpEmitter<'a> {
                 self.emit_n(3, Op::Drop);
                 self.emit(Op::IsOdd);
             }
-            Type::F64 => {
-                unimplemented!("is_odd support for floating-point values is not yet implemented")
-            }
+            Type::F64 => return Err(self.unsupported(Opcode::IsOdd, Type::F64)),
             ty => panic!("expected integral type for is_odd opcode, got {ty}"),
         }
         self.stack.push(Type::I1);
+        Ok(())
     }
 
     /// Count the number of non-zero bits in the integral value on top of the operand stack,
     /// and place the count back on the stack as a u32 value.
     ///
     /// This operation consumes the input operand.
-    pub fn popcnt(&mut self) {
+    pub fn popcnt(&mut self) -> Result<(), CodegenError> {
         let arg = self.stack.pop().expect("operand stack is empty");
         let ty = arg.ty();
         match &ty {
@@ -405,9 +408,10 @@ impl<'a> OpEmitter<'a> {
             ty if !ty.is_integer() => {
                 panic!("invalid popcnt on {ty}: only integral types can be negated")
             }
-            ty => unimplemented!("popcnt for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::Popcnt, ty.clone())),
         }
         self.stack.push(ty);
+        Ok(())
     }
 
     /// Invert the bitwise representation of the integral value on top of the operand stack.
@@ -415,7 +419,7 @@ impl<'a> OpEmitter<'a> {
     /// This has the effect of changing all 1 bits to 0s, and all 0 bits to 1s.
     ///
     /// This operation consumes the input operand.
-    pub fn bnot(&mut self) {
+    pub fn bnot(&mut self) -> Result<(), CodegenError> {
         let arg = self.stack.pop().expect("operand stack is empty");
         let ty = arg.ty();
         match &ty {
@@ -445,9 +449,10 @@ impl<'a> OpEmitter<'a> {
             ty if !ty.is_integer() => {
                 panic!("invalid bnot on {ty}, only integral types are supported")
             }
-            ty => unimplemented!("bnot for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::Bnot, ty.clone())),
         }
         self.stack.push(ty);
+        Ok(())
     }
 
     /// Invert the boolean value on top of the operand stack.
@@ -466,7 +471,7 @@ impl<'a> OpEmitter<'a> {
     /// The input value must be < 64, or execution will trap.
     ///
     /// This operation consumes the input operand.
-    pub fn pow2(&mut self) {
+    pub fn pow2(&mut self) -> Result<(), CodegenError> {
         let arg = self.stack.pop().expect("operand stack is empty");
         let ty = arg.ty();
         match &ty {
@@ -496,9 +501,10 @@ impl<'a> OpEmitter<'a> {
                     "invalid unary operand: pow2 only permits unsigned integer operands, got {ty}"
                 )
             }
-            ty => unimplemented!("pow2 for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::Pow2, ty.clone())),
         }
         self.stack.push(ty);
+        Ok(())
     }
 
     /// Increment the operand on top of the stack by 1.
@@ -506,7 +512,7 @@ impl<'a> OpEmitter<'a> {
     /// The input value must be an integer, and overflow has wrapping semantics.
     ///
     /// This operation consumes the input operand.
-    pub fn incr(&mut self) {
+    pub fn incr(&mut self) -> Result<(), CodegenError> {
         let arg = self.stack.pop().expect("operand stack is empty");
         let ty = arg.ty();
         match &ty {
@@ -531,15 +537,16 @@ impl<'a> OpEmitter<'a> {
             ty if !ty.is_integer() => {
                 panic!("invalid unary operand: incr requires an integer operand, got {ty}")
             }
-            ty => unimplemented!("incr for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::Incr, ty.clone())),
         }
         self.stack.push(ty);
+        Ok(())
     }
 
     /// Compute the modular multiplicative inverse of the operand on top of the stack, `n`, i.e. `n^-1 mod P`.
     ///
     /// This operation consumes the input operand.
-    pub fn inv(&mut self) {
+    pub fn inv(&mut self) -> Result<(), CodegenError> {
         let arg = self.pop().expect("operand stack is empty");
         let ty = arg.ty();
         match &ty {
@@ -549,15 +556,16 @@ impl<'a> OpEmitter<'a> {
             ty if !ty.is_integer() => {
                 panic!("invalid unary operand: inv requires an integer, got {ty}")
             }
-            ty => unimplemented!("inv for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::Inv, ty.clone())),
         }
         self.push(ty);
+        Ok(())
     }
 
     /// Compute the modular negation of the operand on top of the stack, `n`, i.e. `-n mod P`.
     ///
     /// This operation consumes the input operand.
-    pub fn neg(&mut self) {
+    pub fn neg(&mut self) -> Result<(), CodegenError> {
         let arg = self.pop().expect("operand stack is empty");
         let ty = arg.ty();
         match &ty {
@@ -567,8 +575,9 @@ impl<'a> OpEmitter<'a> {
             ty if !ty.is_integer() => {
                 panic!("invalid unary operand: neg requires an integer, got {ty}")
             }
-            ty => unimplemented!("neg for {ty} is not supported"),
+            ty => return Err(self.unsupported(Opcode::Neg, ty.clone())),
         }
         self.push(ty);
+        Ok(())
     }
 }
@@ -3,6 +3,7 @@ pub(crate) mod emit;
 mod operand_stack;
 mod pass;
 mod treegraph;
+mod verify;
 
 pub(crate) use self::dependency_graph::{Dependency, DependencyGraph, DependencyId, Node};
 pub(crate) use self::operand_stack::{Operand, OperandStack, OperandType, TypedValue};
@@ -1,4 +1,9 @@
-use std::{cmp::Ordering, collections::VecDeque, fmt, rc::Rc};
+use std::{
+    cmp::{self, Ordering},
+    collections::VecDeque,
+    fmt,
+    rc::Rc,
+};
 
 use cranelift_entity::packed_option::ReservedValue;
 use miden_hir::{self as hir, assert_matches, BranchInfo, Immediate, Instruction, ProgramPoint};
@@ -7,10 +12,14 @@ use miden_hir_pass::Pass;
 use rustc_hash::{FxHashMap, FxHashSet};
 use smallvec::SmallVec;
 
-use crate::masm::{self, Op};
+use crate::{
+    masm::{self, Op},
+    CodegenError,
+};
 
 use super::{
     emit::{InstOpEmitter, OpEmitter},
+    verify::verify_stack_effects,
     *,
 };
 
@@ -94,7 +103,7 @@ use super::{
 /// * In a reverse postorder traversal of the control flow graph, we visit each block, and
 ///   emit MASM IR according to a scheduling we compute.
 /// * The instruction schedule for a Miden IR basic block is based on the [TreeGraph] data structure.
-///   This scheduling is intended to make maximal use of the operand stack without needing to spill locals,
+///   This scheduling is intended to make maximal use of the operand stack without needing to spill to locals,
 ///   or perform excess operand stack manipulation. The treegraph is computed based on the data flow dependency
 ///   graph of the instructions in each basic block, represented by [DependencyGraph].
 /// * As we emit code, we emulate the state of the operand stack at each point, so that we can determine
@@ -152,11 +161,16 @@ use super::{
 /// the order is not always optimal, due to the presence of multiply-used values, or instructions with multiple results
 /// whose order is fixed, and may require some stack manipulation to adjust.
 ///
-/// Importantly, this approach allows us to forgo the need for locals/temporaries, as we are able to keep values
-/// on the operand stack for their entire live range, and only for as long as necessary. We do still use locals
-/// for automatic allocations (i.e. temporaries that we'd ordinarily need to allocate heap memory for, but
+/// Importantly, this approach largely allows us to forgo the need for locals/temporaries, as we are able to keep
+/// values on the operand stack for their entire live range, and only for as long as necessary. We do still use
+/// locals for automatic allocations (i.e. temporaries that we'd ordinarily need to allocate heap memory for, but
 /// should be freed when the call returns).
 ///
+/// The exception is when register pressure is high enough that emitting the next expression tree could require
+/// access to operands beyond the first 16 elements of the operand stack, which is as deep as Miden can reach.
+/// Before emitting such a tree, we spill operands to locals, choosing those whose next use is furthest away, and
+/// reload them when they are next used.
+///
 /// # Recovering Structured Control Flow
 ///
 /// Miden Assembly only provides us with a very limited set of (3) structured control flow ops, two of which interest
@@ -320,7 +334,13 @@ impl<'p> Pass for Stackify<'p> {
                 stack.push(TypedValue { value: arg, ty });
             }
 
-            emitter.emit(entry, entry_prime, stack);
+            emitter.emit(entry, entry_prime, stack)?;
+        }
+
+        // In debug builds, make sure the emitted code leaves the stack in the expected state
+        if cfg!(debug_assertions) {
+            verify_stack_effects(&f_prime, &f.dfg)
+                .map_err(|err| anyhow::anyhow!("stackification of {} failed: {err}", f.id))?;
         }
 
         Ok(f_prime)
@@ -360,6 +380,11 @@ struct MasmEmitter<'a> {
     /// When visiting blocks a second time, we emit code for branch instructions
     /// differently, so it is important to track this information.
     visited: FxHashSet<hir::Block>,
+    /// The procedure locals to which each spilled value has been stored, one per
+    /// element of the value.
+    ///
+    /// The same locals are reused each time a given value is spilled.
+    spills: FxHashMap<hir::Value, SmallVec<[masm::LocalId; 4]>>,
 }
 
 /// Represents a cached dependency graph, tree graph, and schedule for
@@ -392,6 +417,7 @@ impl<'a> MasmEmitter<'a> {
             current_block: masm::BlockId::reserved_value(),
             cached: Default::default(),
             visited: Default::default(),
+            spills: Default::default(),
         }
     }
 
@@ -402,7 +428,12 @@ impl<'a> MasmEmitter<'a> {
     /// control to another block in the function. Thus we must keep track of when we're
     /// visiting a block for the first time, as well as what block we were in when we started
     /// emitting code for `b`, so that we can properly emit code for loopback edges.
-    fn emit(&mut self, b: hir::Block, b_prime: masm::BlockId, stack: OperandStack) {
+    fn emit(
+        &mut self,
+        b: hir::Block,
+        b_prime: masm::BlockId,
+        stack: OperandStack,
+    ) -> Result<(), CodegenError> {
         let is_first_visit = self.visited.insert(b);
         // Update the current, controlling, and emitting blocks, but saving the previous
         // values so we can restore them when this function returns.
@@ -501,7 +532,7 @@ impl<'a> MasmEmitter<'a> {
                 &cached.treegraph,
                 stack,
                 is_first_visit,
-            );
+            )?;
         } else {
             assert!(is_first_visit, "unexpected cycle");
             let depgraph = build_dependency_graph(b, self.f, self.liveness);
@@ -515,13 +546,14 @@ impl<'a> MasmEmitter<'a> {
                 &treegraph,
                 stack,
                 is_first_visit,
-            );
+            )?;
         }
 
         // Restore the state of the emitter to where it was in the caller
         self.controlling_loop = controlling_loop;
         self.emitting = emitting;
         self.current_block = prev_block;
+        Ok(())
     }
 
     /// Emit code for the schedule corresponding to a basic block in the SSA IR
@@ -544,14 +576,24 @@ impl<'a> MasmEmitter<'a> {
         treegraph: &TreeGraph,
         mut stack: OperandStack,
         is_first_visit: bool,
-    ) {
+    ) -> Result<(), CodegenError> {
         let mut emit_schedule = schedule.to_vec();
         emit_schedule.reverse();
+        let demands = emit_schedule
+            .iter()
+            .map(|node| operand_demand(*node, depgraph, treegraph, self.f, self.liveness))
+            .collect::<Vec<_>>();
         // In reverse topological order, visit each node of the treegraph..
         //
         // Nodes in the schedule appear in program order when no other constraints
         // are present due to dependency ordering.
-        for node in schedule.iter().copied().rev() {
+        for (i, node) in emit_schedule.iter().copied().enumerate() {
+            // Make room on the operand stack for the expression tree we're about to emit,
+            // if necessary. This is only done between trees, so operands of the tree are
+            // never spilled while it is being emitted.
+            if node.is_instruction() {
+                self.spill_operands(&demands[i..], &mut stack);
+            }
             self.emit_node(
                 node,
                 schedule,
@@ -560,8 +602,126 @@ impl<'a> MasmEmitter<'a> {
                 &mut stack,
                 is_first_visit,
                 None,
-            );
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Spill operands to procedure locals, if emitting the expression tree whose operand
+    /// demand is the first of `demands` could otherwise require access to operands beyond
+    /// the first 16 elements of the operand stack, which is all Miden can reach.
+    ///
+    /// `demands` describes the remaining expression trees of the current block, in the
+    /// order they will be emitted.
+    ///
+    /// Victims are chosen by furthest next use, so that values needed soonest stay on the
+    /// operand stack, and operands of the tree itself are only spilled as a last resort.
+    /// Values with no next use are simply dropped. Spilled values are reloaded where they
+    /// are next used, see [Self::find_or_reload].
+    ///
+    /// NOTE: Register pressure as computed by liveness analysis counts values, not the field
+    /// elements they occupy, nor the dead values also on the operand stack, so we check the
+    /// actual size of the stack here instead.
+    fn spill_operands(&mut self, demands: &[OperandDemand], stack: &mut OperandStack) {
+        const MAX_ACCESSIBLE: usize = 16;
+
+        let demand = demands[0].size;
+        while stack.raw_len() + demand > MAX_ACCESSIBLE {
+            // Find the accessible operand with the furthest next use
+            let mut victim = None::<(usize, hir::Value, u32)>;
+            for (index, operand) in stack.iter().rev().enumerate() {
+                if stack.effective_index_inclusive(index) >= MAX_ACCESSIBLE {
+                    break;
+                }
+                let OperandType::Value(TypedValue { value, .. }) = operand.value() else {
+                    continue;
+                };
+                if self.is_loop_carried(*value) {
+                    continue;
+                }
+                let distance = self.next_use_distance(*value, demands);
+                if victim.map(|(_, _, d)| distance > d).unwrap_or(true) {
+                    victim = Some((index, *value, distance));
+                }
+            }
+
+            // If there is nothing we can spill, we must proceed regardless
+            let Some((index, value, distance)) = victim else {
+                break;
+            };
+            if distance == u32::MAX {
+                self.emitter(stack).drop_operand_at_position(index);
+                continue;
+            }
+            let size = stack[index].size();
+            let f_prime = &mut *self.f_prime;
+            let locals = self
+                .spills
+                .entry(value)
+                .or_insert_with(|| {
+                    (0..size)
+                        .map(|_| f_prime.alloc_local(hir::Type::Felt))
+                        .collect()
+                })
+                .clone();
+            self.emitter(stack).spill(index, &locals);
+        }
+    }
+
+    /// Return the distance to the next use of `value`, in terms of the expression trees
+    /// remaining to be emitted in the current block, whose operand demand is given by `demands`.
+    ///
+    /// Uses within the current block are always nearer than those in its successors.
+    fn next_use_distance(&self, value: hir::Value, demands: &[OperandDemand]) -> u32 {
+        if let Some(distance) = demands.iter().position(|d| d.used.contains(&value)) {
+            return distance as u32;
+        }
+        let last_inst = self.f.dfg.last_inst(self.emitting).unwrap();
+        match self
+            .liveness
+            .next_use_after(&value, ProgramPoint::Inst(last_inst))
+        {
+            u32::MAX => u32::MAX,
+            distance => distance.saturating_add(demands.len() as u32),
+        }
+    }
+
+    /// Returns true if `value` is live on entry to the header of any loop containing the
+    /// current block.
+    ///
+    /// Such values must not be spilled, as every edge to a loop header must agree on the
+    /// state of the operand stack on entry to it.
+    fn is_loop_carried(&self, value: hir::Value) -> bool {
+        let mut current_loop = self.loops.innermost_loop(self.emitting);
+        while let Some(lp) = current_loop {
+            let header = self.loops.loop_header(lp);
+            if self
+                .liveness
+                .is_live_at(&value, ProgramPoint::Block(header))
+            {
+                return true;
+            }
+            current_loop = self.loops.loop_parent(lp);
         }
+        false
+    }
+
+    /// Find `value` on the operand stack, reloading it from its spill slots if it was spilled.
+    ///
+    /// Returns the position of the value on the operand stack, and whether it was reloaded.
+    fn find_or_reload(&mut self, value: hir::Value, stack: &mut OperandStack) -> (usize, bool) {
+        if let Some(pos) = stack.find(&value) {
+            return (pos, false);
+        }
+        let locals = self
+            .spills
+            .get(&value)
+            .cloned()
+            .unwrap_or_else(|| panic!("value {value} not found on operand stack"));
+        let ty = self.f.dfg.value_type(value).clone();
+        self.emitter(stack)
+            .reload(TypedValue { value, ty }, &locals);
+        (0, true)
     }
 
     /// Emit code for a treegraph node (or a depedency of one)
@@ -626,7 +786,7 @@ impl<'a> MasmEmitter<'a> {
         stack: &mut OperandStack,
         is_first_visit: bool,
         dependent: Option<Node>,
-    ) {
+    ) -> Result<(), CodegenError> {
         match node {
             // We're emitting an instruction, or code to fetch one of the instruction results
             //
@@ -653,7 +813,7 @@ impl<'a> MasmEmitter<'a> {
                     is_first_visit,
                     dependent,
                     inst,
-                ),
+                )?,
                 None => self.emit_inst(
                     inst,
                     schedule,
@@ -662,7 +822,7 @@ impl<'a> MasmEmitter<'a> {
                     stack,
                     is_first_visit,
                     node,
-                ),
+                )?,
             },
             // We're emitting code for a value which is known to be on the operand stack
             // upon entry to the current block, i.e. it is the result of an instruction in
@@ -680,9 +840,10 @@ impl<'a> MasmEmitter<'a> {
                         node, schedule, depgraph, treegraph, stack, dependent, value,
                     );
                 } else {
-                    let pos = stack
-                        .find(&value)
-                        .expect("value not found on operand stack");
+                    // The value may have been spilled, in which case there is nothing to drop
+                    let Some(pos) = stack.find(&value) else {
+                        return Ok(());
+                    };
                     let num_dependents = treegraph.num_dependents(&node);
                     let is_live_after_block = self.liveness.is_live_after(
                         &value,
@@ -695,6 +856,7 @@ impl<'a> MasmEmitter<'a> {
                 }
             }
         }
+        Ok(())
     }
 
     /// Emit a node that represents an dependency on a value on the operand stack
@@ -761,11 +923,10 @@ impl<'a> MasmEmitter<'a> {
             let ix = self.f.dfg.inst(dependent_inst);
             ix.is_binary() && ix.is_commutative()
         };
-        let pos = stack
-            .find(&value)
-            .expect("value not found on operand stack");
+        let (pos, is_reloaded) = self.find_or_reload(value, stack);
         let mut emitter = self.emitter(stack);
-        if is_last_dependent {
+        // A reloaded value is always consumed, as it remains available in its spill slots
+        if is_last_dependent || is_reloaded {
             // This is the last usage, so move, rather than copy the value
             emitter.move_operand_to_position(pos, 0, is_operand_order_flexible);
         } else {
@@ -791,7 +952,7 @@ impl<'a> MasmEmitter<'a> {
         is_first_visit: bool,
         dependent: Node,
         inst: hir::Inst,
-    ) {
+    ) -> Result<(), CodegenError> {
         // When an instruction node is a dependency of a node in the same
         // tree graph tree, it is guaranteed to be the first time we have
         // observed that instruction, and thus we should emit code for the
@@ -841,7 +1002,7 @@ impl<'a> MasmEmitter<'a> {
                 stack,
                 is_first_visit,
                 node,
-            );
+            )?;
             // Handle copies before we proceed
             let results = self.f.dfg.inst_results(inst);
             let mut stack_index = 0u8;
@@ -935,7 +1096,6 @@ impl<'a> MasmEmitter<'a> {
                 let ix = self.f.dfg.inst(dependent.as_instruction().unwrap());
                 ix.is_binary() && ix.is_commutative()
             };
-            let mut emitter = self.emitter(stack);
             match inst_results.len() {
                 // This case represents situations in which control/data dependencies on
                 // an instruction are introduced, in order to affect the order in which code
@@ -944,11 +1104,9 @@ impl<'a> MasmEmitter<'a> {
                 // Currently, instructions only produce 1 or no results
                 1 => {
                     let operand = inst_results[0];
-                    let pos = emitter
-                        .stack()
-                        .find(&operand)
-                        .expect("could not find value on operand stack");
-                    if is_last_dependent {
+                    let (pos, is_reloaded) = self.find_or_reload(operand, stack);
+                    let mut emitter = self.emitter(stack);
+                    if is_last_dependent || is_reloaded {
                         emitter.move_operand_to_position(pos, 0, is_operand_order_flexible);
                     } else {
                         emitter.copy_operand_to_position(pos, 0, is_operand_order_flexible);
@@ -960,11 +1118,9 @@ impl<'a> MasmEmitter<'a> {
                     // Place values on the stack in LIFO order
                     for used in dependency.used().iter().rev() {
                         assert!(inst_results.contains(&used.value));
-                        let pos = emitter
-                            .stack()
-                            .find(&used.value)
-                            .expect("could not find value on operand stack");
-                        if is_last_dependent {
+                        let (pos, is_reloaded) = self.find_or_reload(used.value, stack);
+                        let mut emitter = self.emitter(stack);
+                        if is_last_dependent || is_reloaded {
                             emitter.move_operand_to_position(pos, 0, is_operand_order_flexible);
                         } else {
                             emitter.copy_operand_to_position(pos, 0, is_operand_order_flexible);
@@ -973,6 +1129,7 @@ impl<'a> MasmEmitter<'a> {
                 }
             }
         }
+        Ok(())
     }
 
     /// Emit code for a single instruction and it's dependencies
@@ -985,7 +1142,7 @@ impl<'a> MasmEmitter<'a> {
         stack: &mut OperandStack,
         is_first_visit: bool,
         node: Node,
-    ) {
+    ) -> Result<(), CodegenError> {
         // Emit all dependencies of this node in LIFO order
         //
         // These dependencies roughly correspond to the instruction arguments, but we
@@ -1009,7 +1166,7 @@ impl<'a> MasmEmitter<'a> {
                 stack,
                 is_first_visit,
                 Some(node),
-            );
+            )?;
         }
 
         // Emit code for the instruction, as well as maintenance of the operand
@@ -1026,10 +1183,10 @@ impl<'a> MasmEmitter<'a> {
                 // so empty the stack before proceeding.
                 emitter.truncate_stack(0);
                 // Push the result on the stack
-                emitter.literal(*arg);
+                emitter.literal(*arg)?;
                 // If we're in a loop, push N zeroes on the stack, where N is the current loop depth
                 for _ in 0..level {
-                    emitter.literal(false);
+                    emitter.literal(false)?;
                 }
             }
             Instruction::Ret(hir::Ret { args, .. }) => {
@@ -1060,7 +1217,7 @@ impl<'a> MasmEmitter<'a> {
                 emitter.truncate_stack(1);
                 // If we're in a loop, push N zeroes on the stack, where N is the current loop depth
                 for _ in 0..level {
-                    emitter.literal(false);
+                    emitter.literal(false)?;
                 }
             }
             // When we hit an unconditional branch instruction for the first time, one of the following
@@ -1132,10 +1289,10 @@ impl<'a> MasmEmitter<'a> {
                         block.push(Op::PushU8(1));
                         block.push(Op::While(body_blk));
                     }
-                    self.emit(destination, body_blk, stack.clone());
+                    self.emit(destination, body_blk, stack.clone())?;
                 } else {
                     // We're in a normal block, emit the target block inline
-                    self.emit(destination, self.current_block, stack.clone());
+                    self.emit(destination, self.current_block, stack.clone())?;
                 }
             }
             // When we reach an unconditional branch a second time, it is because a first-visit branch instruction
@@ -1179,9 +1336,9 @@ impl<'a> MasmEmitter<'a> {
                     .expect("expected controlling loop to be set");
                 let current_level = self.loops.level(current_loop).level();
                 let target_level = self.loops.loop_level(self.emitting).level();
-                emitter.literal(true);
+                emitter.literal(true)?;
                 for _ in 0..(current_level - target_level) {
-                    emitter.literal(false);
+                    emitter.literal(false)?;
                 }
             }
             // When visiting a conditional branch for the first time, the process is much the same
@@ -1260,7 +1417,7 @@ impl<'a> MasmEmitter<'a> {
                         let params = self.f.dfg.block_params(*then_dest);
                         prepare_stack_arguments(&then_args, params, &mut emitter);
                     }
-                    self.emit(*then_dest, then_blk, then_stack);
+                    self.emit(*then_dest, then_blk, then_stack)?;
                     // if.false
                     let mut else_stack = stack.clone();
                     {
@@ -1282,7 +1439,7 @@ impl<'a> MasmEmitter<'a> {
                         let params = self.f.dfg.block_params(*else_dest);
                         prepare_stack_arguments(&else_args, params, &mut emitter);
                     }
-                    self.emit(*else_dest, else_blk, else_stack);
+                    self.emit(*else_dest, else_blk, else_stack)?;
                 } else {
                     // This is a simple conditional statement
                     {
@@ -1308,7 +1465,7 @@ impl<'a> MasmEmitter<'a> {
                         let params = self.f.dfg.block_params(*then_dest);
                         prepare_stack_arguments(&then_args, params, &mut emitter);
                     }
-                    self.emit(*then_dest, then_blk, then_stack);
+                    self.emit(*then_dest, then_blk, then_stack)?;
                     // if.false
                     let mut else_stack = stack.clone();
                     {
@@ -1327,7 +1484,7 @@ impl<'a> MasmEmitter<'a> {
                         let params = self.f.dfg.block_params(*else_dest);
                         prepare_stack_arguments(&else_args, params, &mut emitter);
                     }
-                    self.emit(*else_dest, else_blk, else_stack);
+                    self.emit(*else_dest, else_blk, else_stack)?;
                 }
             }
             // Just like the unconditional case, when reaching a conditional branch a second time, we
@@ -1368,9 +1525,9 @@ impl<'a> MasmEmitter<'a> {
                 // Continue the target loop when it is reached, the top of the stack
                 // prior to this push.1 instruction holds the actual conditional, which
                 // will be evaluated by the `if.true` nested inside the target `while.true`
-                emitter.literal(true);
+                emitter.literal(true)?;
                 for _ in 0..(current_level - target_level) {
-                    emitter.literal(false);
+                    emitter.literal(false)?;
                 }
             }
             Instruction::Switch(_) => {
@@ -1378,29 +1535,35 @@ impl<'a> MasmEmitter<'a> {
             }
             // This is a non-terminator instruction, so emit the code for it, and update the
             // stack state to reflect the changes made
-            ix => self.emit_op(inst, ix, stack),
+            ix => self.emit_op(inst, ix, stack)?,
         }
+        Ok(())
     }
 
     /// Emit code for a non-terminator instruction, which consumes and produces values on the operand stack
-    fn emit_op(&mut self, inst: hir::Inst, ix: &hir::Instruction, stack: &mut OperandStack) {
+    fn emit_op(
+        &mut self,
+        inst: hir::Inst,
+        ix: &hir::Instruction,
+        stack: &mut OperandStack,
+    ) -> Result<(), CodegenError> {
         assert!(
             !ix.opcode().is_terminator(),
             "unhandled terminator in non-terminator context: {:?}",
             ix
         );
         match ix {
-            Instruction::GlobalValue(op) => self.emit_global_value(inst, op, stack),
-            Instruction::UnaryOpImm(op) => self.emit_unary_imm_op(inst, op, stack),
-            Instruction::UnaryOp(op) => self.emit_unary_op(inst, op, stack),
-            Instruction::BinaryOpImm(op) => self.emit_binary_imm_op(inst, op, stack),
-            Instruction::BinaryOp(op) => self.emit_binary_op(inst, op, stack),
-            Instruction::Test(op) => self.emit_test_op(inst, op, stack),
-            Instruction::Load(op) => self.emit_load_op(inst, op, stack),
-            Instruction::PrimOp(op) => self.emit_primop(inst, op, stack),
-            Instruction::PrimOpImm(op) => self.emit_primop_imm(inst, op, stack),
-            Instruction::Call(op) => self.emit_call_op(inst, op, stack),
-            Instruction::InlineAsm(op) => self.emit_inline_asm(inst, op, stack),
+            Instruction::GlobalValue(op) => self.emit_global_value(inst, op, stack)?,
+            Instruction::UnaryOpImm(op) => self.emit_unary_imm_op(inst, op, stack)?,
+            Instruction::UnaryOp(op) => self.emit_unary_op(inst, op, stack)?,
+            Instruction::BinaryOpImm(op) => self.emit_binary_imm_op(inst, op, stack)?,
+            Instruction::BinaryOp(op) => self.emit_binary_op(inst, op, stack)?,
+            Instruction::Test(op) => self.emit_test_op(inst, op, stack)?,
+            Instruction::Load(op) => self.emit_load_op(inst, op, stack)?,
+            Instruction::PrimOp(op) => self.emit_primop(inst, op, stack)?,
+            Instruction::PrimOpImm(op) => self.emit_primop_imm(inst, op, stack)?,
+            Instruction::Call(op) => self.emit_call_op(inst, op, stack)?,
+            Instruction::InlineAsm(op) => self.emit_inline_asm(inst, op, stack)?,
             // Control flow instructions are handled before `emit_op` is called
             Instruction::RetImm(_)
             | Instruction::Ret(_)
@@ -1408,6 +1571,7 @@ impl<'a> MasmEmitter<'a> {
             | Instruction::CondBr(_)
             | Instruction::Switch(_) => unreachable!(),
         }
+        Ok(())
     }
 
     fn emit_global_value(
@@ -1415,19 +1579,20 @@ impl<'a> MasmEmitter<'a> {
         inst: hir::Inst,
         op: &hir::GlobalValueOp,
         stack: &mut OperandStack,
-    ) {
+    ) -> Result<(), CodegenError> {
         assert_eq!(op.op, hir::Opcode::GlobalValue);
         let addr = self.calculate_global_value_addr(op.global);
         match self.f.dfg.global_value(op.global) {
             hir::GlobalValueData::Load { ref ty, .. } => {
                 let mut emitter = self.inst_emitter(inst, stack);
-                emitter.load_imm(addr, ty.clone());
+                emitter.load_imm(addr, ty.clone())?;
             }
             hir::GlobalValueData::IAddImm { .. } | hir::GlobalValueData::Symbol { .. } => {
                 let mut emitter = self.inst_emitter(inst, stack);
                 emitter.stack_mut().push(addr);
             }
         }
+        Ok(())
     }
 
     fn emit_unary_imm_op(
@@ -1435,68 +1600,79 @@ impl<'a> MasmEmitter<'a> {
         inst: hir::Inst,
         op: &hir::UnaryOpImm,
         stack: &mut OperandStack,
-    ) {
+    ) -> Result<(), CodegenError> {
         let mut emitter = self.inst_emitter(inst, stack);
         match op.op {
             hir::Opcode::ImmI1 => {
                 assert_matches!(op.imm, Immediate::I1(_));
-                emitter.literal(op.imm);
+                emitter.literal(op.imm)?;
             }
             hir::Opcode::ImmI8 => {
                 assert_matches!(op.imm, Immediate::I8(_));
-                emitter.literal(op.imm);
+                emitter.literal(op.imm)?;
             }
             hir::Opcode::ImmU8 => {
                 assert_matches!(op.imm, Immediate::U8(_));
-                emitter.literal(op.imm);
+                emitter.literal(op.imm)?;
             }
             hir::Opcode::ImmI16 => {
                 assert_matches!(op.imm, Immediate::I16(_));
-                emitter.literal(op.imm);
+                emitter.literal(op.imm)?;
             }
             hir::Opcode::ImmU16 => {
                 assert_matches!(op.imm, Immediate::U16(_));
-                emitter.literal(op.imm);
+                emitter.literal(op.imm)?;
             }
             hir::Opcode::ImmI32 => {
                 assert_matches!(op.imm, Immediate::I32(_));
-                emitter.literal(op.imm);
+                emitter.literal(op.imm)?;
             }
             hir::Opcode::ImmU32 => {
                 assert_matches!(op.imm, Immediate::U32(_));
-                emitter.literal(op.imm);
+                emitter.literal(op.imm)?;
             }
             hir::Opcode::ImmI64 => {
                 assert_matches!(op.imm, Immediate::I64(_));
-                emitter.literal(op.imm);
+                emitter.literal(op.imm)?;
             }
             hir::Opcode::ImmU64 => {
                 assert_matches!(op.imm, Immediate::U64(_));
-                emitter.literal(op.imm);
+                emitter.literal(op.imm)?;
             }
             hir::Opcode::ImmFelt => {
                 assert_matches!(op.imm, Immediate::Felt(_));
-                emitter.literal(op.imm);
+                emitter.literal(op.imm)?;
             }
             hir::Opcode::ImmF64 => {
                 assert_matches!(op.imm, Immediate::F64(_));
-                emitter.literal(op.imm);
+                emitter.literal(op.imm)?;
+            }
+            opcode => {
+                return Err(CodegenError::UnsupportedOpcode {
+                    op: opcode,
+                    span: self.f.dfg.inst_span(inst),
+                })
             }
-            opcode => unimplemented!("unrecognized unary with immediate opcode: '{opcode}'"),
         }
+        Ok(())
     }
 
-    fn emit_unary_op(&mut self, inst: hir::Inst, op: &hir::UnaryOp, stack: &mut OperandStack) {
+    fn emit_unary_op(
+        &mut self,
+        inst: hir::Inst,
+        op: &hir::UnaryOp,
+        stack: &mut OperandStack,
+    ) -> Result<(), CodegenError> {
         let result = self.f.dfg.first_result(inst);
         let mut emitter = self.inst_emitter(inst, stack);
         match op.op {
-            hir::Opcode::Neg => emitter.neg(),
-            hir::Opcode::Inv => emitter.inv(),
-            hir::Opcode::Incr => emitter.incr(),
-            hir::Opcode::Pow2 => emitter.pow2(),
+            hir::Opcode::Neg => emitter.neg()?,
+            hir::Opcode::Inv => emitter.inv()?,
+            hir::Opcode::Incr => emitter.incr()?,
+            hir::Opcode::Pow2 => emitter.pow2()?,
             hir::Opcode::Not => emitter.not(),
-            hir::Opcode::Bnot => emitter.bnot(),
-            hir::Opcode::Popcnt => emitter.popcnt(),
+            hir::Opcode::Bnot => emitter.bnot()?,
+            hir::Opcode::Popcnt => emitter.popcnt()?,
             // This opcode is a no-op
             hir::Opcode::PtrToInt => {
                 let result_ty = emitter.value_type(result).clone();
@@ -1514,23 +1690,29 @@ impl<'a> MasmEmitter<'a> {
             // We may eliminate this in favor of more specific casts in the future
             hir::Opcode::Cast => {
                 let dst_ty = emitter.value_type(result).clone();
-                emitter.cast(&dst_ty);
+                emitter.cast(&dst_ty)?;
             }
             hir::Opcode::Trunc => {
                 let dst_ty = emitter.value_type(result).clone();
-                emitter.trunc(&dst_ty);
+                emitter.trunc(&dst_ty)?;
             }
             hir::Opcode::Zext => {
                 let dst_ty = emitter.value_type(result).clone();
-                emitter.zext(&dst_ty);
+                emitter.zext(&dst_ty)?;
             }
             hir::Opcode::Sext => {
                 let dst_ty = emitter.value_type(result).clone();
-                emitter.sext(&dst_ty);
+                emitter.sext(&dst_ty)?;
+            }
+            hir::Opcode::IsOdd => emitter.is_odd()?,
+            opcode => {
+                return Err(CodegenError::UnsupportedOpcode {
+                    op: opcode,
+                    span: self.f.dfg.inst_span(inst),
+                })
             }
-            hir::Opcode::IsOdd => emitter.is_odd(),
-            opcode => unimplemented!("unrecognized unary opcode: '{opcode}'"),
         }
+        Ok(())
     }
 
     fn emit_binary_imm_op(
@@ -1538,90 +1720,128 @@ impl<'a> MasmEmitter<'a> {
         inst: hir::Inst,
         op: &hir::BinaryOpImm,
         stack: &mut OperandStack,
-    ) {
+    ) -> Result<(), CodegenError> {
         let mut emitter = self.inst_emitter(inst, stack);
         match op.op {
-            hir::Opcode::Eq => emitter.eq_imm(op.imm),
-            hir::Opcode::Neq => emitter.neq_imm(op.imm),
-            hir::Opcode::Gt => emitter.gt_imm(op.imm),
-            hir::Opcode::Gte => emitter.gte_imm(op.imm),
-            hir::Opcode::Lt => emitter.lt_imm(op.imm),
-            hir::Opcode::Lte => emitter.lte_imm(op.imm),
-            hir::Opcode::Add => emitter.add_imm(op.imm, op.overflow),
-            hir::Opcode::Sub => emitter.sub_imm(op.imm, op.overflow),
-            hir::Opcode::Mul => emitter.mul_imm(op.imm, op.overflow),
-            hir::Opcode::Div if op.overflow.is_checked() => emitter.checked_div_imm(op.imm),
-            hir::Opcode::Div => emitter.unchecked_div_imm(op.imm),
-            hir::Opcode::Min => emitter.min_imm(op.imm),
-            hir::Opcode::Max => emitter.max_imm(op.imm),
-            hir::Opcode::Mod if op.overflow.is_checked() => emitter.checked_mod_imm(op.imm),
-            hir::Opcode::Mod => emitter.unchecked_mod_imm(op.imm),
-            hir::Opcode::DivMod if op.overflow.is_checked() => emitter.checked_divmod_imm(op.imm),
-            hir::Opcode::DivMod => emitter.unchecked_divmod_imm(op.imm),
-            hir::Opcode::Exp => emitter.exp_imm(op.imm),
+            hir::Opcode::Eq => emitter.eq_imm(op.imm)?,
+            hir::Opcode::Neq => emitter.neq_imm(op.imm)?,
+            hir::Opcode::Gt => emitter.gt_imm(op.imm)?,
+            hir::Opcode::Gte => emitter.gte_imm(op.imm)?,
+            hir::Opcode::Lt => emitter.lt_imm(op.imm)?,
+            hir::Opcode::Lte => emitter.lte_imm(op.imm)?,
+            hir::Opcode::Add => emitter.add_imm(op.imm, op.overflow)?,
+            hir::Opcode::Sub => emitter.sub_imm(op.imm, op.overflow)?,
+            hir::Opcode::Mul => emitter.mul_imm(op.imm, op.overflow)?,
+            hir::Opcode::Div if op.overflow.is_checked() => emitter.checked_div_imm(op.imm)?,
+            hir::Opcode::Div => emitter.unchecked_div_imm(op.imm)?,
+            hir::Opcode::Min => emitter.min_imm(op.imm)?,
+            hir::Opcode::Max => emitter.max_imm(op.imm)?,
+            hir::Opcode::Mod if op.overflow.is_checked() => emitter.checked_mod_imm(op.imm)?,
+            hir::Opcode::Mod => emitter.unchecked_mod_imm(op.imm)?,
+            hir::Opcode::DivMod if op.overflow.is_checked() => {
+                emitter.checked_divmod_imm(op.imm)?
+            }
+            hir::Opcode::DivMod => emitter.unchecked_divmod_imm(op.imm)?,
+            hir::Opcode::Exp => emitter.exp_imm(op.imm)?,
             hir::Opcode::And => emitter.and_imm(op.imm),
-            hir::Opcode::Band => emitter.band_imm(op.imm),
+            hir::Opcode::Band => emitter.band_imm(op.imm)?,
             hir::Opcode::Or => emitter.or_imm(op.imm),
-            hir::Opcode::Bor => emitter.bor_imm(op.imm),
+            hir::Opcode::Bor => emitter.bor_imm(op.imm)?,
             hir::Opcode::Xor => emitter.xor_imm(op.imm),
-            hir::Opcode::Bxor => emitter.bxor_imm(op.imm),
-            hir::Opcode::Shl => emitter.shl_imm(op.imm),
-            hir::Opcode::Shr => emitter.shr_imm(op.imm),
-            hir::Opcode::Rotl => emitter.rotl_imm(op.imm),
-            hir::Opcode::Rotr => emitter.rotr_imm(op.imm),
-            opcode => unimplemented!("unrecognized binary with immediate opcode: '{opcode}'"),
+            hir::Opcode::Bxor => emitter.bxor_imm(op.imm)?,
+            hir::Opcode::Shl => emitter.shl_imm(op.imm)?,
+            hir::Opcode::Shr => emitter.shr_imm(op.imm)?,
+            hir::Opcode::Rotl => emitter.rotl_imm(op.imm)?,
+            hir::Opcode::Rotr => emitter.rotr_imm(op.imm)?,
+            opcode => {
+                return Err(CodegenError::UnsupportedOpcode {
+                    op: opcode,
+                    span: self.f.dfg.inst_span(inst),
+                })
+            }
         }
+        Ok(())
     }
 
-    fn emit_binary_op(&mut self, inst: hir::Inst, op: &hir::BinaryOp, stack: &mut OperandStack) {
+    fn emit_binary_op(
+        &mut self,
+        inst: hir::Inst,
+        op: &hir::BinaryOp,
+        stack: &mut OperandStack,
+    ) -> Result<(), CodegenError> {
         let mut emitter = self.inst_emitter(inst, stack);
         match op.op {
-            hir::Opcode::Eq => emitter.eq(),
-            hir::Opcode::Neq => emitter.neq(),
-            hir::Opcode::Gt => emitter.gt(),
-            hir::Opcode::Gte => emitter.gte(),
-            hir::Opcode::Lt => emitter.lt(),
-            hir::Opcode::Lte => emitter.lte(),
-            hir::Opcode::Add => emitter.add(op.overflow),
-            hir::Opcode::Sub => emitter.sub(op.overflow),
-            hir::Opcode::Mul => emitter.mul(op.overflow),
-            hir::Opcode::Div if op.overflow.is_checked() => emitter.checked_div(),
-            hir::Opcode::Div => emitter.unchecked_div(),
-            hir::Opcode::Min => emitter.min(),
-            hir::Opcode::Max => emitter.max(),
-            hir::Opcode::Mod if op.overflow.is_checked() => emitter.checked_mod(),
-            hir::Opcode::Mod => emitter.unchecked_mod(),
-            hir::Opcode::DivMod if op.overflow.is_checked() => emitter.checked_divmod(),
-            hir::Opcode::DivMod => emitter.unchecked_divmod(),
-            hir::Opcode::Exp => emitter.exp(),
+            hir::Opcode::Eq => emitter.eq()?,
+            hir::Opcode::Neq => emitter.neq()?,
+            hir::Opcode::Gt => emitter.gt()?,
+            hir::Opcode::Gte => emitter.gte()?,
+            hir::Opcode::Lt => emitter.lt()?,
+            hir::Opcode::Lte => emitter.lte()?,
+            hir::Opcode::Add => emitter.add(op.overflow)?,
+            hir::Opcode::Sub => emitter.sub(op.overflow)?,
+            hir::Opcode::Mul => emitter.mul(op.overflow)?,
+            hir::Opcode::Div if op.overflow.is_checked() => emitter.checked_div()?,
+            hir::Opcode::Div => emitter.unchecked_div()?,
+            hir::Opcode::Min => emitter.min()?,
+            hir::Opcode::Max => emitter.max()?,
+            hir::Opcode::Mod if op.overflow.is_checked() => emitter.checked_mod()?,
+            hir::Opcode::Mod => emitter.unchecked_mod()?,
+            hir::Opcode::DivMod if op.overflow.is_checked() => emitter.checked_divmod()?,
+            hir::Opcode::DivMod => emitter.unchecked_divmod()?,
+            hir::Opcode::Exp => emitter.exp()?,
             hir::Opcode::And => emitter.and(),
-            hir::Opcode::Band => emitter.band(),
+            hir::Opcode::Band => emitter.band()?,
             hir::Opcode::Or => emitter.or(),
-            hir::Opcode::Bor => emitter.bor(),
+            hir::Opcode::Bor => emitter.bor()?,
             hir::Opcode::Xor => emitter.xor(),
-            hir::Opcode::Bxor => emitter.bxor(),
-            hir::Opcode::Shl => emitter.shl(),
-            hir::Opcode::Shr => emitter.shr(),
-            hir::Opcode::Rotl => emitter.rotl(),
-            hir::Opcode::Rotr => emitter.rotr(),
-            opcode => unimplemented!("unrecognized binary opcode: '{opcode}'"),
+            hir::Opcode::Bxor => emitter.bxor()?,
+            hir::Opcode::Shl => emitter.shl()?,
+            hir::Opcode::Shr => emitter.shr()?,
+            hir::Opcode::Rotl => emitter.rotl()?,
+            hir::Opcode::Rotr => emitter.rotr()?,
+            opcode => {
+                return Err(CodegenError::UnsupportedOpcode {
+                    op: opcode,
+                    span: self.f.dfg.inst_span(inst),
+                })
+            }
         }
+        Ok(())
     }
 
-    fn emit_test_op(&mut self, _inst: hir::Inst, op: &hir::Test, _stack: &mut OperandStack) {
-        unimplemented!("unrecognized test opcode: '{}'", &op.op);
+    fn emit_test_op(
+        &mut self,
+        inst: hir::Inst,
+        op: &hir::Test,
+        _stack: &mut OperandStack,
+    ) -> Result<(), CodegenError> {
+        Err(CodegenError::Unsupported {
+            op: op.op,
+            ty: op.ty.clone(),
+            span: self.f.dfg.inst_span(inst),
+        })
     }
 
-    fn emit_load_op(&mut self, inst: hir::Inst, op: &hir::LoadOp, stack: &mut OperandStack) {
+    fn emit_load_op(
+        &mut self,
+        inst: hir::Inst,
+        op: &hir::LoadOp,
+        stack: &mut OperandStack,
+    ) -> Result<(), CodegenError> {
         let mut emitter = self.inst_emitter(inst, stack);
-        emitter.load(op.ty.clone());
+        emitter.load(op.ty.clone())
     }
 
-    fn emit_primop_imm(&mut self, inst: hir::Inst, op: &hir::PrimOpImm, stack: &mut OperandStack) {
+    fn emit_primop_imm(
+        &mut self,
+        inst: hir::Inst,
+        op: &hir::PrimOpImm,
+        stack: &mut OperandStack,
+    ) -> Result<(), CodegenError> {
         let mut emitter = self.inst_emitter(inst, stack);
         match op.op {
             hir::Opcode::AssertEq => {
-                emitter.assert_eq_imm(op.imm);
+                emitter.assert_eq_imm(op.imm)?;
             }
             // Store a value at a constant address
             hir::Opcode::Store => {
@@ -1629,30 +1849,41 @@ impl<'a> MasmEmitter<'a> {
                     op.imm
                         .as_u32()
                         .expect("invalid address immediate: out of range"),
-                );
+                )?;
+            }
+            opcode => {
+                return Err(CodegenError::UnsupportedOpcode {
+                    op: opcode,
+                    span: self.f.dfg.inst_span(inst),
+                })
             }
-            opcode => unimplemented!("unrecognized primop with immediate opcode: '{opcode}'"),
         }
+        Ok(())
     }
 
-    fn emit_primop(&mut self, inst: hir::Inst, op: &hir::PrimOp, stack: &mut OperandStack) {
+    fn emit_primop(
+        &mut self,
+        inst: hir::Inst,
+        op: &hir::PrimOp,
+        stack: &mut OperandStack,
+    ) -> Result<(), CodegenError> {
         let args = op.args.as_slice(&self.f.dfg.value_lists);
         let mut emitter = self.inst_emitter(inst, stack);
         match op.op {
             // Pop a value of the given type off the stack and assert it's value is one
             hir::Opcode::Assert => {
                 assert_eq!(args.len(), 1);
-                emitter.assert();
+                emitter.assert()?;
             }
             // Pop a value of the given type off the stack and assert it's value is zero
             hir::Opcode::Assertz => {
                 assert_eq!(args.len(), 1);
-                emitter.assertz();
+                emitter.assertz()?;
             }
             // Pop two values of the given type off the stack and assert equality
             hir::Opcode::AssertEq => {
                 assert_eq!(args.len(), 2);
-                emitter.assert_eq();
+                emitter.assert_eq()?;
             }
             // Allocate a local and push its address on the operand stack
             hir::Opcode::Alloca => {
@@ -1664,17 +1895,17 @@ impl<'a> MasmEmitter<'a> {
             // Store a value at a given pointer
             hir::Opcode::Store => {
                 assert_eq!(args.len(), 2);
-                emitter.store();
+                emitter.store()?;
             }
             // Copy `count * sizeof(ctrl_ty)` bytes from source to destination address
             hir::Opcode::MemCpy => {
                 assert_eq!(args.len(), 3);
-                emitter.memcpy();
+                emitter.memcpy()?;
             }
             // Conditionally select between two values
             hir::Opcode::Select => {
                 assert_eq!(args.len(), 3);
-                emitter.select();
+                emitter.select()?;
             }
             // This instruction should not be reachable at runtime, so we emit an assertion
             // that will always fail if for some reason it is reached
@@ -1682,22 +1913,44 @@ impl<'a> MasmEmitter<'a> {
                 // assert(false)
                 emitter.emit_all(&[Op::PushU32(0), Op::Assert]);
             }
-            opcode => unimplemented!("unrecognized primop with immediate opcode: '{opcode}'"),
+            opcode => {
+                return Err(CodegenError::UnsupportedOpcode {
+                    op: opcode,
+                    span: self.f.dfg.inst_span(inst),
+                })
+            }
         }
+        Ok(())
     }
 
-    fn emit_call_op(&mut self, inst: hir::Inst, op: &hir::Call, stack: &mut OperandStack) {
+    fn emit_call_op(
+        &mut self,
+        inst: hir::Inst,
+        op: &hir::Call,
+        stack: &mut OperandStack,
+    ) -> Result<(), CodegenError> {
         assert_ne!(op.callee, self.f.id, "unexpected recursive call");
 
         let mut emitter = self.inst_emitter(inst, stack);
         match op.op {
-            hir::Opcode::Syscall => emitter.syscall(op.callee),
-            hir::Opcode::Call => emitter.exec(op.callee),
-            opcode => unimplemented!("unrecognized procedure call opcode: '{opcode}'"),
+            hir::Opcode::Syscall => emitter.syscall(op.callee)?,
+            hir::Opcode::Call => emitter.exec(op.callee)?,
+            opcode => {
+                return Err(CodegenError::UnsupportedOpcode {
+                    op: opcode,
+                    span: self.f.dfg.inst_span(inst),
+                })
+            }
         }
+        Ok(())
     }
 
-    fn emit_inline_asm(&mut self, inst: hir::Inst, op: &hir::InlineAsm, stack: &mut OperandStack) {
+    fn emit_inline_asm(
+        &mut self,
+        inst: hir::Inst,
+        op: &hir::InlineAsm,
+        stack: &mut OperandStack,
+    ) -> Result<(), CodegenError> {
         // Port over the blocks from the inline assembly chunk, except the body block, which will
         // be inlined at the current block
         let mut mapped = FxHashMap::<masm::BlockId, masm::BlockId>::default();
@@ -1710,7 +1963,15 @@ impl<'a> MasmEmitter<'a> {
         }
 
         // Inline the body, rewriting any references to other blocks
-        rewrite_inline_assembly_block(self.f_prime, op, op.body, self.current_block, &mapped);
+        let span = self.f.dfg.inst_span(inst);
+        rewrite_inline_assembly_block(
+            self.f_prime,
+            op,
+            op.body,
+            self.current_block,
+            &mapped,
+            span,
+        )?;
 
         // Pop arguments, push results
         stack.dropn(op.args.len(&self.f.dfg.value_lists));
@@ -1718,6 +1979,7 @@ impl<'a> MasmEmitter<'a> {
             let ty = self.f.dfg.value_type(result).clone();
             stack.push(TypedValue { value: result, ty });
         }
+        Ok(())
     }
 
     /// Computes the absolute offset (address) represented by the given global value
@@ -1800,7 +2062,8 @@ fn rewrite_inline_assembly_block(
     prev: masm::BlockId,
     new: masm::BlockId,
     rewrites: &FxHashMap<masm::BlockId, masm::BlockId>,
-) {
+    span: hir::SourceSpan,
+) -> Result<(), CodegenError> {
     let body = asm.blocks[prev].ops.clone();
     for mut op in body.into_iter() {
         match op {
@@ -1809,19 +2072,48 @@ fn rewrite_inline_assembly_block(
                 let prev_else_blk = *else_blk;
                 *then_blk = rewrites[&prev_then_blk];
                 *else_blk = rewrites[&prev_else_blk];
-                rewrite_inline_assembly_block(f_prime, asm, prev_then_blk, *then_blk, rewrites);
-                rewrite_inline_assembly_block(f_prime, asm, prev_else_blk, *else_blk, rewrites);
+                rewrite_inline_assembly_block(
+                    f_prime,
+                    asm,
+                    prev_then_blk,
+                    *then_blk,
+                    rewrites,
+                    span,
+                )?;
+                rewrite_inline_assembly_block(
+                    f_prime,
+                    asm,
+                    prev_else_blk,
+                    *else_blk,
+                    rewrites,
+                    span,
+                )?;
             }
             Op::While(ref mut body_blk) | Op::Repeat(_, ref mut body_blk) => {
                 let prev_body_blk = *body_blk;
                 *body_blk = rewrites[&prev_body_blk];
-                rewrite_inline_assembly_block(f_prime, asm, prev_body_blk, *body_blk, rewrites);
+                rewrite_inline_assembly_block(
+                    f_prime,
+                    asm,
+                    prev_body_blk,
+                    *body_blk,
+                    rewrites,
+                    span,
+                )?;
+            }
+            // Inline assembly has no locals of its own, and cannot refer to those of the
+            // function it is inlined into, as they are allocated by the code generator
+            Op::LocAddr(_) => {
+                return Err(CodegenError::UnsupportedInlineAsm {
+                    op: "locaddr",
+                    span,
+                })
             }
-            Op::LocAddr(_) => todo!(),
             _ => (),
         }
         f_prime.blocks[new].push(op);
     }
+    Ok(())
 }
 
 /// This function ensures that the values of `args` used by `inst` are on the
@@ -1918,6 +2210,95 @@ fn drop_unused_operands_after(
     }
 }
 
+/// Describes the operands needed to emit an expression tree of the tree graph
+#[derive(Default)]
+struct OperandDemand {
+    /// The values defined outside the tree which are used by it
+    used: SmallVec<[hir::Value; 4]>,
+    /// An upper bound on the number of elements the operand stack grows by while
+    /// emitting the tree
+    size: usize,
+}
+
+/// Compute the [OperandDemand] of the expression tree rooted at `root`
+fn operand_demand(
+    root: Node,
+    depgraph: &DependencyGraph,
+    treegraph: &TreeGraph,
+    function: &hir::Function,
+    liveness: &LivenessAnalysis,
+) -> OperandDemand {
+    let mut demand = OperandDemand::default();
+    if let Node::Inst(inst, _) = root {
+        let block = function.dfg.inst_block(inst).unwrap();
+        let block_end = ProgramPoint::Inst(function.dfg.last_inst(block).unwrap());
+        let ctx = DemandContext {
+            root,
+            depgraph,
+            treegraph,
+            function,
+            liveness,
+            block_end,
+        };
+        demand.size = ctx.growth(root, &mut demand.used);
+    }
+    demand
+}
+
+struct DemandContext<'a> {
+    root: Node,
+    depgraph: &'a DependencyGraph,
+    treegraph: &'a TreeGraph,
+    function: &'a hir::Function,
+    liveness: &'a LivenessAnalysis,
+    block_end: ProgramPoint,
+}
+impl DemandContext<'_> {
+    /// Compute an upper bound on the growth of the operand stack while emitting `node`,
+    /// recording the values it uses from outside the tree in `used`.
+    ///
+    /// This mirrors the order in which dependencies are visited by [MasmEmitter::emit_inst],
+    /// assuming that values used from outside the tree are always copied into place, and
+    /// that results which are live after the block are copied as well.
+    fn growth(&self, node: Node, used: &mut SmallVec<[hir::Value; 4]>) -> usize {
+        let dependencies = self
+            .depgraph
+            .successors(&node)
+            .collect::<SmallVec<[_; 2]>>();
+        let mut held = 0;
+        let mut peak = 0;
+        for dependency in dependencies.into_iter().rev() {
+            if self
+                .treegraph
+                .is_member_of(&dependency.dependency, &self.root)
+            {
+                let inst = dependency.dependency.unwrap_inst();
+                peak = cmp::max(peak, held + self.growth(dependency.dependency, used));
+                for result in self.function.dfg.inst_results(inst).iter() {
+                    let copies = self.liveness.is_live_after(result, self.block_end) as usize;
+                    held += self.size_of(*result) * (1 + copies);
+                }
+            } else {
+                for u in dependency.used().iter() {
+                    if !used.contains(&u.value) {
+                        used.push(u.value);
+                    }
+                    held += self.size_of(u.value) * u.count as usize;
+                }
+                peak = cmp::max(peak, held);
+            }
+        }
+        // The operands are consumed, and replaced with the results of the instruction
+        let results = self.function.dfg.inst_results(node.unwrap_inst());
+        let results_size = results.iter().map(|v| self.size_of(*v)).sum::<usize>();
+        cmp::max(peak, cmp::max(held, results_size))
+    }
+
+    fn size_of(&self, value: hir::Value) -> usize {
+        self.function.dfg.value_type(value).size_in_felts()
+    }
+}
+
 /// Determine if `dependent` is the last dependent on `dependency` in the dependency graph.
 ///
 /// This function is used as an oracle for choosing between moving or copying operands on
@@ -1952,13 +2333,11 @@ fn is_last_dependent_visited(
         let dependency_id = depgraph.edge_id(&dependent, &dependency);
         let dependency = depgraph.edge(dependency_id);
         let used = dependency.used();
-        if used.len() > 1 {
-            // Look at instruction arguments to see if the current index is the last possible one to visit
-            todo!()
-        } else {
-            // If there is only one value used, and it is only used once, this is the shallowest dependent
-            used[0].count == 1
-        }
+        // If there is only one value used, and it is only used once, this is the shallowest
+        // dependent. Otherwise, multiple results of `dependency` are used, or one is used more
+        // than once, so we conservatively treat this as not being the last visit, which copies
+        // the operands rather than moving them. Any copies left over are dropped once dead.
+        used.len() == 1 && used[0].count == 1
     } else {
         // This is a stack value, so this must be the last use
         true
@@ -0,0 +1,76 @@
+use std::cell::OnceCell;
+
+use miden_hir::{self as hir, AbiParam, Signature, StackEffectError, StackEffectVerifier, Type};
+use smallvec::SmallVec;
+
+use crate::masm;
+
+/// Verify that `function`, as stackified from a function with the data flow graph `dfg`,
+/// leaves exactly its results on the operand stack, see [StackEffectVerifier] for details.
+///
+/// Besides the functions imported by the original function, the code emitted for it calls
+/// the intrinsics in `intrinsics::mem`, and the 64-bit integer operations in `std::math::u64`,
+/// whose signatures are known here.
+pub(super) fn verify_stack_effects(
+    function: &masm::Function,
+    dfg: &hir::DataFlowGraph,
+) -> Result<(), StackEffectError> {
+    let intrinsics = OnceCell::new();
+    let verifier = StackEffectVerifier::new(&function.blocks, |callee| {
+        if let Some(import) = dfg.get_import(callee) {
+            return Some(import.signature.clone());
+        }
+        match callee.module.as_str() {
+            "intrinsics::mem" => intrinsics
+                .get_or_init(masm::Module::mem_intrinsics)
+                .functions
+                .iter()
+                .find(|f| f.name == *callee)
+                .map(|f| f.signature.clone()),
+            "std::math::u64" => u64_signature(callee.function.as_str()),
+            _ => None,
+        }
+    });
+
+    let params = function
+        .signature
+        .params()
+        .iter()
+        .map(|param| param.ty.clone())
+        .collect::<SmallVec<[Type; 4]>>();
+    let results = function
+        .signature
+        .results()
+        .iter()
+        .map(|result| result.ty.clone())
+        .collect::<SmallVec<[Type; 1]>>();
+    verifier.verify(function.body, &params, &results)
+}
+
+/// Get the signature of `name` in `std::math::u64`, as it is called by the code emitted for
+/// operations on 64-bit integers
+fn u64_signature(name: &str) -> Option<Signature> {
+    let u64 = || AbiParam::new(Type::U64);
+    let i1 = || AbiParam::new(Type::I1);
+    let (params, results) = match name {
+        "checked_eqz" => (vec![u64()], vec![i1()]),
+        "checked_eq" | "checked_neq" | "checked_lt" | "checked_lte" | "checked_gt"
+        | "checked_gte" => (vec![u64(), u64()], vec![i1()]),
+        "checked_add" | "wrapping_add" | "checked_sub" | "wrapping_sub" | "checked_mul"
+        | "wrapping_mul" | "checked_div" | "unchecked_div" | "checked_mod" | "unchecked_mod"
+        | "checked_and" | "checked_or" | "checked_xor" | "checked_min" | "checked_max" => {
+            (vec![u64(), u64()], vec![u64()])
+        }
+        // The overflow flag is placed on top of the result
+        "overflowing_add" | "overflowing_sub" | "overflowing_mul" => {
+            (vec![u64(), u64()], vec![i1(), u64()])
+        }
+        "checked_divmod" | "unchecked_divmod" => (vec![u64(), u64()], vec![u64(), u64()]),
+        // The shift or rotation is a u32 on top of the value
+        "unchecked_shl" | "unchecked_shr" | "unchecked_rotl" | "unchecked_rotr" => {
+            (vec![AbiParam::new(Type::U32), u64()], vec![u64()])
+        }
+        _ => return None,
+    };
+    Some(Signature::new(params, results))
+}
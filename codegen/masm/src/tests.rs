@@ -55,7 +55,7 @@ impl TestByEmulationHarness {
         let mut rewrites = transform::SplitCriticalEdges
             .chain(transform::Treeify)
             .chain(transform::InlineBlocks);
-        rewrites.run(function, analysis)
+        rewrites.run(function, analysis).map(|_| ())
     }
 
     pub fn stackify(
@@ -127,6 +127,11 @@ impl TestByEmulationHarness {
         self.emulator.store(addr, value);
     }
 
+    #[inline(always)]
+    pub fn load(&self, addr: usize) -> Felt {
+        self.emulator.load(addr)
+    }
+
     #[allow(unused)]
     pub fn execute(
         &mut self,
@@ -465,3 +470,1570 @@ fn stackify_sum_matrix() {
     assert_eq!(stack.len(), 1);
     assert_eq!(stack.pop().map(|e| e.as_int()), Some(6));
 }
+
+/// Test that the [Stackify] pass spills values to locals when more values are live
+/// than can be reached on the operand stack, and reloads them when they are used
+#[test]
+fn stackify_spills() {
+    let mut harness = TestByEmulationHarness::default();
+
+    // Build a simple program
+    let mut builder = ProgramBuilder::new(&harness.context.diagnostics);
+
+    // Build test module with a function which keeps 24 values live at once
+    let mut mb = builder.module("test");
+    let id = {
+        let mut fb = mb
+            .function(
+                "spill",
+                Signature::new(
+                    (0..12).map(|_| AbiParam::new(Type::U32)),
+                    [AbiParam::new(Type::U32)],
+                ),
+            )
+            .expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let params = fb.block_params(entry).to_vec();
+        // Each of these values is used twice, and the parameters are used again at the
+        // end, so they all remain live until the sums below are computed
+        let sums = (0..12)
+            .map(|i| {
+                fb.ins()
+                    .add(params[i], params[(i + 1) % 12], SourceSpan::UNKNOWN)
+            })
+            .collect::<Vec<_>>();
+        let mut a = sums[0];
+        let mut b = sums[0];
+        for sum in sums.iter().copied().skip(1) {
+            a = fb.ins().add(sum, a, SourceSpan::UNKNOWN);
+            b = fb.ins().add(sum, b, SourceSpan::UNKNOWN);
+        }
+        let mut result = fb.ins().add(a, b, SourceSpan::UNKNOWN);
+        for param in params.iter().copied() {
+            result = fb.ins().add(param, result, SourceSpan::UNKNOWN);
+        }
+        fb.ins().ret(Some(result), SourceSpan::UNKNOWN);
+        fb.build().expect("unexpected error building function")
+    };
+
+    mb.build()
+        .expect("unexpected error constructing test module");
+
+    // Link the program
+    let mut program = builder
+        .with_entrypoint(id)
+        .link()
+        .expect("failed to link program");
+
+    // Get the spill function
+    let mut function = {
+        let modules = program.modules_mut();
+        let mut test = modules.find_mut("test").remove().expect("undefined module");
+        let function = test
+            .cursor_mut_at(id.function)
+            .remove()
+            .expect("undefined function");
+        modules.insert(test);
+        function
+    };
+
+    let masm = harness
+        .stackify(&program, &mut function)
+        .expect("stackification failed");
+    assert!(
+        !masm.locals().is_empty(),
+        "expected values to be spilled to locals"
+    );
+
+    let mut module = Module::new(id.module);
+    module.functions.push_back(masm);
+    module.entry = Some(id);
+
+    // The sums add up to twice the sum of the parameters, and each sum is used twice,
+    // so the result is five times the sum of the parameters: 5 * (1 + 2 + .. + 12)
+    let args = (1..=12).map(Felt::new).collect::<Vec<_>>();
+    let mut stack = harness
+        .execute_module(module, &args)
+        .expect("execution failed");
+    assert_eq!(stack.len(), 1);
+    assert_eq!(stack.pop().map(|e| e.as_int()), Some(390));
+}
+
+/// Remove the entrypoint `id` from `program` and stackify it, asserting that some of its
+/// values were spilled to locals, then execute it with `args`
+fn run_spill_test(
+    harness: &mut TestByEmulationHarness,
+    mut program: Box<hir::Program>,
+    id: miden_hir::FunctionIdent,
+    args: &[Felt],
+) -> OperandStack<Felt> {
+    let mut function = {
+        let modules = program.modules_mut();
+        let mut test = modules.find_mut("test").remove().expect("undefined module");
+        let function = test
+            .cursor_mut_at(id.function)
+            .remove()
+            .expect("undefined function");
+        modules.insert(test);
+        function
+    };
+
+    let masm = harness
+        .stackify(&program, &mut function)
+        .expect("stackification failed");
+    assert!(
+        !masm.locals().is_empty(),
+        "expected values to be spilled to locals"
+    );
+
+    let mut module = Module::new(id.module);
+    module.functions.push_back(masm);
+    module.entry = Some(id);
+
+    harness
+        .execute_module(module, args)
+        .expect("execution failed")
+}
+
+/// Test that values spilled before a conditional branch are reloaded in the arm which uses them
+#[test]
+fn stackify_spills_in_if_arm() {
+    for (args, expected) in [((1..=16), 136), ((10..=25), 43)] {
+        let mut harness = TestByEmulationHarness::default();
+
+        let mut builder = ProgramBuilder::new(&harness.context.diagnostics);
+        let mut mb = builder.module("test");
+        let id = {
+            let mut fb = mb
+                .function(
+                    "spill",
+                    Signature::new(
+                        (0..16).map(|_| AbiParam::new(Type::U32)),
+                        [AbiParam::new(Type::U32)],
+                    ),
+                )
+                .expect("unexpected symbol conflict");
+            let entry = fb.current_block();
+            let params = fb.block_params(entry).to_vec();
+            let then_blk = fb.create_block();
+            let else_blk = fb.create_block();
+
+            // The operand stack is full on entry, and the last eight parameters are only
+            // used in the arms, so they are the ones spilled to make room for the sum
+            let mut a = params[0];
+            for param in params[1..8].iter().copied() {
+                a = fb.ins().add(param, a, SourceSpan::UNKNOWN);
+            }
+            let c = fb.ins().lt_imm(a, Immediate::U32(50), SourceSpan::UNKNOWN);
+            fb.ins()
+                .cond_br(c, then_blk, &[], else_blk, &[], SourceSpan::UNKNOWN);
+
+            fb.switch_to_block(then_blk);
+            let mut result = a;
+            for param in params[8..].iter().copied() {
+                result = fb.ins().add(param, result, SourceSpan::UNKNOWN);
+            }
+            fb.ins().ret(Some(result), SourceSpan::UNKNOWN);
+
+            fb.switch_to_block(else_blk);
+            let result = fb.ins().add(params[8], params[15], SourceSpan::UNKNOWN);
+            fb.ins().ret(Some(result), SourceSpan::UNKNOWN);
+
+            fb.build().expect("unexpected error building function")
+        };
+        mb.build()
+            .expect("unexpected error constructing test module");
+        let program = builder
+            .with_entrypoint(id)
+            .link()
+            .expect("failed to link program");
+
+        // With arguments 1..=16, the first eight sum to 36, so the result is the sum of all
+        // of them; with 10..=25, they sum to 108, so the result is 18 + 25
+        let args = args.map(Felt::new).collect::<Vec<_>>();
+        let mut stack = run_spill_test(&mut harness, program, id, &args);
+        assert_eq!(stack.len(), 1);
+        assert_eq!(stack.pop().map(|e| e.as_int()), Some(expected));
+    }
+}
+
+/// Test that values spilled before a loop are reloaded on every iteration of the loop body
+#[test]
+fn stackify_spills_in_loop_body() {
+    let mut harness = TestByEmulationHarness::default();
+
+    let mut builder = ProgramBuilder::new(&harness.context.diagnostics);
+    let mut mb = builder.module("test");
+    let id = {
+        let mut fb = mb
+            .function(
+                "spill",
+                Signature::new(
+                    (0..16).map(|_| AbiParam::new(Type::U32)),
+                    [AbiParam::new(Type::U32)],
+                ),
+            )
+            .expect("unexpected symbol conflict");
+        let entry = fb.current_block();
+        let params = fb.block_params(entry).to_vec();
+        let loop_blk = fb.create_block();
+        let acc = fb.append_block_param(loop_blk, Type::U32, SourceSpan::UNKNOWN);
+        let i = fb.append_block_param(loop_blk, Type::U32, SourceSpan::UNKNOWN);
+        let exit_blk = fb.create_block();
+        let result = fb.append_block_param(exit_blk, Type::U32, SourceSpan::UNKNOWN);
+
+        // The last eight parameters are only used in the loop body, so they are spilled
+        // before entering the loop to make room for the sum of the first eight
+        let mut a = params[0];
+        for param in params[1..8].iter().copied() {
+            a = fb.ins().add(param, a, SourceSpan::UNKNOWN);
+        }
+        let zero = fb.ins().u32(0, SourceSpan::UNKNOWN);
+        fb.ins().br(loop_blk, &[a, zero], SourceSpan::UNKNOWN);
+
+        // Add the last eight parameters to the accumulator on each of three iterations
+        fb.switch_to_block(loop_blk);
+        let mut sum = acc;
+        for param in params[8..].iter().copied() {
+            sum = fb.ins().add(param, sum, SourceSpan::UNKNOWN);
+        }
+        let next = fb.ins().add_imm(i, Immediate::U32(1), SourceSpan::UNKNOWN);
+        let c = fb
+            .ins()
+            .lt_imm(next, Immediate::U32(3), SourceSpan::UNKNOWN);
+        fb.ins().cond_br(
+            c,
+            loop_blk,
+            &[sum, next],
+            exit_blk,
+            &[sum],
+            SourceSpan::UNKNOWN,
+        );
+
+        fb.switch_to_block(exit_blk);
+        fb.ins().ret(Some(result), SourceSpan::UNKNOWN);
+
+        fb.build().expect("unexpected error building function")
+    };
+    mb.build()
+        .expect("unexpected error constructing test module");
+    let program = builder
+        .with_entrypoint(id)
+        .link()
+        .expect("failed to link program");
+
+    // The first eight arguments sum to 36, and the last eight to 100: 36 + 3 * 100
+    let args = (1..=16).map(Felt::new).collect::<Vec<_>>();
+    let mut stack = run_spill_test(&mut harness, program, id, &args);
+    assert_eq!(stack.len(), 1);
+    assert_eq!(stack.pop().map(|e| e.as_int()), Some(336));
+}
+
+/// Test that a value which occupies more than one element of the operand stack is spilled
+/// and reloaded intact
+#[test]
+fn stackify_spills_multi_felt_values() {
+    for selected in 0..7u64 {
+        let mut harness = TestByEmulationHarness::default();
+
+        let mut builder = ProgramBuilder::new(&harness.context.diagnostics);
+        let mut mb = builder.module("test");
+        let id = {
+            let mut fb = mb
+                .function(
+                    "spill",
+                    Signature::new(
+                        [AbiParam::new(Type::U32), AbiParam::new(Type::U32)]
+                            .into_iter()
+                            .chain((0..7).map(|_| AbiParam::new(Type::U64))),
+                        [AbiParam::new(Type::U64)],
+                    ),
+                )
+                .expect("unexpected symbol conflict");
+            let entry = fb.current_block();
+            let params = fb.block_params(entry).to_vec();
+
+            // The operand stack is full on entry, and the u64 parameters are only used in
+            // the blocks below, so one of them is spilled to make room for the sum, which
+            // selects the u64 parameter to return
+            let selector = fb.ins().add(params[0], params[1], SourceSpan::UNKNOWN);
+            for (n, param) in params[2..].iter().copied().enumerate() {
+                let ret_blk = fb.create_block();
+                if n < 6 {
+                    let next_blk = fb.create_block();
+                    let c =
+                        fb.ins()
+                            .eq_imm(selector, Immediate::U32(n as u32), SourceSpan::UNKNOWN);
+                    fb.ins()
+                        .cond_br(c, ret_blk, &[], next_blk, &[], SourceSpan::UNKNOWN);
+                    fb.switch_to_block(ret_blk);
+                    fb.ins().ret(Some(param), SourceSpan::UNKNOWN);
+                    fb.switch_to_block(next_blk);
+                } else {
+                    fb.ins().ret(Some(param), SourceSpan::UNKNOWN);
+                }
+            }
+
+            fb.build().expect("unexpected error building function")
+        };
+        mb.build()
+            .expect("unexpected error constructing test module");
+        let program = builder
+            .with_entrypoint(id)
+            .link()
+            .expect("failed to link program");
+
+        // Each u64 argument is passed as two elements, both of which are distinct from
+        // those of every other argument, so a reload of the wrong value, or of its parts
+        // in the wrong order, results in the wrong operand stack
+        let mut args = vec![Felt::new(selected), Felt::new(0)];
+        args.extend((0..14).map(|i| Felt::new(100 + i)));
+        let mut stack = run_spill_test(&mut harness, program, id, &args);
+        let offset = 2 + 2 * selected as usize;
+        assert_eq!(stack.len(), 2);
+        assert_eq!(stack.pop(), Some(args[offset]));
+        assert_eq!(stack.pop(), Some(args[offset + 1]));
+    }
+}
+
+/// Execute the function built by `build` with `args`, both before and after running the
+/// [Peephole] pass on it, and assert that the pass rewrote the function without changing
+/// the resulting operand stack.
+///
+/// Returns the ops of each block of the optimized function, in block order.
+fn run_peephole_test<F>(args: &[Felt], build: F) -> Vec<Vec<Op>>
+where
+    F: Fn(&mut Function),
+{
+    use miden_hir_pass::Pass;
+
+    let id: miden_hir::FunctionIdent = "test::main".parse().unwrap();
+    let signature = Signature::new(args.iter().map(|_| AbiParam::new(Type::Felt)), []);
+    let ops = |function: &Function| {
+        function
+            .blocks
+            .values()
+            .map(|block| block.ops.to_vec())
+            .collect::<Vec<_>>()
+    };
+    let execute = |function: Box<Function>| {
+        let mut harness = TestByEmulationHarness::default();
+        let mut module = Module::new(id.module);
+        module.functions.push_back(function);
+        module.entry = Some(id);
+        let mut stack = harness
+            .execute_module(module, args)
+            .expect("execution failed");
+        let mut results = vec![];
+        while let Some(elem) = stack.pop() {
+            results.push(elem.as_int());
+        }
+        results
+    };
+
+    let mut original = Box::new(Function::new(id, signature.clone()));
+    build(&mut original);
+    let original_ops = ops(&original);
+    let expected = execute(original);
+
+    let mut function = Box::new(Function::new(id, signature));
+    build(&mut function);
+    let function = Peephole
+        .run(function)
+        .expect("peephole optimization failed");
+    let optimized_ops = ops(&function);
+    assert_ne!(
+        optimized_ops, original_ops,
+        "expected the peephole optimizer to rewrite the function"
+    );
+    assert_eq!(
+        execute(function),
+        expected,
+        "peephole optimization changed the result of the function"
+    );
+
+    optimized_ops
+}
+
+/// Test that the [Peephole] pass removes pairs of swaps which undo each other
+#[test]
+fn peephole_swap_pairs() {
+    let args = (1..=8).map(Felt::new).collect::<Vec<_>>();
+    let ops = run_peephole_test(&args, |function| {
+        let body = function.body;
+        function.block_mut(body).ops.extend([
+            Op::Swap(2),
+            Op::Swap(2),
+            Op::Swapw(1),
+            Op::Swapw(1),
+            Op::Sub,
+        ]);
+    });
+    assert_eq!(ops, vec![vec![Op::Sub]]);
+}
+
+/// Test that the [Peephole] pass removes pairs of moves which undo each other
+#[test]
+fn peephole_move_pairs() {
+    let args = (1..=16).map(Felt::new).collect::<Vec<_>>();
+    let ops = run_peephole_test(&args, |function| {
+        let body = function.body;
+        function.block_mut(body).ops.extend([
+            Op::Movup(3),
+            Op::Movdn(3),
+            Op::Movdn(2),
+            Op::Movup(2),
+            Op::Movupw(2),
+            Op::Movdnw(2),
+            Op::Movdnw(2),
+            Op::Movupw(2),
+            Op::Sub,
+        ]);
+    });
+    assert_eq!(ops, vec![vec![Op::Sub]]);
+}
+
+/// Test that the [Peephole] pass removes values which are pushed, only to be dropped,
+/// including those exposed by removing other instructions in between
+#[test]
+fn peephole_push_drop() {
+    let args = (1..=8).map(Felt::new).collect::<Vec<_>>();
+    let ops = run_peephole_test(&args, |function| {
+        let body = function.body;
+        function.block_mut(body).ops.extend([
+            Op::Dup(2),
+            Op::Drop,
+            Op::Dupw(1),
+            Op::Dropw,
+            Op::Padw,
+            Op::Dropw,
+            Op::Pushw([Felt::new(1), Felt::new(2), Felt::new(3), Felt::new(4)]),
+            Op::Dropw,
+            Op::Push(Felt::new(u64::MAX >> 1)),
+            Op::Drop,
+            Op::PushU8(1),
+            Op::PushU16(2),
+            Op::Swap(1),
+            Op::Swap(1),
+            Op::Drop,
+            Op::Drop,
+            Op::Sub,
+        ]);
+    });
+    assert_eq!(ops, vec![vec![Op::Sub]]);
+}
+
+/// Test that the [Peephole] pass folds constants into the immediate form of the
+/// instruction which consumes them
+#[test]
+fn peephole_fold_immediates() {
+    let args = [Felt::new(12)];
+    let ops = run_peephole_test(&args, |function| {
+        let body = function.body;
+        function.block_mut(body).ops.extend([
+            Op::Dup(0),
+            Op::PushU8(7),
+            Op::Lt,
+            Op::Dup(1),
+            Op::PushU32(4),
+            Op::Mul,
+            Op::Dup(2),
+            Op::PushU8(5),
+            Op::U32CheckedDiv,
+            Op::Dup(3),
+            Op::PushU8(3),
+            Op::U32CheckedShl,
+            Op::Dup(4),
+            Op::PushU16(300),
+            Op::U32WrappingSub,
+            Op::Dup(5),
+            Op::PushU8(8),
+            Op::MemStore,
+            Op::PushU8(8),
+            Op::MemLoad,
+            Op::Dup(6),
+            Op::Push(Felt::new(12)),
+            Op::Eq,
+            Op::PushU8(1),
+            Op::And,
+        ]);
+    });
+    assert_eq!(
+        ops,
+        vec![vec![
+            Op::Dup(0),
+            Op::LtImm(Felt::new(7)),
+            Op::Dup(1),
+            Op::MulImm(Felt::new(4)),
+            Op::Dup(2),
+            Op::U32CheckedDivImm(5),
+            Op::Dup(3),
+            Op::U32CheckedShlImm(3),
+            Op::Dup(4),
+            Op::U32WrappingSubImm(300),
+            Op::Dup(5),
+            Op::MemStoreImm(8),
+            Op::MemLoadImm(8),
+            Op::Dup(6),
+            Op::EqImm(Felt::new(12)),
+            Op::AndImm(true),
+        ]]
+    );
+}
+
+/// Test that the [Peephole] pass removes arithmetic on identity immediates, including
+/// those produced by folding a constant, and replaces `add.1` with `incr`
+#[test]
+fn peephole_identity_immediates() {
+    let args = [Felt::new(5)];
+    let ops = run_peephole_test(&args, |function| {
+        let body = function.body;
+        function.block_mut(body).ops.extend([
+            Op::AddImm(Felt::ZERO),
+            Op::SubImm(Felt::ZERO),
+            Op::MulImm(Felt::ONE),
+            Op::DivImm(Felt::ONE),
+            Op::PushU8(0),
+            Op::Add,
+            Op::PushU8(1),
+            Op::Add,
+        ]);
+    });
+    assert_eq!(ops, vec![vec![Op::Incr]]);
+}
+
+/// Test that the [Peephole] pass hoists the instructions common to the end of both
+/// branches of an `if.true` out of it, whichever branch is taken
+#[test]
+fn peephole_hoist_if_tails() {
+    let build = |function: &mut Function| {
+        let body = function.body;
+        let then_blk = function.create_block();
+        let else_blk = function.create_block();
+        function.block_mut(body).ops.extend([
+            Op::Dup(0),
+            Op::LtImm(Felt::new(5)),
+            Op::If(then_blk, else_blk),
+        ]);
+        function.block_mut(then_blk).ops.extend([
+            Op::MulImm(Felt::new(2)),
+            Op::Swap(1),
+            Op::Drop,
+            Op::Incr,
+        ]);
+        function.block_mut(else_blk).ops.extend([
+            Op::MulImm(Felt::new(3)),
+            Op::Swap(1),
+            Op::Drop,
+            Op::Incr,
+        ]);
+    };
+
+    for arg in [3, 7] {
+        let args = [Felt::new(arg), Felt::new(10)];
+        let ops = run_peephole_test(&args, build);
+        let then_blk = miden_hir::MasmBlockId::from_u32(1);
+        let else_blk = miden_hir::MasmBlockId::from_u32(2);
+        assert_eq!(
+            ops,
+            vec![
+                vec![
+                    Op::Dup(0),
+                    Op::LtImm(Felt::new(5)),
+                    Op::If(then_blk, else_blk),
+                    Op::Swap(1),
+                    Op::Drop,
+                    Op::Incr,
+                ],
+                vec![Op::MulImm(Felt::new(2))],
+                vec![Op::MulImm(Felt::new(3))],
+            ]
+        );
+    }
+}
+
+/// Test that [FunctionCost] accounts for each branch of an `if.true`, unrolls `repeat.n`,
+/// and treats `while.true` as unbounded, recording the cost of every block
+#[test]
+fn cycle_cost() {
+    let id: miden_hir::FunctionIdent = "test::main".parse().unwrap();
+    let mut function = Function::new(id, Signature::new([], []));
+    let body = function.body;
+    let then_blk = function.create_block();
+    let else_blk = function.create_block();
+    let repeat_blk = function.create_block();
+    let loop_blk = function.create_block();
+    function.block_mut(body).ops.extend([
+        Op::PushU8(1),
+        Op::Dup(0),
+        Op::If(then_blk, else_blk),
+        Op::Repeat(3, repeat_blk),
+        Op::Drop,
+    ]);
+    function
+        .block_mut(then_blk)
+        .ops
+        .push(Op::AddImm(Felt::new(2)));
+    function
+        .block_mut(else_blk)
+        .ops
+        .extend([Op::Dup(0), Op::U32CheckedShl]);
+    function.block_mut(repeat_blk).ops.push(Op::Incr);
+
+    // push (2) + dup (1) + if (2) + either branch (2 or 48) + 3 * incr (3) + drop (1)
+    let cost = FunctionCost::compute(&function, &Default::default());
+    assert_eq!(cost.total, cost.blocks[body]);
+    assert_eq!(
+        cost.total,
+        Cycles {
+            best: 11,
+            worst: Some(57)
+        }
+    );
+    assert_eq!(cost.blocks[then_blk], Cycles::fixed(2));
+    assert_eq!(cost.blocks[else_blk], Cycles::fixed(48));
+    assert_eq!(cost.blocks[repeat_blk], Cycles::fixed(1));
+
+    // Wrapping the body in a loop makes the worst case unbounded, but skipping the loop
+    // entirely only costs the loop itself and the condition
+    function
+        .block_mut(loop_blk)
+        .ops
+        .extend([Op::Dup(0), Op::While(body)]);
+    function.body = loop_blk;
+    let cost = FunctionCost::compute(&function, &Default::default());
+    assert!(!cost.total.is_bounded());
+    assert_eq!(cost.total.best, 3);
+    assert_eq!(
+        cost.blocks[body],
+        Cycles {
+            best: 11,
+            worst: Some(57)
+        }
+    );
+}
+
+/// Test that the cost of an `exec` includes the cost of the callee, computed before that of its
+/// caller regardless of the order of definition, and that calls outside the program are unbounded
+#[test]
+fn cycle_cost_calls() {
+    let main_id: miden_hir::FunctionIdent = "test::main".parse().unwrap();
+    let callee_id: miden_hir::FunctionIdent = "test::callee".parse().unwrap();
+    let extern_id: miden_hir::FunctionIdent = "std::math::u64::checked_add".parse().unwrap();
+
+    let mut main = Box::new(Function::new(main_id, Signature::new([], [])));
+    let body = main.body;
+    main.block_mut(body)
+        .ops
+        .extend([Op::PushU8(1), Op::Exec(callee_id), Op::Exec(callee_id)]);
+    let mut callee = Box::new(Function::new(callee_id, Signature::new([], [])));
+    let body = callee.body;
+    callee.block_mut(body).ops.extend([Op::Dup(0), Op::Add]);
+
+    let mut module = Module::new(main_id.module);
+    module.functions.push_back(main);
+    module.functions.push_back(callee);
+    let mut program = Program::new();
+    program.modules.push(module);
+
+    // push (2) + 2 * (dup (1) + add (1))
+    let costs = FunctionCost::compute_program(&program);
+    assert_eq!(costs.len(), 2);
+    assert_eq!(costs[0].name, main_id);
+    assert_eq!(costs[0].total, Cycles::fixed(6));
+    assert_eq!(costs[1].name, callee_id);
+    assert_eq!(costs[1].total, Cycles::fixed(2));
+
+    // A call to a function which is not part of the program has no known upper bound
+    let mut main = program.modules[0].functions.pop_front().unwrap();
+    let body = main.body;
+    main.block_mut(body).ops.push(Op::Exec(extern_id));
+    program.modules[0].functions.push_front(main);
+    let costs = FunctionCost::compute_program(&program);
+    assert_eq!(costs[0].total, Cycles::unknown() + Cycles::fixed(6));
+    assert!(!costs[0].total.is_bounded());
+    assert_eq!(costs[1].total, Cycles::fixed(2));
+}
+
+/// Test that a program compiled at each optimization level behaves the same
+#[test]
+fn pipeline_opt_levels() {
+    for level in [OptLevel::O0, OptLevel::O1, OptLevel::O2, OptLevel::Os] {
+        let mut harness = TestByEmulationHarness::default();
+
+        let mut builder = ProgramBuilder::new(&harness.context.diagnostics);
+        let mut mb = builder.module("test");
+        let id = testing::fib1(mb.as_mut(), &harness.context);
+        mb.build()
+            .expect("unexpected error constructing test module");
+        let mut program = builder
+            .with_entrypoint(id)
+            .link()
+            .expect("failed to link program");
+
+        let mut compiler = MasmCompiler::new(&harness.context.diagnostics)
+            .with_pipeline(Pipeline::with_opt_level(level));
+        let program = compiler
+            .compile(&mut program)
+            .unwrap_or_else(|err| panic!("compilation failed at {level}: {err}"));
+
+        let mut stack = harness
+            .execute(program, &[Felt::new(10)])
+            .unwrap_or_else(|err| panic!("execution failed at {level}: {err}"));
+        assert_eq!(stack.len(), 1);
+        assert_eq!(stack.pop().map(|e| e.as_int()), Some(55), "at {level}");
+    }
+}
+
+/// Test that a switch whose arms and default pass block arguments is compiled correctly
+/// through the full pipeline, at each optimization level
+#[test]
+fn compile_switch_with_block_arguments() {
+    let cases = [(0, 103), (1, 104), (2, 12), (7, 1007)];
+    for level in [OptLevel::O0, OptLevel::O1, OptLevel::O2, OptLevel::Os] {
+        for (discriminant, expected) in cases {
+            let mut harness = TestByEmulationHarness::default();
+
+            let mut builder = ProgramBuilder::new(&harness.context.diagnostics);
+            let mut mb = builder.module("test");
+            let id = {
+                let mut fb = mb
+                    .function(
+                        "select",
+                        Signature::new(
+                            [
+                                AbiParam::new(Type::U32),
+                                AbiParam::new(Type::U32),
+                                AbiParam::new(Type::U32),
+                            ],
+                            [AbiParam::new(Type::U32)],
+                        ),
+                    )
+                    .expect("unexpected symbol conflict");
+                let entry = fb.current_block();
+                let (v0, v1, v2) = {
+                    let args = fb.block_params(entry);
+                    (args[0], args[1], args[2])
+                };
+                let offset_blk = fb.create_block();
+                let v3 = fb.append_block_param(offset_blk, Type::U32, SourceSpan::UNKNOWN);
+                let mul_blk = fb.create_block();
+                let v4 = fb.append_block_param(mul_blk, Type::U32, SourceSpan::UNKNOWN);
+                let v5 = fb.append_block_param(mul_blk, Type::U32, SourceSpan::UNKNOWN);
+                let default_blk = fb.create_block();
+                let v6 = fb.append_block_param(default_blk, Type::U32, SourceSpan::UNKNOWN);
+
+                fb.ins().switch(
+                    v0,
+                    &[
+                        (0, offset_blk, &[v1]),
+                        (1, offset_blk, &[v2]),
+                        (2, mul_blk, &[v1, v2]),
+                    ],
+                    default_blk,
+                    &[v0],
+                    SourceSpan::UNKNOWN,
+                );
+
+                fb.switch_to_block(offset_blk);
+                let v7 = fb
+                    .ins()
+                    .add_imm(v3, Immediate::U32(100), SourceSpan::UNKNOWN);
+                fb.ins().ret(Some(v7), SourceSpan::UNKNOWN);
+
+                fb.switch_to_block(mul_blk);
+                let v8 = fb.ins().mul(v4, v5, SourceSpan::UNKNOWN);
+                fb.ins().ret(Some(v8), SourceSpan::UNKNOWN);
+
+                fb.switch_to_block(default_blk);
+                let v9 = fb
+                    .ins()
+                    .add_imm(v6, Immediate::U32(1000), SourceSpan::UNKNOWN);
+                fb.ins().ret(Some(v9), SourceSpan::UNKNOWN);
+
+                fb.build().expect("unexpected error building function")
+            };
+            mb.build()
+                .expect("unexpected error constructing test module");
+            let mut program = builder
+                .with_entrypoint(id)
+                .link()
+                .expect("failed to link program");
+
+            let mut compiler = MasmCompiler::new(&harness.context.diagnostics)
+                .with_pipeline(Pipeline::with_opt_level(level));
+            let program = compiler
+                .compile(&mut program)
+                .unwrap_or_else(|err| panic!("compilation failed at {level}: {err}"));
+
+            let args = [Felt::new(discriminant), Felt::new(3), Felt::new(4)];
+            let mut stack = harness
+                .execute(program, &args)
+                .unwrap_or_else(|err| panic!("execution failed at {level}: {err}"));
+            assert_eq!(stack.len(), 1);
+            assert_eq!(
+                stack.pop().map(|e| e.as_int()),
+                Some(expected),
+                "at {level}, with discriminant {discriminant}"
+            );
+        }
+    }
+}
+
+/// Test that compiling an operation which is not supported for its operand types fails
+/// with an error located at the offending instruction, rather than panicking
+#[test]
+fn compile_unsupported_operation() {
+    let harness = TestByEmulationHarness::default();
+
+    let mut builder = ProgramBuilder::new(&harness.context.diagnostics);
+    let mut mb = builder.module("test");
+    let sig = Signature::new(
+        [AbiParam::new(Type::U64), AbiParam::new(Type::U64)],
+        [AbiParam::new(Type::U64)],
+    );
+    let mut fb = mb.function("pow", sig).expect("unexpected symbol conflict");
+    let entry = fb.current_block();
+    let (base, exponent) = {
+        let args = fb.block_params(entry);
+        (args[0], args[1])
+    };
+    let span = harness.context.current_span();
+    let result = fb.ins().exp(base, exponent, span);
+    fb.ins().ret(Some(result), harness.context.current_span());
+    let id = fb.build().expect("unexpected validation error");
+    mb.build()
+        .expect("unexpected error constructing test module");
+    let mut program = builder
+        .with_entrypoint(id)
+        .link()
+        .expect("failed to link program");
+
+    let mut compiler = MasmCompiler::new(&harness.context.diagnostics);
+    let err = compiler
+        .compile(&mut program)
+        .expect_err("expected compilation to fail");
+    match err.downcast_ref::<CodegenError>() {
+        Some(CodegenError::Unsupported {
+            op: hir::Opcode::Exp,
+            ty: Type::U64,
+            span: actual,
+        }) => assert_eq!(*actual, span),
+        _ => panic!("unexpected error: {err}"),
+    }
+}
+
+/// Test that a `syscall` to a kernel function is lowered to a `syscall` in MASM
+#[test]
+fn stackify_syscall() {
+    let harness = TestByEmulationHarness::default();
+
+    let id = "test::syscall".parse().unwrap();
+    let mut function = hir::Function::new(
+        id,
+        Signature::new([AbiParam::new(Type::U32)], [AbiParam::new(Type::U32)]),
+    );
+    let callee = {
+        let mut builder = hir::FunctionBuilder::new(&mut function);
+        let mut signature = Signature::new([AbiParam::new(Type::U32)], [AbiParam::new(Type::U32)]);
+        signature.cc = hir::CallConv::Kernel;
+        let callee = builder
+            .import_function("kernel", "get", signature, SourceSpan::UNKNOWN)
+            .expect("unexpected symbol conflict");
+        let entry = builder.current_block();
+        let v0 = builder.block_params(entry)[0];
+        let call = builder.ins().syscall(callee, &[v0], SourceSpan::UNKNOWN);
+        let v1 = builder.first_result(call);
+        builder.ins().ret(Some(v1), SourceSpan::UNKNOWN);
+        callee
+    };
+
+    let program = hir::Program::new();
+    let masm = harness
+        .stackify(&program, &mut function)
+        .expect("stackification failed");
+
+    let syscalls = masm
+        .blocks
+        .values()
+        .flat_map(|block| block.ops.iter())
+        .filter(|op| matches!(op, Op::Syscall(id) if *id == callee))
+        .count();
+    assert_eq!(syscalls, 1);
+}
+
+/// Test that a pipeline can be parsed from a list of rewrite names
+#[test]
+fn pipeline_named_passes() {
+    let pipeline = "mem2reg, sccp,dce".parse::<Pipeline>().unwrap();
+    assert_eq!(pipeline.passes(), &["mem2reg", "sccp", "dce"]);
+    assert!("".parse::<Pipeline>().unwrap().passes().is_empty());
+
+    // Inlining is applied to the whole program before any other rewrite
+    let pipeline = "sccp,inline,dce".parse::<Pipeline>().unwrap();
+    assert_eq!(pipeline.passes(), &["inline", "sccp", "dce"]);
+
+    let err = "sccp,licm".parse::<Pipeline>().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "unknown pass 'licm', expected one of [inline, mem2reg, sccp, gvn, dce, simplify-cfg]"
+    );
+}
+
+/// Test that Miden Assembly text can be parsed to MASM IR, and executed alongside
+/// other modules which import it
+#[test]
+fn masm_parser_imported_module() {
+    let mut harness = TestByEmulationHarness::default();
+
+    let u64_source = r#"
+#! Performs addition of two unsigned 64 bit integers preserving the overflow.
+#! Stack transition: [b_hi, b_lo, a_hi, a_lo, ...] -> [overflow_flag, c_hi, c_lo, ...]
+export.overflowing_add
+    swap
+    movup.3
+    u32overflowing_add
+    movup.3
+    movup.3
+    u32overflowing_add3
+end
+
+#! Performs addition of two unsigned 64 bit integers, failing on overflow.
+#! Stack transition: [b_hi, b_lo, a_hi, a_lo, ...] -> [c_hi, c_lo, ...]
+export.checked_add
+    u32assertw
+    exec.overflowing_add
+    eq.0
+    assert
+end
+"#;
+    let test_source = r#"
+use.std::math::u64
+
+export.main
+    push.4294967295
+    push.1
+    push.1
+    push.0
+    exec.u64::checked_add
+end
+"#;
+
+    let u64_name = "std::math::u64".parse().unwrap();
+    let u64_module =
+        Module::parse_str(u64_name, u64_source).expect("failed to parse std::math::u64");
+    let mut test_module = Module::parse_str("test".parse().unwrap(), test_source)
+        .expect("failed to parse test module");
+    assert!(test_module.imports.is_import(&u64_name));
+    let main = "test::main".parse().unwrap();
+    test_module.entry = Some(main);
+
+    harness
+        .emulator
+        .load_module(u64_module)
+        .expect("failed to load module");
+
+    // 0x1_ffff_ffff + 1 == 0x2_0000_0000
+    let mut stack = harness
+        .execute_module(test_module, &[])
+        .expect("execution failed");
+    assert_eq!(stack.len(), 2);
+    assert_eq!(stack.pop().map(|e| e.as_int()), Some(2));
+    assert_eq!(stack.pop().map(|e| e.as_int()), Some(0));
+}
+
+/// Test that structured control flow in Miden Assembly text is parsed to MASM IR correctly
+#[test]
+fn masm_parser_control_flow() {
+    let mut harness = TestByEmulationHarness::default();
+
+    let source = r#"
+# Computes the sum of the integers in the range 1..=n
+proc.sum
+    push.0
+    swap
+    dup.0
+    neq.0
+    while.true
+        dup.0
+        movup.2
+        add
+        swap
+        sub.1
+        dup.0
+        neq.0
+    end
+    drop
+end
+
+export.main
+    push.10
+    exec.sum
+    dup.0
+    push.50
+    gt
+    if.true
+        repeat.2
+            mul.2
+        end
+    else
+        drop
+        push.0
+    end
+end
+"#;
+
+    let mut module =
+        Module::parse_str("test".parse().unwrap(), source).expect("failed to parse module");
+    assert_eq!(module.functions.iter().count(), 2);
+    let main = "test::main".parse().unwrap();
+    module.entry = Some(main);
+
+    harness.set_cycle_budget(1000);
+
+    let mut stack = harness
+        .execute_module(module, &[])
+        .expect("execution failed");
+    assert_eq!(stack.len(), 1);
+    assert_eq!(stack.pop().map(|e| e.as_int()), Some(220));
+}
+
+/// Test that the emulator leaves the operands of `eqw` on the stack beneath the result, as
+/// the VM does
+#[test]
+fn emulator_eqw_preserves_operands() {
+    for (word, expected) in [("1.2.3.4", 1), ("1.2.3.5", 0)] {
+        let mut harness = TestByEmulationHarness::default();
+
+        let source = format!(
+            r#"
+export.main
+    push.1.2.3.4
+    push.{word}
+    eqw
+end
+"#
+        );
+        let mut module =
+            Module::parse_str("test".parse().unwrap(), &source).expect("failed to parse module");
+        let main = "test::main".parse().unwrap();
+        module.entry = Some(main);
+
+        let mut stack = harness
+            .execute_module(module, &[])
+            .expect("execution failed");
+        assert_eq!(stack.len(), 9);
+        assert_eq!(stack.pop().map(|e| e.as_int()), Some(expected));
+        let mut operands = vec![];
+        while let Some(elem) = stack.pop() {
+            operands.push(elem.as_int());
+        }
+        let last = if expected == 1 { 4 } else { 5 };
+        assert_eq!(operands, vec![last, 3, 2, 1, 4, 3, 2, 1]);
+    }
+}
+
+/// Test that emptying an operand stack whose size is not a multiple of the word size
+/// drops only the elements on it
+#[test]
+fn truncate_stack_partial_word() {
+    let mut harness = TestByEmulationHarness::default();
+
+    // A u64 and three u32s occupy five elements, i.e. one word, plus a single element
+    let module = build_emitter_test_module(|emitter| {
+        emitter.push_immediate(Immediate::U64(u64::MAX)).unwrap();
+        for n in 1..=3 {
+            emitter.push_immediate(Immediate::U32(n)).unwrap();
+        }
+        emitter.truncate_stack(0);
+        emitter.push_immediate(Immediate::U32(42)).unwrap();
+    });
+
+    let mut stack = harness
+        .execute_module(module, &[])
+        .expect("execution failed");
+    assert_eq!(stack.len(), 1);
+    assert_eq!(stack.pop().map(|e| e.as_int()), Some(42));
+}
+
+/// Test that equality of 64-bit integers compares both limbs, and consumes its operands
+#[test]
+fn eq_u64() {
+    let u64_source = r#"
+#! Performs equality comparison of two unsigned 64 bit integers.
+#! Stack transition: [b_hi, b_lo, a_hi, a_lo, ...] -> [c, ...], where c = 1 when a == b
+export.checked_eq
+    u32assertw
+    movup.2
+    u32checked_eq
+    swap
+    movup.2
+    u32checked_eq
+    and
+end
+
+#! Performs inequality comparison of two unsigned 64 bit integers.
+#! Stack transition: [b_hi, b_lo, a_hi, a_lo, ...] -> [c, ...], where c = 1 when a != b
+export.checked_neq
+    u32assertw
+    movup.2
+    u32checked_neq
+    swap
+    movup.2
+    u32checked_neq
+    or
+end
+"#;
+
+    let cases = [
+        (u64::MAX, u64::MAX),
+        (u64::MAX, u32::MAX as u64),
+        (1 << 32, 0),
+        (1, 0),
+    ];
+    for (a, b) in cases {
+        for negated in [false, true] {
+            let mut harness = TestByEmulationHarness::default();
+            let u64_module = Module::parse_str("std::math::u64".parse().unwrap(), u64_source)
+                .expect("failed to parse std::math::u64");
+            harness
+                .emulator
+                .load_module(u64_module)
+                .expect("failed to load module");
+
+            let module = build_emitter_test_module(|emitter| {
+                emitter.push_immediate(Immediate::U64(a)).unwrap();
+                emitter.push_immediate(Immediate::U64(b)).unwrap();
+                if negated {
+                    emitter.neq().unwrap();
+                } else {
+                    emitter.eq().unwrap();
+                }
+            });
+
+            let mut stack = harness
+                .execute_module(module, &[])
+                .expect("execution failed");
+            assert_eq!(stack.len(), 1);
+            assert_eq!(
+                stack.pop().map(|e| e.as_int()),
+                Some(((a == b) != negated) as u64),
+                "unexpected result comparing {a} and {b} (negated = {negated})"
+            );
+        }
+    }
+}
+
+/// The number of bytes of memory observed by the store tests
+const STORE_TEST_REGION: usize = 80;
+
+/// Get the little-endian byte representation of `imm` as it is laid out in memory
+fn immediate_to_le_bytes(imm: Immediate) -> Vec<u8> {
+    match imm {
+        Immediate::I1(b) => vec![b as u8],
+        Immediate::U8(i) => vec![i],
+        Immediate::I8(i) => i.to_le_bytes().to_vec(),
+        Immediate::U16(i) => i.to_le_bytes().to_vec(),
+        Immediate::I16(i) => i.to_le_bytes().to_vec(),
+        Immediate::U32(i) => i.to_le_bytes().to_vec(),
+        Immediate::I32(i) => i.to_le_bytes().to_vec(),
+        Immediate::U64(i) => i.to_le_bytes().to_vec(),
+        Immediate::I64(i) => i.to_le_bytes().to_vec(),
+        Immediate::I128(i) => i.to_le_bytes().to_vec(),
+        Immediate::Felt(i) => (i.as_int() as u32).to_le_bytes().to_vec(),
+        Immediate::F64(_) => unreachable!("f64 is not a loadable type"),
+    }
+}
+
+/// Fill the memory region starting at `base` with a recognizable byte pattern,
+/// returning the bytes written
+fn fill_store_test_region(harness: &mut TestByEmulationHarness, base: u32) -> Vec<u8> {
+    let bytes = (0..STORE_TEST_REGION)
+        .map(|i| 0x80 | (i as u8))
+        .collect::<Vec<_>>();
+    for (i, chunk) in bytes.chunks(4).enumerate() {
+        let elem = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        harness.store(base as usize + (i * 4), Felt::new(elem as u64));
+    }
+    bytes
+}
+
+/// Read back the memory region starting at `base` as bytes
+fn read_store_test_region(harness: &TestByEmulationHarness, base: u32) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(STORE_TEST_REGION);
+    for i in 0..(STORE_TEST_REGION / 4) {
+        let elem = harness.load(base as usize + (i * 4)).as_int();
+        let elem: u32 = elem.try_into().expect("expected element to be a valid u32");
+        bytes.extend_from_slice(&elem.to_le_bytes());
+    }
+    bytes
+}
+
+/// Push a pointer to `pointee` with the value `addr` on the operand stack
+fn push_ptr(emitter: &mut crate::stackify::emit::OpEmitter, addr: u32, pointee: Type) {
+    emitter.push_immediate(Immediate::U32(addr)).unwrap();
+    emitter.push(Type::Ptr(Box::new(pointee)));
+}
+
+/// Construct a module containing `test::main`, whose body is emitted by `emit`
+fn build_emitter_test_module<F>(emit: F) -> Module
+where
+    F: FnOnce(&mut crate::stackify::emit::OpEmitter),
+{
+    let id: miden_hir::FunctionIdent = "test::main".parse().unwrap();
+    let mut function = Function::new(id, Signature::new([], []));
+    let entry = function.body;
+    let mut stack = crate::stackify::OperandStack::default();
+    {
+        let mut emitter = crate::stackify::emit::OpEmitter::new(&mut function, entry, &mut stack);
+        emit(&mut emitter);
+    }
+
+    let mut module = Module::new(id.module);
+    module.functions.push_back(Box::new(function));
+    module.entry = Some(id);
+    module
+}
+
+/// Load a value of type `ty` from each of the given offsets into a test region, using either a
+/// dynamic or immediate address, and verify that the value observed is the one in memory.
+fn run_load_test(ty: Type, dynamic: bool, offsets: impl Iterator<Item = u32>) {
+    let parts = ty.clone().to_raw_parts().unwrap();
+    for offset in offsets {
+        let mut harness = TestByEmulationHarness::default();
+        harness
+            .emulator
+            .load_module(Module::mem_intrinsics())
+            .expect("failed to load intrinsics::mem");
+
+        let base = harness.malloc(STORE_TEST_REGION);
+        let bytes = fill_store_test_region(&mut harness, base);
+
+        // Each part of the value is expected to be zero-extended to 32 bits
+        let mut start = offset as usize;
+        let mut expected = vec![];
+        for part in parts.iter() {
+            let size = part.size_in_bytes();
+            let mut chunk = [0u8; 4];
+            chunk[..size].copy_from_slice(&bytes[start..(start + size)]);
+            let mask = u32::MAX >> (32 - part.size_in_bits());
+            expected.push((u32::from_le_bytes(chunk) & mask) as u64);
+            start += size;
+        }
+
+        let addr = base + offset;
+        let module = build_emitter_test_module(|emitter| {
+            if dynamic {
+                push_ptr(emitter, addr, ty.clone());
+                emitter.load(ty.clone()).unwrap();
+            } else {
+                emitter.load_imm(addr, ty.clone()).unwrap();
+            }
+        });
+
+        let mut stack = harness
+            .execute_module(module, &[])
+            .expect("execution failed");
+        let mut actual = vec![];
+        while let Some(elem) = stack.pop() {
+            actual.push(elem.as_int());
+        }
+        assert_eq!(
+            actual, expected,
+            "unexpected value loaded from offset {offset} for {ty} (dynamic = {dynamic})"
+        );
+        // Memory must not be modified by a load
+        assert_eq!(read_store_test_region(&harness, base), bytes);
+    }
+}
+
+/// Test loads of every loadable type at every element index and byte offset of a word
+#[test]
+fn load_all_types() {
+    let types = [
+        Type::I1,
+        Type::U8,
+        Type::I8,
+        Type::U16,
+        Type::I16,
+        Type::U32,
+        Type::I32,
+        Type::Ptr(Box::new(Type::U8)),
+        Type::U64,
+        Type::I64,
+        Type::I128,
+        Type::Array(Box::new(Type::U8), 3),
+        Type::Array(Box::new(Type::U16), 3),
+        Type::Array(Box::new(Type::U32), 3),
+    ];
+
+    for ty in types {
+        run_load_test(ty.clone(), false, 0..16);
+        run_load_test(ty, true, 0..16);
+    }
+}
+
+/// Test loads of field elements at every element index of a word
+#[test]
+fn load_felt() {
+    run_load_test(Type::Felt, false, (0..16).step_by(4));
+    run_load_test(Type::Felt, true, (0..16).step_by(4));
+}
+
+/// Test that loading a field element from an unaligned address traps
+#[test]
+#[should_panic(expected = "assertion failed: expected false, got true")]
+fn load_felt_unaligned() {
+    run_load_test(Type::Felt, true, 1..2);
+}
+
+/// Test loads of the largest aggregate which can be loaded from a dynamic address
+#[test]
+fn load_large_array() {
+    let ty = Type::Array(Box::new(Type::U32), 14);
+    run_load_test(ty.clone(), false, 0..16);
+    run_load_test(ty, true, 0..16);
+}
+
+/// Test that a dynamic load of an aggregate with too many parts to rearrange on the operand
+/// stack is rejected, while the same load from an immediate address is still supported
+#[test]
+fn load_array_too_large() {
+    let ty = Type::Array(Box::new(Type::U32), 15);
+    build_emitter_test_module(|emitter| {
+        push_ptr(emitter, 0, ty.clone());
+        let err = emitter
+            .load(ty.clone())
+            .expect_err("expected load to be rejected");
+        match err {
+            CodegenError::Unsupported {
+                op: hir::Opcode::Load,
+                ty: ref actual,
+                ..
+            } => assert_eq!(actual, &ty),
+            err => panic!("unexpected error: {err}"),
+        }
+    });
+
+    run_load_test(ty, false, 0..16);
+}
+
+/// Store `value` to each offset of the first 16 bytes of a test region, using either a
+/// dynamic or immediate address, and verify that exactly the bytes of the value were written.
+fn run_store_test(value: Immediate, dynamic: bool, offsets: impl Iterator<Item = u32>) {
+    let expected_bytes = immediate_to_le_bytes(value);
+    for offset in offsets {
+        let mut harness = TestByEmulationHarness::default();
+        harness
+            .emulator
+            .load_module(Module::mem_intrinsics())
+            .expect("failed to load intrinsics::mem");
+
+        let base = harness.malloc(STORE_TEST_REGION);
+        let mut expected = fill_store_test_region(&mut harness, base);
+        let start = offset as usize;
+        expected[start..(start + expected_bytes.len())].copy_from_slice(&expected_bytes);
+
+        let addr = base + offset;
+        let module = build_emitter_test_module(|emitter| {
+            emitter.literal(value).unwrap();
+            if dynamic {
+                push_ptr(emitter, addr, value.ty());
+                emitter.store().unwrap();
+            } else {
+                emitter.store_imm(addr).unwrap();
+            }
+        });
+
+        let stack = harness
+            .execute_module(module, &[])
+            .expect("execution failed");
+        assert!(stack.is_empty(), "expected the operand stack to be empty");
+        assert_eq!(
+            read_store_test_region(&harness, base),
+            expected,
+            "unexpected memory contents after storing {value} of type {} at offset {offset} (dynamic = {dynamic})",
+            value.ty()
+        );
+    }
+}
+
+/// Test stores of every integral type at every element index and byte offset of a word
+#[test]
+fn store_integers() {
+    let values = [
+        Immediate::I1(true),
+        Immediate::U8(0xa5),
+        Immediate::I8(-2),
+        Immediate::U16(0xbeef),
+        Immediate::I16(-300),
+        Immediate::U32(0xdeadbeef),
+        Immediate::I32(-123456789),
+        Immediate::U64(0x0123_4567_89ab_cdef),
+        Immediate::I64(-2),
+        Immediate::I128(0x0011_2233_4455_6677_8899_aabb_ccdd_eeff),
+    ];
+
+    for value in values {
+        run_store_test(value, false, 0..16);
+        run_store_test(value, true, 0..16);
+    }
+}
+
+/// Test stores of field elements at every element index of a word
+#[test]
+fn store_felt() {
+    let value = Immediate::Felt(Felt::new(0xdeadbeef));
+    run_store_test(value, false, (0..16).step_by(4));
+    run_store_test(value, true, (0..16).step_by(4));
+}
+
+/// Test that storing a field element to an unaligned address traps
+#[test]
+#[should_panic(expected = "assertion failed: expected false, got true")]
+fn store_felt_unaligned() {
+    let value = Immediate::Felt(Felt::new(1));
+    run_store_test(value, true, 1..2);
+}
+
+/// Test stores of aggregates, whose parts are not all the same size
+#[test]
+fn store_array() {
+    // A `[u16; 3]` is split into a `[u16; 2]` part, followed by a `u16` part
+    let ty = Type::Array(Box::new(Type::U16), 3);
+    let elements = [0x1122u16, 0x3344, 0x5566];
+    let expected_bytes = elements
+        .iter()
+        .flat_map(|e| e.to_le_bytes())
+        .collect::<Vec<_>>();
+
+    for dynamic in [false, true] {
+        for offset in 0..16 {
+            let mut harness = TestByEmulationHarness::default();
+            harness
+                .emulator
+                .load_module(Module::mem_intrinsics())
+                .expect("failed to load intrinsics::mem");
+
+            let base = harness.malloc(STORE_TEST_REGION);
+            let mut expected = fill_store_test_region(&mut harness, base);
+            let start = offset as usize;
+            expected[start..(start + expected_bytes.len())].copy_from_slice(&expected_bytes);
+
+            let addr = base + offset;
+            let module = build_emitter_test_module(|emitter| {
+                // The first part is on top of the stack
+                emitter.literal(Immediate::U32(elements[2] as u32)).unwrap();
+                emitter
+                    .literal(Immediate::U32(
+                        ((elements[1] as u32) << 16) | elements[0] as u32,
+                    ))
+                    .unwrap();
+                emitter.pop();
+                emitter.pop();
+                emitter.push(ty.clone());
+                if dynamic {
+                    push_ptr(emitter, addr, ty.clone());
+                    emitter.store().unwrap();
+                } else {
+                    emitter.store_imm(addr).unwrap();
+                }
+            });
+
+            harness
+                .execute_module(module, &[])
+                .expect("execution failed");
+            assert_eq!(
+                read_store_test_region(&harness, base),
+                expected,
+                "unexpected memory contents after storing {ty} at offset {offset} (dynamic = {dynamic})"
+            );
+        }
+    }
+}
+
+/// Test stores of aggregates with more parts than can be rearranged with `movdn`, which
+/// unlike loads, only ever need to reach the part immediately below the pointer
+#[test]
+fn store_large_array() {
+    let ty = Type::Array(Box::new(Type::U32), 15);
+    let elements = (0..15u32).map(|i| 0x0101_0101 * i).collect::<Vec<_>>();
+    let expected_bytes = elements
+        .iter()
+        .flat_map(|e| e.to_le_bytes())
+        .collect::<Vec<_>>();
+
+    for dynamic in [false, true] {
+        for offset in 0..16 {
+            let mut harness = TestByEmulationHarness::default();
+            harness
+                .emulator
+                .load_module(Module::mem_intrinsics())
+                .expect("failed to load intrinsics::mem");
+
+            let base = harness.malloc(STORE_TEST_REGION);
+            let mut expected = fill_store_test_region(&mut harness, base);
+            let start = offset as usize;
+            expected[start..(start + expected_bytes.len())].copy_from_slice(&expected_bytes);
+
+            let addr = base + offset;
+            let module = build_emitter_test_module(|emitter| {
+                // The first part is on top of the stack
+                for element in elements.iter().rev() {
+                    emitter.literal(Immediate::U32(*element)).unwrap();
+                }
+                for _ in elements.iter() {
+                    emitter.pop();
+                }
+                emitter.push(ty.clone());
+                if dynamic {
+                    push_ptr(emitter, addr, ty.clone());
+                    emitter.store().unwrap();
+                } else {
+                    emitter.store_imm(addr).unwrap();
+                }
+            });
+
+            let stack = harness
+                .execute_module(module, &[])
+                .expect("execution failed");
+            assert!(stack.is_empty(), "expected the operand stack to be empty");
+            assert_eq!(
+                read_store_test_region(&harness, base),
+                expected,
+                "unexpected memory contents after storing {ty} at offset {offset} (dynamic = {dynamic})"
+            );
+        }
+    }
+}
+
+/// Test that memcpy copies exactly `count * sizeof(*ty)` bytes between unaligned addresses
+#[test]
+fn memcpy_unaligned() {
+    let mut harness = TestByEmulationHarness::default();
+    harness
+        .emulator
+        .load_module(Module::mem_intrinsics())
+        .expect("failed to load intrinsics::mem");
+
+    let base = harness.malloc(STORE_TEST_REGION);
+    let mut expected = fill_store_test_region(&mut harness, base);
+
+    // Copy 5 u16 values from offset 3 to offset 22
+    let (src, dst, count) = (3, 22, 5);
+    let len = count * 2;
+    expected.copy_within(src..(src + len), dst);
+
+    let module = build_emitter_test_module(|emitter| {
+        emitter.literal(Immediate::U32(count as u32)).unwrap();
+        push_ptr(emitter, base + dst as u32, Type::U16);
+        push_ptr(emitter, base + src as u32, Type::U16);
+        emitter.memcpy().unwrap();
+    });
+
+    let stack = harness
+        .execute_module(module, &[])
+        .expect("execution failed");
+    assert!(stack.is_empty(), "expected the operand stack to be empty");
+    assert_eq!(read_store_test_region(&harness, base), expected);
+}
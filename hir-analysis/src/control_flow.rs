@@ -207,12 +207,12 @@ pub(crate) fn visit_block_succs<F: FnMut(Inst, Block, bool)>(
 
             Instruction::Switch(Switch {
                 ref arms,
-                default: default_block,
+                default: (default_block, _),
                 ..
             }) => {
                 visit(inst, *default_block, false);
 
-                for (_, dest) in arms.as_slice() {
+                for (_, dest, _) in arms.as_slice() {
                     visit(inst, *dest, true);
                 }
             }
@@ -4,13 +4,44 @@ mod liveness;
 mod loops;
 mod validation;
 
+pub use miden_hir::{CallGraph, CallGraphCycleError, CallKinds};
+
 pub use self::control_flow::{BlockPredecessor, ControlFlowGraph};
 pub use self::dominance::{DominanceFrontier, DominatorTree, DominatorTreePreorder};
 pub use self::liveness::LivenessAnalysis;
 pub use self::loops::{Loop, LoopAnalysis, LoopLevel};
-pub use self::validation::{ModuleValidator, Rule};
+pub use self::validation::{FunctionValidator, ModuleValidator, Rule, ValidationError};
+
+use anyhow::{anyhow, bail};
+use rustc_hash::FxHashMap;
+
+use miden_hir::{FunctionIdent, Ident};
+
+bitflags::bitflags! {
+    /// The set of analyses which remain valid after a rewrite has been applied to a function.
+    ///
+    /// An analysis which is not preserved is invalidated once the rewrite is done, and is
+    /// recomputed the next time it is required. Analyses are computed from one another, so an
+    /// analysis is only considered preserved if those it is computed from are preserved too,
+    /// e.g. preserving [LoopAnalysis] without the [DominatorTree] preserves neither.
+    pub struct PreservedAnalyses: u8 {
+        /// The [ControlFlowGraph]
+        const CFG = 1;
+        /// The [DominatorTree]
+        const DOMTREE = 1 << 1;
+        /// The [LoopAnalysis]
+        const LOOPS = 1 << 2;
+        /// The [LivenessAnalysis]
+        const LIVENESS = 1 << 3;
+        /// The [CallGraph] of the module or program containing the function, i.e. the set of
+        /// functions called by the function is unchanged
+        const CALLGRAPH = 1 << 4;
 
-use anyhow::anyhow;
+        /// An alias for all of the analyses of a single function, i.e. all but the [CallGraph]
+        const FUNCTION =
+            Self::CFG.bits | Self::DOMTREE.bits | Self::LOOPS.bits | Self::LIVENESS.bits;
+    }
+}
 
 /// This structure provides access to various analyses for a single [miden_hir::Function].
 ///
@@ -22,8 +53,10 @@ use anyhow::anyhow;
 /// which will only compute each analysis once, unless `recompute` is called.
 ///
 /// It is up to the owner of this structure to ensure that analyses are recomputed when
-/// the original function is modified. This is not done automatically, so forgetting to
-/// can result in unexpected compilation failures, or worse, miscompilation.
+/// the original function is modified, typically by calling `invalidate` with the set of
+/// [PreservedAnalyses] reported by each rewrite, which the pass manager in
+/// `miden-hir-transform` does automatically. Forgetting to do so can result in unexpected
+/// compilation failures, or worse, miscompilation.
 pub struct FunctionAnalysis {
     cfg: ControlFlowGraph,
     domtree: Option<DominatorTree>,
@@ -181,4 +214,205 @@ impl FunctionAnalysis {
             liveness.recompute(function, &self.cfg, domtree, loops);
         }
     }
+
+    /// Invalidates the analyses which are not in `preserved`, after a rewrite of `function`
+    ///
+    /// The [ControlFlowGraph] is recomputed immediately, as it is always available, while the
+    /// other analyses are discarded, and will be recomputed the next time they are required.
+    pub fn invalidate(&mut self, function: &miden_hir::Function, preserved: PreservedAnalyses) {
+        if !preserved.contains(PreservedAnalyses::CFG) {
+            self.cfg.compute(&function.dfg);
+        }
+
+        let mut required = PreservedAnalyses::CFG | PreservedAnalyses::DOMTREE;
+        if !preserved.contains(required) {
+            self.domtree = None;
+        }
+        required |= PreservedAnalyses::LOOPS;
+        if !preserved.contains(required) {
+            self.loops = None;
+        }
+        required |= PreservedAnalyses::LIVENESS;
+        if !preserved.contains(required) {
+            self.liveness = None;
+        }
+    }
+
+    /// Verifies that each of the analyses in `preserved` which is available, is identical to
+    /// the same analysis computed from scratch for `function`.
+    ///
+    /// This is used to catch rewrites which claim to preserve an analysis they have modified,
+    /// and is expensive, as it recomputes every analysis being verified.
+    ///
+    /// Returns an error naming the first analysis which is out of date.
+    pub fn verify_preserved(
+        &self,
+        function: &miden_hir::Function,
+        preserved: PreservedAnalyses,
+    ) -> anyhow::Result<()> {
+        let cfg = ControlFlowGraph::with_function(function);
+        if preserved.contains(PreservedAnalyses::CFG) && !same_cfg(function, &self.cfg, &cfg) {
+            bail!("the control flow graph is out of date");
+        }
+
+        let Some(prev_domtree) = self.domtree.as_ref() else {
+            return Ok(());
+        };
+        let domtree = DominatorTree::with_function(function, &cfg);
+        if preserved.contains(PreservedAnalyses::DOMTREE)
+            && !same_domtree(function, prev_domtree, &domtree)
+        {
+            bail!("the dominator tree is out of date");
+        }
+
+        let Some(prev_loops) = self.loops.as_ref() else {
+            return Ok(());
+        };
+        let loops = LoopAnalysis::with_function(function, &cfg, &domtree);
+        if preserved.contains(PreservedAnalyses::LOOPS) && !same_loops(function, prev_loops, &loops)
+        {
+            bail!("the loop analysis is out of date");
+        }
+
+        let Some(prev_liveness) = self.liveness.as_ref() else {
+            return Ok(());
+        };
+        if preserved.contains(PreservedAnalyses::LIVENESS)
+            && *prev_liveness != LivenessAnalysis::compute(function, &cfg, &domtree, &loops)
+        {
+            bail!("the liveness analysis is out of date");
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns true if `a` and `b` have the same edges between the blocks of `function`
+fn same_cfg(function: &miden_hir::Function, a: &ControlFlowGraph, b: &ControlFlowGraph) -> bool {
+    function.dfg.blocks().all(|(block, _)| {
+        a.succ_iter(block).eq(b.succ_iter(block)) && a.pred_iter(block).eq(b.pred_iter(block))
+    })
+}
+
+/// Returns true if `a` and `b` agree on the immediate dominator of every block of `function`
+fn same_domtree(function: &miden_hir::Function, a: &DominatorTree, b: &DominatorTree) -> bool {
+    function.dfg.blocks().all(|(block, _)| {
+        a.is_reachable(block) == b.is_reachable(block) && a.idom(block) == b.idom(block)
+    })
+}
+
+/// Returns true if `a` and `b` agree on the innermost loop of every block of `function`,
+/// and its depth, where loops are identified by their headers
+fn same_loops(function: &miden_hir::Function, a: &LoopAnalysis, b: &LoopAnalysis) -> bool {
+    function.dfg.blocks().all(|(block, _)| {
+        a.innermost_loop(block).map(|lp| a.loop_header(lp))
+            == b.innermost_loop(block).map(|lp| b.loop_header(lp))
+            && a.loop_level(block) == b.loop_level(block)
+    })
+}
+
+/// This structure provides access to the analyses of the functions of a single
+/// [miden_hir::Module], and to the [CallGraph] of that module.
+///
+/// Like [FunctionAnalysis], analyses are computed on demand, and it is up to the owner of
+/// this structure to invalidate them when the module is modified.
+#[derive(Default)]
+pub struct ModuleAnalysis {
+    functions: FxHashMap<FunctionIdent, FunctionAnalysis>,
+    callgraph: Option<CallGraph>,
+}
+impl ModuleAnalysis {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the analyses of `function`, constructing the default analysis results
+    /// for it if they are not yet available
+    pub fn function(&mut self, function: &miden_hir::Function) -> &mut FunctionAnalysis {
+        self.functions
+            .entry(function.id)
+            .or_insert_with(|| FunctionAnalysis::new(function))
+    }
+
+    /// Remove the analyses of the function `id` from this structure, if available
+    pub fn take_function(&mut self, id: &FunctionIdent) -> Option<FunctionAnalysis> {
+        self.functions.remove(id)
+    }
+
+    /// Get a reference to the current [CallGraph] of the module
+    ///
+    /// NOTE: This function will panic if the analysis has not yet been computed.
+    pub fn callgraph(&self) -> &CallGraph {
+        self.callgraph
+            .as_ref()
+            .expect("call graph analysis is unavailable")
+    }
+
+    /// Ensures that the [CallGraph] is computed for `module`
+    pub fn ensure_callgraph(&mut self, module: &miden_hir::Module) {
+        self.callgraph
+            .get_or_insert_with(|| CallGraph::with_module(module));
+    }
+
+    /// Invalidates the analyses which are not in `preserved`, after a rewrite of the module
+    ///
+    /// As any function in the module may have been modified, the analyses of every function
+    /// are discarded, unless all of them were preserved.
+    pub fn invalidate(&mut self, preserved: PreservedAnalyses) {
+        if !preserved.contains(PreservedAnalyses::FUNCTION) {
+            self.functions.clear();
+        }
+        if !preserved.contains(PreservedAnalyses::CALLGRAPH) {
+            self.callgraph = None;
+        }
+    }
+}
+
+/// This structure provides access to the analyses of the modules of a [miden_hir::Program],
+/// and to the [CallGraph] of the program.
+///
+/// Like [FunctionAnalysis], analyses are computed on demand, and it is up to the owner of
+/// this structure to invalidate them when the program is modified.
+#[derive(Default)]
+pub struct ProgramAnalysis {
+    modules: FxHashMap<Ident, ModuleAnalysis>,
+    callgraph: Option<CallGraph>,
+}
+impl ProgramAnalysis {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the analyses of `module`
+    pub fn module(&mut self, module: &miden_hir::Module) -> &mut ModuleAnalysis {
+        self.modules.entry(module.name).or_default()
+    }
+
+    /// Get a reference to the current [CallGraph] of the program
+    ///
+    /// NOTE: This function will panic if the analysis has not yet been computed.
+    pub fn callgraph(&self) -> &CallGraph {
+        self.callgraph
+            .as_ref()
+            .expect("call graph analysis is unavailable")
+    }
+
+    /// Ensures that the [CallGraph] is computed for `program`
+    pub fn ensure_callgraph(&mut self, program: &miden_hir::Program) {
+        self.callgraph
+            .get_or_insert_with(|| CallGraph::with_program(program));
+    }
+
+    /// Invalidates the analyses which are not in `preserved`, after a rewrite of the program
+    ///
+    /// As any module in the program may have been modified, this invalidates the analyses
+    /// of every module, see [ModuleAnalysis::invalidate].
+    pub fn invalidate(&mut self, preserved: PreservedAnalyses) {
+        for module in self.modules.values_mut() {
+            module.invalidate(preserved);
+        }
+        if !preserved.contains(PreservedAnalyses::CALLGRAPH) {
+            self.callgraph = None;
+        }
+    }
 }
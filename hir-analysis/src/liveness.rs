@@ -48,7 +48,7 @@ use super::{ControlFlowGraph, DominatorTree, LoopAnalysis};
 /// is that it allows us to be smart about how we spill values, since it can tell us
 /// how "hot" a value is, allowing us to prioritize such values over those which may
 /// not be used for awhile.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, PartialEq, Eq)]
 pub struct LivenessAnalysis {
     // Liveness/global next-uses at a given program point
     live_in: FxHashMap<ProgramPoint, NextUseSet>,
@@ -200,6 +200,7 @@ mod function;
 mod naming;
 mod typecheck;
 
+pub use self::function::FunctionValidator;
 pub use self::typecheck::TypeError;
 
 use miden_diagnostics::{DiagnosticsHandler, SourceSpan};
@@ -207,7 +208,6 @@ use miden_hir::*;
 use miden_hir_pass::Pass;
 
 use self::block::{BlockValidator, DefsDominateUses};
-use self::function::FunctionValidator;
 use self::naming::NamingConventions;
 use self::typecheck::TypeCheck;
 
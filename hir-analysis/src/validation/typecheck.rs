@@ -136,11 +136,18 @@ impl<'a> Rule<BlockData> for TypeCheck<'a> {
                 | Instruction::BinaryOp(_)
                 | Instruction::PrimOp(_)
                 | Instruction::Test(_)
-                | Instruction::InlineAsm(_)
                 | Instruction::Call(_) => {
                     let args = node.arguments(&self.dfg.value_lists);
                     typechecker.check(args, results)?;
                 }
+                Instruction::InlineAsm(ref asm) => {
+                    let args = node.arguments(&self.dfg.value_lists);
+                    typechecker.check(args, results)?;
+                    // The body must consume its arguments, and leave its results on the stack
+                    if let Err(err) = asm.verify(self.dfg) {
+                        invalid_instruction!(diagnostics, node.key, span, "{err}");
+                    }
+                }
                 Instruction::Ret(Ret { ref args, .. }) => {
                     let args = args.as_slice(&self.dfg.value_lists);
                     if args.len() != self.signature.results.len() {
@@ -282,19 +289,47 @@ impl<'a> Rule<BlockData> for TypeCheck<'a> {
                     typechecker.check(&[*arg], results)?;
 
                     let mut seen = FxHashMap::<u32, usize>::default();
-                    for (i, (key, successor)) in arms.iter().enumerate() {
+                    for (i, (key, _, _)) in arms.iter().enumerate() {
                         if let Some(prev) = seen.insert(*key, i) {
                             return Err(ValidationError::InvalidInstruction { span, inst: node.key, reason: format!("all arms of a 'switch' must have a unique discriminant, but the arm at index {i} has the same discriminant as the arm at {prev}") });
                         }
+                    }
 
-                        let expected = self.dfg.block_args(*successor);
-                        if !expected.is_empty() {
-                            return Err(ValidationError::InvalidInstruction { span, inst: node.key, reason: format!("all successors of a 'switch' must not have block parameters, but {successor}, the successor for discriminant {key}, has {} arguments", expected.len()) });
+                    let successors = arms
+                        .iter()
+                        .map(|(_, dest, args)| (*dest, args))
+                        .chain([(fallback.0, &fallback.1)]);
+                    for (successor, dest_args) in successors {
+                        let expected = self.dfg.block_args(successor);
+                        let args = dest_args.as_slice(&self.dfg.value_lists);
+                        if args.len() != expected.len() {
+                            return Err(ValidationError::TypeError(
+                                TypeError::IncorrectSuccessorArgumentCount {
+                                    successor,
+                                    expected: expected.len(),
+                                    actual: args.len(),
+                                },
+                            ));
+                        }
+                        for (index, (param, arg)) in expected
+                            .iter()
+                            .copied()
+                            .zip(args.iter().copied())
+                            .enumerate()
+                        {
+                            let expected = self.dfg.value_type(param);
+                            let actual = self.dfg.value_type(arg);
+                            if actual != expected {
+                                return Err(ValidationError::TypeError(
+                                    TypeError::IncorrectSuccessorArgumentType {
+                                        successor,
+                                        expected: expected.clone(),
+                                        actual: actual.clone(),
+                                        index,
+                                    },
+                                ));
+                            }
                         }
-                    }
-                    let expected = self.dfg.block_args(*fallback);
-                    if !expected.is_empty() {
-                        return Err(ValidationError::InvalidInstruction { span, inst: node.key, reason: format!("all successors of a 'switch' must not have block parameters, but {fallback}, the default successor, has {} arguments", expected.len()) });
                     }
                 }
             }
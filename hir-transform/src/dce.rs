@@ -0,0 +1,308 @@
+use rustc_hash::{FxHashMap, FxHashSet};
+use smallvec::{smallvec, SmallVec};
+
+use miden_hir::{self as hir, *};
+use miden_hir_analysis::{FunctionAnalysis, PreservedAnalyses};
+
+use crate::RewritePass;
+
+/// This pass operates on the SSA IR, and removes code which has no effect on the
+/// observable behavior of the function.
+///
+/// Specifically, it performs the following:
+///
+/// * Blocks which are unreachable from the entry block are detached from the function
+/// * Instructions without side effects (see [Opcode::has_side_effects]) whose results are
+/// never used by a live instruction are removed.
+/// * Block parameters which are never used by a live instruction are removed, along with
+/// the corresponding arguments of all branches to that block.
+///
+/// Liveness is computed by starting from the set of instructions with side effects, which
+/// includes all terminators, and marking everything they transitively depend on as live.
+/// Branch arguments are only considered uses when the parameter they are bound to is live,
+/// so values which only flow around a loop without otherwise being used are removed.
+///
+/// The parameters of the entry block are never removed, as they are determined by the
+/// function signature.
+pub struct DeadCodeElimination;
+impl RewritePass for DeadCodeElimination {
+    type Error = anyhow::Error;
+
+    fn name(&self) -> &'static str {
+        "dce"
+    }
+
+    fn run(
+        &mut self,
+        function: &mut hir::Function,
+        _analysis: &mut FunctionAnalysis,
+    ) -> Result<PreservedAnalyses, Self::Error> {
+        let dfg = &mut function.dfg;
+        let mut changed = false;
+        let mut detached = false;
+
+        // Detach blocks which are unreachable from the entry block
+        let reachable = reachable_blocks(dfg);
+        let blocks = dfg.blocks().map(|(b, _)| b).collect::<Vec<_>>();
+        let mut live_blocks = Vec::with_capacity(blocks.len());
+        for block in blocks.into_iter() {
+            if reachable.contains(&block) {
+                live_blocks.push(block);
+            } else {
+                dfg.detach_block(block);
+                detached = true;
+            }
+        }
+
+        // Determine the predecessors of each block, and the set of instructions
+        // which must be kept due to their side effects
+        let mut preds = FxHashMap::<Block, SmallVec<[Inst; 2]>>::default();
+        let mut live_insts = FxHashSet::<Inst>::default();
+        let mut live_values = FxHashSet::<Value>::default();
+        let mut worklist = Vec::<Value>::default();
+        for block in live_blocks.iter().copied() {
+            for inst in dfg.block_insts(block) {
+                for dest in successors(dfg, inst) {
+                    let block_preds = preds.entry(dest).or_default();
+                    if block_preds.last() != Some(&inst) {
+                        block_preds.push(inst);
+                    }
+                }
+                if dfg[inst].has_side_effects() {
+                    live_insts.insert(inst);
+                    worklist.extend_from_slice(dfg.inst_args(inst));
+                }
+            }
+        }
+
+        // Mark everything the live instructions depend on as live
+        while let Some(value) = worklist.pop() {
+            if !live_values.insert(value) {
+                continue;
+            }
+            match dfg.value_data(value) {
+                ValueData::Inst { inst, .. } => {
+                    if live_insts.insert(*inst) {
+                        worklist.extend_from_slice(dfg.inst_args(*inst));
+                    }
+                }
+                ValueData::Param { block, num, .. } => {
+                    let num = *num as usize;
+                    for pred in preds.get(block).into_iter().flatten() {
+                        match dfg.analyze_branch(*pred) {
+                            BranchInfo::NotABranch => unreachable!(),
+                            BranchInfo::SingleDest(_, args) => worklist.push(args[num]),
+                            BranchInfo::MultiDest(jts) => worklist.extend(
+                                jts.into_iter()
+                                    .filter(|jt| jt.destination == *block)
+                                    .map(|jt| jt.args[num]),
+                            ),
+                        }
+                    }
+                }
+            }
+        }
+
+        // Remove dead instructions
+        for block in live_blocks.iter().copied() {
+            let block = &mut dfg.blocks[block];
+            let mut insts = block.insts.take();
+            while let Some(inst) = insts.pop_front() {
+                if live_insts.contains(&inst.key) {
+                    block.insts.push_back(inst);
+                } else {
+                    changed = true;
+                }
+            }
+        }
+
+        // Remove dead block parameters, and their corresponding branch arguments
+        let entry = dfg.entry_block();
+        for block in live_blocks.iter().copied() {
+            if block == entry {
+                continue;
+            }
+            let params = SmallVec::<[Value; 4]>::from_slice(dfg.block_params(block));
+            for (index, param) in params.iter().enumerate().rev() {
+                if live_values.contains(param) {
+                    continue;
+                }
+                for pred in preds.get(&block).into_iter().flatten() {
+                    dfg.visit_edges_to(*pred, block, |_, args, pool| {
+                        args.remove(index, pool);
+                    });
+                }
+                dfg.remove_block_param(block, index);
+                changed = true;
+            }
+        }
+
+        // Removing unreachable blocks changes the control flow graph, and may remove calls,
+        // while removing instructions and block parameters from the remaining blocks does not
+        if detached {
+            Ok(PreservedAnalyses::empty())
+        } else if changed {
+            Ok(PreservedAnalyses::all() - PreservedAnalyses::LIVENESS)
+        } else {
+            Ok(PreservedAnalyses::all())
+        }
+    }
+}
+
+pub(crate) fn successors(dfg: &DataFlowGraph, inst: Inst) -> SmallVec<[Block; 2]> {
+    match dfg.analyze_branch(inst) {
+        BranchInfo::NotABranch => SmallVec::new(),
+        BranchInfo::SingleDest(dest, _) => smallvec![dest],
+        BranchInfo::MultiDest(jts) => jts.into_iter().map(|jt| jt.destination).collect(),
+    }
+}
+
+pub(crate) fn reachable_blocks(dfg: &DataFlowGraph) -> FxHashSet<Block> {
+    let mut reachable = FxHashSet::<Block>::default();
+    let mut worklist = vec![dfg.entry_block()];
+    while let Some(block) = worklist.pop() {
+        if !reachable.insert(block) {
+            continue;
+        }
+        if let Some(inst) = dfg.last_inst(block) {
+            worklist.extend(successors(dfg, inst));
+        }
+    }
+    reachable
+}
+
+#[cfg(test)]
+mod tests {
+    use miden_hir::{
+        AbiParam, Function, FunctionBuilder, Immediate, InstBuilder, Signature, SourceSpan, Type,
+    };
+    use miden_hir_analysis::FunctionAnalysis;
+    use pretty_assertions::assert_eq;
+
+    use crate::{DeadCodeElimination, RewritePass};
+
+    /// Unused pure instructions, unused block parameters along with the values passed
+    /// to them, and unreachable blocks should all be removed.
+    #[test]
+    fn dce_removes_dead_code() {
+        let id = "test::dce".parse().unwrap();
+        let mut function = Function::new(
+            id,
+            Signature::new([AbiParam::new(Type::U32)], [AbiParam::new(Type::U32)]),
+        );
+
+        {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let entry = builder.current_block();
+            let v0 = builder.block_params(entry)[0];
+
+            let a = builder.create_block();
+            let v1 = builder.append_block_param(a, Type::U32, SourceSpan::UNKNOWN);
+            builder.append_block_param(a, Type::U32, SourceSpan::UNKNOWN);
+            let b = builder.create_block();
+
+            // entry
+            let v3 = builder
+                .ins()
+                .add_imm(v0, Immediate::U32(1), SourceSpan::UNKNOWN);
+            let v4 = builder
+                .ins()
+                .mul_imm(v0, Immediate::U32(2), SourceSpan::UNKNOWN);
+            builder
+                .ins()
+                .sub_imm(v0, Immediate::U32(3), SourceSpan::UNKNOWN);
+            builder.ins().br(a, &[v3, v4], SourceSpan::UNKNOWN);
+
+            // block1
+            builder.switch_to_block(a);
+            builder.ins().ret(Some(v1), SourceSpan::UNKNOWN);
+
+            // block2
+            builder.switch_to_block(b);
+            let v6 = builder.ins().u32(1, SourceSpan::UNKNOWN);
+            builder.ins().ret(Some(v6), SourceSpan::UNKNOWN);
+        }
+
+        let mut analysis = FunctionAnalysis::new(&function);
+        let mut pass = DeadCodeElimination;
+        pass.run(&mut function, &mut analysis)
+            .expect("dead code elimination failed");
+
+        let expected = "pub fn dce(u32) -> u32 {
+block0(v0: u32):
+    v3 = add v0, 1  : u32
+    br block1(v3)
+
+block1(v1: u32):
+    ret v1
+}
+";
+
+        assert_eq!(function.to_string().as_str(), expected);
+    }
+
+    /// A value which only flows around a loop, without being used by anything
+    /// else, is dead, even though it is used by instructions in the loop.
+    #[test]
+    fn dce_removes_dead_loop_carried_values() {
+        let id = "test::dce_loop".parse().unwrap();
+        let mut function = Function::new(
+            id,
+            Signature::new([AbiParam::new(Type::U32)], [AbiParam::new(Type::U32)]),
+        );
+
+        {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let entry = builder.current_block();
+            let v0 = builder.block_params(entry)[0];
+
+            let header = builder.create_block();
+            let v1 = builder.append_block_param(header, Type::U32, SourceSpan::UNKNOWN);
+            let v2 = builder.append_block_param(header, Type::U32, SourceSpan::UNKNOWN);
+            let exit = builder.create_block();
+
+            // entry
+            builder.ins().br(header, &[v0, v0], SourceSpan::UNKNOWN);
+
+            // block1
+            builder.switch_to_block(header);
+            let v3 = builder
+                .ins()
+                .add_imm(v2, Immediate::U32(1), SourceSpan::UNKNOWN);
+            let v4 = builder
+                .ins()
+                .add_imm(v1, Immediate::U32(1), SourceSpan::UNKNOWN);
+            let v5 = builder
+                .ins()
+                .lt_imm(v4, Immediate::U32(10), SourceSpan::UNKNOWN);
+            builder
+                .ins()
+                .cond_br(v5, header, &[v4, v3], exit, &[], SourceSpan::UNKNOWN);
+
+            // block2
+            builder.switch_to_block(exit);
+            builder.ins().ret(Some(v0), SourceSpan::UNKNOWN);
+        }
+
+        let mut analysis = FunctionAnalysis::new(&function);
+        let mut pass = DeadCodeElimination;
+        pass.run(&mut function, &mut analysis)
+            .expect("dead code elimination failed");
+
+        let expected = "pub fn dce_loop(u32) -> u32 {
+block0(v0: u32):
+    br block1(v0)
+
+block1(v1: u32):
+    v4 = add v1, 1  : u32
+    v5 = lt v4, 10  : i1
+    condbr v5, block1(v4), block2
+
+block2:
+    ret v0
+}
+";
+
+        assert_eq!(function.to_string().as_str(), expected);
+    }
+}
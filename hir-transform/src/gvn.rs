@@ -0,0 +1,364 @@
+use std::rc::Rc;
+
+use rustc_hash::{FxHashMap, FxHashSet};
+use smallvec::SmallVec;
+
+use miden_hir::{self as hir, *};
+use miden_hir_analysis::{DominatorTreePreorder, FunctionAnalysis, PreservedAnalyses};
+
+use crate::{adt::ScopedMap, inline_blocks::rewrite_use, RewritePass};
+
+/// This pass operates on the SSA IR, and performs global value numbering, i.e. it
+/// finds pure computations which are redundant with an equivalent computation that
+/// dominates them, and replaces all uses of the redundant computation with the
+/// result of the dominating one.
+///
+/// Two instructions are considered equivalent when they have the same opcode, overflow
+/// behavior, operands, immediate, and result type. The operands of commutative operators
+/// are considered equal in either order.
+///
+/// The dominator tree is traversed in pre-order, with a [ScopedMap] of available
+/// expressions for each block which inherits the expressions available in its immediate
+/// dominator, so that an expression is only ever reused where its definition dominates
+/// the use.
+///
+/// Some things to note:
+///
+/// * Constants are not numbered: on Miden, pushing an immediate is cheaper than keeping a
+/// value live on the operand stack until it is needed again.
+/// * Loads are only numbered within a single block, and the set of available loads is
+/// cleared by any instruction which may write memory.
+/// * Instructions with side effects, or multiple results, are never numbered.
+pub struct GlobalValueNumbering;
+impl RewritePass for GlobalValueNumbering {
+    type Error = anyhow::Error;
+
+    fn name(&self) -> &'static str {
+        "gvn"
+    }
+
+    fn run(
+        &mut self,
+        function: &mut hir::Function,
+        analysis: &mut FunctionAnalysis,
+    ) -> Result<PreservedAnalyses, Self::Error> {
+        analysis.ensure_domtree(function);
+        let domtree = DominatorTreePreorder::with_function(analysis.domtree(), function);
+
+        let dfg = &mut function.dfg;
+        let mut rewrites = ScopedMap::<Value, Value>::default();
+        let mut visited = FxHashSet::<Block>::default();
+        let mut changed = false;
+
+        let mut worklist = vec![(
+            dfg.entry_block(),
+            Rc::new(ScopedMap::<Key, Value>::default()),
+        )];
+        while let Some((block, parent)) = worklist.pop() {
+            visited.insert(block);
+
+            let mut available = ScopedMap::new(Some(parent));
+            let mut loads = FxHashMap::<Key, Value>::default();
+            let mut redundant = FxHashSet::<Inst>::default();
+            let insts = dfg.block_insts(block).collect::<SmallVec<[Inst; 16]>>();
+            for inst in insts {
+                // Replace uses of values which were found to be redundant
+                rewrite_use(dfg.insts[inst].as_mut(), &mut dfg.value_lists, &rewrites);
+
+                if may_write_memory(dfg[inst].opcode()) {
+                    loads.clear();
+                }
+
+                let Some(key) = Key::new(dfg, inst) else {
+                    continue;
+                };
+                let result = dfg.first_result(inst);
+                let existing = if key.op == Opcode::Load {
+                    loads.get(&key)
+                } else {
+                    available.get(&key)
+                };
+                match existing.copied() {
+                    Some(value) => {
+                        rewrites.insert(result, value);
+                        redundant.insert(inst);
+                    }
+                    None if key.op == Opcode::Load => {
+                        loads.insert(key, result);
+                    }
+                    None => available.insert(key, result),
+                }
+            }
+
+            // Remove the redundant instructions from the block
+            if !redundant.is_empty() {
+                let block = &mut dfg.blocks[block];
+                let mut insts = block.insts.take();
+                while let Some(inst) = insts.pop_front() {
+                    if !redundant.contains(&inst.key) {
+                        block.insts.push_back(inst);
+                    }
+                }
+                changed = true;
+            }
+
+            let available = Rc::new(available);
+            for child in domtree.children(block) {
+                worklist.push((child, available.clone()));
+            }
+        }
+
+        // Blocks which are unreachable are not visited above, but may still refer to
+        // values which were replaced, so make sure those uses are rewritten as well
+        if changed {
+            let blocks = dfg.blocks().map(|(b, _)| b).collect::<Vec<_>>();
+            for block in blocks.into_iter() {
+                if visited.contains(&block) {
+                    continue;
+                }
+                let insts = dfg.block_insts(block).collect::<SmallVec<[Inst; 16]>>();
+                for inst in insts {
+                    rewrite_use(dfg.insts[inst].as_mut(), &mut dfg.value_lists, &rewrites);
+                }
+            }
+        }
+
+        // Only redundant instructions without side effects were removed
+        if changed {
+            Ok(PreservedAnalyses::all() - PreservedAnalyses::LIVENESS)
+        } else {
+            Ok(PreservedAnalyses::all())
+        }
+    }
+}
+
+/// The value number of a pure instruction
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Key {
+    op: Opcode,
+    overflow: Option<Overflow>,
+    args: SmallVec<[Value; 3]>,
+    imm: Option<Immediate>,
+    ty: Type,
+}
+impl Key {
+    /// Compute the key for `inst`, if it is a candidate for numbering
+    fn new(dfg: &DataFlowGraph, inst: Inst) -> Option<Self> {
+        let ix = &dfg[inst];
+        if ix.has_side_effects() {
+            return None;
+        }
+        let ty = match dfg.inst_results(inst) {
+            [result] => dfg.value_type(*result).clone(),
+            _ => return None,
+        };
+        let (args, imm) = match ix {
+            Instruction::BinaryOp(BinaryOp { op, args, .. }) => {
+                let mut args = *args;
+                if op.is_commutative() {
+                    args.sort();
+                }
+                (SmallVec::from_slice(&args), None)
+            }
+            Instruction::BinaryOpImm(BinaryOpImm { arg, imm, .. }) => {
+                (SmallVec::from_slice(&[*arg]), Some(*imm))
+            }
+            Instruction::UnaryOp(UnaryOp { arg, .. })
+            | Instruction::Load(LoadOp { addr: arg, .. }) => (SmallVec::from_slice(&[*arg]), None),
+            Instruction::PrimOp(PrimOp {
+                op: Opcode::Select,
+                args,
+            }) => (SmallVec::from_slice(args.as_slice(&dfg.value_lists)), None),
+            _ => return None,
+        };
+        Some(Self {
+            op: ix.opcode(),
+            overflow: ix.overflow(),
+            args,
+            imm,
+            ty,
+        })
+    }
+}
+
+fn may_write_memory(op: Opcode) -> bool {
+    matches!(
+        op,
+        Opcode::Store
+            | Opcode::MemCpy
+            | Opcode::MemGrow
+            | Opcode::Call
+            | Opcode::Syscall
+            | Opcode::InlineAsm
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use miden_hir::{
+        AbiParam, Function, FunctionBuilder, InstBuilder, Signature, SourceSpan, Type,
+    };
+    use miden_hir_analysis::FunctionAnalysis;
+    use pretty_assertions::assert_eq;
+
+    use crate::{GlobalValueNumbering, RewritePass};
+
+    /// Redundant computations within a block are replaced, with commutative operands
+    /// considered equal in either order, while differing overflow modes or operand
+    /// order of non-commutative operators are considered distinct.
+    #[test]
+    fn gvn_local_redundancy() {
+        let id = "test::gvn".parse().unwrap();
+        let mut function = Function::new(
+            id,
+            Signature::new(
+                [AbiParam::new(Type::U32), AbiParam::new(Type::U32)],
+                [AbiParam::new(Type::U32)],
+            ),
+        );
+
+        {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let entry = builder.current_block();
+            let (v0, v1) = {
+                let args = builder.block_params(entry);
+                (args[0], args[1])
+            };
+
+            let v2 = builder.ins().add(v0, v1, SourceSpan::UNKNOWN);
+            let v3 = builder.ins().add(v1, v0, SourceSpan::UNKNOWN);
+            let v4 = builder.ins().mul(v2, v3, SourceSpan::UNKNOWN);
+            builder.ins().add_wrapping(v0, v1, SourceSpan::UNKNOWN);
+            builder.ins().sub(v0, v1, SourceSpan::UNKNOWN);
+            builder.ins().sub(v1, v0, SourceSpan::UNKNOWN);
+            builder.ins().ret(Some(v4), SourceSpan::UNKNOWN);
+        }
+
+        let mut analysis = FunctionAnalysis::new(&function);
+        let mut pass = GlobalValueNumbering;
+        pass.run(&mut function, &mut analysis)
+            .expect("global value numbering failed");
+
+        let expected = "pub fn gvn(u32, u32) -> u32 {
+block0(v0: u32, v1: u32):
+    v2 = add v0, v1  : u32
+    v4 = mul v2, v2  : u32
+    v5 = add.wrapping v0, v1  : u32
+    v6 = sub v0, v1  : u32
+    v7 = sub v1, v0  : u32
+    ret v4
+}
+";
+
+        assert_eq!(function.to_string().as_str(), expected);
+    }
+
+    /// Computations are only reused where the original definition dominates the
+    /// redundant one, i.e. never between sibling blocks.
+    #[test]
+    fn gvn_respects_dominance() {
+        let id = "test::gvn_dom".parse().unwrap();
+        let mut function = Function::new(
+            id,
+            Signature::new(
+                [AbiParam::new(Type::U32), AbiParam::new(Type::U32)],
+                [AbiParam::new(Type::U32)],
+            ),
+        );
+
+        {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let entry = builder.current_block();
+            let (v0, v1) = {
+                let args = builder.block_params(entry);
+                (args[0], args[1])
+            };
+            let a = builder.create_block();
+            let b = builder.create_block();
+
+            // entry
+            builder.ins().add(v0, v1, SourceSpan::UNKNOWN);
+            let v3 = builder.ins().eq(v0, v1, SourceSpan::UNKNOWN);
+            builder
+                .ins()
+                .cond_br(v3, a, &[], b, &[], SourceSpan::UNKNOWN);
+
+            // block1
+            builder.switch_to_block(a);
+            let v4 = builder.ins().add(v0, v1, SourceSpan::UNKNOWN);
+            let v5 = builder.ins().mul(v0, v1, SourceSpan::UNKNOWN);
+            let v6 = builder.ins().add(v4, v5, SourceSpan::UNKNOWN);
+            builder.ins().ret(Some(v6), SourceSpan::UNKNOWN);
+
+            // block2
+            builder.switch_to_block(b);
+            let v7 = builder.ins().mul(v0, v1, SourceSpan::UNKNOWN);
+            builder.ins().ret(Some(v7), SourceSpan::UNKNOWN);
+        }
+
+        let mut analysis = FunctionAnalysis::new(&function);
+        let mut pass = GlobalValueNumbering;
+        pass.run(&mut function, &mut analysis)
+            .expect("global value numbering failed");
+
+        let expected = "pub fn gvn_dom(u32, u32) -> u32 {
+block0(v0: u32, v1: u32):
+    v2 = add v0, v1  : u32
+    v3 = eq v0, v1  : i1
+    condbr v3, block1, block2
+
+block1:
+    v5 = mul v0, v1  : u32
+    v6 = add v2, v5  : u32
+    ret v6
+
+block2:
+    v7 = mul v0, v1  : u32
+    ret v7
+}
+";
+
+        assert_eq!(function.to_string().as_str(), expected);
+    }
+
+    /// Redundant loads are replaced, but not across an instruction which may write memory
+    #[test]
+    fn gvn_loads() {
+        let id = "test::gvn_load".parse().unwrap();
+        let ptr_ty = Type::Ptr(Box::new(Type::U32));
+        let mut function = Function::new(
+            id,
+            Signature::new([AbiParam::new(ptr_ty)], [AbiParam::new(Type::U32)]),
+        );
+
+        {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let entry = builder.current_block();
+            let v0 = builder.block_params(entry)[0];
+
+            let v1 = builder.ins().load(v0, SourceSpan::UNKNOWN);
+            let v2 = builder.ins().load(v0, SourceSpan::UNKNOWN);
+            builder.ins().store(v0, v2, SourceSpan::UNKNOWN);
+            let v3 = builder.ins().load(v0, SourceSpan::UNKNOWN);
+            let v4 = builder.ins().add(v1, v3, SourceSpan::UNKNOWN);
+            builder.ins().ret(Some(v4), SourceSpan::UNKNOWN);
+        }
+
+        let mut analysis = FunctionAnalysis::new(&function);
+        let mut pass = GlobalValueNumbering;
+        pass.run(&mut function, &mut analysis)
+            .expect("global value numbering failed");
+
+        let expected = "pub fn gvn_load(*mut u32) -> u32 {
+block0(v0: *mut u32):
+    v1 = load v0  : u32
+    store v0, v1
+    v3 = load v0  : u32
+    v4 = add v1, v3  : u32
+    ret v4
+}
+";
+
+        assert_eq!(function.to_string().as_str(), expected);
+    }
+}
@@ -0,0 +1,889 @@
+use rustc_hash::{FxHashMap, FxHashSet};
+use smallvec::SmallVec;
+
+use miden_diagnostics::Span;
+use miden_hir::{self as hir, *};
+use miden_hir_analysis::{ControlFlowGraph, DominatorTree, LoopAnalysis};
+use miden_hir_pass::Pass;
+
+use crate::{adt::ScopedMap, inline_blocks::rewrite_use, sccp::imm_opcode};
+
+/// This pass operates on a linked [hir::Program], and inlines calls to functions
+/// defined in the program into their callers.
+///
+/// The linker rejects programs whose call graph contains cycles, so the call graph
+/// is visited bottom-up, i.e. a function is only inlined into its callers once all
+/// of the calls in its own body which are going to be inlined, have been.
+///
+/// Inlining a call is done as follows:
+///
+/// * The blocks of the callee are cloned into the caller, with all of the values, blocks,
+/// and global values they refer to remapped to their copies in the caller.
+/// * The block containing the call is split after the call, and the instructions which
+/// follow it are moved into a new continuation block, whose parameters take the place
+/// of the results of the call.
+/// * The call is replaced with a branch to the copy of the callee's entry block, passing
+/// the arguments of the call.
+/// * Every return in the copy of the callee is replaced with a branch to the continuation
+/// block, passing the returned values.
+///
+/// The source spans of the callee's instructions and block parameters are preserved.
+///
+/// Whether a call is inlined is determined by the [Inline] hint of the callee:
+///
+/// * [Inline::Always] - calls are always inlined, if it is legal to do so
+/// * [Inline::Never] - calls are never inlined
+/// * [Inline::Auto] - calls are inlined if the size of the callee, in instructions, is
+/// at or below [Inliner::threshold], as the fixed cost of an `exec` will be a significant
+/// fraction of the cost of such a call. A call in a loop is executed on every iteration, so
+/// the threshold is doubled for each level of loop nesting the call is in, up to
+/// [Inliner::MAX_LOOP_LEVEL] levels. Private functions with a single call site are also
+/// inlined regardless of their size.
+///
+/// Once all of the calls to a private function have been inlined, and it is no longer
+/// referenced, it is removed from the program. So inlining a private function with a single
+/// call site does not increase the overall size of the program.
+///
+/// It is never legal to inline a kernel function, or a function from another module which
+/// calls private functions of its own module. Only calls using `call` are considered, as
+/// a `syscall` must execute in the root context.
+pub struct Inliner {
+    /// The maximum size of a callee, in instructions, which will be inlined by default
+    pub threshold: usize,
+}
+impl Default for Inliner {
+    fn default() -> Self {
+        Self {
+            threshold: Self::DEFAULT_THRESHOLD,
+        }
+    }
+}
+impl Pass for Inliner {
+    type Input<'a> = &'a mut hir::Program;
+    type Output<'a> = &'a mut hir::Program;
+    type Error = anyhow::Error;
+
+    fn run<'a>(&mut self, program: Self::Input<'a>) -> Result<Self::Output<'a>, Self::Error> {
+        // Visit callees before their callers
+        let mut callgraph = CallGraph::with_program(program);
+        let mut functions = callgraph.toposort()?;
+        functions.reverse();
+
+        // Remove the modules from the program, so that we can modify the functions
+        // in one module while reading functions from another.
+        let mut tree = program.modules_mut().take();
+        let mut modules = FxHashMap::<Ident, Box<Module>>::default();
+        while let Some(module) = tree.front_mut().remove() {
+            modules.insert(module.name, module);
+        }
+
+        let result = self.inline_all(&mut modules, &mut callgraph, &functions);
+
+        for module in modules.into_values() {
+            program.modules_mut().insert(module);
+        }
+
+        result.map(|_| program)
+    }
+}
+impl Inliner {
+    /// The default value of [Inliner::threshold]
+    pub const DEFAULT_THRESHOLD: usize = 16;
+
+    /// The number of levels of loop nesting for which the threshold is doubled
+    pub const MAX_LOOP_LEVEL: usize = 2;
+
+    fn inline_all(
+        &self,
+        modules: &mut FxHashMap<Ident, Box<Module>>,
+        callgraph: &mut CallGraph,
+        functions: &[FunctionIdent],
+    ) -> anyhow::Result<()> {
+        // Count the call sites of each function defined in this program
+        let mut uses = FxHashMap::<FunctionIdent, usize>::default();
+        for id in functions.iter() {
+            let Some(function) = get_function(modules, id) else {
+                continue;
+            };
+            for (_, callee) in call_sites(function) {
+                if get_function(modules, &callee).is_some() {
+                    *uses.entry(callee).or_default() += 1;
+                }
+            }
+        }
+
+        let mut inlined = FxHashSet::<FunctionIdent>::default();
+        for id in functions.iter() {
+            if get_function(modules, id).is_some() {
+                self.inline_calls(modules, callgraph, *id, &mut uses, &mut inlined)?;
+            }
+        }
+
+        // Remove the private functions which are no longer referenced now that their calls
+        // have been inlined
+        for id in inlined.into_iter() {
+            let function = get_function(modules, &id).unwrap();
+            let is_dead = uses.get(&id) == Some(&0) && callgraph.callers(id).next().is_none();
+            if is_dead && !function.is_public() && callgraph.entrypoint() != Some(id) {
+                let module = modules.get_mut(&id.module).unwrap();
+                module.cursor_mut_at(id.function).remove();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Inline all of the calls in the function `id` which should be inlined
+    ///
+    /// The number of call sites of each callee in `uses`, and the calls made by `id` in
+    /// `callgraph`, are updated to reflect the inlined calls, and each inlined callee is
+    /// recorded in `inlined`.
+    fn inline_calls(
+        &self,
+        modules: &mut FxHashMap<Ident, Box<Module>>,
+        callgraph: &mut CallGraph,
+        id: FunctionIdent,
+        uses: &mut FxHashMap<FunctionIdent, usize>,
+        inlined: &mut FxHashSet<FunctionIdent>,
+    ) -> anyhow::Result<()> {
+        let caller = get_function(modules, &id).unwrap();
+        let cfg = ControlFlowGraph::with_function(caller);
+        let domtree = DominatorTree::with_function(caller, &cfg);
+        let loops = LoopAnalysis::with_function(caller, &cfg, &domtree);
+        let calls = call_sites(caller)
+            .into_iter()
+            .filter(|(call, callee)| {
+                let block = caller.dfg.inst_block(*call).unwrap();
+                let level = loops.loop_level(block).level();
+                self.should_inline(modules, caller, callee, level, uses)
+            })
+            .collect::<Vec<_>>();
+        if calls.is_empty() {
+            return Ok(());
+        }
+
+        // Detach the caller from its module, so that it can be modified
+        let (mut caller, next) = {
+            let module = modules.get_mut(&id.module).unwrap();
+            let mut cursor = module.cursor_mut_at(id.function);
+            let caller = cursor.remove().unwrap();
+            (caller, cursor.get().map(|f| f.id.function))
+        };
+
+        let mut rewrites = ScopedMap::<Value, Value>::default();
+        for (call, callee) in calls.into_iter() {
+            let callee = get_function(modules, &callee).unwrap();
+            inline_call(&mut caller, call, callee, &mut rewrites)?;
+
+            // The calls made by the callee are now also made by the caller
+            *uses.get_mut(&callee.id).unwrap() -= 1;
+            for (_, c) in call_sites(callee) {
+                if let Some(count) = uses.get_mut(&c) {
+                    *count += 1;
+                }
+            }
+            inlined.insert(callee.id);
+        }
+
+        // Replace uses of the results of the inlined calls
+        let dfg = &mut caller.dfg;
+        let blocks = dfg.blocks().map(|(b, _)| b).collect::<Vec<_>>();
+        for block in blocks.into_iter() {
+            let insts = dfg.block_insts(block).collect::<SmallVec<[Inst; 16]>>();
+            for inst in insts {
+                rewrite_use(dfg.insts[inst].as_mut(), &mut dfg.value_lists, &rewrites);
+            }
+        }
+        callgraph.function_changed(&caller);
+
+        // Re-attach the caller to its module, in its original position
+        let module = modules.get_mut(&id.module).unwrap();
+        match next {
+            Some(next) => module.insert_before(caller, next)?,
+            None => module.push(caller)?,
+        }
+
+        Ok(())
+    }
+
+    /// Returns true if `callee` should be inlined into `caller`, at a call site which is
+    /// nested in `level` loops
+    fn should_inline(
+        &self,
+        modules: &FxHashMap<Ident, Box<Module>>,
+        caller: &Function,
+        callee: &FunctionIdent,
+        level: usize,
+        uses: &FxHashMap<FunctionIdent, usize>,
+    ) -> bool {
+        let Some(callee) = get_function(modules, callee) else {
+            return false;
+        };
+        if callee.id == caller.id || !can_inline(modules, caller, callee) {
+            return false;
+        }
+        match callee.inline() {
+            Inline::Always => true,
+            Inline::Never => false,
+            Inline::Auto => {
+                let is_only_use = !callee.is_public() && uses.get(&callee.id) == Some(&1);
+                let threshold = self.threshold << level.min(Self::MAX_LOOP_LEVEL);
+                is_only_use || size(callee) <= threshold
+            }
+        }
+    }
+}
+
+/// Returns true if it is legal to inline `callee` into `caller`
+fn can_inline(
+    modules: &FxHashMap<Ident, Box<Module>>,
+    caller: &Function,
+    callee: &Function,
+) -> bool {
+    // Kernel functions must be invoked via syscall
+    if callee.is_kernel() {
+        return false;
+    }
+
+    let is_same_module = caller.id.module == callee.id.module;
+    for (block, _) in callee.dfg.blocks() {
+        for inst in callee.dfg.block_insts(block) {
+            match &callee.dfg[inst] {
+                // We must be able to materialize returned immediates
+                Instruction::RetImm(RetImm { arg, .. }) if imm_opcode(*arg).is_none() => {
+                    return false;
+                }
+                // Private functions cannot be called from another module
+                Instruction::Call(Call { callee: id, .. })
+                    if !is_same_module && id.module == callee.id.module =>
+                {
+                    let is_public = get_function(modules, id)
+                        .map(|f| f.is_public())
+                        .unwrap_or(true);
+                    if !is_public {
+                        return false;
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    true
+}
+
+/// Inline the body of `callee` in place of `call`, which must be a call to `callee`
+///
+/// The results of `call` are mapped to their replacements in `rewrites`, it is up to
+/// the caller to rewrite the uses of those values once all calls have been inlined.
+fn inline_call(
+    caller: &mut Function,
+    call: Inst,
+    callee: &Function,
+    rewrites: &mut ScopedMap<Value, Value>,
+) -> anyhow::Result<()> {
+    let dfg = &mut caller.dfg;
+    let block = dfg
+        .inst_block(call)
+        .expect("expected call to be attached to a block");
+    let span = dfg.inst_span(call);
+    let args = SmallVec::<[Value; 4]>::from_slice(dfg.inst_args(call));
+    let results = SmallVec::<[Value; 1]>::from_slice(dfg.inst_results(call));
+
+    // The functions called by the callee must be visible to the caller
+    for import in callee.imports() {
+        dfg.import_function(
+            import.id.module,
+            import.id.function,
+            import.signature.clone(),
+        )?;
+    }
+
+    // Allocate copies of all of the blocks, values, and instructions of the callee
+    // up front, as instructions may refer to blocks and values defined later on
+    let mut mapping = Mapping::default();
+    let mut insts = FxHashMap::<Inst, Inst>::default();
+    let mut prev = block;
+    for (b, _) in callee.dfg.blocks() {
+        let new_block = dfg.create_block_after(prev);
+        mapping.blocks.insert(b, new_block);
+        prev = new_block;
+
+        for param in callee.dfg.block_params(b).iter().copied() {
+            let ty = callee.dfg.value_type(param).clone();
+            let span = callee.dfg.value_span(param);
+            let new_param = dfg.append_block_param(new_block, ty, span);
+            mapping.values.insert(param, new_param);
+        }
+
+        for inst in callee.dfg.block_insts(b) {
+            let key = dfg.insts.alloc_key();
+            insts.insert(inst, key);
+            for (num, result) in callee.dfg.inst_results(inst).iter().copied().enumerate() {
+                let ty = callee.dfg.value_type(result).clone();
+                let new_result = dfg.make_value(ValueData::Inst {
+                    ty,
+                    num: num as u16,
+                    inst: key,
+                });
+                dfg.results[key].push(new_result, &mut dfg.value_lists);
+                mapping.values.insert(result, new_result);
+            }
+        }
+    }
+
+    // The continuation block receives the results of the call
+    let cont = dfg.create_block_after(prev);
+    for result in results.iter().copied() {
+        let ty = dfg.value_type(result).clone();
+        let param = dfg.append_block_param(cont, ty, span);
+        rewrites.insert(result, param);
+    }
+
+    // Populate the copies of the callee's blocks
+    for (b, _) in callee.dfg.blocks() {
+        let new_block = mapping.blocks[&b];
+        for inst in callee.dfg.block_insts(b) {
+            let key = insts[&inst];
+            let span = callee.dfg.inst_span(inst);
+            let data = match &callee.dfg[inst] {
+                Instruction::Ret(Ret { args, .. }) => Instruction::Br(Br {
+                    op: Opcode::Br,
+                    destination: cont,
+                    args: mapping.list(args, &callee.dfg.value_lists, &mut dfg.value_lists),
+                }),
+                Instruction::RetImm(RetImm { arg, .. }) => {
+                    let imm = dfg.insert_inst(
+                        InsertionPoint::after(ProgramPoint::Block(new_block)),
+                        Instruction::UnaryOpImm(UnaryOpImm {
+                            op: imm_opcode(*arg).unwrap(),
+                            overflow: Overflow::default(),
+                            imm: *arg,
+                        }),
+                        arg.ty(),
+                        span,
+                    );
+                    let value = dfg.first_result(imm);
+                    Instruction::Br(Br {
+                        op: Opcode::Br,
+                        destination: cont,
+                        args: ValueList::from_slice(&[value], &mut dfg.value_lists),
+                    })
+                }
+                ix => mapping.clone_inst(ix, &callee.dfg, dfg),
+            };
+            dfg.insts
+                .append(key, InstNode::new(key, new_block, Span::new(span, data)));
+            let node = unsafe { UnsafeRef::from_raw(&dfg.insts[key]) };
+            dfg.blocks[new_block].append(node);
+        }
+    }
+
+    // Split the block containing the call, moving everything after the call
+    // to the continuation block, and dropping the call itself
+    let mut moved = SmallVec::<[UnsafeRef<InstNode>; 8]>::default();
+    {
+        let block = &mut dfg.blocks[block];
+        let mut insts = block.insts.take();
+        let mut found = false;
+        while let Some(inst) = insts.pop_front() {
+            if found {
+                moved.push(inst);
+            } else if inst.key == call {
+                found = true;
+            } else {
+                block.insts.push_back(inst);
+            }
+        }
+    }
+    for inst in moved.into_iter() {
+        let key = inst.key;
+        dfg.blocks[cont].append(inst);
+        dfg.insts[key].block = cont;
+    }
+
+    // Branch to the inlined entry block in place of the call
+    let entry = mapping.blocks[&callee.dfg.entry_block()];
+    let mut vlist = ValueList::default();
+    vlist.extend(args.iter().copied(), &mut dfg.value_lists);
+    dfg.append_inst(
+        block,
+        Instruction::Br(Br {
+            op: Opcode::Br,
+            destination: entry,
+            args: vlist,
+        }),
+        Type::Unit,
+        span,
+    );
+
+    Ok(())
+}
+
+/// The mapping from entities of an inlined function to their copies in the caller
+#[derive(Default)]
+struct Mapping {
+    values: FxHashMap<Value, Value>,
+    blocks: FxHashMap<Block, Block>,
+    globals: FxHashMap<GlobalValue, GlobalValue>,
+}
+impl Mapping {
+    fn value(&self, value: Value) -> Value {
+        self.values[&value]
+    }
+
+    fn block(&self, block: Block) -> Block {
+        self.blocks[&block]
+    }
+
+    /// Copy `list` from the value list pool of the callee to that of the caller
+    fn list(&self, list: &ValueList, from: &ValueListPool, to: &mut ValueListPool) -> ValueList {
+        let mut vlist = ValueList::default();
+        vlist.extend(list.as_slice(from).iter().map(|v| self.value(*v)), to);
+        vlist
+    }
+
+    /// Copy the global value `gv`, and any global values it is derived from, to `dfg`
+    fn global(
+        &mut self,
+        gv: GlobalValue,
+        callee: &DataFlowGraph,
+        dfg: &mut DataFlowGraph,
+    ) -> GlobalValue {
+        if let Some(mapped) = self.globals.get(&gv).copied() {
+            return mapped;
+        }
+        let data = match callee.global_value(gv).clone() {
+            GlobalValueData::Load { base, offset, ty } => GlobalValueData::Load {
+                base: self.global(base, callee, dfg),
+                offset,
+                ty,
+            },
+            GlobalValueData::IAddImm { base, offset, ty } => GlobalValueData::IAddImm {
+                base: self.global(base, callee, dfg),
+                offset,
+                ty,
+            },
+            data => data,
+        };
+        let mapped = dfg.create_global_value(data);
+        self.globals.insert(gv, mapped);
+        mapped
+    }
+
+    /// Copy the instruction data `ix` from `callee`, remapping all of its operands
+    fn clone_inst(
+        &mut self,
+        ix: &Instruction,
+        callee: &DataFlowGraph,
+        dfg: &mut DataFlowGraph,
+    ) -> Instruction {
+        let from = &callee.value_lists;
+        match ix {
+            Instruction::GlobalValue(GlobalValueOp { op, global }) => {
+                Instruction::GlobalValue(GlobalValueOp {
+                    op: *op,
+                    global: self.global(*global, callee, dfg),
+                })
+            }
+            Instruction::BinaryOp(op) => Instruction::BinaryOp(BinaryOp {
+                args: [self.value(op.args[0]), self.value(op.args[1])],
+                ..op.clone()
+            }),
+            Instruction::BinaryOpImm(op) => Instruction::BinaryOpImm(BinaryOpImm {
+                arg: self.value(op.arg),
+                ..op.clone()
+            }),
+            Instruction::UnaryOp(op) => Instruction::UnaryOp(UnaryOp {
+                arg: self.value(op.arg),
+                ..op.clone()
+            }),
+            Instruction::UnaryOpImm(op) => Instruction::UnaryOpImm(op.clone()),
+            Instruction::Call(op) => Instruction::Call(Call {
+                args: self.list(&op.args, from, &mut dfg.value_lists),
+                ..op.clone()
+            }),
+            Instruction::Br(op) => Instruction::Br(Br {
+                op: op.op,
+                destination: self.block(op.destination),
+                args: self.list(&op.args, from, &mut dfg.value_lists),
+            }),
+            Instruction::CondBr(op) => Instruction::CondBr(CondBr {
+                op: op.op,
+                cond: self.value(op.cond),
+                then_dest: (
+                    self.block(op.then_dest.0),
+                    self.list(&op.then_dest.1, from, &mut dfg.value_lists),
+                ),
+                else_dest: (
+                    self.block(op.else_dest.0),
+                    self.list(&op.else_dest.1, from, &mut dfg.value_lists),
+                ),
+            }),
+            Instruction::Switch(op) => Instruction::Switch(Switch {
+                op: op.op,
+                arg: self.value(op.arg),
+                arms: op
+                    .arms
+                    .iter()
+                    .map(|(value, dest, args)| {
+                        (
+                            *value,
+                            self.block(*dest),
+                            self.list(args, from, &mut dfg.value_lists),
+                        )
+                    })
+                    .collect(),
+                default: (
+                    self.block(op.default.0),
+                    self.list(&op.default.1, from, &mut dfg.value_lists),
+                ),
+            }),
+            Instruction::Load(op) => Instruction::Load(LoadOp {
+                addr: self.value(op.addr),
+                ..op.clone()
+            }),
+            Instruction::PrimOp(op) => Instruction::PrimOp(PrimOp {
+                op: op.op,
+                args: self.list(&op.args, from, &mut dfg.value_lists),
+            }),
+            Instruction::PrimOpImm(op) => Instruction::PrimOpImm(PrimOpImm {
+                args: self.list(&op.args, from, &mut dfg.value_lists),
+                ..op.clone()
+            }),
+            Instruction::Test(op) => Instruction::Test(Test {
+                arg: self.value(op.arg),
+                ..op.clone()
+            }),
+            Instruction::InlineAsm(op) => Instruction::InlineAsm(InlineAsm {
+                args: self.list(&op.args, from, &mut dfg.value_lists),
+                ..op.clone()
+            }),
+            Instruction::Ret(_) | Instruction::RetImm(_) => {
+                unreachable!("returns must be rewritten by the inliner")
+            }
+        }
+    }
+}
+
+fn get_function<'a>(
+    modules: &'a FxHashMap<Ident, Box<Module>>,
+    id: &FunctionIdent,
+) -> Option<&'a Function> {
+    modules
+        .get(&id.module)
+        .and_then(|module| module.function(id.function))
+}
+
+/// Returns the calls in `function` which are candidates for inlining, with their callee
+fn call_sites(function: &Function) -> Vec<(Inst, FunctionIdent)> {
+    let mut calls = vec![];
+    for (block, _) in function.dfg.blocks() {
+        for inst in function.dfg.block_insts(block) {
+            if let Instruction::Call(Call {
+                op: Opcode::Call,
+                callee,
+                ..
+            }) = &function.dfg[inst]
+            {
+                calls.push((inst, *callee));
+            }
+        }
+    }
+    calls
+}
+
+/// Returns the size of `function` in instructions
+fn size(function: &Function) -> usize {
+    function
+        .dfg
+        .blocks()
+        .map(|(block, _)| function.dfg.block_insts(block).count())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use miden_hir::{testing::TestContext, Module, Parser, Program, ProgramBuilder};
+    use miden_hir_pass::Pass;
+    use pretty_assertions::assert_eq;
+
+    use crate::Inliner;
+
+    fn parse(context: &TestContext, source: &str) -> Box<Module> {
+        let parser = Parser::new(&context.diagnostics, context.codemap.clone());
+        parser
+            .parse_str(source)
+            .expect("unexpected parse error, see diagnostics output")
+    }
+
+    fn link(context: &TestContext, module: Box<Module>) -> Box<Program> {
+        ProgramBuilder::new(&context.diagnostics)
+            .with_module(module)
+            .expect("unexpected module conflict")
+            .link()
+            .expect("failed to link program")
+    }
+
+    fn print_function(program: &Program, name: &str) -> String {
+        let module = program.modules().iter().next().unwrap();
+        module
+            .function(name.into())
+            .expect("undefined function")
+            .to_string()
+    }
+
+    /// A small function is inlined, with the code following the call moved to a
+    /// continuation block which receives the returned value.
+    #[test]
+    fn inliner_inlines_small_functions() {
+        let context = TestContext::default();
+        let module = parse(
+            &context,
+            "module test
+
+fn add3(u32, u32) -> u32 {
+block0(v0: u32, v1: u32):
+    v2 = add v0, v1  : u32
+    v3 = add v2, 3  : u32
+    ret v3
+}
+
+pub fn main(u32) -> u32 {
+block0(v0: u32):
+    v1 = mul v0, 2  : u32
+    v2 = call test::add3(v0, v1)  : u32
+    v3 = mul v2, v2  : u32
+    ret v3
+}
+",
+        );
+        let mut program = link(&context, module);
+
+        let mut pass = Inliner::default();
+        pass.run(&mut program).expect("inlining failed");
+
+        let expected = "pub fn main(u32) -> u32 {
+block0(v0: u32):
+    v1 = mul v0, 2  : u32
+    br block1(v0, v1)
+
+block1(v4: u32, v5: u32):
+    v6 = add v4, v5  : u32
+    v7 = add v6, 3  : u32
+    br block2(v7)
+
+block2(v8: u32):
+    v3 = mul v8, v8  : u32
+    ret v3
+}
+";
+
+        assert_eq!(print_function(&program, "main").as_str(), expected);
+    }
+
+    /// The inline hint of a callee takes precedence over the size threshold
+    #[test]
+    fn inliner_honors_inline_hints() {
+        let context = TestContext::default();
+        let module = parse(
+            &context,
+            "module test
+
+pub inline(never) fn inc(u32) -> u32 {
+block0(v0: u32):
+    v1 = add v0, 1  : u32
+    ret v1
+}
+
+pub inline(always) fn double(u32) -> u32 {
+block0(v0: u32):
+    v1 = mul v0, 2  : u32
+    ret v1
+}
+
+pub fn main(u32) -> u32 {
+block0(v0: u32):
+    v1 = call test::inc(v0)  : u32
+    v2 = call test::double(v1)  : u32
+    ret v2
+}
+",
+        );
+        let mut program = link(&context, module);
+
+        let mut pass = Inliner { threshold: 0 };
+        pass.run(&mut program).expect("inlining failed");
+
+        let expected = "pub fn main(u32) -> u32 {
+block0(v0: u32):
+    v1 = call test::inc(v0)  : u32
+    br block1(v1)
+
+block1(v3: u32):
+    v4 = mul v3, 2  : u32
+    br block2(v4)
+
+block2(v5: u32):
+    ret v5
+}
+";
+
+        assert_eq!(print_function(&program, "main").as_str(), expected);
+    }
+
+    /// Every return in the callee, including returns of an immediate, becomes a
+    /// branch to the continuation block
+    #[test]
+    fn inliner_rewrites_returns() {
+        let context = TestContext::default();
+        let module = parse(
+            &context,
+            "module test
+
+fn clamp(u32) -> u32 {
+block0(v0: u32):
+    v1 = gt v0, 255  : i1
+    condbr v1, block1, block2
+
+block1:
+    ret 255
+
+block2:
+    ret v0
+}
+
+pub fn main(u32) -> u32 {
+block0(v0: u32):
+    v1 = call test::clamp(v0)  : u32
+    v2 = add v1, 1  : u32
+    ret v2
+}
+",
+        );
+        let mut program = link(&context, module);
+
+        let mut pass = Inliner::default();
+        pass.run(&mut program).expect("inlining failed");
+
+        let expected = "pub fn main(u32) -> u32 {
+block0(v0: u32):
+    br block1(v0)
+
+block1(v3: u32):
+    v4 = gt v3, 255  : i1
+    condbr v4, block2, block3
+
+block2:
+    v6 = const.u32 255  : u32
+    br block4(v6)
+
+block3:
+    br block4(v3)
+
+block4(v5: u32):
+    v2 = add v5, 1  : u32
+    ret v2
+}
+";
+
+        assert_eq!(print_function(&program, "main").as_str(), expected);
+    }
+
+    /// A private function whose only call site is inlined is removed from the program,
+    /// regardless of its size, but functions which are still called, or which are public,
+    /// are kept
+    #[test]
+    fn inliner_removes_inlined_private_functions() {
+        let context = TestContext::default();
+        let module = parse(
+            &context,
+            "module test
+
+fn once(u32) -> u32 {
+block0(v0: u32):
+    v1 = add v0, 1  : u32
+    ret v1
+}
+
+fn twice(u32) -> u32 {
+block0(v0: u32):
+    v1 = mul v0, 2  : u32
+    ret v1
+}
+
+pub fn exported(u32) -> u32 {
+block0(v0: u32):
+    v1 = sub v0, 1  : u32
+    ret v1
+}
+
+pub fn main(u32) -> u32 {
+block0(v0: u32):
+    v1 = call test::once(v0)  : u32
+    v2 = call test::twice(v1)  : u32
+    v3 = call test::twice(v2)  : u32
+    v4 = call test::exported(v3)  : u32
+    ret v4
+}
+",
+        );
+        let mut program = link(&context, module);
+
+        let mut pass = Inliner { threshold: 0 };
+        pass.run(&mut program).expect("inlining failed");
+
+        let module = program.modules().iter().next().unwrap();
+        assert!(module.function("once".into()).is_none());
+        assert!(module.function("twice".into()).is_some());
+        assert!(module.function("exported".into()).is_some());
+
+        let main = print_function(&program, "main");
+        assert!(!main.contains("call test::once"));
+        assert_eq!(main.matches("call test::twice").count(), 2);
+        assert_eq!(main.matches("call test::exported").count(), 1);
+    }
+
+    /// The size threshold is larger for calls in a loop, as they are executed on every
+    /// iteration of the loop
+    #[test]
+    fn inliner_favors_calls_in_loops() {
+        let context = TestContext::default();
+        let module = parse(
+            &context,
+            "module test
+
+pub fn add2(u32) -> u32 {
+block0(v0: u32):
+    v1 = add v0, 1  : u32
+    v2 = add v1, 1  : u32
+    ret v2
+}
+
+pub fn main(u32) -> u32 {
+block0(v0: u32):
+    v1 = call test::add2(v0)  : u32
+    br block1(v1)
+
+block1(v2: u32):
+    v3 = call test::add2(v2)  : u32
+    v4 = lt v3, 10  : i1
+    condbr v4, block1(v3), block2
+
+block2:
+    ret v3
+}
+",
+        );
+        let mut program = link(&context, module);
+
+        // `add2` is 3 instructions, which is over the threshold outside of the loop,
+        // but within the doubled threshold inside it
+        let mut pass = Inliner { threshold: 2 };
+        pass.run(&mut program).expect("inlining failed");
+
+        let main = print_function(&program, "main");
+        assert_eq!(main.matches("call test::add2").count(), 1);
+        assert!(main.contains("v1 = call test::add2(v0)"));
+    }
+}
@@ -4,7 +4,7 @@ use rustc_hash::FxHashSet;
 use smallvec::SmallVec;
 
 use miden_hir::{self as hir, *};
-use miden_hir_analysis::{ControlFlowGraph, FunctionAnalysis};
+use miden_hir_analysis::{ControlFlowGraph, FunctionAnalysis, PreservedAnalyses};
 
 use crate::{adt::ScopedMap, RewritePass};
 
@@ -29,11 +29,15 @@ pub struct InlineBlocks;
 impl RewritePass for InlineBlocks {
     type Error = anyhow::Error;
 
+    fn name(&self) -> &'static str {
+        "inline-blocks"
+    }
+
     fn run(
         &mut self,
         function: &mut hir::Function,
         analysis: &mut FunctionAnalysis,
-    ) -> Result<(), Self::Error> {
+    ) -> Result<PreservedAnalyses, Self::Error> {
         let cfg = analysis.cfg_mut();
 
         let entry = function.dfg.entry_block();
@@ -111,10 +115,10 @@ impl RewritePass for InlineBlocks {
         rewrite_uses(entry, function, &rewrites);
 
         if changed {
-            analysis.cfg_changed(function);
+            Ok(PreservedAnalyses::CALLGRAPH)
+        } else {
+            Ok(PreservedAnalyses::all())
         }
-
-        Ok(())
     }
 }
 
@@ -208,7 +212,8 @@ fn rewrite_uses(root: Block, function: &mut hir::Function, rewrites: &ScopedMap<
     }
 }
 
-fn rewrite_use(
+/// Rewrite the arguments of `inst` using `rewrites`, returning the successors of `inst`, if any
+pub(crate) fn rewrite_use(
     inst: &mut Instruction,
     pool: &mut hir::ValueListPool,
     rewrites: &ScopedMap<Value, Value>,
@@ -249,6 +254,30 @@ fn rewrite_use(
                 }
             }
         }
+        Instruction::Switch(Switch {
+            ref mut arg,
+            ref mut arms,
+            default: (default, ref mut default_args),
+            ..
+        }) => {
+            if let Some(replacement) = rewrites.get(arg).copied() {
+                *arg = replacement;
+            }
+            for (_, dest, args) in arms.iter_mut() {
+                worklist.push(*dest);
+                for arg in args.as_mut_slice(pool) {
+                    if let Some(replacement) = rewrites.get(arg).copied() {
+                        *arg = replacement;
+                    }
+                }
+            }
+            worklist.push(*default);
+            for arg in default_args.as_mut_slice(pool) {
+                if let Some(replacement) = rewrites.get(arg).copied() {
+                    *arg = replacement;
+                }
+            }
+        }
         op => {
             for arg in op.arguments_mut(pool) {
                 if let Some(replacement) = rewrites.get(arg).copied() {
@@ -0,0 +1,178 @@
+use std::fmt;
+use std::time::Duration;
+
+use miden_diagnostics::{DiagnosticsHandler, Severity};
+use miden_hir::{self as hir, Block, FunctionIdent, Inst};
+use rustc_hash::FxHashSet;
+
+/// This structure determines when the IR of a function is printed by the [crate::PassManager],
+/// or by any other pipeline which supports it, e.g. the code generator.
+///
+/// Passes are identified by name, see [crate::RewritePass::name]. The IR is emitted as a
+/// note to a [DiagnosticsHandler], rather than written to stdout, so that it is reported in
+/// the same way as everything else the compiler has to say.
+#[derive(Debug, Default, Clone)]
+pub struct PrintOptions {
+    /// Print the IR before each of the passes named here
+    pub before: Vec<String>,
+    /// Print the IR after each of the passes named here
+    pub after: Vec<String>,
+    /// Print the IR after every pass
+    pub after_all: bool,
+    /// Only print the IR of the functions named here, either by their fully-qualified
+    /// name, e.g. `foo::bar`, or by their name alone. If empty, all functions are printed.
+    pub functions: Vec<String>,
+}
+impl PrintOptions {
+    /// Returns true if the IR of `function` should be printed before running `pass`
+    pub fn print_before(&self, pass: &str, function: &FunctionIdent) -> bool {
+        self.before.iter().any(|name| name == pass) && self.matches(function)
+    }
+
+    /// Returns true if the IR of `function` should be printed after running `pass`
+    pub fn print_after(&self, pass: &str, function: &FunctionIdent) -> bool {
+        (self.after_all || self.after.iter().any(|name| name == pass)) && self.matches(function)
+    }
+
+    /// Emit `ir`, the IR of `function`, as it was just before or after (as given by `when`)
+    /// running `pass`, to `diagnostics`
+    pub fn print(
+        &self,
+        diagnostics: &DiagnosticsHandler,
+        when: &str,
+        pass: &str,
+        function: &FunctionIdent,
+        ir: impl fmt::Display,
+    ) {
+        diagnostics
+            .diagnostic(Severity::Note)
+            .with_message(format!("IR {when} '{pass}' on {function}"))
+            .with_note(ir.to_string().trim_end())
+            .emit();
+    }
+
+    fn matches(&self, function: &FunctionIdent) -> bool {
+        self.functions.is_empty()
+            || self.functions.iter().any(|name| {
+                name.as_str() == function.function.as_str() || *name == function.to_string()
+            })
+    }
+}
+
+/// The statistics collected for a single pass, over every function it was run on
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct PassStatistics {
+    /// The number of times the pass was run
+    pub runs: usize,
+    /// The total wall-clock time spent running the pass
+    pub time: Duration,
+    /// The number of instructions added by the pass
+    pub insts_added: usize,
+    /// The number of instructions removed by the pass
+    pub insts_removed: usize,
+    /// The number of blocks added by the pass
+    pub blocks_added: usize,
+    /// The number of blocks removed by the pass
+    pub blocks_removed: usize,
+}
+impl PassStatistics {
+    /// Record a change in the number of instructions and blocks from `before` to `after`,
+    /// each given as a pair of the number of instructions and the number of blocks.
+    ///
+    /// This is meant for IRs where instructions have no identity, so only the net change
+    /// can be recorded, see [Snapshot] for the HIR.
+    pub fn record_counts(&mut self, before: (usize, usize), after: (usize, usize)) {
+        self.insts_added += after.0.saturating_sub(before.0);
+        self.insts_removed += before.0.saturating_sub(after.0);
+        self.blocks_added += after.1.saturating_sub(before.1);
+        self.blocks_removed += before.1.saturating_sub(after.1);
+    }
+}
+impl fmt::Display for PassStatistics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} runs in {:.2?}, instructions +{}/-{}, blocks +{}/-{}",
+            self.runs,
+            self.time,
+            self.insts_added,
+            self.insts_removed,
+            self.blocks_added,
+            self.blocks_removed
+        )
+    }
+}
+
+/// The [PassStatistics] of each pass run in a pipeline, in the order the passes first ran
+#[derive(Debug, Default)]
+pub struct Statistics {
+    passes: Vec<(&'static str, PassStatistics)>,
+}
+impl Statistics {
+    /// Get the statistics for `pass`, if it has been run
+    pub fn get(&self, pass: &str) -> Option<&PassStatistics> {
+        self.passes
+            .iter()
+            .find_map(|(name, stats)| if *name == pass { Some(stats) } else { None })
+    }
+
+    /// Get a mutable reference to the statistics for `pass`, creating them if it has not
+    /// been run yet
+    pub fn pass_mut(&mut self, pass: &'static str) -> &mut PassStatistics {
+        match self.passes.iter().position(|(name, _)| *name == pass) {
+            Some(index) => &mut self.passes[index].1,
+            None => {
+                self.passes.push((pass, PassStatistics::default()));
+                &mut self.passes.last_mut().unwrap().1
+            }
+        }
+    }
+
+    /// Iterate over the statistics of each pass, in the order the passes first ran
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &PassStatistics)> + '_ {
+        self.passes.iter().map(|(name, stats)| (*name, stats))
+    }
+}
+impl fmt::Display for Statistics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, stats) in self.passes.iter() {
+            writeln!(f, "{name}: {stats}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The set of blocks and instructions in the layout of a function at some point in time,
+/// which is used to determine the changes made to the function by a pass.
+///
+/// Instructions and blocks are identified by their keys, which are never reused, so
+/// an instruction which is replaced with an equivalent one counts as added and removed.
+pub struct Snapshot {
+    blocks: FxHashSet<Block>,
+    insts: FxHashSet<Inst>,
+}
+impl Snapshot {
+    pub fn new(function: &hir::Function) -> Self {
+        let mut blocks = FxHashSet::default();
+        let mut insts = FxHashSet::default();
+        for (block, _) in function.dfg.blocks() {
+            blocks.insert(block);
+            insts.extend(function.dfg.block_insts(block));
+        }
+        Self { blocks, insts }
+    }
+
+    /// Record the changes made to `function` since this snapshot was taken in `stats`
+    pub fn record_changes(&self, function: &hir::Function, stats: &mut PassStatistics) {
+        let after = Self::new(function);
+        stats.blocks_added += after.blocks.difference(&self.blocks).count();
+        stats.blocks_removed += self.blocks.difference(&after.blocks).count();
+        stats.insts_added += after.insts.difference(&self.insts).count();
+        stats.insts_removed += self.insts.difference(&after.insts).count();
+    }
+
+    /// The number of instructions and blocks in this snapshot
+    pub fn counts(&self) -> (usize, usize) {
+        (self.insts.len(), self.blocks.len())
+    }
+}
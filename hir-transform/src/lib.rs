@@ -1,13 +1,31 @@
 pub(crate) mod adt;
+mod dce;
+mod gvn;
+mod inline;
 mod inline_blocks;
+mod instrument;
+mod lower_switch;
+mod manager;
+mod mem2reg;
+mod sccp;
+mod simplify_cfg;
 mod split_critical_edges;
 mod treeify;
 
+pub use self::dce::DeadCodeElimination;
+pub use self::gvn::GlobalValueNumbering;
+pub use self::inline::Inliner;
 pub use self::inline_blocks::InlineBlocks;
+pub use self::instrument::{PassStatistics, PrintOptions, Snapshot, Statistics};
+pub use self::lower_switch::LowerSwitch;
+pub use self::manager::PassManager;
+pub use self::mem2reg::Mem2Reg;
+pub use self::sccp::Sccp;
+pub use self::simplify_cfg::SimplifyCfg;
 pub use self::split_critical_edges::SplitCriticalEdges;
 pub use self::treeify::Treeify;
 
-use miden_hir_analysis::FunctionAnalysis;
+use miden_hir_analysis::{FunctionAnalysis, PreservedAnalyses};
 use miden_hir_pass::Pass;
 
 /// A [RewritePass] is a special kind of [Pass] which is designed to perform some
@@ -17,10 +35,17 @@ use miden_hir_pass::Pass;
 /// determined by the requirements of the pass itself. The [FunctionAnalysis]
 /// structure is designed for this purpose, allowing one to request specific
 /// analysis results, which will be computed on-demand if not yet available.
+///
+/// Rewrites do not update the analyses they invalidate. Instead, they report the set of
+/// [PreservedAnalyses] which remain valid, and it is up to the caller to invalidate the
+/// rest, see [PassManager].
 pub trait RewritePass {
     type Error;
 
-    /// Runs the rewrite on `function` with `analyses`.
+    /// The name of this rewrite, as used in diagnostics
+    fn name(&self) -> &'static str;
+
+    /// Runs the rewrite on `function` with `analyses`, returning the analyses it preserved.
     ///
     /// Rewrites should return `Err` to signal that the pass has failed
     /// and compilation should be aborted
@@ -28,7 +53,7 @@ pub trait RewritePass {
         &mut self,
         function: &mut miden_hir::Function,
         analyses: &mut FunctionAnalysis,
-    ) -> Result<(), Self::Error>;
+    ) -> Result<PreservedAnalyses, Self::Error>;
 
     /// Chains two rewrites together to form a new, fused rewrite
     fn chain<P>(self, pass: P) -> RewriteChain<Self, P>
@@ -46,6 +71,9 @@ pub trait RewritePass {
 /// of hand quickly when combining multiple rewrites. Instead, you should invoke `chain` on a
 /// [RewritePass] implementation, and use it as a trait object. In some cases this may require boxing
 /// the `RewriteChain`, depending on how it is being used.
+///
+/// The analyses which are not preserved by each rewrite in the chain are invalidated once it is
+/// done, so all of the analyses available when the chain finishes are valid.
 pub struct RewriteChain<A, B> {
     a: A,
     b: B,
@@ -78,13 +106,20 @@ where
 {
     type Error = <B as RewritePass>::Error;
 
+    fn name(&self) -> &'static str {
+        "chain"
+    }
+
     fn run(
         &mut self,
         function: &mut miden_hir::Function,
         analyses: &mut FunctionAnalysis,
-    ) -> Result<(), Self::Error> {
-        self.a.run(function, analyses)?;
-        self.b.run(function, analyses)
+    ) -> Result<PreservedAnalyses, Self::Error> {
+        let a = self.a.run(function, analyses)?;
+        analyses.invalidate(function, a);
+        let b = self.b.run(function, analyses)?;
+        analyses.invalidate(function, b);
+        Ok(PreservedAnalyses::FUNCTION | (a & b & PreservedAnalyses::CALLGRAPH))
     }
 }
 impl<A, B, E> Pass for RewriteChain<A, B>
@@ -98,8 +133,7 @@ where
 
     fn run<'a>(&mut self, input: Self::Input<'a>) -> Result<Self::Output<'a>, Self::Error> {
         let (function, analyses) = input;
-        self.a.run(function, analyses)?;
-        self.b.run(function, analyses)?;
+        RewritePass::run(self, function, analyses)?;
         Ok((function, analyses))
     }
 }
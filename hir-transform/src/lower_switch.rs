@@ -0,0 +1,394 @@
+use smallvec::SmallVec;
+
+use miden_hir::{self as hir, *};
+use miden_hir_analysis::{FunctionAnalysis, PreservedAnalyses};
+
+use crate::RewritePass;
+
+/// The maximum number of arms for which a switch, or a subtree of its decision tree,
+/// is lowered to a chain of equality tests, rather than being split further
+const MAX_CHAIN_LEN: usize = 3;
+
+/// This pass lowers `switch` instructions to a decision tree of conditional branches,
+/// as there is no equivalent of `switch` in Miden Assembly.
+///
+/// The arms of each switch are sorted by discriminant, and then split in half
+/// repeatedly, branching on whether the selector is less than the first discriminant of
+/// the upper half, until few enough arms remain that they are tested for equality one
+/// after the other, falling through to the default successor if none of them match.
+/// Once stackified, this results in a balanced tree of `if.true` blocks for large
+/// switches, and a chain of them for small ones.
+///
+/// The arguments passed by each arm, and by the default successor, are passed on the
+/// conditional branch to the destination of that arm. The default successor is reached
+/// from more than one place in the decision tree, so this pass must run before
+/// [crate::SplitCriticalEdges] and [crate::Treeify].
+pub struct LowerSwitch;
+impl RewritePass for LowerSwitch {
+    type Error = anyhow::Error;
+
+    fn name(&self) -> &'static str {
+        "lower-switch"
+    }
+
+    fn run(
+        &mut self,
+        function: &mut hir::Function,
+        _analysis: &mut FunctionAnalysis,
+    ) -> Result<PreservedAnalyses, Self::Error> {
+        let switches = function
+            .dfg
+            .blocks()
+            .filter_map(|(b, _)| function.dfg.last_inst(b))
+            .filter(|inst| matches!(function.dfg.inst(*inst), Instruction::Switch(_)))
+            .collect::<SmallVec<[Inst; 4]>>();
+        if switches.is_empty() {
+            return Ok(PreservedAnalyses::all());
+        }
+
+        for inst in switches.into_iter() {
+            lower_switch(&mut function.dfg, inst);
+        }
+
+        Ok(PreservedAnalyses::CALLGRAPH)
+    }
+}
+
+/// Replace the switch `inst` with its decision tree
+fn lower_switch(dfg: &mut DataFlowGraph, inst: Inst) {
+    let Instruction::Switch(switch) = dfg.inst(inst) else {
+        unreachable!()
+    };
+    let Switch {
+        arg,
+        mut arms,
+        default,
+        ..
+    } = switch.clone();
+    arms.sort_by_key(|(discriminant, _, _)| *discriminant);
+
+    let block = dfg.inst_block(inst).unwrap();
+    let span = dfg.inst_span(inst);
+    let default_args = SmallVec::from_slice(default.1.as_slice(&dfg.value_lists));
+    let mut lowering = Lowering {
+        dfg,
+        arg,
+        default: default.0,
+        default_args,
+        span,
+        last_block: block,
+    };
+    lowering.lower(block, Some(inst), &arms);
+}
+
+struct Lowering<'a> {
+    dfg: &'a mut DataFlowGraph,
+    /// The selector of the switch being lowered
+    arg: Value,
+    default: Block,
+    default_args: SmallVec<[Value; 2]>,
+    span: SourceSpan,
+    /// The last block created for the decision tree, new blocks are placed after it
+    last_block: Block,
+}
+impl Lowering<'_> {
+    /// Emit the decision tree for `arms` at the end of `block`, replacing `terminator`,
+    /// the original switch, if given
+    fn lower(&mut self, block: Block, terminator: Option<Inst>, arms: &[(u32, Block, ValueList)]) {
+        if arms.len() > MAX_CHAIN_LEN {
+            let (lower, upper) = arms.split_at(arms.len() / 2);
+            let lower_block = self.create_block();
+            let upper_block = self.create_block();
+            self.cond_br(
+                block,
+                terminator,
+                Opcode::Lt,
+                upper[0].0,
+                (lower_block, ValueList::default()),
+                (upper_block, ValueList::default()),
+            );
+            self.lower(lower_block, None, lower);
+            self.lower(upper_block, None, upper);
+            return;
+        }
+
+        if arms.is_empty() {
+            let default = self.default_edge();
+            self.terminate(
+                block,
+                terminator,
+                Instruction::Br(Br {
+                    op: Opcode::Br,
+                    destination: default.0,
+                    args: default.1,
+                }),
+            );
+            return;
+        }
+
+        let mut block = block;
+        let mut terminator = terminator;
+        for (i, (discriminant, dest, args)) in arms.iter().enumerate() {
+            let else_dest = if i + 1 == arms.len() {
+                self.default_edge()
+            } else {
+                (self.create_block(), ValueList::default())
+            };
+            let next = else_dest.0;
+            self.cond_br(
+                block,
+                terminator.take(),
+                Opcode::Eq,
+                *discriminant,
+                (*dest, args.clone()),
+                else_dest,
+            );
+            block = next;
+        }
+    }
+
+    fn create_block(&mut self) -> Block {
+        self.last_block = self.dfg.create_block_after(self.last_block);
+        self.last_block
+    }
+
+    /// Get a new edge to the default successor
+    ///
+    /// Each edge gets its own copy of the arguments, as they may later be rewritten independently
+    fn default_edge(&mut self) -> (Block, ValueList) {
+        let args = ValueList::from_slice(&self.default_args, &mut self.dfg.value_lists);
+        (self.default, args)
+    }
+
+    /// Emit `cond = <op> arg, discriminant`, and a conditional branch on `cond`, at the end of `block`
+    fn cond_br(
+        &mut self,
+        block: Block,
+        terminator: Option<Inst>,
+        op: Opcode,
+        discriminant: u32,
+        then_dest: (Block, ValueList),
+        else_dest: (Block, ValueList),
+    ) {
+        let ip = match terminator {
+            Some(inst) => InsertionPoint::before(ProgramPoint::Inst(inst)),
+            None => InsertionPoint::after(ProgramPoint::Block(block)),
+        };
+        let test = self.dfg.insert_inst(
+            ip,
+            Instruction::BinaryOpImm(BinaryOpImm {
+                op,
+                overflow: Overflow::default(),
+                arg: self.arg,
+                imm: Immediate::U32(discriminant),
+            }),
+            Type::I1,
+            self.span,
+        );
+        let cond = self.dfg.first_result(test);
+        self.terminate(
+            block,
+            terminator,
+            Instruction::CondBr(CondBr {
+                op: Opcode::CondBr,
+                cond,
+                then_dest,
+                else_dest,
+            }),
+        );
+    }
+
+    /// Append `data` as the terminator of `block`, or replace `terminator` with it, if given
+    fn terminate(&mut self, block: Block, terminator: Option<Inst>, data: Instruction) {
+        match terminator {
+            Some(inst) => *self.dfg.inst_mut(inst) = data,
+            None => {
+                self.dfg.append_inst(block, data, Type::Unit, self.span);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use miden_hir::{
+        AbiParam, Function, FunctionBuilder, InstBuilder, Signature, SourceSpan, Type,
+    };
+    use miden_hir_analysis::FunctionAnalysis;
+    use pretty_assertions::assert_eq;
+
+    use crate::{LowerSwitch, RewritePass};
+
+    /// A switch with few arms is lowered to a chain of equality tests, in order of
+    /// discriminant, with the block arguments of each arm passed along.
+    #[test]
+    fn lower_switch_chain() {
+        let id = "test::switch_chain".parse().unwrap();
+        let mut function = Function::new(
+            id,
+            Signature::new(
+                [AbiParam::new(Type::U32), AbiParam::new(Type::U32)],
+                [AbiParam::new(Type::U32)],
+            ),
+        );
+
+        {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let entry = builder.current_block();
+            let (v0, v1) = {
+                let args = builder.block_params(entry);
+                (args[0], args[1])
+            };
+            let a = builder.create_block();
+            let b = builder.create_block();
+            let c = builder.create_block();
+            let v2 = builder.append_block_param(a, Type::U32, SourceSpan::UNKNOWN);
+            let v3 = builder.append_block_param(c, Type::U32, SourceSpan::UNKNOWN);
+
+            // entry
+            builder.ins().switch(
+                v0,
+                &[(3, b, &[]), (0, a, &[v1])],
+                c,
+                &[v1],
+                SourceSpan::UNKNOWN,
+            );
+
+            // block1
+            builder.switch_to_block(a);
+            builder.ins().ret(Some(v2), SourceSpan::UNKNOWN);
+
+            // block2
+            builder.switch_to_block(b);
+            builder.ins().ret(Some(v0), SourceSpan::UNKNOWN);
+
+            // block3
+            builder.switch_to_block(c);
+            builder.ins().ret(Some(v3), SourceSpan::UNKNOWN);
+        }
+
+        let mut analysis = FunctionAnalysis::new(&function);
+        let mut pass = LowerSwitch;
+        pass.run(&mut function, &mut analysis)
+            .expect("switch lowering failed");
+
+        let expected = "pub fn switch_chain(u32, u32) -> u32 {
+block0(v0: u32, v1: u32):
+    v4 = eq v0, 0  : i1
+    condbr v4, block1(v1), block4
+
+block4:
+    v5 = eq v0, 3  : i1
+    condbr v5, block2, block3(v1)
+
+block1(v2: u32):
+    ret v2
+
+block2:
+    ret v0
+
+block3(v3: u32):
+    ret v3
+}
+";
+
+        assert_eq!(function.to_string().as_str(), expected);
+    }
+
+    /// A switch with many arms is split into a balanced decision tree, whose leaves
+    /// are chains of equality tests falling through to the default successor.
+    #[test]
+    fn lower_switch_tree() {
+        let id = "test::switch_tree".parse().unwrap();
+        let mut function = Function::new(
+            id,
+            Signature::new(
+                [AbiParam::new(Type::U32), AbiParam::new(Type::U32)],
+                [AbiParam::new(Type::U32)],
+            ),
+        );
+
+        {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let entry = builder.current_block();
+            let (v0, v1) = {
+                let args = builder.block_params(entry);
+                (args[0], args[1])
+            };
+            let a = builder.create_block();
+            let b = builder.create_block();
+            let c = builder.create_block();
+            let v2 = builder.append_block_param(a, Type::U32, SourceSpan::UNKNOWN);
+
+            // entry
+            builder.ins().switch(
+                v0,
+                &[
+                    (50, a, &[v1]),
+                    (10, a, &[v0]),
+                    (40, b, &[]),
+                    (20, b, &[]),
+                    (30, a, &[v1]),
+                ],
+                c,
+                &[],
+                SourceSpan::UNKNOWN,
+            );
+
+            // block1
+            builder.switch_to_block(a);
+            builder.ins().ret(Some(v2), SourceSpan::UNKNOWN);
+
+            // block2
+            builder.switch_to_block(b);
+            builder.ins().ret(Some(v0), SourceSpan::UNKNOWN);
+
+            // block3
+            builder.switch_to_block(c);
+            builder.ins().ret(Some(v1), SourceSpan::UNKNOWN);
+        }
+
+        let mut analysis = FunctionAnalysis::new(&function);
+        let mut pass = LowerSwitch;
+        pass.run(&mut function, &mut analysis)
+            .expect("switch lowering failed");
+
+        let expected = "pub fn switch_tree(u32, u32) -> u32 {
+block0(v0: u32, v1: u32):
+    v3 = lt v0, 30  : i1
+    condbr v3, block4, block5
+
+block4:
+    v4 = eq v0, 10  : i1
+    condbr v4, block1(v0), block6
+
+block5:
+    v6 = eq v0, 30  : i1
+    condbr v6, block1(v1), block7
+
+block6:
+    v5 = eq v0, 20  : i1
+    condbr v5, block2, block3
+
+block7:
+    v7 = eq v0, 40  : i1
+    condbr v7, block2, block8
+
+block8:
+    v8 = eq v0, 50  : i1
+    condbr v8, block1(v1), block3
+
+block1(v2: u32):
+    ret v2
+
+block2:
+    ret v0
+
+block3:
+    ret v1
+}
+";
+
+        assert_eq!(function.to_string().as_str(), expected);
+    }
+}
@@ -0,0 +1,519 @@
+use std::time::Instant;
+
+use anyhow::Context;
+use miden_diagnostics::DiagnosticsHandler;
+use miden_hir as hir;
+use miden_hir_analysis::{
+    FunctionAnalysis, FunctionValidator, ModuleAnalysis, PreservedAnalyses, ProgramAnalysis, Rule,
+};
+
+use crate::{PrintOptions, RewritePass, Snapshot, Statistics};
+
+/// The [PassManager] runs a pipeline of [RewritePass] over a function, module, or program,
+/// taking care of the analyses the rewrites depend on.
+///
+/// After each rewrite, the analyses it did not preserve are invalidated, so that the next
+/// rewrite in the pipeline only ever observes valid analyses. Analyses which are invalidated
+/// are recomputed on demand by the rewrites that require them, except for the control flow
+/// graph, which is always available, and so is recomputed immediately.
+///
+/// When `verify_preserved` is set, which is the default in debug builds, the analyses each
+/// rewrite claims to preserve are checked against freshly computed ones after it runs, and
+/// a rewrite which did not actually preserve them results in an error naming that rewrite.
+///
+/// The pipeline can also be instrumented, to print the IR of a function around the rewrites
+/// selected by [PrintOptions], and to collect [Statistics] about each rewrite. As the IR is
+/// printed to a [DiagnosticsHandler], it is only printed when running the pipeline with one,
+/// see [PassManager::run_instrumented]. To catch rewrites which break the invariants of the
+/// IR where they happen, the function can be validated after every rewrite, see
+/// [PassManager::run_validated].
+pub struct PassManager {
+    passes: Vec<Box<dyn RewritePass<Error = anyhow::Error>>>,
+    verify_preserved: bool,
+    print: PrintOptions,
+    statistics: Option<Statistics>,
+}
+impl Default for PassManager {
+    fn default() -> Self {
+        Self {
+            passes: vec![],
+            verify_preserved: cfg!(debug_assertions),
+            print: PrintOptions::default(),
+            statistics: None,
+        }
+    }
+}
+impl PassManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append `pass` to the pipeline
+    pub fn add<P>(&mut self, pass: P) -> &mut Self
+    where
+        P: RewritePass<Error = anyhow::Error> + 'static,
+    {
+        self.passes.push(Box::new(pass));
+        self
+    }
+
+    /// Set whether the analyses preserved by each rewrite are verified after it runs
+    pub fn verify_preserved(&mut self, verify: bool) -> &mut Self {
+        self.verify_preserved = verify;
+        self
+    }
+
+    /// Set the options which determine when the IR is printed around each rewrite
+    pub fn print_options(&mut self, options: PrintOptions) -> &mut Self {
+        self.print = options;
+        self
+    }
+
+    /// Set whether [Statistics] are collected for each rewrite
+    ///
+    /// Statistics are accumulated across runs of the pipeline until this is disabled.
+    pub fn collect_statistics(&mut self, collect: bool) -> &mut Self {
+        if !collect {
+            self.statistics = None;
+        } else if self.statistics.is_none() {
+            self.statistics = Some(Statistics::default());
+        }
+        self
+    }
+
+    /// Get the statistics collected so far, if enabled
+    pub fn statistics(&self) -> Option<&Statistics> {
+        self.statistics.as_ref()
+    }
+
+    /// Get a mutable reference to the statistics collected so far, if enabled, e.g. to
+    /// record statistics for passes which run outside of this pipeline
+    pub fn statistics_mut(&mut self) -> Option<&mut Statistics> {
+        self.statistics.as_mut()
+    }
+
+    /// Run the pipeline on `function`, like [RewritePass::run], but print the IR selected
+    /// by [PassManager::print_options] to `diagnostics`.
+    pub fn run_instrumented(
+        &mut self,
+        function: &mut hir::Function,
+        analyses: &mut FunctionAnalysis,
+        diagnostics: &DiagnosticsHandler,
+    ) -> anyhow::Result<PreservedAnalyses> {
+        self.run_pipeline(function, analyses, Some(diagnostics), false)
+    }
+
+    /// Run the pipeline on `function`, like [PassManager::run_instrumented], but validate
+    /// the function after every rewrite, see [FunctionValidator].
+    ///
+    /// If a rewrite produces invalid IR, the diagnostics describing the problem are emitted
+    /// to `diagnostics`, and an error naming the offending rewrite is returned.
+    pub fn run_validated(
+        &mut self,
+        function: &mut hir::Function,
+        analyses: &mut FunctionAnalysis,
+        diagnostics: &DiagnosticsHandler,
+    ) -> anyhow::Result<PreservedAnalyses> {
+        self.run_pipeline(function, analyses, Some(diagnostics), true)
+    }
+
+    fn run_pipeline(
+        &mut self,
+        function: &mut hir::Function,
+        analyses: &mut FunctionAnalysis,
+        diagnostics: Option<&DiagnosticsHandler>,
+        validate: bool,
+    ) -> anyhow::Result<PreservedAnalyses> {
+        let mut callgraph = PreservedAnalyses::CALLGRAPH;
+        for pass in self.passes.iter_mut() {
+            let name = pass.name();
+            if let Some(diagnostics) = diagnostics {
+                if self.print.print_before(name, &function.id) {
+                    self.print
+                        .print(diagnostics, "before", name, &function.id, &*function);
+                }
+            }
+
+            let snapshot = self.statistics.as_ref().map(|_| Snapshot::new(function));
+            let start = Instant::now();
+            let preserved = pass.run(function, analyses)?;
+            if let Some(statistics) = self.statistics.as_mut() {
+                let stats = statistics.pass_mut(name);
+                stats.runs += 1;
+                stats.time += start.elapsed();
+                snapshot.unwrap().record_changes(function, stats);
+            }
+
+            if let Some(diagnostics) = diagnostics {
+                if self.print.print_after(name, &function.id) {
+                    self.print
+                        .print(diagnostics, "after", name, &function.id, &*function);
+                }
+            }
+
+            if let Some(diagnostics) = diagnostics.filter(|_| validate) {
+                // The module the function belongs to is not known here, so kernel functions
+                // are assumed to be in a kernel module
+                FunctionValidator::new(function.is_kernel())
+                    .validate(function, diagnostics)
+                    .with_context(|| {
+                        format!("rewrite '{name}' produced invalid IR in '{}'", function.id)
+                    })?;
+            }
+
+            if self.verify_preserved {
+                analyses
+                    .verify_preserved(function, preserved)
+                    .with_context(|| {
+                        format!("rewrite '{name}' did not preserve the analyses it claimed to")
+                    })?;
+            }
+            analyses.invalidate(function, preserved);
+            callgraph &= preserved;
+        }
+        Ok(PreservedAnalyses::FUNCTION | callgraph)
+    }
+
+    /// Run the pipeline on every function of `module`, returning the analyses preserved.
+    ///
+    /// The function analyses in `analyses` are kept up to date, while the [CallGraph] of
+    /// the module is discarded if any rewrite did not preserve it.
+    ///
+    /// [CallGraph]: miden_hir_analysis::CallGraph
+    pub fn run_on_module(
+        &mut self,
+        module: &mut hir::Module,
+        analyses: &mut ModuleAnalysis,
+    ) -> anyhow::Result<PreservedAnalyses> {
+        let mut preserved = PreservedAnalyses::all();
+        // Removing a function via this cursor will move the cursor to the next function
+        // in the module, so we put it back before the cursor once it has been rewritten
+        let mut cursor = module.cursor_mut();
+        while let Some(mut function) = cursor.remove() {
+            let analysis = analyses.function(&function);
+            let result = self.run(&mut function, analysis);
+            cursor.insert_before(function);
+            preserved &= result?;
+        }
+        analyses.invalidate(preserved);
+        Ok(preserved)
+    }
+
+    /// Run the pipeline on every function of every module in `program`, returning the
+    /// analyses preserved.
+    ///
+    /// See [PassManager::run_on_module] for how `analyses` is maintained.
+    pub fn run_on_program(
+        &mut self,
+        program: &mut hir::Program,
+        analyses: &mut ProgramAnalysis,
+    ) -> anyhow::Result<PreservedAnalyses> {
+        let mut preserved = PreservedAnalyses::all();
+        let mut modules = program.modules_mut().take();
+        while let Some(mut module) = modules.front_mut().remove() {
+            let analysis = analyses.module(&module);
+            let result = self.run_on_module(&mut module, analysis);
+            program.modules_mut().insert(module);
+            match result {
+                Ok(result) => preserved &= result,
+                Err(err) => {
+                    // Put back the modules we haven't visited yet, so that the program
+                    // remains intact when the pipeline fails
+                    while let Some(module) = modules.front_mut().remove() {
+                        program.modules_mut().insert(module);
+                    }
+                    return Err(err);
+                }
+            }
+        }
+        analyses.invalidate(preserved);
+        Ok(preserved)
+    }
+}
+impl RewritePass for PassManager {
+    type Error = anyhow::Error;
+
+    fn name(&self) -> &'static str {
+        "pipeline"
+    }
+
+    fn run(
+        &mut self,
+        function: &mut hir::Function,
+        analyses: &mut FunctionAnalysis,
+    ) -> Result<PreservedAnalyses, Self::Error> {
+        self.run_pipeline(function, analyses, None, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use miden_hir::{
+        testing::TestContext, AbiParam, Function, FunctionBuilder, InstBuilder, Parser,
+        ProgramBuilder, Signature, SourceSpan, Type,
+    };
+    use miden_hir_analysis::{FunctionAnalysis, PreservedAnalyses, ProgramAnalysis};
+    use pretty_assertions::assert_eq;
+
+    use crate::{
+        InlineBlocks, LowerSwitch, PassManager, PassStatistics, PrintOptions, RewritePass,
+        SplitCriticalEdges, Treeify,
+    };
+
+    /// A rewrite which lowers switches, but claims to preserve all analyses
+    struct Liar;
+    impl RewritePass for Liar {
+        type Error = anyhow::Error;
+
+        fn name(&self) -> &'static str {
+            "liar"
+        }
+
+        fn run(
+            &mut self,
+            function: &mut Function,
+            analyses: &mut FunctionAnalysis,
+        ) -> Result<PreservedAnalyses, Self::Error> {
+            LowerSwitch.run(function, analyses)?;
+            Ok(PreservedAnalyses::all())
+        }
+    }
+
+    /// A rewrite which adds an empty block to the function layout, which is invalid
+    struct EmptyBlock;
+    impl RewritePass for EmptyBlock {
+        type Error = anyhow::Error;
+
+        fn name(&self) -> &'static str {
+            "empty-block"
+        }
+
+        fn run(
+            &mut self,
+            function: &mut Function,
+            _analyses: &mut FunctionAnalysis,
+        ) -> Result<PreservedAnalyses, Self::Error> {
+            function.dfg.create_block();
+            Ok(PreservedAnalyses::CALLGRAPH)
+        }
+    }
+
+    /// A rewrite which always fails
+    struct Fail;
+    impl RewritePass for Fail {
+        type Error = anyhow::Error;
+
+        fn name(&self) -> &'static str {
+            "fail"
+        }
+
+        fn run(
+            &mut self,
+            _function: &mut Function,
+            _analyses: &mut FunctionAnalysis,
+        ) -> Result<PreservedAnalyses, Self::Error> {
+            anyhow::bail!("rewrite failed")
+        }
+    }
+
+    /// Build a function which switches on its first parameter, with the
+    /// default successor shared by the arms of the lowered switch
+    fn switch_function() -> Function {
+        let id = "test::switch".parse().unwrap();
+        let mut function = Function::new(
+            id,
+            Signature::new(
+                [AbiParam::new(Type::U32), AbiParam::new(Type::U32)],
+                [AbiParam::new(Type::U32)],
+            ),
+        );
+
+        {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let entry = builder.current_block();
+            let (v0, v1) = {
+                let args = builder.block_params(entry);
+                (args[0], args[1])
+            };
+            let a = builder.create_block();
+            let b = builder.create_block();
+            let c = builder.create_block();
+            let v2 = builder.append_block_param(c, Type::U32, SourceSpan::UNKNOWN);
+
+            // entry
+            builder.ins().switch(
+                v0,
+                &[(0, a, &[]), (1, b, &[]), (2, a, &[])],
+                c,
+                &[v1],
+                SourceSpan::UNKNOWN,
+            );
+
+            // block1
+            builder.switch_to_block(a);
+            builder.ins().ret(Some(v1), SourceSpan::UNKNOWN);
+
+            // block2
+            builder.switch_to_block(b);
+            builder.ins().ret(Some(v0), SourceSpan::UNKNOWN);
+
+            // block3
+            builder.switch_to_block(c);
+            builder.ins().ret(Some(v2), SourceSpan::UNKNOWN);
+        }
+
+        function
+    }
+
+    /// The rewrites used by the code generator preserve what they claim to, and
+    /// leave the analyses in a state that can be brought up to date on demand
+    #[test]
+    fn pass_manager_pipeline_test() {
+        let mut function = switch_function();
+        let mut analysis = FunctionAnalysis::new(&function);
+        let mut pipeline = PassManager::new();
+        pipeline
+            .add(LowerSwitch)
+            .add(SplitCriticalEdges)
+            .add(Treeify)
+            .add(InlineBlocks)
+            .verify_preserved(true);
+        let preserved = pipeline
+            .run(&mut function, &mut analysis)
+            .expect("rewrite pipeline failed");
+        assert_eq!(preserved, PreservedAnalyses::all());
+
+        analysis.ensure_all(&function);
+        analysis
+            .verify_preserved(&function, PreservedAnalyses::all())
+            .expect("analyses are out of date");
+    }
+
+    /// A rewrite which claims to preserve analyses it invalidated is caught
+    #[test]
+    fn pass_manager_verify_preserved_test() {
+        let mut function = switch_function();
+        let mut analysis = FunctionAnalysis::new(&function);
+        let mut pipeline = PassManager::new();
+        pipeline.add(Liar).verify_preserved(true);
+        let err = pipeline
+            .run(&mut function, &mut analysis)
+            .expect_err("expected verification to fail");
+        assert_eq!(
+            format!("{err:#}"),
+            "rewrite 'liar' did not preserve the analyses it claimed to: the control flow graph is out of date"
+        );
+    }
+
+    /// A rewrite which produces invalid IR is caught when validating after each rewrite
+    #[test]
+    fn pass_manager_validation_test() {
+        let context = TestContext::default();
+        let mut function = switch_function();
+        let mut analysis = FunctionAnalysis::new(&function);
+        let mut pipeline = PassManager::new();
+        pipeline.add(LowerSwitch).add(EmptyBlock);
+        let err = pipeline
+            .run_validated(&mut function, &mut analysis, &context.diagnostics)
+            .expect_err("expected validation to fail");
+        assert_eq!(
+            format!("{err:#}"),
+            "rewrite 'empty-block' produced invalid IR in 'test::switch': invalid block 'block6': block cannot be empty"
+        );
+    }
+
+    /// A rewrite which fails on a program leaves all of the modules of the program in place
+    #[test]
+    fn pass_manager_program_failure_test() {
+        let context = TestContext::default();
+        let parser = Parser::new(&context.diagnostics, context.codemap.clone());
+        let mut builder = ProgramBuilder::new(&context.diagnostics);
+        for name in ["a", "b", "c"] {
+            let source = format!(
+                "module {name}
+
+pub fn id(u32) -> u32 {{
+block0(v0: u32):
+    ret v0
+}}
+"
+            );
+            let module = parser
+                .parse_str(&source)
+                .expect("unexpected parse error, see diagnostics output");
+            builder = builder
+                .with_module(module)
+                .expect("unexpected module conflict");
+        }
+        let mut program = builder.link().expect("failed to link program");
+
+        let mut analyses = ProgramAnalysis::new();
+        let mut pipeline = PassManager::new();
+        pipeline.add(Fail);
+        let err = pipeline
+            .run_on_program(&mut program, &mut analyses)
+            .expect_err("expected the pipeline to fail");
+        assert_eq!(err.to_string(), "rewrite failed");
+
+        let mut modules = program
+            .modules()
+            .iter()
+            .map(|module| module.name.as_str().to_string())
+            .collect::<Vec<_>>();
+        modules.sort();
+        assert_eq!(modules, vec!["a", "b", "c"]);
+    }
+
+    /// Statistics count the instructions and blocks added by each rewrite
+    #[test]
+    fn pass_manager_statistics_test() {
+        let mut function = switch_function();
+        let mut analysis = FunctionAnalysis::new(&function);
+        let mut pipeline = PassManager::new();
+        pipeline.add(LowerSwitch).collect_statistics(true);
+        pipeline
+            .run(&mut function, &mut analysis)
+            .expect("rewrite pipeline failed");
+
+        // The switch is replaced by a chain of three equality tests, the first of which is
+        // placed in the entry block, and the others in a new block each
+        let mut stats = *pipeline
+            .statistics()
+            .and_then(|statistics| statistics.get("lower-switch"))
+            .expect("expected statistics for lower-switch");
+        stats.time = Default::default();
+        assert_eq!(
+            stats,
+            PassStatistics {
+                runs: 1,
+                insts_added: 5,
+                blocks_added: 2,
+                ..Default::default()
+            }
+        );
+    }
+
+    /// The IR is printed for the selected rewrites and functions only
+    #[test]
+    fn print_options_test() {
+        let id = "test::switch".parse().unwrap();
+        let other = "test::other".parse().unwrap();
+        let mut options = PrintOptions {
+            before: vec!["treeify".to_string()],
+            after: vec!["lower-switch".to_string()],
+            ..Default::default()
+        };
+        assert!(options.print_before("treeify", &id));
+        assert!(!options.print_after("treeify", &id));
+        assert!(options.print_after("lower-switch", &other));
+
+        options.functions = vec!["switch".to_string()];
+        assert!(options.print_after("lower-switch", &id));
+        assert!(!options.print_after("lower-switch", &other));
+
+        options.functions = vec!["test::other".to_string()];
+        options.after_all = true;
+        assert!(!options.print_after("treeify", &id));
+        assert!(options.print_after("treeify", &other));
+    }
+}
@@ -0,0 +1,642 @@
+use std::rc::Rc;
+
+use rustc_hash::{FxHashMap, FxHashSet};
+use smallvec::SmallVec;
+
+use miden_hir::{self as hir, *};
+use miden_hir_analysis::{
+    DominanceFrontier, DominatorTreePreorder, FunctionAnalysis, PreservedAnalyses,
+};
+
+use crate::{adt::ScopedMap, inline_blocks::rewrite_use, sccp::imm_opcode, RewritePass};
+
+/// This pass operates on the SSA IR, and promotes stack slots allocated with `alloca`
+/// to SSA values, rewriting the loads and stores of those slots into plain value flow.
+///
+/// A slot is promoted when its address does not escape, i.e. it is only ever used as the
+/// address of a `load`, or as the destination pointer of a `store`, of the allocated type.
+/// Any other use of the address, e.g. passing it to a call, casting it to an integer, or
+/// storing the address itself to memory, prevents promotion of that slot.
+///
+/// Block parameters are placed on the iterated dominance frontier of the blocks which store
+/// to each slot, and the dominator tree is then traversed in pre-order to determine the value
+/// of each slot at every load, and along every edge to a block with a new parameter. The
+/// placement is not pruned by liveness, so [crate::DeadCodeElimination] should be run
+/// afterwards to remove parameters which end up unused.
+///
+/// Some things to note:
+///
+/// * Only slots of scalar type are promoted. A slot which is read before it is ever written
+/// is given the value zero, which must be representable as an immediate.
+/// * Slots which would require a block parameter on the entry block are not promoted.
+/// * Slots which are used in unreachable blocks are not promoted.
+pub struct Mem2Reg;
+impl RewritePass for Mem2Reg {
+    type Error = anyhow::Error;
+
+    fn name(&self) -> &'static str {
+        "mem2reg"
+    }
+
+    fn run(
+        &mut self,
+        function: &mut hir::Function,
+        analysis: &mut FunctionAnalysis,
+    ) -> Result<PreservedAnalyses, Self::Error> {
+        analysis.ensure_domtree(function);
+
+        let mut slots = find_promotable_slots(&function.dfg, analysis);
+        if slots.is_empty() {
+            return Ok(PreservedAnalyses::all());
+        }
+
+        // Place block parameters for each slot on the iterated dominance frontier of
+        // the blocks in which it is written
+        let frontier = DominanceFrontier::compute(analysis.domtree(), analysis.cfg(), function);
+        let dfg = &mut function.dfg;
+        let entry = dfg.entry_block();
+        let mut phis = FxHashMap::<Block, SmallVec<[(Value, Value); 2]>>::default();
+        let mut allocas = slots.keys().copied().collect::<Vec<_>>();
+        allocas.sort();
+        for alloca in allocas.into_iter() {
+            let slot = &slots[&alloca];
+            let (ty, span) = (slot.ty.clone(), slot.span);
+            let mut placed = FxHashSet::<Block>::default();
+            let mut worklist = slot.defs.iter().copied().collect::<Vec<_>>();
+            while let Some(block) = worklist.pop() {
+                for df in frontier.iter(&block) {
+                    if placed.insert(df) {
+                        worklist.push(df);
+                    }
+                }
+            }
+            if placed.contains(&entry) {
+                slots.remove(&alloca);
+                continue;
+            }
+            let mut placed = placed.into_iter().collect::<Vec<_>>();
+            placed.sort();
+            for block in placed.into_iter() {
+                let param = dfg.append_block_param(block, ty.clone(), span);
+                phis.entry(block).or_default().push((alloca, param));
+            }
+        }
+        if slots.is_empty() {
+            return Ok(PreservedAnalyses::all());
+        }
+
+        // Rename loads and stores in dominator tree pre-order, tracking the current
+        // value of each slot, which is inherited from the immediate dominator
+        let domtree = DominatorTreePreorder::with_function(analysis.domtree(), function);
+        let dfg = &mut function.dfg;
+        let mut zeros = FxHashMap::<Value, Value>::default();
+        let mut rewrites = ScopedMap::<Value, Value>::default();
+        let mut dead = FxHashSet::<Inst>::default();
+        let mut visited = FxHashSet::<Block>::default();
+        let mut worklist = vec![(entry, Rc::new(ScopedMap::<Value, Value>::default()))];
+        while let Some((block, parent)) = worklist.pop() {
+            visited.insert(block);
+
+            let mut current = ScopedMap::new(Some(parent));
+            for (alloca, param) in phis.get(&block).into_iter().flatten() {
+                current.insert(*alloca, *param);
+            }
+
+            let insts = dfg.block_insts(block).collect::<SmallVec<[Inst; 16]>>();
+            for inst in insts {
+                rewrite_use(dfg.insts[inst].as_mut(), &mut dfg.value_lists, &rewrites);
+
+                match Access::new(dfg, inst, &slots) {
+                    Some(Access::Alloca) => {
+                        dead.insert(inst);
+                    }
+                    Some(Access::Load(alloca, result)) => {
+                        let value = match current.get(&alloca).copied() {
+                            Some(value) => value,
+                            None => zero(dfg, &mut zeros, &slots, alloca),
+                        };
+                        rewrites.insert(result, value);
+                        dead.insert(inst);
+                    }
+                    Some(Access::Store(alloca, value)) => {
+                        current.insert(alloca, value);
+                        dead.insert(inst);
+                    }
+                    None => (),
+                }
+            }
+
+            // Pass the current value of each slot along all edges to blocks with new parameters
+            if let Some(inst) = dfg.last_inst(block) {
+                append_phi_args(dfg, inst, &phis, Some(&current), &mut zeros, &slots);
+            }
+
+            let current = Rc::new(current);
+            for child in domtree.children(block) {
+                worklist.push((child, current.clone()));
+            }
+        }
+
+        // Remove the promoted allocas, and all of the loads and stores of them
+        let blocks = dfg.blocks().map(|(b, _)| b).collect::<Vec<_>>();
+        for block in blocks.into_iter() {
+            if !visited.contains(&block) {
+                // Blocks which are unreachable may still refer to the results of loads
+                // which were removed, so make sure those uses are rewritten
+                let insts = dfg.block_insts(block).collect::<SmallVec<[Inst; 16]>>();
+                for inst in insts {
+                    rewrite_use(dfg.insts[inst].as_mut(), &mut dfg.value_lists, &rewrites);
+                }
+                // They may also branch to blocks with new parameters, and as no value of
+                // any slot reaches them, those edges are given the initial value instead
+                if let Some(inst) = dfg.last_inst(block) {
+                    append_phi_args(dfg, inst, &phis, None, &mut zeros, &slots);
+                }
+                continue;
+            }
+            let block = &mut dfg.blocks[block];
+            let mut insts = block.insts.take();
+            while let Some(inst) = insts.pop_front() {
+                if !dead.contains(&inst.key) {
+                    block.insts.push_back(inst);
+                }
+            }
+        }
+
+        // Only block parameters, branch arguments, and instructions other than branches
+        // were added or removed
+        Ok(PreservedAnalyses::all() - PreservedAnalyses::LIVENESS)
+    }
+}
+
+/// A stack slot which is a candidate for promotion
+struct Slot {
+    /// The type of value stored in the slot
+    ty: Type,
+    /// The span of the `alloca` which allocated the slot
+    span: SourceSpan,
+    /// The set of blocks containing a store to the slot
+    defs: FxHashSet<Block>,
+}
+
+/// An access to a promotable slot, identified by the result of its `alloca`
+enum Access {
+    /// The `alloca` which allocates the slot
+    Alloca,
+    /// A load from the slot, producing the given value
+    Load(Value, Value),
+    /// A store of the given value to the slot
+    Store(Value, Value),
+}
+impl Access {
+    fn new(dfg: &DataFlowGraph, inst: Inst, slots: &FxHashMap<Value, Slot>) -> Option<Self> {
+        match &dfg[inst] {
+            Instruction::PrimOp(PrimOp {
+                op: Opcode::Alloca, ..
+            }) if slots.contains_key(&dfg.first_result(inst)) => Some(Self::Alloca),
+            Instruction::Load(LoadOp { addr, .. }) if slots.contains_key(addr) => {
+                Some(Self::Load(*addr, dfg.first_result(inst)))
+            }
+            Instruction::PrimOp(PrimOp {
+                op: Opcode::Store,
+                args,
+            }) => match args.as_slice(&dfg.value_lists) {
+                [ptr, value] if slots.contains_key(ptr) => Some(Self::Store(*ptr, *value)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Find all of the slots allocated by `alloca` in `dfg` whose address does not escape
+fn find_promotable_slots(
+    dfg: &DataFlowGraph,
+    analysis: &FunctionAnalysis,
+) -> FxHashMap<Value, Slot> {
+    let domtree = analysis.domtree();
+
+    let mut slots = FxHashMap::<Value, Slot>::default();
+    for (block, _) in dfg.blocks() {
+        if !domtree.is_reachable(block) {
+            continue;
+        }
+        for inst in dfg.block_insts(block) {
+            if dfg[inst].opcode() != Opcode::Alloca {
+                continue;
+            }
+            let alloca = dfg.first_result(inst);
+            let ty = dfg.value_type(alloca).pointee().unwrap().clone();
+            if zero_imm(&ty).is_some() {
+                slots.insert(
+                    alloca,
+                    Slot {
+                        ty,
+                        span: dfg.inst_span(inst),
+                        defs: FxHashSet::default(),
+                    },
+                );
+            }
+        }
+    }
+
+    let mut escaped = FxHashSet::<Value>::default();
+    for (block, _) in dfg.blocks() {
+        let reachable = domtree.is_reachable(block);
+        for inst in dfg.block_insts(block) {
+            match &dfg[inst] {
+                Instruction::Load(LoadOp { addr, ty, .. }) => {
+                    if let Some(slot) = slots.get(addr) {
+                        if !reachable || &slot.ty != ty {
+                            escaped.insert(*addr);
+                        }
+                    }
+                }
+                Instruction::PrimOp(PrimOp {
+                    op: Opcode::Store,
+                    args,
+                }) => {
+                    let args = args.as_slice(&dfg.value_lists);
+                    let (ptr, value) = (args[0], args[1]);
+                    if slots.contains_key(&value) {
+                        escaped.insert(value);
+                    }
+                    if let Some(slot) = slots.get_mut(&ptr) {
+                        if !reachable || &slot.ty != dfg.value_type(value) {
+                            escaped.insert(ptr);
+                        } else {
+                            slot.defs.insert(block);
+                        }
+                    }
+                }
+                ix => {
+                    let branch_args = match dfg.analyze_branch(inst) {
+                        BranchInfo::NotABranch => SmallVec::<[Value; 4]>::new(),
+                        BranchInfo::SingleDest(_, args) => SmallVec::from_slice(args),
+                        BranchInfo::MultiDest(jts) => {
+                            jts.iter().flat_map(|jt| jt.args.iter().copied()).collect()
+                        }
+                    };
+                    let args = ix.arguments(&dfg.value_lists).iter().copied();
+                    for arg in args.chain(branch_args) {
+                        if slots.contains_key(&arg) {
+                            escaped.insert(arg);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    slots.retain(|alloca, _| !escaped.contains(alloca));
+    slots
+}
+
+/// Append the value of each slot given a parameter in `phis` to the arguments of every edge
+/// from `inst` to the block with that parameter.
+///
+/// The value of a slot is taken from `current`, or is zero if `current` has no value for it.
+fn append_phi_args(
+    dfg: &mut DataFlowGraph,
+    inst: Inst,
+    phis: &FxHashMap<Block, SmallVec<[(Value, Value); 2]>>,
+    current: Option<&ScopedMap<Value, Value>>,
+    zeros: &mut FxHashMap<Value, Value>,
+    slots: &FxHashMap<Value, Slot>,
+) {
+    let mut dests = match dfg.analyze_branch(inst) {
+        BranchInfo::NotABranch => SmallVec::<[Block; 2]>::new(),
+        BranchInfo::SingleDest(dest, _) => SmallVec::from_slice(&[dest]),
+        BranchInfo::MultiDest(jts) => jts.into_iter().map(|jt| jt.destination).collect(),
+    };
+    // Each destination is visited once, as all of the edges to it are extended at once
+    dests.sort();
+    dests.dedup();
+    for dest in dests.into_iter() {
+        let Some(params) = phis.get(&dest) else {
+            continue;
+        };
+        let mut args = SmallVec::<[Value; 2]>::with_capacity(params.len());
+        for (alloca, _) in params.iter() {
+            let value = current.and_then(|current| current.get(alloca).copied());
+            args.push(match value {
+                Some(value) => value,
+                None => zero(dfg, zeros, slots, *alloca),
+            });
+        }
+        dfg.visit_edges_to(inst, dest, |_, edge_args, pool| {
+            edge_args.extend(args.iter().copied(), pool);
+        });
+    }
+}
+
+/// Get the value of `alloca` when it is read before ever being written, which is
+/// materialized at the start of the entry block the first time it is needed
+fn zero(
+    dfg: &mut DataFlowGraph,
+    zeros: &mut FxHashMap<Value, Value>,
+    slots: &FxHashMap<Value, Slot>,
+    alloca: Value,
+) -> Value {
+    if let Some(value) = zeros.get(&alloca) {
+        return *value;
+    }
+    let slot = &slots[&alloca];
+    let imm = zero_imm(&slot.ty).unwrap();
+    let entry = dfg.entry_block();
+    let inst = dfg.insert_inst(
+        InsertionPoint::before(ProgramPoint::Block(entry)),
+        Instruction::UnaryOpImm(UnaryOpImm {
+            op: imm_opcode(imm).unwrap(),
+            overflow: Overflow::default(),
+            imm,
+        }),
+        imm.ty(),
+        slot.span,
+    );
+    let value = dfg.first_result(inst);
+    zeros.insert(alloca, value);
+    value
+}
+
+/// Get the zero value of `ty`, if it can be represented as an immediate
+fn zero_imm(ty: &Type) -> Option<Immediate> {
+    match ty {
+        Type::I1 => Some(Immediate::I1(false)),
+        Type::I8 => Some(Immediate::I8(0)),
+        Type::U8 => Some(Immediate::U8(0)),
+        Type::I16 => Some(Immediate::I16(0)),
+        Type::U16 => Some(Immediate::U16(0)),
+        Type::I32 => Some(Immediate::I32(0)),
+        Type::U32 => Some(Immediate::U32(0)),
+        Type::I64 => Some(Immediate::I64(0)),
+        Type::U64 => Some(Immediate::U64(0)),
+        Type::Felt => Some(Immediate::Felt(Felt::ZERO)),
+        Type::F64 => Some(Immediate::F64(0.0)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use miden_hir::{
+        AbiParam, Function, FunctionBuilder, Immediate, InstBuilder, Signature, SourceSpan, Type,
+    };
+    use miden_hir_analysis::FunctionAnalysis;
+    use pretty_assertions::assert_eq;
+
+    use crate::{Mem2Reg, RewritePass};
+
+    /// Stores on either side of a diamond should result in a new block parameter where
+    /// the paths join, and the load from the slot replaced with that parameter.
+    #[test]
+    fn mem2reg_diamond() {
+        let id = "test::mem2reg".parse().unwrap();
+        let mut function = Function::new(
+            id,
+            Signature::new(
+                [AbiParam::new(Type::U32), AbiParam::new(Type::U32)],
+                [AbiParam::new(Type::U32)],
+            ),
+        );
+
+        {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let entry = builder.current_block();
+            let (v0, v1) = {
+                let args = builder.block_params(entry);
+                (args[0], args[1])
+            };
+            let a = builder.create_block();
+            let b = builder.create_block();
+            let c = builder.create_block();
+
+            // entry
+            let v2 = builder.ins().alloca(Type::U32, SourceSpan::UNKNOWN);
+            builder.ins().store(v2, v0, SourceSpan::UNKNOWN);
+            let v3 = builder.ins().gt(v0, v1, SourceSpan::UNKNOWN);
+            builder
+                .ins()
+                .cond_br(v3, a, &[], b, &[], SourceSpan::UNKNOWN);
+
+            // block1
+            builder.switch_to_block(a);
+            builder.ins().store(v2, v1, SourceSpan::UNKNOWN);
+            builder.ins().br(c, &[], SourceSpan::UNKNOWN);
+
+            // block2
+            builder.switch_to_block(b);
+            builder.ins().br(c, &[], SourceSpan::UNKNOWN);
+
+            // block3
+            builder.switch_to_block(c);
+            let v4 = builder.ins().load(v2, SourceSpan::UNKNOWN);
+            builder.ins().ret(Some(v4), SourceSpan::UNKNOWN);
+        }
+
+        let mut analysis = FunctionAnalysis::new(&function);
+        let mut pass = Mem2Reg;
+        pass.run(&mut function, &mut analysis)
+            .expect("mem2reg failed");
+
+        let expected = "pub fn mem2reg(u32, u32) -> u32 {
+block0(v0: u32, v1: u32):
+    v3 = gt v0, v1  : i1
+    condbr v3, block1, block2
+
+block1:
+    br block3(v1)
+
+block2:
+    br block3(v0)
+
+block3(v5: u32):
+    ret v5
+}
+";
+
+        assert_eq!(function.to_string().as_str(), expected);
+    }
+
+    /// A slot which is written in a loop should result in a new parameter on the loop
+    /// header, with the value stored in the loop passed along the back edge.
+    #[test]
+    fn mem2reg_loop() {
+        let id = "test::mem2reg_loop".parse().unwrap();
+        let mut function = Function::new(
+            id,
+            Signature::new([AbiParam::new(Type::U32)], [AbiParam::new(Type::U32)]),
+        );
+
+        {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let entry = builder.current_block();
+            let v0 = builder.block_params(entry)[0];
+            let header = builder.create_block();
+            let exit = builder.create_block();
+
+            // entry
+            let v1 = builder.ins().alloca(Type::U32, SourceSpan::UNKNOWN);
+            builder.ins().store(v1, v0, SourceSpan::UNKNOWN);
+            builder.ins().br(header, &[], SourceSpan::UNKNOWN);
+
+            // block1
+            builder.switch_to_block(header);
+            let v2 = builder.ins().load(v1, SourceSpan::UNKNOWN);
+            let v3 = builder
+                .ins()
+                .add_imm(v2, Immediate::U32(1), SourceSpan::UNKNOWN);
+            builder.ins().store(v1, v3, SourceSpan::UNKNOWN);
+            let v4 = builder
+                .ins()
+                .lt_imm(v3, Immediate::U32(10), SourceSpan::UNKNOWN);
+            builder
+                .ins()
+                .cond_br(v4, header, &[], exit, &[], SourceSpan::UNKNOWN);
+
+            // block2
+            builder.switch_to_block(exit);
+            let v5 = builder.ins().load(v1, SourceSpan::UNKNOWN);
+            builder.ins().ret(Some(v5), SourceSpan::UNKNOWN);
+        }
+
+        let mut analysis = FunctionAnalysis::new(&function);
+        let mut pass = Mem2Reg;
+        pass.run(&mut function, &mut analysis)
+            .expect("mem2reg failed");
+
+        let expected = "pub fn mem2reg_loop(u32) -> u32 {
+block0(v0: u32):
+    br block1(v0)
+
+block1(v6: u32):
+    v3 = add v6, 1  : u32
+    v4 = lt v3, 10  : i1
+    condbr v4, block1(v3), block2
+
+block2:
+    ret v3
+}
+";
+
+        assert_eq!(function.to_string().as_str(), expected);
+    }
+
+    /// A block which gains a parameter, and is the destination of more than one edge from
+    /// the same branch, should be given exactly one argument along each of those edges, and
+    /// an edge to it from an unreachable block should be given the initial value of the slot.
+    #[test]
+    fn mem2reg_switch_with_shared_destination() {
+        let id = "test::mem2reg_switch".parse().unwrap();
+        let mut function = Function::new(
+            id,
+            Signature::new(
+                [AbiParam::new(Type::U32), AbiParam::new(Type::U32)],
+                [AbiParam::new(Type::U32)],
+            ),
+        );
+
+        {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let entry = builder.current_block();
+            let (v0, v1) = {
+                let args = builder.block_params(entry);
+                (args[0], args[1])
+            };
+            let a = builder.create_block();
+            let join = builder.create_block();
+            let unreachable = builder.create_block();
+
+            // entry
+            let v2 = builder.ins().alloca(Type::U32, SourceSpan::UNKNOWN);
+            builder.ins().store(v2, v1, SourceSpan::UNKNOWN);
+            builder.ins().switch(
+                v0,
+                &[(0, a, &[]), (1, join, &[])],
+                join,
+                &[],
+                SourceSpan::UNKNOWN,
+            );
+
+            // block1
+            builder.switch_to_block(a);
+            builder.ins().store(v2, v0, SourceSpan::UNKNOWN);
+            builder.ins().br(join, &[], SourceSpan::UNKNOWN);
+
+            // block2
+            builder.switch_to_block(join);
+            let v3 = builder.ins().load(v2, SourceSpan::UNKNOWN);
+            builder.ins().ret(Some(v3), SourceSpan::UNKNOWN);
+
+            // block3
+            builder.switch_to_block(unreachable);
+            builder.ins().br(join, &[], SourceSpan::UNKNOWN);
+        }
+
+        let mut analysis = FunctionAnalysis::new(&function);
+        let mut pass = Mem2Reg;
+        pass.run(&mut function, &mut analysis)
+            .expect("mem2reg failed");
+
+        let expected = "pub fn mem2reg_switch(u32, u32) -> u32 {
+block0(v0: u32, v1: u32):
+    v5 = const.u32 0  : u32
+    switch v0, 0 => block1, 1 => block2(v1), block2(v1)
+
+block1:
+    br block2(v0)
+
+block2(v4: u32):
+    ret v4
+
+block3:
+    br block2(v5)
+}
+";
+
+        assert_eq!(function.to_string().as_str(), expected);
+    }
+
+    /// A slot whose address escapes is left alone, while a slot which is read before
+    /// it is written is promoted with an initial value of zero.
+    #[test]
+    fn mem2reg_escaping_and_uninitialized_slots() {
+        let id = "test::mem2reg_escape".parse().unwrap();
+        let mut function = Function::new(
+            id,
+            Signature::new([AbiParam::new(Type::U32)], [AbiParam::new(Type::U32)]),
+        );
+
+        {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let entry = builder.current_block();
+            let v0 = builder.block_params(entry)[0];
+
+            let v1 = builder.ins().alloca(Type::U32, SourceSpan::UNKNOWN);
+            let v2 = builder.ins().alloca(Type::U32, SourceSpan::UNKNOWN);
+            let v3 = builder.ins().load(v1, SourceSpan::UNKNOWN);
+            let v4 = builder.ins().ptrtoint(v2, Type::U32, SourceSpan::UNKNOWN);
+            builder.ins().store(v2, v0, SourceSpan::UNKNOWN);
+            let v5 = builder.ins().add(v3, v4, SourceSpan::UNKNOWN);
+            builder.ins().ret(Some(v5), SourceSpan::UNKNOWN);
+        }
+
+        let mut analysis = FunctionAnalysis::new(&function);
+        let mut pass = Mem2Reg;
+        pass.run(&mut function, &mut analysis)
+            .expect("mem2reg failed");
+
+        let expected = "pub fn mem2reg_escape(u32) -> u32 {
+block0(v0: u32):
+    v6 = const.u32 0  : u32
+    v2 = alloca   : *mut u32
+    v4 = ptrtoint v2  : u32
+    store v2, v0
+    v5 = add v6, v4  : u32
+    ret v5
+}
+";
+
+        assert_eq!(function.to_string().as_str(), expected);
+    }
+}
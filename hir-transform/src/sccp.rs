@@ -0,0 +1,831 @@
+use rustc_hash::{FxHashMap, FxHashSet};
+use smallvec::SmallVec;
+
+use miden_hir::{self as hir, *};
+use miden_hir_analysis::{FunctionAnalysis, PreservedAnalyses};
+
+use crate::RewritePass;
+
+/// This pass operates on the SSA IR, and performs sparse conditional constant propagation,
+/// folding instructions whose operands are known constants, and removing code which is
+/// proven to be unreachable.
+///
+/// The analysis is optimistic: all values start out as undefined, and only blocks which
+/// are reachable from the entry block along edges which can actually be taken are visited.
+/// Values are lowered to a constant, or to "overdefined" (i.e. not a constant), as their
+/// definitions are visited, and block parameters receive the meet of all arguments passed
+/// to them along executable edges. This is iterated until a fixpoint is reached, and lets
+/// us discover constants flowing around loops, as well as branches which are never taken.
+///
+/// Once the analysis is complete, the function is rewritten as follows:
+///
+/// * Instructions which produce a constant are replaced with the equivalent `const.*`
+/// instruction.
+/// * Block parameters which are constant are replaced with a `const.*` instruction at
+/// the start of the block, if they are used.
+/// * Conditional branches and switches on a constant selector are replaced with an
+/// unconditional branch to the destination which is taken.
+/// * Blocks which were never found to be executable are removed from the function.
+///
+/// Folding follows the semantics of each instruction with respect to its [Overflow] mode
+/// and the width of its type: an operation is only folded if it is guaranteed not to trap,
+/// and the result is only wrapped into range if the instruction has wrapping semantics.
+/// Operations whose result is out of range for its type in any other mode are left as-is.
+pub struct Sccp;
+impl RewritePass for Sccp {
+    type Error = anyhow::Error;
+
+    fn name(&self) -> &'static str {
+        "sccp"
+    }
+
+    fn run(
+        &mut self,
+        function: &mut hir::Function,
+        _analysis: &mut FunctionAnalysis,
+    ) -> Result<PreservedAnalyses, Self::Error> {
+        let mut solver = Solver::default();
+        solver.solve(&function.dfg);
+
+        if solver.rewrite(&mut function.dfg) {
+            Ok(PreservedAnalyses::empty())
+        } else {
+            Ok(PreservedAnalyses::all())
+        }
+    }
+}
+
+/// The lattice of values tracked by [Sccp]
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum LatticeValue {
+    /// No definition of the value has been seen yet
+    Undefined,
+    /// The value is always equal to the given constant
+    Constant(Immediate),
+    /// The value may take on more than one value at runtime
+    Overdefined,
+}
+impl LatticeValue {
+    fn meet(self, other: Self) -> Self {
+        match (self, other) {
+            (Self::Undefined, x) | (x, Self::Undefined) => x,
+            (Self::Constant(a), Self::Constant(b)) if a == b => Self::Constant(a),
+            _ => Self::Overdefined,
+        }
+    }
+}
+
+enum Fold {
+    /// Replace the instruction with a constant
+    Constant(Immediate),
+    /// Replace the branch with an unconditional branch to the given block
+    Branch(Block, SmallVec<[Value; 4]>),
+}
+
+#[derive(Default)]
+struct Solver {
+    values: FxHashMap<Value, LatticeValue>,
+    executable: FxHashSet<Block>,
+}
+impl Solver {
+    fn get(&self, value: Value) -> LatticeValue {
+        self.values
+            .get(&value)
+            .copied()
+            .unwrap_or(LatticeValue::Undefined)
+    }
+
+    fn constant(&self, value: Value) -> Option<Immediate> {
+        match self.get(value) {
+            LatticeValue::Constant(imm) => Some(imm),
+            _ => None,
+        }
+    }
+
+    /// Lower the lattice value of `value` to its meet with `new`, returning true if it changed
+    fn update(&mut self, value: Value, new: LatticeValue) -> bool {
+        let old = self.get(value);
+        let merged = old.meet(new);
+        if merged == old {
+            return false;
+        }
+        self.values.insert(value, merged);
+        true
+    }
+
+    fn solve(&mut self, dfg: &DataFlowGraph) {
+        // The entry block is always executable, and its parameters are unknown
+        let entry = dfg.entry_block();
+        self.executable.insert(entry);
+        for param in dfg.block_params(entry).iter().copied() {
+            self.values.insert(param, LatticeValue::Overdefined);
+        }
+
+        // Visit every executable block in layout order until nothing changes.
+        //
+        // Lattice values can only ever be lowered, and blocks can only ever become
+        // executable, so this is guaranteed to terminate.
+        let blocks = dfg.blocks().map(|(b, _)| b).collect::<Vec<_>>();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for block in blocks.iter() {
+                if !self.executable.contains(block) {
+                    continue;
+                }
+                for inst in dfg.block_insts(*block) {
+                    changed |= self.visit(dfg, inst);
+                }
+            }
+        }
+    }
+
+    fn visit(&mut self, dfg: &DataFlowGraph, inst: Inst) -> bool {
+        let pool = &dfg.value_lists;
+        match &dfg[inst] {
+            Instruction::Br(Br {
+                destination, args, ..
+            }) => self.visit_edge(dfg, *destination, args.as_slice(pool)),
+            Instruction::CondBr(CondBr {
+                cond,
+                then_dest: (then_blk, then_args),
+                else_dest: (else_blk, else_args),
+                ..
+            }) => {
+                let (then_taken, else_taken) = match self.get(*cond) {
+                    LatticeValue::Undefined => (false, false),
+                    LatticeValue::Constant(imm) => match imm.as_bool() {
+                        Some(cond) => (cond, !cond),
+                        None => (true, true),
+                    },
+                    LatticeValue::Overdefined => (true, true),
+                };
+                let mut changed = false;
+                if then_taken {
+                    changed |= self.visit_edge(dfg, *then_blk, then_args.as_slice(pool));
+                }
+                if else_taken {
+                    changed |= self.visit_edge(dfg, *else_blk, else_args.as_slice(pool));
+                }
+                changed
+            }
+            Instruction::Switch(Switch {
+                arg, arms, default, ..
+            }) => match self.get(*arg) {
+                LatticeValue::Undefined => false,
+                LatticeValue::Constant(imm) => {
+                    let (dest, args) = switch_target(imm, arms, default);
+                    self.visit_edge(dfg, dest, args.as_slice(pool))
+                }
+                LatticeValue::Overdefined => {
+                    let mut changed = false;
+                    let successors = arms
+                        .iter()
+                        .map(|(_, dest, args)| (*dest, args))
+                        .chain([(default.0, &default.1)]);
+                    for (dest, args) in successors {
+                        changed |= self.visit_edge(dfg, dest, args.as_slice(pool));
+                    }
+                    changed
+                }
+            },
+            _ => {
+                let results = dfg.inst_results(inst);
+                let value = match results {
+                    [] => return false,
+                    [result] => self.evaluate(dfg, inst, *result),
+                    _ => LatticeValue::Overdefined,
+                };
+                let mut changed = false;
+                for result in results.iter().copied() {
+                    changed |= self.update(result, value);
+                }
+                changed
+            }
+        }
+    }
+
+    /// Mark the edge to `dest` as executable, propagating `args` to its parameters
+    fn visit_edge(&mut self, dfg: &DataFlowGraph, dest: Block, args: &[Value]) -> bool {
+        let mut changed = self.executable.insert(dest);
+        for (param, arg) in dfg.block_params(dest).iter().zip(args.iter()) {
+            changed |= self.update(*param, self.get(*arg));
+        }
+        changed
+    }
+
+    /// Compute the lattice value of `result`, the sole result of `inst`
+    fn evaluate(&self, dfg: &DataFlowGraph, inst: Inst, result: Value) -> LatticeValue {
+        let ty = dfg.value_type(result);
+        let folded = |imm: Option<Immediate>| match imm {
+            Some(imm) => LatticeValue::Constant(imm),
+            None => LatticeValue::Overdefined,
+        };
+        match &dfg[inst] {
+            Instruction::UnaryOpImm(UnaryOpImm { imm, .. }) => LatticeValue::Constant(*imm),
+            Instruction::UnaryOp(UnaryOp { op, overflow, arg }) => match self.get(*arg) {
+                LatticeValue::Constant(imm) => folded(fold_unary(*op, *overflow, imm, ty)),
+                value => value,
+            },
+            Instruction::BinaryOp(BinaryOp { op, overflow, args }) => {
+                match (self.get(args[0]), self.get(args[1])) {
+                    (LatticeValue::Constant(lhs), LatticeValue::Constant(rhs)) => {
+                        folded(fold_binary(*op, *overflow, lhs, rhs, ty))
+                    }
+                    (LatticeValue::Overdefined, _) | (_, LatticeValue::Overdefined) => {
+                        LatticeValue::Overdefined
+                    }
+                    _ => LatticeValue::Undefined,
+                }
+            }
+            Instruction::BinaryOpImm(BinaryOpImm {
+                op,
+                overflow,
+                arg,
+                imm,
+            }) => match self.get(*arg) {
+                LatticeValue::Constant(lhs) => folded(fold_binary(*op, *overflow, lhs, *imm, ty)),
+                value => value,
+            },
+            Instruction::PrimOp(PrimOp {
+                op: Opcode::Select,
+                args,
+            }) => {
+                let args = args.as_slice(&dfg.value_lists);
+                let (cond, a, b) = (args[0], args[1], args[2]);
+                match self.get(cond) {
+                    LatticeValue::Undefined => LatticeValue::Undefined,
+                    LatticeValue::Constant(imm) => match imm.as_bool() {
+                        Some(true) => self.get(a),
+                        Some(false) => self.get(b),
+                        None => LatticeValue::Overdefined,
+                    },
+                    LatticeValue::Overdefined => self.get(a).meet(self.get(b)),
+                }
+            }
+            _ => LatticeValue::Overdefined,
+        }
+    }
+
+    /// Determine how `inst` can be simplified, if at all, given the results of the analysis
+    fn fold(&self, dfg: &DataFlowGraph, inst: Inst) -> Option<Fold> {
+        let pool = &dfg.value_lists;
+        match &dfg[inst] {
+            // Constants are already as simple as they can get
+            Instruction::UnaryOpImm(_) => None,
+            Instruction::CondBr(CondBr {
+                cond,
+                then_dest,
+                else_dest,
+                ..
+            }) => {
+                let (dest, args) = if self.constant(*cond)?.as_bool()? {
+                    then_dest
+                } else {
+                    else_dest
+                };
+                Some(Fold::Branch(
+                    *dest,
+                    SmallVec::from_slice(args.as_slice(pool)),
+                ))
+            }
+            Instruction::Switch(Switch {
+                arg, arms, default, ..
+            }) => {
+                let (dest, args) = switch_target(self.constant(*arg)?, arms, default);
+                Some(Fold::Branch(
+                    dest,
+                    SmallVec::from_slice(args.as_slice(pool)),
+                ))
+            }
+            ix if ix.has_side_effects() => None,
+            _ => match dfg.inst_results(inst) {
+                [result] => {
+                    let imm = self.constant(*result)?;
+                    // We can only materialize constants for which there is an instruction
+                    imm_opcode(imm)?;
+                    Some(Fold::Constant(imm))
+                }
+                _ => None,
+            },
+        }
+    }
+
+    /// Rewrite the function based on the results of the analysis, returning true if
+    /// any changes were made.
+    fn rewrite(&self, dfg: &mut DataFlowGraph) -> bool {
+        let mut changed = false;
+        let blocks = dfg.blocks().map(|(b, _)| b).collect::<Vec<_>>();
+
+        // Fold instructions in executable blocks
+        for block in blocks.iter().copied() {
+            if !self.executable.contains(&block) {
+                continue;
+            }
+            let insts = dfg.block_insts(block).collect::<SmallVec<[Inst; 16]>>();
+            for inst in insts {
+                let span = dfg.inst_span(inst);
+                match self.fold(dfg, inst) {
+                    None => continue,
+                    Some(Fold::Constant(imm)) => {
+                        let op = imm_opcode(imm).unwrap();
+                        dfg.replace(inst).UnaryImm(op, imm.ty(), imm, span);
+                    }
+                    Some(Fold::Branch(dest, args)) => {
+                        dfg.replace(inst).br(dest, &args, span);
+                    }
+                }
+                changed = true;
+            }
+        }
+
+        // Replace used block parameters which are constant with the constant itself
+        let mut used = FxHashSet::<Value>::default();
+        for block in blocks.iter().copied() {
+            if !self.executable.contains(&block) {
+                continue;
+            }
+            for inst in dfg.block_insts(block) {
+                used.extend(dfg.inst_args(inst).iter().copied());
+                match dfg.analyze_branch(inst) {
+                    BranchInfo::NotABranch => (),
+                    BranchInfo::SingleDest(_, args) => used.extend(args.iter().copied()),
+                    BranchInfo::MultiDest(jts) => {
+                        for jt in jts.into_iter() {
+                            used.extend(jt.args.iter().copied());
+                        }
+                    }
+                }
+            }
+        }
+        let mut rewrites = SmallVec::<[(Value, Value); 4]>::default();
+        for block in blocks.iter().copied() {
+            if !self.executable.contains(&block) {
+                continue;
+            }
+            let params = SmallVec::<[Value; 4]>::from_slice(dfg.block_params(block));
+            for param in params.into_iter() {
+                if !used.contains(&param) {
+                    continue;
+                }
+                let Some(imm) = self.constant(param) else {
+                    continue;
+                };
+                let Some(op) = imm_opcode(imm) else {
+                    continue;
+                };
+                let span = dfg.value_span(param);
+                let inst = dfg.insert_inst(
+                    InsertionPoint::before(ProgramPoint::Block(block)),
+                    Instruction::UnaryOpImm(UnaryOpImm {
+                        op,
+                        overflow: Overflow::default(),
+                        imm,
+                    }),
+                    imm.ty(),
+                    span,
+                );
+                rewrites.push((param, dfg.first_result(inst)));
+            }
+        }
+        if !rewrites.is_empty() {
+            for block in blocks.iter().copied() {
+                if !self.executable.contains(&block) {
+                    continue;
+                }
+                let insts = dfg.block_insts(block).collect::<SmallVec<[Inst; 16]>>();
+                for inst in insts {
+                    for (param, replacement) in rewrites.iter().copied() {
+                        dfg.replace_uses(inst, param, replacement);
+                    }
+                }
+            }
+            changed = true;
+        }
+
+        // Remove blocks which can never be reached
+        for block in blocks.into_iter() {
+            if !self.executable.contains(&block) {
+                dfg.detach_block(block);
+                changed = true;
+            }
+        }
+
+        changed
+    }
+}
+
+fn switch_target<'a>(
+    selector: Immediate,
+    arms: &'a [(u32, Block, ValueList)],
+    default: &'a (Block, ValueList),
+) -> (Block, &'a ValueList) {
+    selector
+        .as_u32()
+        .and_then(|selector| arms.iter().find(|(value, _, _)| *value == selector))
+        .map(|(_, dest, args)| (*dest, args))
+        .unwrap_or((default.0, &default.1))
+}
+
+/// Get the opcode used to materialize `imm`, if one exists
+pub(crate) fn imm_opcode(imm: Immediate) -> Option<Opcode> {
+    match imm {
+        Immediate::I1(_) => Some(Opcode::ImmI1),
+        Immediate::U8(_) => Some(Opcode::ImmU8),
+        Immediate::I8(_) => Some(Opcode::ImmI8),
+        Immediate::U16(_) => Some(Opcode::ImmU16),
+        Immediate::I16(_) => Some(Opcode::ImmI16),
+        Immediate::U32(_) => Some(Opcode::ImmU32),
+        Immediate::I32(_) => Some(Opcode::ImmI32),
+        Immediate::U64(_) => Some(Opcode::ImmU64),
+        Immediate::I64(_) => Some(Opcode::ImmI64),
+        Immediate::Felt(_) => Some(Opcode::ImmFelt),
+        Immediate::F64(_) => Some(Opcode::ImmF64),
+        Immediate::I128(_) => None,
+    }
+}
+
+/// Get the width in bits of the integral type `ty`
+fn bit_width(ty: &Type) -> Option<u32> {
+    match ty {
+        Type::I1 => Some(1),
+        Type::U8 | Type::I8 => Some(8),
+        Type::U16 | Type::I16 => Some(16),
+        Type::U32 | Type::I32 => Some(32),
+        Type::U64 | Type::I64 => Some(64),
+        Type::I128 => Some(128),
+        _ => None,
+    }
+}
+
+/// Get the bit pattern of `value` as an integer of `bits` width
+fn to_bits(value: i128, bits: u32) -> u128 {
+    (value as u128) & (u128::MAX >> (128 - bits))
+}
+
+/// Interpret the low `bits` of `pattern` as a two's complement signed integer
+fn sign_extend(pattern: u128, bits: u32) -> i128 {
+    let shift = 128 - bits;
+    ((pattern << shift) as i128) >> shift
+}
+
+/// Construct an immediate of type `ty` from the exact result of an operation.
+///
+/// If `value` is out of range for `ty`, it is wrapped if `overflow` has wrapping
+/// semantics, otherwise the result cannot be folded.
+fn make_imm(ty: &Type, value: i128, overflow: Overflow) -> Option<Immediate> {
+    let bits = bit_width(ty)?;
+    let (min, max) = if ty.is_signed_integer() {
+        (
+            sign_extend(1 << (bits - 1), bits),
+            (to_bits(-1, bits) >> 1) as i128,
+        )
+    } else {
+        (0, to_bits(-1, bits) as i128)
+    };
+    if (value < min || value > max) && !matches!(overflow, Overflow::Wrapping) {
+        return None;
+    }
+    Some(match ty {
+        Type::I1 => Immediate::I1(value & 1 == 1),
+        Type::U8 => Immediate::U8(value as u8),
+        Type::I8 => Immediate::I8(value as i8),
+        Type::U16 => Immediate::U16(value as u16),
+        Type::I16 => Immediate::I16(value as i16),
+        Type::U32 => Immediate::U32(value as u32),
+        Type::I32 => Immediate::I32(value as i32),
+        Type::U64 => Immediate::U64(value as u64),
+        Type::I64 => Immediate::I64(value as i64),
+        Type::I128 => Immediate::I128(value),
+        _ => unreachable!(),
+    })
+}
+
+/// Evaluate the unary operator `op` on `arg`, producing a value of type `ty`
+fn fold_unary(op: Opcode, overflow: Overflow, arg: Immediate, ty: &Type) -> Option<Immediate> {
+    if overflow.is_overflowing() {
+        return None;
+    }
+
+    if let Immediate::Felt(felt) = arg {
+        return match op {
+            Opcode::Neg => Some(Immediate::Felt(-felt)),
+            Opcode::Incr => Some(Immediate::Felt(felt + Felt::ONE)),
+            Opcode::Cast => make_imm(ty, felt.as_int() as i128, Overflow::Checked),
+            _ => None,
+        };
+    }
+
+    let bits = bit_width(&arg.ty())?;
+    let value = arg.as_i128()?;
+    match op {
+        Opcode::Neg => make_imm(ty, value.checked_neg()?, overflow),
+        Opcode::Incr => make_imm(ty, value.checked_add(1)?, overflow),
+        Opcode::Pow2 => make_imm(ty, 2i128.checked_pow(u32::try_from(value).ok()?)?, overflow),
+        Opcode::Not => Some(Immediate::I1(value == 0)),
+        Opcode::IsOdd => Some(Immediate::I1(value & 1 == 1)),
+        Opcode::Bnot => make_imm(
+            ty,
+            sign_extend(!to_bits(value, bits), bits),
+            Overflow::Wrapping,
+        ),
+        Opcode::Popcnt => make_imm(ty, to_bits(value, bits).count_ones() as i128, overflow),
+        // Truncation discards the high bits by definition
+        Opcode::Trunc => make_imm(ty, value, Overflow::Wrapping),
+        Opcode::Zext => make_imm(ty, to_bits(value, bits) as i128, Overflow::Checked),
+        Opcode::Sext => make_imm(
+            ty,
+            sign_extend(to_bits(value, bits), bits),
+            Overflow::Wrapping,
+        ),
+        // Casts are only folded when the value is representable in the target type
+        Opcode::Cast => match ty {
+            Type::Felt => u64::try_from(value)
+                .ok()
+                .filter(|v| *v < Felt::MODULUS)
+                .map(|v| Immediate::Felt(Felt::new(v))),
+            _ => make_imm(ty, value, Overflow::Checked),
+        },
+        _ => None,
+    }
+}
+
+/// Evaluate the binary operator `op` on `lhs` and `rhs`, producing a value of type `ty`
+fn fold_binary(
+    op: Opcode,
+    overflow: Overflow,
+    lhs: Immediate,
+    rhs: Immediate,
+    ty: &Type,
+) -> Option<Immediate> {
+    if overflow.is_overflowing() {
+        return None;
+    }
+
+    if let Immediate::Felt(a) = lhs {
+        let b = match rhs {
+            Immediate::Felt(b) => b,
+            imm => Felt::new(imm.as_u64().filter(|v| *v < Felt::MODULUS)?),
+        };
+        return match op {
+            Opcode::Add => Some(Immediate::Felt(a + b)),
+            Opcode::Sub => Some(Immediate::Felt(a - b)),
+            Opcode::Mul => Some(Immediate::Felt(a * b)),
+            Opcode::Eq => Some(Immediate::I1(a == b)),
+            Opcode::Neq => Some(Immediate::I1(a != b)),
+            Opcode::Gt => Some(Immediate::I1(a.as_int() > b.as_int())),
+            Opcode::Gte => Some(Immediate::I1(a.as_int() >= b.as_int())),
+            Opcode::Lt => Some(Immediate::I1(a.as_int() < b.as_int())),
+            Opcode::Lte => Some(Immediate::I1(a.as_int() <= b.as_int())),
+            _ => None,
+        };
+    }
+
+    let bits = bit_width(&lhs.ty())?;
+    let a = lhs.as_i128()?;
+    let b = match rhs {
+        Immediate::F64(_) => return None,
+        rhs => rhs.as_i128()?,
+    };
+    let value = match op {
+        Opcode::Add => a.checked_add(b)?,
+        Opcode::Sub => a.checked_sub(b)?,
+        Opcode::Mul => a.checked_mul(b)?,
+        // Division by zero traps, and we only fold unsigned division to avoid
+        // depending on the rounding behavior of signed division
+        Opcode::Div if b != 0 && !ty.is_signed_integer() => a / b,
+        Opcode::Mod if b != 0 && !ty.is_signed_integer() => a % b,
+        Opcode::Exp => a.checked_pow(u32::try_from(b).ok()?)?,
+        Opcode::Min => a.min(b),
+        Opcode::Max => a.max(b),
+        Opcode::And | Opcode::Band => a & b,
+        Opcode::Or | Opcode::Bor => a | b,
+        Opcode::Xor | Opcode::Bxor => a ^ b,
+        Opcode::Shl => {
+            let shift = u32::try_from(b).ok().filter(|s| *s < bits)?;
+            a.checked_mul(2i128.checked_pow(shift)?)?
+        }
+        Opcode::Shr => {
+            let shift = u32::try_from(b).ok().filter(|s| *s < bits)?;
+            a >> shift
+        }
+        Opcode::Rotl | Opcode::Rotr => {
+            let shift = u32::try_from(b).ok()? % bits;
+            let pattern = to_bits(a, bits);
+            let shift = if op == Opcode::Rotl {
+                shift
+            } else {
+                (bits - shift) % bits
+            };
+            let rotated = if shift == 0 {
+                pattern
+            } else {
+                to_bits(
+                    ((pattern << shift) | (pattern >> (bits - shift))) as i128,
+                    bits,
+                )
+            };
+            return make_imm(ty, rotated as i128, Overflow::Wrapping);
+        }
+        Opcode::Eq => return Some(Immediate::I1(a == b)),
+        Opcode::Neq => return Some(Immediate::I1(a != b)),
+        Opcode::Gt => return Some(Immediate::I1(a > b)),
+        Opcode::Gte => return Some(Immediate::I1(a >= b)),
+        Opcode::Lt => return Some(Immediate::I1(a < b)),
+        Opcode::Lte => return Some(Immediate::I1(a <= b)),
+        _ => return None,
+    };
+    make_imm(ty, value, overflow)
+}
+
+#[cfg(test)]
+mod tests {
+    use miden_hir::{
+        AbiParam, Function, FunctionBuilder, Immediate, InstBuilder, Signature, SourceSpan, Type,
+    };
+    use miden_hir_analysis::FunctionAnalysis;
+    use pretty_assertions::assert_eq;
+
+    use crate::{RewritePass, Sccp};
+
+    /// Constants should be folded through arithmetic and comparisons, and a conditional
+    /// branch on a known condition should become an unconditional branch, removing the
+    /// block which can no longer be reached. The constant block argument is propagated
+    /// into the successor.
+    #[test]
+    fn sccp_folds_constant_branch() {
+        let id = "test::sccp".parse().unwrap();
+        let mut function = Function::new(
+            id,
+            Signature::new([AbiParam::new(Type::U32)], [AbiParam::new(Type::U32)]),
+        );
+
+        {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let entry = builder.current_block();
+            let v0 = builder.block_params(entry)[0];
+
+            let a = builder.create_block();
+            let v1 = builder.append_block_param(a, Type::U32, SourceSpan::UNKNOWN);
+            let b = builder.create_block();
+
+            // entry
+            let v2 = builder.ins().u32(2, SourceSpan::UNKNOWN);
+            let v3 = builder
+                .ins()
+                .add_imm(v2, Immediate::U32(3), SourceSpan::UNKNOWN);
+            let v4 = builder
+                .ins()
+                .gt_imm(v3, Immediate::U32(4), SourceSpan::UNKNOWN);
+            builder
+                .ins()
+                .cond_br(v4, a, &[v3], b, &[], SourceSpan::UNKNOWN);
+
+            // block1
+            builder.switch_to_block(a);
+            let v5 = builder.ins().add(v1, v0, SourceSpan::UNKNOWN);
+            builder.ins().ret(Some(v5), SourceSpan::UNKNOWN);
+
+            // block2
+            builder.switch_to_block(b);
+            builder.ins().ret(Some(v0), SourceSpan::UNKNOWN);
+        }
+
+        let mut analysis = FunctionAnalysis::new(&function);
+        let mut pass = Sccp;
+        pass.run(&mut function, &mut analysis)
+            .expect("constant propagation failed");
+
+        let expected = "pub fn sccp(u32) -> u32 {
+block0(v0: u32):
+    v2 = const.u32 2  : u32
+    v3 = const.u32 5  : u32
+    v4 = const.i1 true  : i1
+    br block1(v3)
+
+block1(v1: u32):
+    v6 = const.u32 5  : u32
+    v5 = add v6, v0  : u32
+    ret v5
+}
+";
+
+        assert_eq!(function.to_string().as_str(), expected);
+    }
+
+    /// Operations are only folded when the result is defined by the overflow mode
+    /// of the instruction: checked and unchecked operations which overflow are left
+    /// alone, while wrapping operations are wrapped to the width of their type.
+    #[test]
+    fn sccp_honors_overflow_mode() {
+        let id = "test::sccp_overflow".parse().unwrap();
+        let mut function = Function::new(
+            id,
+            Signature::new([AbiParam::new(Type::U8)], [AbiParam::new(Type::U8)]),
+        );
+
+        {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let v1 = builder.ins().u8(255, SourceSpan::UNKNOWN);
+            builder
+                .ins()
+                .add_imm_checked(v1, Immediate::U8(1), SourceSpan::UNKNOWN);
+            let v3 = builder
+                .ins()
+                .add_imm_wrapping(v1, Immediate::U8(1), SourceSpan::UNKNOWN);
+            builder
+                .ins()
+                .add_imm(v1, Immediate::U8(1), SourceSpan::UNKNOWN);
+            let v5 = builder.ins().i8(-128, SourceSpan::UNKNOWN);
+            builder
+                .ins()
+                .sub_imm_wrapping(v5, Immediate::I8(1), SourceSpan::UNKNOWN);
+            builder.ins().ret(Some(v3), SourceSpan::UNKNOWN);
+        }
+
+        let mut analysis = FunctionAnalysis::new(&function);
+        let mut pass = Sccp;
+        pass.run(&mut function, &mut analysis)
+            .expect("constant propagation failed");
+
+        let expected = "pub fn sccp_overflow(u8) -> u8 {
+block0(v0: u8):
+    v1 = const.u8 255  : u8
+    v2 = add.checked v1, 1  : u8
+    v3 = const.u8 0  : u8
+    v4 = add v1, 1  : u8
+    v5 = const.i8 -128  : i8
+    v6 = const.i8 127  : i8
+    ret v3
+}
+";
+
+        assert_eq!(function.to_string().as_str(), expected);
+    }
+
+    /// A value which is constant around a loop back-edge should be discovered as such,
+    /// even though the loop header has multiple predecessors.
+    #[test]
+    fn sccp_propagates_through_loops() {
+        let id = "test::sccp_loop".parse().unwrap();
+        let mut function = Function::new(
+            id,
+            Signature::new([AbiParam::new(Type::U32)], [AbiParam::new(Type::U32)]),
+        );
+
+        {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let entry = builder.current_block();
+            let v0 = builder.block_params(entry)[0];
+
+            let header = builder.create_block();
+            let v1 = builder.append_block_param(header, Type::U32, SourceSpan::UNKNOWN);
+            let v2 = builder.append_block_param(header, Type::U32, SourceSpan::UNKNOWN);
+            let exit = builder.create_block();
+            let v3 = builder.append_block_param(exit, Type::U32, SourceSpan::UNKNOWN);
+
+            // entry
+            let v4 = builder.ins().u32(1, SourceSpan::UNKNOWN);
+            builder.ins().br(header, &[v0, v4], SourceSpan::UNKNOWN);
+
+            // block1
+            builder.switch_to_block(header);
+            let v5 = builder.ins().mul(v2, v4, SourceSpan::UNKNOWN);
+            let v6 = builder.ins().add(v1, v5, SourceSpan::UNKNOWN);
+            let v7 = builder
+                .ins()
+                .lt_imm(v6, Immediate::U32(10), SourceSpan::UNKNOWN);
+            builder
+                .ins()
+                .cond_br(v7, header, &[v6, v5], exit, &[v5], SourceSpan::UNKNOWN);
+
+            // block2
+            builder.switch_to_block(exit);
+            builder.ins().ret(Some(v3), SourceSpan::UNKNOWN);
+        }
+
+        let mut analysis = FunctionAnalysis::new(&function);
+        let mut pass = Sccp;
+        pass.run(&mut function, &mut analysis)
+            .expect("constant propagation failed");
+
+        let expected = "pub fn sccp_loop(u32) -> u32 {
+block0(v0: u32):
+    v4 = const.u32 1  : u32
+    br block1(v0, v4)
+
+block1(v1: u32, v2: u32):
+    v5 = const.u32 1  : u32
+    v6 = add v1, v5  : u32
+    v7 = lt v6, 10  : i1
+    condbr v7, block1(v6, v5), block2(v5)
+
+block2(v3: u32):
+    v8 = const.u32 1  : u32
+    ret v8
+}
+";
+
+        assert_eq!(function.to_string().as_str(), expected);
+    }
+}
@@ -0,0 +1,514 @@
+use rustc_hash::{FxHashMap, FxHashSet};
+use smallvec::SmallVec;
+
+use miden_hir::{self as hir, *};
+use miden_hir_analysis::{FunctionAnalysis, PreservedAnalyses};
+
+use crate::{
+    adt::ScopedMap,
+    dce::{reachable_blocks, successors},
+    inline_blocks::rewrite_use,
+    RewritePass,
+};
+
+/// This pass operates on the SSA IR, and simplifies the control flow graph of a function,
+/// with the goal of reducing the number of blocks before [crate::Treeify] has to duplicate
+/// any of them.
+///
+/// Specifically, it performs the following, until no further changes can be made:
+///
+/// * Blocks which are unreachable from the entry block are detached from the function
+/// * A `condbr` whose destinations, and the arguments passed to them, are identical is
+/// replaced with a `br` to that destination.
+/// * Edges to a block which consists solely of a `br` to another block are forwarded to
+/// the destination of that `br`, substituting the block arguments of the edge for uses of
+/// the block parameters in the arguments of the `br`.
+/// * A block whose only predecessor is a `br` is merged into the predecessor, replacing uses
+/// of its block parameters with the arguments of the `br`.
+pub struct SimplifyCfg;
+impl RewritePass for SimplifyCfg {
+    type Error = anyhow::Error;
+
+    fn name(&self) -> &'static str {
+        "simplify-cfg"
+    }
+
+    fn run(
+        &mut self,
+        function: &mut hir::Function,
+        _analysis: &mut FunctionAnalysis,
+    ) -> Result<PreservedAnalyses, Self::Error> {
+        let mut changed = false;
+        while simplify(&mut function.dfg) {
+            changed = true;
+        }
+
+        if changed {
+            Ok(PreservedAnalyses::empty())
+        } else {
+            Ok(PreservedAnalyses::all())
+        }
+    }
+}
+
+/// Perform a single round of simplification of the CFG, returning true if anything changed.
+///
+/// Any block which is modified in this round is not considered for further changes until the
+/// next round, as the predecessors computed at the start of the round may no longer be accurate.
+fn simplify(dfg: &mut DataFlowGraph) -> bool {
+    let entry = dfg.entry_block();
+    let mut changed = false;
+
+    // Detach blocks which are unreachable from the entry block
+    let reachable = reachable_blocks(dfg);
+    let blocks = dfg.blocks().map(|(b, _)| b).collect::<Vec<_>>();
+    let mut live_blocks = Vec::with_capacity(blocks.len());
+    for block in blocks.into_iter() {
+        if reachable.contains(&block) {
+            live_blocks.push(block);
+        } else {
+            dfg.detach_block(block);
+            changed = true;
+        }
+    }
+
+    // Fold conditional branches whose destinations are identical
+    for block in live_blocks.iter().copied() {
+        let inst = dfg.last_inst(block).unwrap();
+        let folded = match &dfg[inst] {
+            Instruction::CondBr(CondBr {
+                then_dest: (then_dest, then_args),
+                else_dest: (else_dest, else_args),
+                ..
+            }) if then_dest == else_dest => {
+                let then_args = then_args.as_slice(&dfg.value_lists);
+                let else_args = else_args.as_slice(&dfg.value_lists);
+                (then_args == else_args)
+                    .then(|| (*then_dest, SmallVec::<[Value; 4]>::from_slice(then_args)))
+            }
+            _ => None,
+        };
+        if let Some((dest, args)) = folded {
+            let span = dfg.inst_span(inst);
+            dfg.replace(inst).br(dest, &args, span);
+            changed = true;
+        }
+    }
+
+    // Determine the predecessors of each block
+    let mut preds = FxHashMap::<Block, SmallVec<[Inst; 2]>>::default();
+    for block in live_blocks.iter().copied() {
+        let inst = dfg.last_inst(block).unwrap();
+        for dest in successors(dfg, inst) {
+            let block_preds = preds.entry(dest).or_default();
+            if block_preds.last() != Some(&inst) {
+                block_preds.push(inst);
+            }
+        }
+    }
+
+    // Determine which blocks have parameters that are used outside of the block itself
+    let mut escaping = FxHashSet::<Block>::default();
+    for block in live_blocks.iter().copied() {
+        for inst in dfg.block_insts(block) {
+            for value in used_values(dfg, inst) {
+                if let ValueData::Param { block: owner, .. } = dfg.value_data(value) {
+                    if *owner != block {
+                        escaping.insert(*owner);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut touched = FxHashSet::<Block>::default();
+
+    // Forward edges through blocks which consist solely of an unconditional branch
+    for block in live_blocks.iter().copied() {
+        // A block whose parameters are used by the blocks it dominates cannot be forwarded
+        // through, as those uses would be left without a definition once it is removed
+        if block == entry || touched.contains(&block) || escaping.contains(&block) {
+            continue;
+        }
+        let Some((dest, args)) = forwarding_target(dfg, block) else {
+            continue;
+        };
+        // Forward to the end of a chain of such blocks one step at a time, starting from
+        // the end, so that a cycle of them is left alone rather than forwarded forever
+        if touched.contains(&dest) || forwarding_target(dfg, dest).is_some() {
+            continue;
+        }
+        let params = SmallVec::<[Value; 4]>::from_slice(dfg.block_params(block));
+        let mut forwarded = false;
+        for pred in preds.get(&block).into_iter().flatten().copied() {
+            let pred_block = dfg.inst_block(pred).unwrap();
+            if touched.contains(&pred_block) {
+                continue;
+            }
+            forward_edges(dfg, pred, block, dest, &params, &args);
+            touched.insert(pred_block);
+            forwarded = true;
+        }
+        if forwarded {
+            touched.insert(block);
+            touched.insert(dest);
+            changed = true;
+        }
+    }
+
+    // Merge blocks into their only predecessor, when that predecessor unconditionally
+    // branches to them
+    let mut rewrites = FxHashMap::<Value, Value>::default();
+    for block in live_blocks.iter().copied() {
+        if block == entry || touched.contains(&block) {
+            continue;
+        }
+        let pred = match preds.get(&block).map(|preds| preds.as_slice()) {
+            Some([pred]) => *pred,
+            _ => continue,
+        };
+        let pred_block = dfg.inst_block(pred).unwrap();
+        if pred_block == block || touched.contains(&pred_block) {
+            continue;
+        }
+        let Instruction::Br(Br {
+            op: Opcode::Br,
+            args,
+            ..
+        }) = &dfg[pred]
+        else {
+            continue;
+        };
+
+        for (param, arg) in dfg
+            .block_params(block)
+            .iter()
+            .copied()
+            .zip(args.as_slice(&dfg.value_lists).iter().copied())
+        {
+            rewrites.insert(param, arg);
+        }
+
+        // Replace the branch with the contents of the merged block
+        let mut insts = dfg.blocks[block].insts.take();
+        dfg.blocks[pred_block].insts.pop_back();
+        while let Some(inst) = insts.pop_front() {
+            let key = inst.key;
+            dfg.blocks[pred_block].insts.push_back(inst);
+            dfg.insts[key].block = pred_block;
+        }
+        dfg.detach_block(block);
+
+        touched.insert(block);
+        touched.insert(pred_block);
+        changed = true;
+    }
+
+    // Replace uses of the parameters of merged blocks
+    if !rewrites.is_empty() {
+        // The argument for a merged block parameter may itself be a parameter of
+        // another block merged in this round, so make sure we rewrite to the final value
+        let mut resolved = ScopedMap::<Value, Value>::default();
+        for (param, arg) in rewrites.iter() {
+            let mut value = *arg;
+            while let Some(next) = rewrites.get(&value) {
+                value = *next;
+            }
+            resolved.insert(*param, value);
+        }
+        let blocks = dfg.blocks().map(|(b, _)| b).collect::<Vec<_>>();
+        for block in blocks.into_iter() {
+            let insts = dfg.block_insts(block).collect::<SmallVec<[Inst; 16]>>();
+            for inst in insts {
+                rewrite_use(dfg.insts[inst].as_mut(), &mut dfg.value_lists, &resolved);
+            }
+        }
+    }
+
+    changed
+}
+
+/// If `block` consists solely of a `br` to some other block, return the destination and
+/// the arguments passed to it.
+fn forwarding_target(dfg: &DataFlowGraph, block: Block) -> Option<(Block, SmallVec<[Value; 4]>)> {
+    let inst = dfg.last_inst(block)?;
+    if dfg.block_insts(block).next() != Some(inst) {
+        return None;
+    }
+    match &dfg[inst] {
+        Instruction::Br(Br {
+            op: Opcode::Br,
+            destination,
+            args,
+        }) if *destination != block => Some((
+            *destination,
+            SmallVec::from_slice(args.as_slice(&dfg.value_lists)),
+        )),
+        _ => None,
+    }
+}
+
+/// Returns all of the values used by `inst`, including the arguments it passes to its successors
+fn used_values(dfg: &DataFlowGraph, inst: Inst) -> SmallVec<[Value; 4]> {
+    let mut values = SmallVec::<[Value; 4]>::from_slice(dfg.inst_args(inst));
+    match dfg.analyze_branch(inst) {
+        BranchInfo::NotABranch => (),
+        BranchInfo::SingleDest(_, args) => values.extend_from_slice(args),
+        BranchInfo::MultiDest(jts) => {
+            for jt in jts.into_iter() {
+                values.extend_from_slice(jt.args);
+            }
+        }
+    }
+    values
+}
+
+/// Redirect all edges from `inst` to `block` so that they go to `dest` instead
+///
+/// `params` are the parameters of `block`, and `args` are the arguments `block` passes to
+/// `dest`. The arguments of each forwarded edge are computed by substituting the arguments
+/// of the original edge for the parameters of `block`.
+fn forward_edges(
+    dfg: &mut DataFlowGraph,
+    inst: Inst,
+    block: Block,
+    dest: Block,
+    params: &[Value],
+    args: &[Value],
+) {
+    let substitute = |edge_args: &[Value]| -> SmallVec<[Value; 4]> {
+        args.iter()
+            .map(|arg| match params.iter().position(|p| p == arg) {
+                Some(index) => edge_args[index],
+                None => *arg,
+            })
+            .collect()
+    };
+
+    dfg.visit_edges_to(inst, block, |destination, edge_args, pool| {
+        let forwarded = substitute(edge_args.as_slice(pool));
+        *destination = dest;
+        *edge_args = ValueList::from_slice(&forwarded, pool);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use miden_hir::{
+        AbiParam, Function, FunctionBuilder, Immediate, InstBuilder, Signature, SourceSpan, Type,
+    };
+    use miden_hir_analysis::FunctionAnalysis;
+    use pretty_assertions::assert_eq;
+
+    use crate::{RewritePass, SimplifyCfg};
+
+    /// Empty blocks are forwarded through, which exposes a `condbr` with identical
+    /// destinations that is folded into a `br`, and the resulting chain of blocks is
+    /// then merged, along with their block arguments, while unreachable blocks are removed.
+    #[test]
+    fn simplify_cfg_merges_blocks() {
+        let id = "test::simplify".parse().unwrap();
+        let mut function = Function::new(
+            id,
+            Signature::new(
+                [AbiParam::new(Type::U32), AbiParam::new(Type::U32)],
+                [AbiParam::new(Type::U32)],
+            ),
+        );
+
+        {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let entry = builder.current_block();
+            let (v0, v1) = {
+                let args = builder.block_params(entry);
+                (args[0], args[1])
+            };
+            let a = builder.create_block();
+            let b = builder.create_block();
+            let c = builder.create_block();
+            let d = builder.create_block();
+            let e = builder.create_block();
+
+            // entry
+            let v2 = builder.ins().eq(v0, v1, SourceSpan::UNKNOWN);
+            builder
+                .ins()
+                .cond_br(v2, a, &[], b, &[], SourceSpan::UNKNOWN);
+            let v3 = builder.append_block_param(c, Type::U32, SourceSpan::UNKNOWN);
+
+            // block1
+            builder.switch_to_block(a);
+            builder.ins().br(c, &[v0], SourceSpan::UNKNOWN);
+
+            // block2
+            builder.switch_to_block(b);
+            builder.ins().br(c, &[v0], SourceSpan::UNKNOWN);
+
+            // block3
+            builder.switch_to_block(c);
+            let v4 = builder.ins().add(v3, v1, SourceSpan::UNKNOWN);
+            let v5 = builder.append_block_param(d, Type::U32, SourceSpan::UNKNOWN);
+            builder.ins().br(d, &[v4], SourceSpan::UNKNOWN);
+
+            // block4
+            builder.switch_to_block(d);
+            let v6 = builder.ins().mul(v5, v5, SourceSpan::UNKNOWN);
+            builder.ins().ret(Some(v6), SourceSpan::UNKNOWN);
+
+            // block5
+            builder.switch_to_block(e);
+            builder.ins().ret(Some(v0), SourceSpan::UNKNOWN);
+        }
+
+        let mut analysis = FunctionAnalysis::new(&function);
+        let mut pass = SimplifyCfg;
+        pass.run(&mut function, &mut analysis)
+            .expect("cfg simplification failed");
+
+        let expected = "pub fn simplify(u32, u32) -> u32 {
+block0(v0: u32, v1: u32):
+    v2 = eq v0, v1  : i1
+    v4 = add v0, v1  : u32
+    v6 = mul v4, v4  : u32
+    ret v6
+}
+";
+
+        assert_eq!(function.to_string().as_str(), expected);
+    }
+
+    /// An empty block on the back edge of a loop is forwarded through, but the loop
+    /// header is never merged into its predecessor, as it has more than one.
+    #[test]
+    fn simplify_cfg_loop() {
+        let id = "test::simplify_loop".parse().unwrap();
+        let mut function = Function::new(
+            id,
+            Signature::new([AbiParam::new(Type::U32)], [AbiParam::new(Type::U32)]),
+        );
+
+        {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let entry = builder.current_block();
+            let v0 = builder.block_params(entry)[0];
+            let header = builder.create_block();
+            let v1 = builder.append_block_param(header, Type::U32, SourceSpan::UNKNOWN);
+            let latch = builder.create_block();
+            let exit = builder.create_block();
+
+            // entry
+            builder.ins().br(header, &[v0], SourceSpan::UNKNOWN);
+
+            // block1
+            builder.switch_to_block(header);
+            let v2 = builder
+                .ins()
+                .add_imm(v1, Immediate::U32(1), SourceSpan::UNKNOWN);
+            let v3 = builder
+                .ins()
+                .lt_imm(v2, Immediate::U32(10), SourceSpan::UNKNOWN);
+            builder
+                .ins()
+                .cond_br(v3, latch, &[], exit, &[], SourceSpan::UNKNOWN);
+
+            // block2
+            builder.switch_to_block(latch);
+            builder.ins().br(header, &[v2], SourceSpan::UNKNOWN);
+
+            // block3
+            builder.switch_to_block(exit);
+            builder.ins().ret(Some(v2), SourceSpan::UNKNOWN);
+        }
+
+        let mut analysis = FunctionAnalysis::new(&function);
+        let mut pass = SimplifyCfg;
+        pass.run(&mut function, &mut analysis)
+            .expect("cfg simplification failed");
+
+        let expected = "pub fn simplify_loop(u32) -> u32 {
+block0(v0: u32):
+    br block1(v0)
+
+block1(v1: u32):
+    v2 = add v1, 1  : u32
+    v3 = lt v2, 10  : i1
+    condbr v3, block1(v2), block3
+
+block3:
+    ret v2
+}
+";
+
+        assert_eq!(function.to_string().as_str(), expected);
+    }
+
+    /// A block consisting solely of a `br` is not forwarded through when one of its
+    /// parameters is used by a block it dominates, as that would leave the use undefined.
+    /// Instead, its successor is merged into it.
+    #[test]
+    fn simplify_cfg_does_not_forward_escaping_params() {
+        let id = "test::simplify_escaping".parse().unwrap();
+        let mut function = Function::new(
+            id,
+            Signature::new(
+                [AbiParam::new(Type::U32), AbiParam::new(Type::U32)],
+                [AbiParam::new(Type::U32)],
+            ),
+        );
+
+        {
+            let mut builder = FunctionBuilder::new(&mut function);
+            let entry = builder.current_block();
+            let (v0, v1) = {
+                let args = builder.block_params(entry);
+                (args[0], args[1])
+            };
+            let a = builder.create_block();
+            let b = builder.create_block();
+            let join = builder.create_block();
+            let v3 = builder.append_block_param(join, Type::U32, SourceSpan::UNKNOWN);
+            let exit = builder.create_block();
+
+            // entry
+            let v2 = builder.ins().eq(v0, v1, SourceSpan::UNKNOWN);
+            builder
+                .ins()
+                .cond_br(v2, a, &[], b, &[], SourceSpan::UNKNOWN);
+
+            // block1
+            builder.switch_to_block(a);
+            builder.ins().br(join, &[v0], SourceSpan::UNKNOWN);
+
+            // block2
+            builder.switch_to_block(b);
+            builder.ins().br(join, &[v1], SourceSpan::UNKNOWN);
+
+            // block3
+            builder.switch_to_block(join);
+            builder.ins().br(exit, &[], SourceSpan::UNKNOWN);
+
+            // block4
+            builder.switch_to_block(exit);
+            let v4 = builder.ins().add(v3, v3, SourceSpan::UNKNOWN);
+            builder.ins().ret(Some(v4), SourceSpan::UNKNOWN);
+        }
+
+        let mut analysis = FunctionAnalysis::new(&function);
+        let mut pass = SimplifyCfg;
+        pass.run(&mut function, &mut analysis)
+            .expect("cfg simplification failed");
+
+        let expected = "pub fn simplify_escaping(u32, u32) -> u32 {
+block0(v0: u32, v1: u32):
+    v2 = eq v0, v1  : i1
+    condbr v2, block3(v0), block3(v1)
+
+block3(v3: u32):
+    v4 = add v3, v3  : u32
+    ret v4
+}
+";
+
+        assert_eq!(function.to_string().as_str(), expected);
+    }
+}
@@ -4,7 +4,7 @@ use rustc_hash::FxHashSet;
 use smallvec::SmallVec;
 
 use miden_hir::{self as hir, Block as BlockId, *};
-use miden_hir_analysis::FunctionAnalysis;
+use miden_hir_analysis::{FunctionAnalysis, PreservedAnalyses};
 
 use super::RewritePass;
 
@@ -29,11 +29,15 @@ pub struct SplitCriticalEdges;
 impl RewritePass for SplitCriticalEdges {
     type Error = anyhow::Error;
 
+    fn name(&self) -> &'static str {
+        "split-critical-edges"
+    }
+
     fn run(
         &mut self,
         function: &mut hir::Function,
         analysis: &mut FunctionAnalysis,
-    ) -> Result<(), Self::Error> {
+    ) -> Result<PreservedAnalyses, Self::Error> {
         // Search for blocks with multiple successors with edges to blocks with
         // multiple predecessors; these blocks form critical edges in the control
         // flow graph which must be split.
@@ -47,6 +51,7 @@ impl RewritePass for SplitCriticalEdges {
         worklist.push_back(function.dfg.entry_block());
 
         let cfg = analysis.cfg_mut();
+        let mut changed = false;
 
         while let Some(p) = worklist.pop_front() {
             // If we've already visited a block, skip it
@@ -79,57 +84,91 @@ impl RewritePass for SplitCriticalEdges {
                 // * Insert an unconditional branch to the successor with the block
                 // arguments of the original terminator
                 // * Recompute the control flow graph for affected blocks
-                let split = function.dfg.create_block_after(p);
                 let terminator = function.dfg.last_inst(p).unwrap();
                 let span = function.dfg.inst_span(terminator);
+
+                // A conditional branch or switch may have more than one edge to the same
+                // successor, each of which is split separately, as they may pass different
+                // arguments
+                let num_edges = match function.dfg.analyze_branch(terminator) {
+                    BranchInfo::MultiDest(jts) => {
+                        jts.iter().filter(|jt| jt.destination == b).count()
+                    }
+                    _ => 1,
+                };
+                let mut splits = SmallVec::<[BlockId; 2]>::default();
+                let mut after = p;
+                for _ in 0..num_edges {
+                    after = function.dfg.create_block_after(after);
+                    splits.push(after);
+                }
+
                 let ix = function.dfg.inst_mut(terminator);
-                let args: ValueList;
+                let mut edges = SmallVec::<[(BlockId, ValueList); 2]>::default();
+                let mut splits = splits.into_iter();
+                let mut split_edge = |dest: &mut BlockId, args: &mut ValueList| {
+                    if *dest == b {
+                        let split = splits.next().unwrap();
+                        *dest = split;
+                        edges.push((split, args.take()));
+                    }
+                };
                 match ix {
                     Instruction::Br(hir::Br {
                         ref mut destination,
-                        args: ref mut orig_args,
+                        ref mut args,
                         ..
-                    }) => {
-                        args = orig_args.take();
-                        *destination = split;
-                    }
+                    }) => split_edge(destination, args),
                     Instruction::CondBr(hir::CondBr {
                         then_dest: (ref mut then_dest, ref mut then_args),
                         else_dest: (ref mut else_dest, ref mut else_args),
                         ..
                     }) => {
-                        if *then_dest == b {
-                            *then_dest = split;
-                            args = then_args.take();
-                        } else {
-                            *else_dest = split;
-                            args = else_args.take();
+                        split_edge(then_dest, then_args);
+                        split_edge(else_dest, else_args);
+                    }
+                    Instruction::Switch(hir::Switch {
+                        ref mut arms,
+                        default: (ref mut default_dest, ref mut default_args),
+                        ..
+                    }) => {
+                        for (_, dest, args) in arms.iter_mut() {
+                            split_edge(dest, args);
                         }
+                        split_edge(default_dest, default_args);
                     }
-                    Instruction::Switch(_) => unimplemented!(),
                     _ => unreachable!(),
                 }
-                function.dfg.insert_inst(
-                    InsertionPoint {
-                        at: ProgramPoint::Block(split),
-                        action: Insert::After,
-                    },
-                    Instruction::Br(hir::Br {
-                        op: hir::Opcode::Br,
-                        destination: b,
-                        args,
-                    }),
-                    Type::Unknown,
-                    span,
-                );
-
-                cfg.recompute_block(&function.dfg, split);
+                for (split, args) in edges.into_iter() {
+                    function.dfg.insert_inst(
+                        InsertionPoint {
+                            at: ProgramPoint::Block(split),
+                            action: Insert::After,
+                        },
+                        Instruction::Br(hir::Br {
+                            op: hir::Opcode::Br,
+                            destination: b,
+                            args,
+                        }),
+                        Type::Unknown,
+                        span,
+                    );
+
+                    cfg.recompute_block(&function.dfg, split);
+                }
+
+                changed = true;
             }
 
             cfg.recompute_block(&function.dfg, p);
         }
 
-        Ok(())
+        // The control flow graph was updated as each edge was split
+        if changed {
+            Ok(PreservedAnalyses::CFG | PreservedAnalyses::CALLGRAPH)
+        } else {
+            Ok(PreservedAnalyses::all())
+        }
     }
 }
 
@@ -2,7 +2,9 @@ use std::collections::VecDeque;
 use std::rc::Rc;
 
 use miden_hir::{self as hir, Block as BlockId, Value as ValueId, *};
-use miden_hir_analysis::{BlockPredecessor, ControlFlowGraph, FunctionAnalysis, LoopAnalysis};
+use miden_hir_analysis::{
+    BlockPredecessor, ControlFlowGraph, FunctionAnalysis, LoopAnalysis, PreservedAnalyses,
+};
 use rustc_hash::FxHashSet;
 
 use crate::{adt::ScopedMap, RewritePass};
@@ -256,11 +258,15 @@ pub struct Treeify;
 impl RewritePass for Treeify {
     type Error = anyhow::Error;
 
+    fn name(&self) -> &'static str {
+        "treeify"
+    }
+
     fn run(
         &mut self,
         function: &mut hir::Function,
         analysis: &mut FunctionAnalysis,
-    ) -> Result<(), Self::Error> {
+    ) -> Result<PreservedAnalyses, Self::Error> {
         // Require the dominator tree and loop analyses
         analysis.ensure_loops(function);
 
@@ -330,12 +336,13 @@ impl RewritePass for Treeify {
             }
         }
 
-        // If we made any changes, we need to recompute all analyses
+        // If we made any changes, we need to recompute all analyses, but while blocks
+        // containing calls may have been copied, the set of functions called is unchanged
         if changed {
-            analysis.recompute(function);
+            Ok(PreservedAnalyses::CALLGRAPH)
+        } else {
+            Ok(PreservedAnalyses::all())
         }
-
-        Ok(())
     }
 }
 
@@ -315,7 +315,7 @@ impl fmt::Display for Type {
             Self::Felt => f.write_str("felt"),
             Self::Ptr(inner) => write!(f, "*mut {}", &inner),
             Self::NativePtr(inner, addrspace) => {
-                write!(f, "*mut(addrspace {}) {}", inner, addrspace)
+                write!(f, "*mut(addrspace {}) {}", addrspace, inner)
             }
             Self::Struct(sty) => write!(f, "{sty}"),
             Self::Array(element_ty, arity) => write!(f, "[{}; {}]", &element_ty, arity),
@@ -692,7 +692,7 @@ impl FromStr for AddressSpace {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
-            "unknown" => Ok(Self::Unknown),
+            "?" | "unknown" => Ok(Self::Unknown),
             id => {
                 use core::num::IntErrorKind;
                 match NonZeroU16::from_str(id) {
@@ -605,7 +605,9 @@ impl<'a> MasmOpBuilder<'a> {
         self.build(self.ip, MasmOp::EqImm(imm));
     }
 
-    /// Pops two words off the stack, and pushes 1 on the stack if they are equal, else 0.
+    /// Pushes 1 on the stack if the two words on top of the stack are equal, else 0.
+    ///
+    /// Unlike [Self::eq], the words being compared are left on the stack.
     pub fn eqw(mut self) {
         self.build(self.ip, MasmOp::Eqw);
     }
@@ -1822,7 +1824,10 @@ fn apply_op_stack_effects(
             stack.push(Type::I1);
         }
         MasmOp::Eqw => {
-            stack.dropn(8);
+            assert!(
+                stack.len() > 7,
+                "expected at least 8 elements on the operand stack"
+            );
             stack.push(Type::I1);
         }
         MasmOp::Clk => {
@@ -285,7 +285,7 @@ impl<'a> fmt::Display for DisplayOp<'a> {
             MasmOp::U32Assert => f.write_str("u32.assert"),
             MasmOp::U32Assert2 => f.write_str("u32.assert2"),
             MasmOp::U32Assertw => f.write_str("u32.assertw"),
-            MasmOp::U32Cast => f.write_str("u23.cast"),
+            MasmOp::U32Cast => f.write_str("u32.cast"),
             MasmOp::U32Split => f.write_str("u32.split"),
             MasmOp::U32CheckedAdd => f.write_str("u32.add.checked"),
             MasmOp::U32CheckedAddImm(imm) => write!(f, "u32.add.checked.{imm}"),
@@ -314,7 +314,7 @@ impl<'a> fmt::Display for DisplayOp<'a> {
             MasmOp::U32UncheckedDiv => f.write_str("u32.div.unchecked"),
             MasmOp::U32UncheckedDivImm(imm) => write!(f, "u32.div.unchecked.{imm}"),
             MasmOp::U32CheckedMod => f.write_str("u32.mod.checked"),
-            MasmOp::U32CheckedModImm(imm) => write!(f, "u32.mod.unchecked.{imm}"),
+            MasmOp::U32CheckedModImm(imm) => write!(f, "u32.mod.checked.{imm}"),
             MasmOp::U32UncheckedMod => f.write_str("u32.mod.unchecked"),
             MasmOp::U32UncheckedModImm(imm) => write!(f, "u32.mod.unchecked.{imm}"),
             MasmOp::U32CheckedDivMod => f.write_str("u32.divmod.checked"),
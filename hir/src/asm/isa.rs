@@ -332,10 +332,11 @@ pub enum MasmOp {
     LteImm(Felt),
     /// Pops `a` off the stack, and places the 1 on the stack if `a` is odd, else 0
     IsOdd,
-    /// Pops `B, A` off the stack, and places the result of `A == B` on the stack,
+    /// Peeks `B, A` from the top of the stack, and places the result of `A == B` on the stack,
     /// where the uppercase variables here represent words, rather than field elements.
     ///
-    /// The comparison works by comparing pairs of elements from each word
+    /// The comparison works by comparing pairs of elements from each word, and unlike `eq`,
+    /// the words being compared remain on the stack below the result
     Eqw,
     /// Pushes the current value of the cycle counter (clock) on the stack
     Clk,
@@ -3,15 +3,17 @@ mod display;
 mod import;
 mod isa;
 mod stack;
+mod verify;
 
 pub use self::builder::*;
 pub use self::display::{DisplayInlineAsm, DisplayMasmBlock};
 pub use self::import::{MasmImport, ModuleImportInfo};
 pub use self::isa::*;
 pub use self::stack::{OperandStack, Stack, StackElement};
+pub use self::verify::{StackEffectError, StackEffectVerifier};
 
 use cranelift_entity::PrimaryMap;
-use smallvec::smallvec;
+use smallvec::{smallvec, SmallVec};
 
 use super::{DataFlowGraph, Opcode, Type, ValueList};
 
@@ -81,6 +83,24 @@ impl InlineAsm {
         self.blocks[block].push(op);
     }
 
+    /// Verify that this inline assembly leaves exactly its results on the operand stack,
+    /// given its arguments, see [StackEffectVerifier] for details.
+    ///
+    /// The signatures of the functions it calls are those imported into `dfg`.
+    pub fn verify(&self, dfg: &DataFlowGraph) -> Result<(), StackEffectError> {
+        let params = self
+            .args
+            .as_slice(&dfg.value_lists)
+            .iter()
+            .map(|arg| dfg.value_type(*arg).clone())
+            .collect::<SmallVec<[Type; 4]>>();
+        let verifier = StackEffectVerifier::new(&self.blocks, |callee| {
+            dfg.get_import(callee)
+                .map(|import| import.signature.clone())
+        });
+        verifier.verify(self.body, &params, &self.results)
+    }
+
     pub fn display<'a, 'b: 'a>(
         &'b self,
         dfg: &'b DataFlowGraph,
@@ -0,0 +1,837 @@
+use cranelift_entity::PrimaryMap;
+use smallvec::SmallVec;
+
+use crate::{Felt, FieldElement, FunctionIdent, Signature, StarkField, Type};
+
+use super::*;
+
+/// This error is produced by [StackEffectVerifier] when a block of Miden Assembly does not have
+/// the expected effect on the operand stack, identifying the instruction responsible.
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum StackEffectError {
+    /// An instruction requires more operands than are on the operand stack
+    #[error("'{op}' at {block}[{index}] requires {needed} operands, but only {available} are on the operand stack")]
+    Underflow {
+        block: MasmBlockId,
+        index: usize,
+        op: MasmOp,
+        needed: usize,
+        available: usize,
+    },
+    /// The condition of an instruction is known not to be a boolean
+    #[error("'{op}' at {block}[{index}] expects a boolean condition on top of the operand stack")]
+    ExpectedBool {
+        block: MasmBlockId,
+        index: usize,
+        op: MasmOp,
+    },
+    /// The branches of an `if.true` leave the operand stack with a different number of elements,
+    /// and one of them does not have the number of elements expected by the code following it
+    #[error("the branches of '{op}' at {block}[{index}] have different stack effects: one leaves {actual} elements on the operand stack, where {expected} are expected")]
+    BranchMismatch {
+        block: MasmBlockId,
+        index: usize,
+        op: MasmOp,
+        expected: usize,
+        actual: usize,
+    },
+    /// The body of a `while.true` does not leave the operand stack as it found it when looping
+    #[error("the body of '{op}' at {block}[{index}] is not stack-neutral: it is entered with {expected} elements on the operand stack, but loops with {actual}")]
+    LoopNotNeutral {
+        block: MasmBlockId,
+        index: usize,
+        op: MasmOp,
+        expected: usize,
+        actual: usize,
+    },
+    /// The operand stack does not hold the results on exit
+    #[error("expected {expected} elements on the operand stack on exit, but found {actual}")]
+    ResultMismatch { expected: usize, actual: usize },
+    /// The stack effect of a callee is not known
+    #[error("cannot determine the stack effect of '{op}' at {block}[{index}]: no signature is known for {callee}")]
+    UnknownCallee {
+        block: MasmBlockId,
+        index: usize,
+        op: MasmOp,
+        callee: FunctionIdent,
+    },
+}
+
+/// The location of an instruction, as its block and index in that block
+type Location = (MasmBlockId, usize);
+
+/// This structure verifies that a block of Miden Assembly, and the blocks nested within it,
+/// have the expected effect on the operand stack, by abstractly interpreting each instruction.
+///
+/// Given the types of the arguments on the operand stack on entry, it verifies that:
+///
+/// * No instruction requires more operands than are on the operand stack
+/// * The condition of an `if.true` or `while.true` is not known to be a non-boolean value
+/// * Both branches of an `if.true` leave the same number of elements on the operand stack
+/// * The body of a `while.true` leaves the operand stack as it found it when looping
+/// * The elements on the operand stack on exit are exactly those of the given result types
+///
+/// Constants pushed on the stack are tracked, so that a path which is known to trap, i.e. an
+/// `assert` of zero, or which is known to leave a loop, i.e. the body of a `while.true` which ends
+/// by pushing zero, need not have the same stack effect as the code it is an alternative to. This
+/// is how code exits from nested loops, e.g. returning from a function in the body of a loop.
+///
+/// The stack effects of the callees of `exec` and `syscall` are determined by their signatures,
+/// which must be provided by the given callback.
+pub struct StackEffectVerifier<'a, F> {
+    blocks: &'a PrimaryMap<MasmBlockId, MasmBlock>,
+    signatures: F,
+}
+impl<'a, F> StackEffectVerifier<'a, F>
+where
+    F: Fn(&FunctionIdent) -> Option<Signature>,
+{
+    /// Create a verifier for code in `blocks`, using `signatures` to look up callees
+    pub fn new(blocks: &'a PrimaryMap<MasmBlockId, MasmBlock>, signatures: F) -> Self {
+        Self { blocks, signatures }
+    }
+
+    /// Verify the code in `body`, given the types of the arguments on the operand stack on entry,
+    /// and the types of the results expected on it on exit, in the order they are passed, i.e.
+    /// the first argument or result is on top of the stack.
+    pub fn verify(
+        &self,
+        body: MasmBlockId,
+        params: &[Type],
+        results: &[Type],
+    ) -> Result<(), StackEffectError> {
+        let mut stack = SmallVec::new();
+        for ty in params.iter().rev() {
+            push_type(&mut stack, ty);
+        }
+        let mut states = States::default();
+        states.insert(State {
+            stack,
+            origin: None,
+        });
+
+        let expected = results.iter().map(|ty| ty.size_in_felts()).sum();
+        for state in self.block(body, states)?.0.iter() {
+            if state.depth() != expected {
+                return Err(self.mismatch(state, expected, |actual| {
+                    StackEffectError::ResultMismatch { expected, actual }
+                }));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Interpret `block` in each of `states`, returning the set of states on exit from it
+    fn block(&self, block: MasmBlockId, mut states: States) -> Result<States, StackEffectError> {
+        for (index, op) in self.blocks[block].ops.iter().enumerate() {
+            let loc = (block, index);
+            states = match op {
+                MasmOp::If(then_blk, else_blk) => {
+                    self.if_true(loc, *then_blk, *else_blk, states)?
+                }
+                MasmOp::While(body) => self.while_true(loc, *body, states)?,
+                MasmOp::Repeat(n, body) => {
+                    for _ in 0..*n {
+                        let next = self.block(*body, states.clone())?;
+                        // Further iterations would have the same result
+                        if next == states {
+                            break;
+                        }
+                        states = next;
+                    }
+                    states
+                }
+                op => {
+                    let mut next = States::default();
+                    for mut state in states.0.into_iter() {
+                        if self.apply(loc, op, &mut state)? {
+                            next.insert(state);
+                        }
+                    }
+                    next
+                }
+            };
+        }
+
+        Ok(states)
+    }
+
+    fn if_true(
+        &self,
+        loc: Location,
+        then_blk: MasmBlockId,
+        else_blk: MasmBlockId,
+        states: States,
+    ) -> Result<States, StackEffectError> {
+        let mut entry = States::default();
+        for mut state in states.0.into_iter() {
+            self.pop_condition(loc, &mut state)?;
+            entry.insert(state);
+        }
+
+        let then_states = self.block(then_blk, entry.clone())?;
+        let else_states = self.block(else_blk, entry)?;
+
+        // A state with a depth which the other branch does not produce is attributed to this
+        // instruction, unless it was already attributed to one nested within it
+        let mut states = States::default();
+        for (branch, other) in [(&then_states, &else_states), (&else_states, &then_states)] {
+            for state in branch.0.iter() {
+                let mut state = state.clone();
+                if !other.0.is_empty() && !other.has_depth(state.depth()) {
+                    state.origin = state.origin.or(Some(loc));
+                }
+                states.insert(state);
+            }
+        }
+
+        Ok(states)
+    }
+
+    fn while_true(
+        &self,
+        loc: Location,
+        body: MasmBlockId,
+        states: States,
+    ) -> Result<States, StackEffectError> {
+        let mut exits = States::default();
+        let mut entry = States::default();
+        for mut state in states.0.into_iter() {
+            let cond = self.pop_condition(loc, &mut state)?;
+            if cond != Element::Const(Felt::ONE) {
+                exits.insert(state.clone());
+            }
+            if cond != Element::Const(Felt::ZERO) {
+                entry.insert(state);
+            }
+        }
+
+        // Interpret the body until the states it loops with are the same as those it is
+        // entered with, which must occur, as the elements of each state only ever become
+        // less precise
+        loop {
+            let mut next = entry.clone();
+            for mut state in self.block(body, entry.clone())?.0.into_iter() {
+                let cond = self.pop_condition(loc, &mut state)?;
+                if cond != Element::Const(Felt::ONE) {
+                    exits.insert(state.clone());
+                }
+                if cond != Element::Const(Felt::ZERO) {
+                    if !entry.has_depth(state.depth()) {
+                        let expected = entry.0[0].depth();
+                        return Err(self.mismatch(&state, expected, |actual| {
+                            StackEffectError::LoopNotNeutral {
+                                block: loc.0,
+                                index: loc.1,
+                                op: self.op(loc),
+                                expected,
+                                actual,
+                            }
+                        }));
+                    }
+                    state.origin = None;
+                    next.insert(state);
+                }
+            }
+            if next == entry {
+                break;
+            }
+            entry = next;
+        }
+
+        Ok(exits)
+    }
+
+    /// Apply the stack effect of `op` to `state`, returning false if `op` is known to trap
+    fn apply(
+        &self,
+        loc: Location,
+        op: &MasmOp,
+        state: &mut State,
+    ) -> Result<bool, StackEffectError> {
+        use Element::{Bool, Const, Unknown};
+
+        let needed = match op {
+            MasmOp::Exec(callee) | MasmOp::Syscall(callee) => {
+                let Some(signature) = (self.signatures)(callee) else {
+                    return Err(StackEffectError::UnknownCallee {
+                        block: loc.0,
+                        index: loc.1,
+                        op: *op,
+                        callee: *callee,
+                    });
+                };
+                let needed = signature
+                    .params()
+                    .iter()
+                    .map(|param| param.ty.size_in_felts())
+                    .sum();
+                self.require(loc, state, needed)?;
+                let len = state.depth() - needed;
+                state.stack.truncate(len);
+                for result in signature.results().iter().rev() {
+                    push_type(&mut state.stack, &result.ty);
+                }
+                return Ok(true);
+            }
+            op => num_operands(op),
+        };
+        self.require(loc, state, needed)?;
+
+        let stack = &mut state.stack;
+        let depth = stack.len();
+        let top = depth.saturating_sub(1);
+        match op {
+            MasmOp::Padw => stack.extend([Const(Felt::ZERO); 4]),
+            MasmOp::Push(imm) => stack.push(Const(*imm)),
+            MasmOp::Push2([a, b]) => stack.extend([Const(*a), Const(*b)]),
+            MasmOp::Pushw(word) => stack.extend(word.iter().rev().copied().map(Const)),
+            MasmOp::PushU8(imm) => stack.push(Const(Felt::new(*imm as u64))),
+            MasmOp::PushU16(imm) => stack.push(Const(Felt::new(*imm as u64))),
+            MasmOp::PushU32(imm) => stack.push(Const(Felt::new(*imm as u64))),
+            MasmOp::Drop => {
+                stack.pop();
+            }
+            MasmOp::Dropw => stack.truncate(depth - 4),
+            MasmOp::Dup(n) => stack.push(stack[top - *n as usize]),
+            MasmOp::Dupw(n) => {
+                let start = depth - 4 * (*n as usize + 1);
+                let word = SmallVec::<[Element; 4]>::from_slice(&stack[start..(start + 4)]);
+                stack.extend(word);
+            }
+            MasmOp::Swap(n) => stack.swap(top, top - *n as usize),
+            MasmOp::Swapw(n) => {
+                for i in 0..4 {
+                    stack.swap(top - i, top - i - 4 * (*n as usize));
+                }
+            }
+            MasmOp::Movup(n) => {
+                let elem = stack.remove(top - *n as usize);
+                stack.push(elem);
+            }
+            MasmOp::Movdn(n) => {
+                let elem = stack.pop().unwrap();
+                stack.insert(top - *n as usize, elem);
+            }
+            MasmOp::Movupw(n) => {
+                let start = depth - 4 * (*n as usize + 1);
+                let word = stack
+                    .drain(start..(start + 4))
+                    .collect::<SmallVec<[Element; 4]>>();
+                stack.extend(word);
+            }
+            MasmOp::Movdnw(n) => {
+                let word = stack
+                    .drain((depth - 4)..)
+                    .collect::<SmallVec<[Element; 4]>>();
+                stack.insert_many(depth - 4 * (*n as usize + 1), word);
+            }
+            // Either operand may end up in either position, so nothing more is known about
+            // each one than what is known about both
+            MasmOp::Cswap | MasmOp::Cswapw | MasmOp::Cdrop | MasmOp::Cdropw => {
+                self.pop_condition(loc, state)?;
+                let stack = &mut state.stack;
+                let top = stack.len() - 1;
+                let width = if matches!(op, MasmOp::Cswap | MasmOp::Cdrop) {
+                    1
+                } else {
+                    4
+                };
+                for i in 0..width {
+                    let elem = stack[top - i].join(stack[top - i - width]);
+                    stack[top - i] = elem;
+                    stack[top - i - width] = elem;
+                }
+                if matches!(op, MasmOp::Cdrop | MasmOp::Cdropw) {
+                    stack.truncate(stack.len() - width);
+                }
+            }
+            MasmOp::Assert => {
+                if stack.pop() == Some(Const(Felt::ZERO)) {
+                    return Ok(false);
+                }
+            }
+            MasmOp::Assertz => {
+                if matches!(stack.pop(), Some(Const(c)) if c != Felt::ZERO) {
+                    return Ok(false);
+                }
+            }
+            MasmOp::AssertEq => {
+                let b = stack.pop().unwrap();
+                let a = stack.pop().unwrap();
+                if matches!((a, b), (Const(a), Const(b)) if a != b) {
+                    return Ok(false);
+                }
+            }
+            MasmOp::AssertEqw => stack.truncate(depth - 8),
+            MasmOp::LocAddr(_) | MasmOp::MemLoadImm(_) | MasmOp::MemLoadOffsetImm(_, _) => {
+                stack.push(Unknown)
+            }
+            MasmOp::MemLoadOffset => {
+                stack.truncate(depth - 2);
+                stack.push(Unknown);
+            }
+            MasmOp::MemLoadw => {
+                stack.truncate(depth - 5);
+                stack.extend([Unknown; 4]);
+            }
+            MasmOp::MemLoadwImm(_) => {
+                stack.truncate(depth - 4);
+                stack.extend([Unknown; 4]);
+            }
+            MasmOp::MemStore => stack.truncate(depth - 2),
+            MasmOp::MemStoreImm(_) | MasmOp::MemStoreOffsetImm(_, _) | MasmOp::MemStorew => {
+                stack.pop();
+            }
+            MasmOp::MemStoreOffset => stack.truncate(depth - 3),
+            MasmOp::MemStorewImm(_) => (),
+            MasmOp::Clk => stack.push(Unknown),
+            // Instructions which only validate their operands
+            MasmOp::U32Assert | MasmOp::U32Assert2 | MasmOp::U32Assertw => (),
+            // Instructions which push a boolean without consuming their operands
+            MasmOp::Eqw | MasmOp::U32Test | MasmOp::U32Testw => stack.push(Bool),
+            // Instructions which replace their operands with a boolean
+            MasmOp::Not
+            | MasmOp::And
+            | MasmOp::AndImm(_)
+            | MasmOp::Or
+            | MasmOp::OrImm(_)
+            | MasmOp::Xor
+            | MasmOp::XorImm(_)
+            | MasmOp::Eq
+            | MasmOp::EqImm(_)
+            | MasmOp::Neq
+            | MasmOp::NeqImm(_)
+            | MasmOp::Gt
+            | MasmOp::GtImm(_)
+            | MasmOp::Gte
+            | MasmOp::GteImm(_)
+            | MasmOp::Lt
+            | MasmOp::LtImm(_)
+            | MasmOp::Lte
+            | MasmOp::LteImm(_)
+            | MasmOp::IsOdd
+            | MasmOp::U32Eq
+            | MasmOp::U32EqImm(_)
+            | MasmOp::U32Neq
+            | MasmOp::U32NeqImm(_)
+            | MasmOp::U32CheckedLt
+            | MasmOp::U32UncheckedLt
+            | MasmOp::U32CheckedLte
+            | MasmOp::U32UncheckedLte
+            | MasmOp::U32CheckedGt
+            | MasmOp::U32UncheckedGt
+            | MasmOp::U32CheckedGte
+            | MasmOp::U32UncheckedGte => {
+                stack.truncate(depth - needed);
+                stack.push(Bool);
+            }
+            // Instructions which replace their operands with a result, and an overflow flag
+            MasmOp::U32OverflowingAdd
+            | MasmOp::U32OverflowingAddImm(_)
+            | MasmOp::U32OverflowingSub
+            | MasmOp::U32OverflowingSubImm(_)
+            | MasmOp::U32OverflowingMul
+            | MasmOp::U32OverflowingMulImm(_) => {
+                stack.truncate(depth - needed);
+                stack.extend([Unknown, Bool]);
+            }
+            // Instructions which replace their operands with two results
+            MasmOp::U32Split
+            | MasmOp::U32OverflowingAdd3
+            | MasmOp::U32OverflowingMadd
+            | MasmOp::U32CheckedDivMod
+            | MasmOp::U32CheckedDivModImm(_)
+            | MasmOp::U32UncheckedDivMod
+            | MasmOp::U32UncheckedDivModImm(_) => {
+                stack.truncate(depth - needed);
+                stack.extend([Unknown; 2]);
+            }
+            MasmOp::If(_, _)
+            | MasmOp::While(_)
+            | MasmOp::Repeat(_, _)
+            | MasmOp::Exec(_)
+            | MasmOp::Syscall(_) => unreachable!(),
+            // All other instructions replace their operands with a single result
+            _ => {
+                stack.truncate(depth - needed);
+                stack.push(Unknown);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Pop the condition of the instruction at `loc` off the stack, which must not be known
+    /// to be a value other than a boolean
+    fn pop_condition(&self, loc: Location, state: &mut State) -> Result<Element, StackEffectError> {
+        self.require(loc, state, 1)?;
+        let cond = state.stack.pop().unwrap();
+        if !cond.may_be_bool() {
+            return Err(StackEffectError::ExpectedBool {
+                block: loc.0,
+                index: loc.1,
+                op: self.op(loc),
+            });
+        }
+        Ok(cond)
+    }
+
+    /// Ensure that there are at least `needed` elements on the stack in `state`
+    fn require(&self, loc: Location, state: &State, needed: usize) -> Result<(), StackEffectError> {
+        if state.depth() < needed {
+            return Err(StackEffectError::Underflow {
+                block: loc.0,
+                index: loc.1,
+                op: self.op(loc),
+                needed,
+                available: state.depth(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Get the error for `state` not having `expected` elements on the stack, attributing
+    /// it to the `if.true` it originates from, if any, or constructing it with `error` otherwise
+    fn mismatch(
+        &self,
+        state: &State,
+        expected: usize,
+        error: impl FnOnce(usize) -> StackEffectError,
+    ) -> StackEffectError {
+        let actual = state.depth();
+        match state.origin {
+            Some(loc) => StackEffectError::BranchMismatch {
+                block: loc.0,
+                index: loc.1,
+                op: self.op(loc),
+                expected,
+                actual,
+            },
+            None => error(actual),
+        }
+    }
+
+    #[inline]
+    fn op(&self, loc: Location) -> MasmOp {
+        self.blocks[loc.0].ops[loc.1]
+    }
+}
+
+/// Get the number of elements on top of the operand stack which `op` requires to be present
+///
+/// This is not defined for `exec` and `syscall`, which depend on the callee.
+fn num_operands(op: &MasmOp) -> usize {
+    match op {
+        MasmOp::Padw
+        | MasmOp::Push(_)
+        | MasmOp::Push2(_)
+        | MasmOp::Pushw(_)
+        | MasmOp::PushU8(_)
+        | MasmOp::PushU16(_)
+        | MasmOp::PushU32(_)
+        | MasmOp::LocAddr(_)
+        | MasmOp::MemLoadImm(_)
+        | MasmOp::MemLoadOffsetImm(_, _)
+        | MasmOp::Repeat(_, _)
+        | MasmOp::Exec(_)
+        | MasmOp::Syscall(_)
+        | MasmOp::Clk => 0,
+        MasmOp::Dup(n) | MasmOp::Swap(n) | MasmOp::Movup(n) | MasmOp::Movdn(n) => *n as usize + 1,
+        MasmOp::Dupw(n) | MasmOp::Swapw(n) | MasmOp::Movupw(n) | MasmOp::Movdnw(n) => {
+            4 * (*n as usize + 1)
+        }
+        MasmOp::Dropw
+        | MasmOp::MemLoadwImm(_)
+        | MasmOp::MemStorewImm(_)
+        | MasmOp::U32Testw
+        | MasmOp::U32Assertw => 4,
+        MasmOp::MemLoadw | MasmOp::MemStorew => 5,
+        MasmOp::AssertEqw | MasmOp::Eqw => 8,
+        MasmOp::Cswap | MasmOp::Cdrop => 3,
+        MasmOp::Cswapw | MasmOp::Cdropw => 9,
+        MasmOp::MemStoreOffset
+        | MasmOp::U32OverflowingAdd3
+        | MasmOp::U32WrappingAdd3
+        | MasmOp::U32OverflowingMadd
+        | MasmOp::U32WrappingMadd => 3,
+        MasmOp::AssertEq
+        | MasmOp::MemLoadOffset
+        | MasmOp::MemStore
+        | MasmOp::Add
+        | MasmOp::Sub
+        | MasmOp::Mul
+        | MasmOp::Div
+        | MasmOp::Exp
+        | MasmOp::And
+        | MasmOp::Or
+        | MasmOp::Xor
+        | MasmOp::Eq
+        | MasmOp::Neq
+        | MasmOp::Gt
+        | MasmOp::Gte
+        | MasmOp::Lt
+        | MasmOp::Lte
+        | MasmOp::U32Assert2
+        | MasmOp::U32CheckedAdd
+        | MasmOp::U32OverflowingAdd
+        | MasmOp::U32WrappingAdd
+        | MasmOp::U32CheckedSub
+        | MasmOp::U32OverflowingSub
+        | MasmOp::U32WrappingSub
+        | MasmOp::U32CheckedMul
+        | MasmOp::U32OverflowingMul
+        | MasmOp::U32WrappingMul
+        | MasmOp::U32CheckedDiv
+        | MasmOp::U32UncheckedDiv
+        | MasmOp::U32CheckedMod
+        | MasmOp::U32UncheckedMod
+        | MasmOp::U32CheckedDivMod
+        | MasmOp::U32UncheckedDivMod
+        | MasmOp::U32And
+        | MasmOp::U32Or
+        | MasmOp::U32Xor
+        | MasmOp::U32CheckedShl
+        | MasmOp::U32UncheckedShl
+        | MasmOp::U32CheckedShr
+        | MasmOp::U32UncheckedShr
+        | MasmOp::U32CheckedRotl
+        | MasmOp::U32UncheckedRotl
+        | MasmOp::U32CheckedRotr
+        | MasmOp::U32UncheckedRotr
+        | MasmOp::U32Eq
+        | MasmOp::U32Neq
+        | MasmOp::U32CheckedLt
+        | MasmOp::U32UncheckedLt
+        | MasmOp::U32CheckedLte
+        | MasmOp::U32UncheckedLte
+        | MasmOp::U32CheckedGt
+        | MasmOp::U32UncheckedGt
+        | MasmOp::U32CheckedGte
+        | MasmOp::U32UncheckedGte
+        | MasmOp::U32CheckedMin
+        | MasmOp::U32UncheckedMin
+        | MasmOp::U32CheckedMax
+        | MasmOp::U32UncheckedMax => 2,
+        // Everything else, including `if.true` and `while.true`, which pop their condition,
+        // operate on the top element of the stack
+        _ => 1,
+    }
+}
+
+/// Push the elements of a value of type `ty` on `stack`
+fn push_type(stack: &mut SmallVec<[Element; 16]>, ty: &Type) {
+    let elem = if matches!(ty, Type::I1) {
+        Element::Bool
+    } else {
+        Element::Unknown
+    };
+    stack.extend((0..ty.size_in_felts()).map(|_| elem));
+}
+
+/// What is known about an element on the operand stack
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Element {
+    /// The element is the given constant
+    Const(Felt),
+    /// The element is either 0 or 1
+    Bool,
+    /// Nothing is known about the element
+    Unknown,
+}
+impl Element {
+    /// Returns false if this element is known to be neither 0 nor 1
+    fn may_be_bool(self) -> bool {
+        match self {
+            Self::Const(c) => c.as_int() <= 1,
+            Self::Bool | Self::Unknown => true,
+        }
+    }
+
+    /// Get what is known about an element which is either `self` or `other`
+    fn join(self, other: Self) -> Self {
+        if self == other {
+            self
+        } else if self.is_bool() && other.is_bool() {
+            Self::Bool
+        } else {
+            Self::Unknown
+        }
+    }
+
+    /// Returns true if this element is known to be either 0 or 1
+    fn is_bool(self) -> bool {
+        match self {
+            Self::Const(c) => c.as_int() <= 1,
+            Self::Bool => true,
+            Self::Unknown => false,
+        }
+    }
+}
+
+/// The abstract state of the operand stack along some path through the code
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct State {
+    /// The elements on the stack, with the top of the stack last
+    stack: SmallVec<[Element; 16]>,
+    /// The `if.true` whose branches diverged to produce this state, if any
+    origin: Option<Location>,
+}
+impl State {
+    #[inline]
+    fn depth(&self) -> usize {
+        self.stack.len()
+    }
+}
+
+/// The set of states of the operand stack reaching some point in the code, of which there is
+/// at most one for each depth of the stack
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct States(SmallVec<[State; 2]>);
+impl States {
+    fn has_depth(&self, depth: usize) -> bool {
+        self.0.iter().any(|state| state.depth() == depth)
+    }
+
+    /// Add `state` to this set, merging it with the existing state of the same depth, if any
+    fn insert(&mut self, state: State) {
+        match self.0.iter_mut().find(|s| s.depth() == state.depth()) {
+            Some(existing) => {
+                for (elem, other) in existing.stack.iter_mut().zip(state.stack.iter()) {
+                    *elem = elem.join(*other);
+                }
+                existing.origin = existing.origin.or(state.origin);
+            }
+            None => self.0.push(state),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verify `asm`, given the types of its arguments, which may not call any functions
+    fn verify(asm: &InlineAsm, params: &[Type]) -> Result<(), StackEffectError> {
+        StackEffectVerifier::new(&asm.blocks, |_| None).verify(asm.body, params, &asm.results)
+    }
+
+    #[test]
+    fn stack_effect_results_test() {
+        let mut asm = InlineAsm::new(vec![Type::U32]);
+        asm.push(asm.body, MasmOp::U32CheckedAdd);
+        assert_eq!(verify(&asm, &[Type::U32, Type::U32]), Ok(()));
+
+        asm.push(asm.body, MasmOp::PushU8(1));
+        assert_eq!(
+            verify(&asm, &[Type::U32, Type::U32]),
+            Err(StackEffectError::ResultMismatch {
+                expected: 1,
+                actual: 2
+            })
+        );
+
+        let mut asm = InlineAsm::new(vec![Type::Felt]);
+        asm.push(asm.body, MasmOp::Add);
+        asm.push(asm.body, MasmOp::Add);
+        assert_eq!(
+            verify(&asm, &[Type::Felt, Type::Felt]),
+            Err(StackEffectError::Underflow {
+                block: asm.body,
+                index: 1,
+                op: MasmOp::Add,
+                needed: 2,
+                available: 1,
+            })
+        );
+
+        let callee = "test::callee".parse().unwrap();
+        let mut asm = InlineAsm::new(vec![]);
+        asm.push(asm.body, MasmOp::Exec(callee));
+        assert_eq!(
+            verify(&asm, &[]),
+            Err(StackEffectError::UnknownCallee {
+                block: asm.body,
+                index: 0,
+                op: MasmOp::Exec(callee),
+                callee,
+            })
+        );
+    }
+
+    #[test]
+    fn stack_effect_if_test() {
+        // if.true drop else nop end
+        let mut asm = InlineAsm::new(vec![Type::Felt]);
+        let then_blk = asm.create_block();
+        let else_blk = asm.create_block();
+        asm.push(then_blk, MasmOp::Drop);
+        asm.push(asm.body, MasmOp::If(then_blk, else_blk));
+        assert_eq!(
+            verify(&asm, &[Type::I1, Type::Felt]),
+            Err(StackEffectError::BranchMismatch {
+                block: asm.body,
+                index: 0,
+                op: MasmOp::If(then_blk, else_blk),
+                expected: 1,
+                actual: 0,
+            })
+        );
+
+        // A branch which is known to trap may leave anything on the stack
+        asm.push(then_blk, MasmOp::PushU8(0));
+        asm.push(then_blk, MasmOp::Assert);
+        assert_eq!(verify(&asm, &[Type::I1, Type::Felt]), Ok(()));
+
+        // The condition must be a boolean
+        asm.blocks[asm.body].ops.insert(0, MasmOp::PushU8(2));
+        assert_eq!(
+            verify(&asm, &[Type::I1, Type::Felt]),
+            Err(StackEffectError::ExpectedBool {
+                block: asm.body,
+                index: 1,
+                op: MasmOp::If(then_blk, else_blk),
+            })
+        );
+    }
+
+    #[test]
+    fn stack_effect_while_test() {
+        // push.1 while.true dup.0 push.1 end
+        let mut asm = InlineAsm::new(vec![Type::Felt]);
+        let body = asm.create_block();
+        asm.push(body, MasmOp::Dup(0));
+        asm.push(body, MasmOp::PushU8(1));
+        asm.push(asm.body, MasmOp::PushU8(1));
+        asm.push(asm.body, MasmOp::While(body));
+        assert_eq!(
+            verify(&asm, &[Type::Felt]),
+            Err(StackEffectError::LoopNotNeutral {
+                block: asm.body,
+                index: 1,
+                op: MasmOp::While(body),
+                expected: 1,
+                actual: 2,
+            })
+        );
+
+        // Leaving the loop with push.0 need not be stack-neutral:
+        //
+        // push.1 while.true dup.0 gt.10 if.true dup.0 push.0 else incr push.1 end end
+        let mut asm = InlineAsm::new(vec![Type::Felt, Type::Felt]);
+        let body = asm.create_block();
+        let exit_blk = asm.create_block();
+        let continue_blk = asm.create_block();
+        asm.push(exit_blk, MasmOp::Dup(0));
+        asm.push(exit_blk, MasmOp::PushU8(0));
+        asm.push(continue_blk, MasmOp::Incr);
+        asm.push(continue_blk, MasmOp::PushU8(1));
+        asm.push(body, MasmOp::Dup(0));
+        asm.push(body, MasmOp::GtImm(Felt::new(10)));
+        asm.push(body, MasmOp::If(exit_blk, continue_blk));
+        asm.push(asm.body, MasmOp::PushU8(1));
+        asm.push(asm.body, MasmOp::While(body));
+        assert_eq!(verify(&asm, &[Type::Felt]), Ok(()));
+    }
+}
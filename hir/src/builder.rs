@@ -3,10 +3,12 @@ use cranelift_entity::packed_option::PackedOption;
 use miden_diagnostics::SourceSpan;
 
 use super::*;
+use crate::ssa::SsaBuilder;
 
 pub struct FunctionBuilder<'f> {
     pub func: &'f mut Function,
     position: PackedOption<Block>,
+    ssa: SsaBuilder,
 }
 impl<'f> FunctionBuilder<'f> {
     pub fn new(func: &'f mut Function) -> Self {
@@ -16,6 +18,7 @@ impl<'f> FunctionBuilder<'f> {
         Self {
             func,
             position: position.into(),
+            ssa: SsaBuilder::default(),
         }
     }
 
@@ -89,6 +92,53 @@ impl<'f> FunctionBuilder<'f> {
             .expect("must be in a block to insert instructions");
         DefaultInstBuilder::new(&mut self.func.dfg, block)
     }
+
+    /// Declare a new [Variable] of type `ty`
+    ///
+    /// Variables let you build the function without threading block parameters by hand:
+    /// define a variable with [Self::def_var], and read it with [Self::use_var], and the
+    /// block parameters and branch arguments required to make the value flow from its
+    /// definitions to its uses are inserted automatically.
+    pub fn declare_var(&mut self, ty: Type) -> Variable {
+        self.ssa.declare_var(ty)
+    }
+
+    /// Define the value of `var` at the current position to be `value`
+    pub fn def_var(&mut self, var: Variable, value: Value) {
+        let block = self.current_block();
+        self.ssa.def_var(&self.func.dfg, var, value, block)
+    }
+
+    /// Get the value of `var` at the current position
+    ///
+    /// If the current block has not been sealed, this may add a block parameter to it,
+    /// whose arguments are provided once the block is sealed, see [Self::seal_block].
+    ///
+    /// NOTE: Block parameters added for a variable may later be found to be redundant, in
+    /// which case they are removed, and all uses of them in the function are replaced. You
+    /// should therefore avoid holding on to the returned value beyond using it as the
+    /// operand of an instruction.
+    pub fn use_var(&mut self, var: Variable) -> Value {
+        let block = self.current_block();
+        self.ssa.use_var(&mut self.func.dfg, var, block)
+    }
+
+    /// Seal `block`, indicating that all of its predecessors have been built.
+    ///
+    /// Sealing a block as early as possible produces fewer redundant block parameters,
+    /// but no branch to `block` may be built once it is sealed. The entry block is
+    /// always considered sealed.
+    pub fn seal_block(&mut self, block: Block) {
+        self.ssa.seal_block(&mut self.func.dfg, block)
+    }
+
+    /// Seal all blocks in the function which have not yet been sealed
+    pub fn seal_all_blocks(&mut self) {
+        let blocks = self.func.dfg.blocks().map(|(b, _)| b).collect::<Vec<_>>();
+        for block in blocks.into_iter() {
+            self.ssa.seal_block(&mut self.func.dfg, block);
+        }
+    }
 }
 
 pub struct DefaultInstBuilder<'f> {
@@ -1177,8 +1227,23 @@ pub trait InstBuilder<'f>: InstBuilderBase<'f> {
             .0
     }
 
-    fn switch(self, arg: Value, arms: Vec<(u32, Block)>, default: Block, span: SourceSpan) -> Inst {
+    fn switch(
+        mut self,
+        arg: Value,
+        arms: &[(u32, Block, &[Value])],
+        default: Block,
+        default_args: &[Value],
+        span: SourceSpan,
+    ) -> Inst {
         require_integer!(self, arg, Type::U32);
+        let (arms, default) = {
+            let pool = &mut self.data_flow_graph_mut().value_lists;
+            let arms = arms
+                .iter()
+                .map(|(value, dest, args)| (*value, *dest, ValueList::from_slice(args, pool)))
+                .collect();
+            (arms, (default, ValueList::from_slice(default_args, pool)))
+        };
         self.Switch(arg, arms, default, span).0
     }
 
@@ -1256,8 +1321,8 @@ pub trait InstBuilder<'f>: InstBuilderBase<'f> {
     fn Switch(
         self,
         arg: Value,
-        arms: Vec<(u32, Block)>,
-        default: Block,
+        arms: Vec<(u32, Block, ValueList)>,
+        default: (Block, ValueList),
         span: SourceSpan,
     ) -> (Inst, &'f mut DataFlowGraph) {
         let data = Instruction::Switch(Switch {
@@ -0,0 +1,401 @@
+use std::collections::BTreeSet;
+
+use petgraph::{
+    algo,
+    prelude::DiGraphMap,
+    visit::{depth_first_search, Dfs, DfsEvent},
+    Direction,
+};
+use rustc_hash::FxHashSet;
+
+use crate::{Call, Function, FunctionIdent, Ident, Instruction, MasmOp, Module, Opcode, Program};
+
+/// The ways in which a function invokes one of its callees, i.e. the weight of an
+/// edge in the [CallGraph]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct CallKinds {
+    /// The callee is invoked with `call`, or with `exec` from inline assembly
+    pub call: bool,
+    /// The callee is invoked with `syscall`
+    pub syscall: bool,
+}
+
+/// This error is raised when the functions of a [CallGraph] are requested in topological
+/// order, but the graph contains a cycle, i.e. there is recursion in the program.
+///
+/// The call from `caller` to `callee` is an edge which closes the cycle.
+#[derive(Debug, thiserror::Error)]
+#[error("encountered a cycle in the call graph: '{caller}' calls '{callee}'")]
+pub struct CallGraphCycleError {
+    pub caller: FunctionIdent,
+    pub callee: FunctionIdent,
+}
+
+/// The call graph of a [Program], or of a single [Module].
+///
+/// There is a node in the graph for every function defined in the program or module, and for
+/// every function they reference which is defined elsewhere, e.g. in the standard library or in
+/// another module. There is an edge from a caller to each of its callees, whether the callee is
+/// invoked by a `call` or `syscall` instruction, or by `exec` or `syscall` in inline assembly.
+///
+/// The graph is not kept up to date automatically: if a function is modified in a way that
+/// changes the set of functions it calls, e.g. by inlining, [CallGraph::function_changed] must
+/// be called with the modified function.
+#[derive(Default)]
+pub struct CallGraph {
+    graph: DiGraphMap<FunctionIdent, CallKinds>,
+    /// The functions which are defined in the program or module this graph was computed for
+    defined: FxHashSet<FunctionIdent>,
+    /// The names of the kernel modules in the program or module this graph was computed for
+    kernels: FxHashSet<Ident>,
+    entrypoint: Option<FunctionIdent>,
+}
+impl CallGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compute the call graph of `program`
+    pub fn with_program(program: &Program) -> Self {
+        let mut callgraph = Self::new();
+        callgraph.entrypoint = program.entrypoint();
+        for module in program.modules().iter() {
+            callgraph.add_module(module);
+        }
+        callgraph
+    }
+
+    /// Compute the call graph of `module`
+    ///
+    /// Calls to functions in other modules are included in the graph, but since the definitions
+    /// of those functions are unknown, the graph does not include any of their callees.
+    pub fn with_module(module: &Module) -> Self {
+        let mut callgraph = Self::new();
+        callgraph.add_module(module);
+        callgraph
+    }
+
+    /// Add the functions defined in `module`, and the calls they make, to the graph
+    pub fn add_module(&mut self, module: &Module) {
+        if module.is_kernel() {
+            self.kernels.insert(module.name);
+        }
+        for function in module.functions() {
+            self.defined.insert(function.id);
+            self.add_calls(function);
+        }
+    }
+
+    /// Add the calls made by `function` to the graph
+    fn add_calls(&mut self, function: &Function) {
+        let caller = self.graph.add_node(function.id);
+        for (block, _) in function.dfg.blocks() {
+            for inst in function.dfg.block_insts(block) {
+                match function.dfg.inst(inst) {
+                    Instruction::Call(Call { op, callee, .. }) => {
+                        self.add_call(caller, *callee, *op == Opcode::Syscall);
+                    }
+                    Instruction::InlineAsm(asm) => {
+                        for op in asm.blocks.values().flat_map(|block| block.ops.iter()) {
+                            match op {
+                                MasmOp::Exec(callee) => self.add_call(caller, *callee, false),
+                                MasmOp::Syscall(callee) => self.add_call(caller, *callee, true),
+                                _ => (),
+                            }
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+
+    fn add_call(&mut self, caller: FunctionIdent, callee: FunctionIdent, syscall: bool) {
+        self.graph.add_node(callee);
+        match self.graph.edge_weight_mut(caller, callee) {
+            Some(kinds) if syscall => kinds.syscall = true,
+            Some(kinds) => kinds.call = true,
+            None => {
+                let kinds = CallKinds {
+                    call: !syscall,
+                    syscall,
+                };
+                self.graph.add_edge(caller, callee, kinds);
+            }
+        }
+    }
+
+    /// Update the graph with the calls made by `function`, after it was modified
+    ///
+    /// The calls previously made by `function` are replaced by the calls it makes now.
+    pub fn function_changed(&mut self, function: &Function) {
+        let callees = self.callees(function.id).collect::<Vec<_>>();
+        for callee in callees.into_iter() {
+            self.graph.remove_edge(function.id, callee);
+        }
+        self.defined.insert(function.id);
+        self.add_calls(function);
+    }
+
+    /// Returns the entrypoint of the program this graph was computed for, if it has one
+    #[inline]
+    pub fn entrypoint(&self) -> Option<FunctionIdent> {
+        self.entrypoint
+    }
+
+    /// Returns true if `id` is a node in this graph, i.e. it is defined or called
+    pub fn contains(&self, id: FunctionIdent) -> bool {
+        self.graph.contains_node(id)
+    }
+
+    /// Returns true if `id` is defined in the program or module this graph was computed for
+    pub fn is_defined(&self, id: FunctionIdent) -> bool {
+        self.defined.contains(&id)
+    }
+
+    /// Returns true if `id` is defined in a kernel module of the program or module
+    /// this graph was computed for
+    pub fn is_kernel(&self, id: FunctionIdent) -> bool {
+        self.kernels.contains(&id.module)
+    }
+
+    /// Get an iterator over all of the functions in this graph
+    pub fn functions(&self) -> impl Iterator<Item = FunctionIdent> + '_ {
+        self.graph.nodes()
+    }
+
+    /// Get an iterator over the functions called by `id`
+    pub fn callees(&self, id: FunctionIdent) -> impl Iterator<Item = FunctionIdent> + '_ {
+        self.graph.neighbors_directed(id, Direction::Outgoing)
+    }
+
+    /// Get an iterator over the functions which call `id`
+    pub fn callers(&self, id: FunctionIdent) -> impl Iterator<Item = FunctionIdent> + '_ {
+        self.graph.neighbors_directed(id, Direction::Incoming)
+    }
+
+    /// Returns the ways in which `caller` invokes `callee`, or `None` if it does not
+    pub fn calls(&self, caller: FunctionIdent, callee: FunctionIdent) -> Option<CallKinds> {
+        self.graph.edge_weight(caller, callee).copied()
+    }
+
+    /// Get an iterator over the `(caller, callee)` pairs in which `caller` invokes `callee`
+    /// with `syscall`.
+    ///
+    /// Use [CallGraph::is_kernel] to determine whether the callee is a kernel function.
+    pub fn syscalls(&self) -> impl Iterator<Item = (FunctionIdent, FunctionIdent)> + '_ {
+        self.graph
+            .all_edges()
+            .filter(|(_, _, kinds)| kinds.syscall)
+            .map(|(caller, callee, _)| (caller, callee))
+    }
+
+    /// Returns true if `id` may call itself, directly or indirectly
+    pub fn is_recursive(&self, id: FunctionIdent) -> bool {
+        self.callees(id)
+            .any(|callee| algo::has_path_connecting(&self.graph, callee, id, None))
+    }
+
+    /// Get the strongly connected components of this graph, i.e. the sets of mutually
+    /// recursive functions, with a component per function if there is no recursion.
+    ///
+    /// The components are returned bottom-up, i.e. each component comes after those of
+    /// all the functions it calls, unless they are in the same component.
+    pub fn sccs(&self) -> Vec<Vec<FunctionIdent>> {
+        algo::tarjan_scc(&self.graph)
+    }
+
+    /// Get all of the functions in this graph in topological order, i.e. each function comes
+    /// before all of the functions it calls.
+    ///
+    /// Returns [CallGraphCycleError] if this graph contains a cycle.
+    pub fn toposort(&self) -> Result<Vec<FunctionIdent>, CallGraphCycleError> {
+        algo::toposort(&self.graph, None).map_err(|_| {
+            // The error here only gives us a node in the cycle, but we'd like to know the
+            // call which closes it, so search for the back edge which does so
+            self.find_cycle()
+                .expect_err("expected call graph to contain a cycle")
+        })
+    }
+
+    /// Look for a cycle in this graph, by searching for a call from a function to one
+    /// of the functions on the path by which it was reached
+    fn find_cycle(&self) -> Result<(), CallGraphCycleError> {
+        depth_first_search(&self.graph, self.graph.nodes(), |event| match event {
+            DfsEvent::BackEdge(caller, callee) => Err(CallGraphCycleError { caller, callee }),
+            _ => Ok(()),
+        })
+    }
+
+    /// Get the set of functions reachable from `roots`, including `roots` themselves
+    pub fn reachable_from<I>(&self, roots: I) -> BTreeSet<FunctionIdent>
+    where
+        I: IntoIterator<Item = FunctionIdent>,
+    {
+        let mut reachable = BTreeSet::new();
+        let mut dfs = Dfs::empty(&self.graph);
+        for root in roots.into_iter() {
+            if !self.contains(root) {
+                continue;
+            }
+            dfs.move_to(root);
+            while let Some(id) = dfs.next(&self.graph) {
+                reachable.insert(id);
+            }
+        }
+        reachable
+    }
+
+    /// Get the set of functions reachable from the entrypoint, or `None` if there isn't one
+    pub fn reachable(&self) -> Option<BTreeSet<FunctionIdent>> {
+        self.entrypoint.map(|entry| self.reachable_from([entry]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        testing::{self, TestContext},
+        *,
+    };
+
+    fn id(name: &str) -> FunctionIdent {
+        name.parse().unwrap()
+    }
+
+    #[test]
+    fn call_graph_program_test() {
+        let context = TestContext::default();
+
+        let mut builder = ProgramBuilder::new(&context.diagnostics);
+        testing::hello_world(&mut builder, &context)
+            .expect("unexpected error constructing test modules");
+        let program = builder
+            .with_entrypoint(id("test::main"))
+            .link()
+            .expect("failed to link program");
+
+        let callgraph = CallGraph::with_program(&program);
+        let main = id("test::main");
+        let alloc = id("mem::alloc");
+        let memory_grow = id("mem::memory_grow");
+
+        let callees = callgraph.callees(main).collect::<BTreeSet<_>>();
+        let expected = [alloc, id("str::from_raw_parts"), id("str::compare")];
+        assert_eq!(callees, BTreeSet::from(expected));
+        assert_eq!(
+            callgraph.callers(memory_grow).collect::<Vec<_>>(),
+            vec![alloc]
+        );
+        assert_eq!(
+            callgraph.calls(main, alloc),
+            Some(CallKinds {
+                call: true,
+                syscall: false
+            })
+        );
+        assert_eq!(callgraph.calls(alloc, main), None);
+        assert!(callgraph.is_defined(memory_grow));
+        assert!(!callgraph.is_recursive(main));
+        assert_eq!(callgraph.syscalls().count(), 0);
+
+        // `mem::memory_size` is defined, but never called
+        let reachable = callgraph.reachable().unwrap();
+        assert!(reachable.contains(&memory_grow));
+        assert!(!reachable.contains(&id("mem::memory_size")));
+        assert_eq!(reachable.len(), 5);
+
+        // Every function must precede its callees
+        let order = callgraph.toposort().expect("unexpected cycle");
+        let position = |id| order.iter().position(|f| *f == id).unwrap();
+        assert!(position(main) < position(alloc));
+        assert!(position(alloc) < position(memory_grow));
+
+        // Without recursion, every function is in a component of its own
+        let sccs = callgraph.sccs();
+        assert_eq!(sccs.len(), callgraph.functions().count());
+        assert!(sccs.iter().all(|scc| scc.len() == 1));
+    }
+
+    #[test]
+    fn call_graph_syscall_test() {
+        let context = TestContext::default();
+
+        let signature = Signature {
+            params: vec![AbiParam::new(Type::U32)],
+            results: vec![AbiParam::new(Type::U32)],
+            cc: CallConv::Kernel,
+            linkage: Linkage::External,
+        };
+
+        // Define the 'kernel' module, containing a single syscall
+        let mut kernel = ModuleBuilder::new_kernel("kernel");
+        let mut fb = kernel
+            .function("identity", signature.clone())
+            .expect("unexpected symbol conflict");
+        let v0 = {
+            let entry = fb.entry_block();
+            fb.block_params(entry)[0]
+        };
+        fb.ins().ret(Some(v0), SourceSpan::UNKNOWN);
+        fb.build(&context.diagnostics)
+            .expect("unexpected validation error, see diagnostics output");
+        let kernel = kernel.build();
+
+        // Define the 'test' module, which calls into the kernel
+        let mut mb = ModuleBuilder::new("test");
+        let mut fb = mb
+            .function(
+                "main",
+                Signature::new([AbiParam::new(Type::U32)], [AbiParam::new(Type::U32)]),
+            )
+            .expect("unexpected symbol conflict");
+        let v0 = {
+            let entry = fb.entry_block();
+            fb.block_params(entry)[0]
+        };
+        let callee = fb.import_function("kernel", "identity", signature).unwrap();
+        let call = fb.ins().syscall(callee, &[v0], SourceSpan::UNKNOWN);
+        let v1 = fb.first_result(call);
+        fb.ins().ret(Some(v1), SourceSpan::UNKNOWN);
+        fb.build(&context.diagnostics)
+            .expect("unexpected validation error, see diagnostics output");
+        let module = mb.build();
+
+        // The definition of the callee is unknown to the call graph of the calling module
+        let callgraph = CallGraph::with_module(&module);
+        assert_eq!(
+            callgraph.syscalls().collect::<Vec<_>>(),
+            vec![(id("test::main"), callee)]
+        );
+        assert!(callgraph.contains(callee));
+        assert!(!callgraph.is_defined(callee));
+        assert!(!callgraph.is_kernel(callee));
+        assert_eq!(callgraph.entrypoint(), None);
+
+        let mut builder = ProgramBuilder::new(&context.diagnostics);
+        builder.add_module(kernel).unwrap();
+        builder.add_module(module).unwrap();
+        let program = builder
+            .with_entrypoint(id("test::main"))
+            .link()
+            .expect("failed to link program");
+
+        let callgraph = CallGraph::with_program(&program);
+        assert_eq!(
+            callgraph.syscalls().collect::<Vec<_>>(),
+            vec![(id("test::main"), callee)]
+        );
+        assert!(callgraph.is_defined(callee));
+        assert!(callgraph.is_kernel(callee));
+        assert!(!callgraph.is_kernel(id("test::main")));
+        assert_eq!(
+            callgraph.calls(id("test::main"), callee),
+            Some(CallKinds {
+                call: false,
+                syscall: true
+            })
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use std::{collections::BTreeMap, fmt};
+use std::{collections::BTreeMap, fmt, str::FromStr};
 
 use cranelift_entity::{entity_impl, EntityRef};
 
@@ -104,6 +104,28 @@ impl fmt::Display for ConstantData {
     }
 }
 
+impl FromStr for ConstantData {
+    type Err = ();
+
+    /// Parse constant data from the hexadecimal format produced by the `Display` implementation.
+    ///
+    /// The empty string parses as empty constant data.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Ok(Self::default());
+        }
+        let hex = s.strip_prefix("0x").ok_or(())?;
+        if hex.is_empty() || hex.len() % 2 != 0 || !hex.is_ascii() {
+            return Err(());
+        }
+        let mut bytes = Vec::with_capacity(hex.len() / 2);
+        for i in (0..hex.len()).step_by(2).rev() {
+            bytes.push(u8::from_str_radix(&hex[i..(i + 2)], 16).map_err(|_| ())?);
+        }
+        Ok(Self(bytes))
+    }
+}
+
 /// This maintains the storage for constants used within a function
 #[derive(Default)]
 pub struct ConstantPool {
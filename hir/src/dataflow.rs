@@ -268,6 +268,26 @@ impl DataFlowGraph {
         id
     }
 
+    /// Append an instruction to the end of `block`, using a key allocated in advance with
+    /// `insts.alloc_key`, and result values which were already created for it.
+    ///
+    /// This is used when instructions may refer to results which are defined later on, e.g.
+    /// when parsing; use [DataFlowGraph::append_inst] otherwise.
+    pub(crate) fn append_allocated_inst(
+        &mut self,
+        inst: Inst,
+        block: Block,
+        data: Instruction,
+        results: &[Value],
+        span: SourceSpan,
+    ) {
+        self.insts
+            .append(inst, InstNode::new(inst, block, Span::new(span, data)));
+        self.results[inst].extend(results.iter().copied(), &mut self.value_lists);
+        let data = unsafe { UnsafeRef::from_raw(&self.insts[inst]) };
+        self.blocks[block].append(data);
+    }
+
     /// Create a new instruction which is a clone of `inst`, but detached from any block.
     ///
     /// NOTE: The instruction is in a temporarily invalid state, because if it has arguments,
@@ -414,6 +434,28 @@ impl DataFlowGraph {
                     }
                 }
             }
+            Instruction::Switch(Switch {
+                ref mut arg,
+                ref mut arms,
+                default: (_, ref mut default_args),
+                ..
+            }) => {
+                if arg == &value {
+                    *arg = replacement;
+                }
+                for (_, _, args) in arms.iter_mut() {
+                    for arg in args.as_mut_slice(&mut self.value_lists) {
+                        if arg == &value {
+                            *arg = replacement;
+                        }
+                    }
+                }
+                for arg in default_args.as_mut_slice(&mut self.value_lists) {
+                    if arg == &value {
+                        *arg = replacement;
+                    }
+                }
+            }
             ix => {
                 for arg in ix.arguments_mut(&mut self.value_lists) {
                     if arg == &value {
@@ -424,6 +466,49 @@ impl DataFlowGraph {
         }
     }
 
+    /// Call `f` with the destination and arguments of every edge from `inst` to `dest`,
+    /// which may be more than one if `inst` is a `cond_br` or `switch`
+    ///
+    /// The callback may modify both the arguments and the destination of each edge.
+    /// Nothing is visited if `inst` is not a branch.
+    pub fn visit_edges_to<F>(&mut self, inst: Inst, dest: Block, mut f: F)
+    where
+        F: FnMut(&mut Block, &mut ValueList, &mut ValueListPool),
+    {
+        let pool = &mut self.value_lists;
+        match &mut *self.insts[inst] {
+            Instruction::Br(Br {
+                destination, args, ..
+            }) => {
+                if *destination == dest {
+                    f(destination, args, pool);
+                }
+            }
+            Instruction::CondBr(CondBr {
+                then_dest,
+                else_dest,
+                ..
+            }) => {
+                for (destination, args) in [then_dest, else_dest] {
+                    if *destination == dest {
+                        f(destination, args, pool);
+                    }
+                }
+            }
+            Instruction::Switch(Switch { arms, default, .. }) => {
+                let arms = arms
+                    .iter_mut()
+                    .map(|(_, destination, args)| (destination, args));
+                for (destination, args) in arms.chain([(&mut default.0, &mut default.1)]) {
+                    if *destination == dest {
+                        f(destination, args, pool);
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
     pub fn pp_block(&self, pp: ProgramPoint) -> Block {
         match pp {
             ProgramPoint::Block(block) => block,
@@ -606,6 +691,23 @@ impl DataFlowGraph {
         })
     }
 
+    /// Removes the parameter at `index` from the parameter list of `block`, renumbering the
+    /// parameters which follow it.
+    ///
+    /// NOTE: It is up to the caller to ensure that the parameter is unused, and that the
+    /// corresponding argument is removed from all branches to `block`.
+    pub fn remove_block_param(&mut self, block: Block, index: usize) {
+        self.blocks[block]
+            .params
+            .remove(index, &mut self.value_lists);
+        let params = SmallVec::<[Value; 4]>::from_slice(self.block_params(block));
+        for (num, param) in params.into_iter().enumerate().skip(index) {
+            if let ValueData::Param { num: ref mut n, .. } = self.values[param] {
+                *n = num as u16;
+            }
+        }
+    }
+
     pub fn is_block_terminated(&self, block: Block) -> bool {
         if let Some(inst) = self.last_inst(block) {
             self.inst(inst).opcode().is_terminator()
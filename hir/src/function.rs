@@ -111,6 +111,28 @@ impl fmt::Display for ArgumentExtension {
     }
 }
 
+/// Represents a hint to the inliner about whether calls to a function should be inlined.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Inline {
+    /// The inliner decides whether or not to inline calls to this function,
+    /// based on its own heuristics.
+    #[default]
+    Auto,
+    /// Calls to this function should always be inlined, wherever it is legal to do so
+    Always,
+    /// Calls to this function should never be inlined
+    Never,
+}
+impl fmt::Display for Inline {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Auto => f.write_str("auto"),
+            Self::Always => f.write_str("always"),
+            Self::Never => f.write_str("never"),
+        }
+    }
+}
+
 /// Describes a function parameter or result.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct AbiParam {
@@ -278,6 +300,8 @@ pub struct Function {
     pub id: FunctionIdent,
     pub signature: Signature,
     pub dfg: DataFlowGraph,
+    /// A hint to the inliner regarding calls to this function
+    pub inline: Inline,
 }
 impl Function {
     /// Create a new [Function] with the given name, signature, and source location.
@@ -302,6 +326,7 @@ impl Function {
             id,
             signature,
             dfg,
+            inline: Inline::default(),
         }
     }
 
@@ -357,6 +382,16 @@ impl Function {
         self.signature.cc = cc;
     }
 
+    /// Return the [Inline] hint for this function
+    pub fn inline(&self) -> Inline {
+        self.inline
+    }
+
+    /// Set the [Inline] hint for this function
+    pub fn set_inline(&mut self, inline: Inline) {
+        self.inline = inline;
+    }
+
     /// Iterate over all of the external functions imported by this function
     pub fn imports<'a, 'b: 'a>(&'b self) -> impl Iterator<Item = &'a ExternalFunction> + 'a {
         self.dfg.imports().filter(|ext| ext.id != self.id)
@@ -129,7 +129,15 @@ impl Instruction {
                 else_dest: (br.else_dest.0, br.else_dest.1.deep_clone(value_lists)),
                 ..br.clone()
             }),
-            Self::Switch(op) => Self::Switch(op.clone()),
+            Self::Switch(op) => Self::Switch(Switch {
+                arms: op
+                    .arms
+                    .iter()
+                    .map(|(value, dest, args)| (*value, *dest, args.deep_clone(value_lists)))
+                    .collect(),
+                default: (op.default.0, op.default.1.deep_clone(value_lists)),
+                ..op.clone()
+            }),
             Self::Ret(op) => Self::Ret(Ret {
                 args: op.args.deep_clone(value_lists),
                 ..op.clone()
@@ -200,6 +208,17 @@ impl Instruction {
         self.opcode().is_commutative()
     }
 
+    /// Returns the overflow behavior of this instruction, if applicable
+    pub fn overflow(&self) -> Option<Overflow> {
+        match self {
+            Self::BinaryOp(BinaryOp { overflow, .. })
+            | Self::BinaryOpImm(BinaryOpImm { overflow, .. })
+            | Self::UnaryOp(UnaryOp { overflow, .. })
+            | Self::UnaryOpImm(UnaryOpImm { overflow, .. }) => Some(*overflow),
+            _ => None,
+        }
+    }
+
     pub fn arguments<'a>(&'a self, pool: &'a ValueListPool) -> &[Value] {
         match self {
             Self::BinaryOp(BinaryOp { ref args, .. }) => args.as_slice(),
@@ -254,9 +273,9 @@ impl Instruction {
             }) => {
                 let mut targets = arms
                     .iter()
-                    .map(|(_, b)| JumpTable::new(*b, &[]))
+                    .map(|(_, dest, args)| JumpTable::new(*dest, args.as_slice(pool)))
                     .collect::<Vec<_>>();
-                targets.push(JumpTable::new(*default, &[]));
+                targets.push(JumpTable::new(default.0, default.1.as_slice(pool)));
                 BranchInfo::MultiDest(targets)
             }
             _ => BranchInfo::NotABranch,
@@ -773,7 +792,7 @@ impl fmt::Display for Opcode {
 /// Always check the documentation of the specific instruction involved to see if there
 /// are any specific differences in how this enum is interpreted compared to the default
 /// meaning of each variant.
-#[derive(Copy, Clone, Default, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq, Hash)]
 pub enum Overflow {
     /// Typically, this means the operation is performed using the equivalent field element operation, rather
     /// than a dedicated operation for the given type. Because of this, the result of the operation may exceed
@@ -809,6 +828,16 @@ impl Overflow {
         matches!(self, Self::Overflowing)
     }
 }
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Unchecked => f.write_str("unchecked"),
+            Self::Checked => f.write_str("checked"),
+            Self::Wrapping => f.write_str("wrapping"),
+            Self::Overflowing => f.write_str("overflowing"),
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct GlobalValueOp {
@@ -870,12 +899,14 @@ pub struct CondBr {
 }
 
 /// Multi-way Branch w/Selector
+///
+/// Each arm, as well as the default, may pass arguments to its destination block.
 #[derive(Debug, Clone)]
 pub struct Switch {
     pub op: Opcode,
     pub arg: Value,
-    pub arms: Vec<(u32, Block)>,
-    pub default: Block,
+    pub arms: Vec<(u32, Block, ValueList)>,
+    pub default: (Block, ValueList),
 }
 
 /// Return
@@ -133,6 +133,7 @@ macro_rules! diagnostic {
 mod asm;
 mod block;
 mod builder;
+mod call_graph;
 mod constants;
 mod dataflow;
 mod display;
@@ -145,8 +146,10 @@ mod instruction;
 mod layout;
 mod locals;
 mod module;
+mod parser;
 mod program;
 mod segments;
+mod ssa;
 pub mod testing;
 #[cfg(test)]
 mod tests;
@@ -158,6 +161,7 @@ pub use self::block::{Block, BlockData};
 pub use self::builder::{
     DefaultInstBuilder, FunctionBuilder, InstBuilder, InstBuilderBase, ReplaceBuilder,
 };
+pub use self::call_graph::{CallGraph, CallGraphCycleError, CallKinds};
 pub use self::constants::{Constant, ConstantData, ConstantPool, IntoBytes};
 pub use self::dataflow::DataFlowGraph;
 pub use self::display::{Decorator, DisplayValues};
@@ -170,8 +174,10 @@ pub use self::instruction::*;
 pub use self::layout::{ArenaMap, LayoutAdapter, LayoutNode, OrderedArenaMap};
 pub use self::locals::{Local, LocalId};
 pub use self::module::*;
+pub use self::parser::{ParseError, Parser};
 pub use self::program::{Linker, LinkerError, Program, ProgramBuilder};
 pub use self::segments::{DataSegment, DataSegmentAdapter, DataSegmentError, DataSegmentTable};
+pub use self::ssa::Variable;
 pub use self::value::{Value, ValueData, ValueList, ValueListPool};
 pub use self::write::{write_external_function, write_function};
 
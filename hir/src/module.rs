@@ -9,6 +9,7 @@ use miden_diagnostics::{DiagnosticsHandler, Severity, Spanned};
 use rustc_hash::FxHashSet;
 
 use super::*;
+use crate::ssa::SsaBuilder;
 
 /// This error is raised when two modules conflict with the same symbol name
 #[derive(Debug, thiserror::Error)]
@@ -76,9 +77,15 @@ impl fmt::Display for Module {
             f.write_char('\n')?;
             f.write_str("memory {\n")?;
             for segment in self.segments.iter() {
+                let readonly = if segment.is_readonly() {
+                    "readonly "
+                } else {
+                    ""
+                };
                 writeln!(
                     f,
-                    "    segment @{:#x} x {} = {};",
+                    "    segment {}@{:#x} x {} = {};",
+                    readonly,
                     segment.offset(),
                     segment.size(),
                     segment.init(),
@@ -602,6 +609,7 @@ impl ModuleBuilder {
             builder: self,
             function,
             position: entry,
+            ssa: SsaBuilder::default(),
         })
     }
 
@@ -614,6 +622,7 @@ pub struct ModuleFunctionBuilder<'m> {
     builder: &'m mut ModuleBuilder,
     function: Box<Function>,
     position: Block,
+    ssa: SsaBuilder,
 }
 impl<'m> ModuleFunctionBuilder<'m> {
     pub fn with_span(&mut self, span: SourceSpan) -> &mut Self {
@@ -621,6 +630,12 @@ impl<'m> ModuleFunctionBuilder<'m> {
         self
     }
 
+    /// Set the [Inline] hint for the underlying function
+    pub fn with_inline(&mut self, inline: Inline) -> &mut Self {
+        self.function.set_inline(inline);
+        self
+    }
+
     /// Get the fully-qualified name of the underlying function
     pub fn id(&self) -> FunctionIdent {
         self.function.id
@@ -695,6 +710,41 @@ impl<'m> ModuleFunctionBuilder<'m> {
         DefaultInstBuilder::new(&mut self.function.dfg, self.position)
     }
 
+    /// Declare a new [Variable] of type `ty`, see [FunctionBuilder::declare_var]
+    pub fn declare_var(&mut self, ty: Type) -> Variable {
+        self.ssa.declare_var(ty)
+    }
+
+    /// Define the value of `var` at the current position, see [FunctionBuilder::def_var]
+    pub fn def_var(&mut self, var: Variable, value: Value) {
+        self.ssa
+            .def_var(&self.function.dfg, var, value, self.position)
+    }
+
+    /// Get the value of `var` at the current position, see [FunctionBuilder::use_var]
+    pub fn use_var(&mut self, var: Variable) -> Value {
+        self.ssa.use_var(&mut self.function.dfg, var, self.position)
+    }
+
+    /// Seal `block`, indicating that all of its predecessors have been built,
+    /// see [FunctionBuilder::seal_block]
+    pub fn seal_block(&mut self, block: Block) {
+        self.ssa.seal_block(&mut self.function.dfg, block)
+    }
+
+    /// Seal all blocks in the function which have not yet been sealed
+    pub fn seal_all_blocks(&mut self) {
+        let blocks = self
+            .function
+            .dfg
+            .blocks()
+            .map(|(b, _)| b)
+            .collect::<Vec<_>>();
+        for block in blocks.into_iter() {
+            self.ssa.seal_block(&mut self.function.dfg, block);
+        }
+    }
+
     pub fn build(
         self,
         diagnostics: &DiagnosticsHandler,
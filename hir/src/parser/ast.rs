@@ -0,0 +1,169 @@
+use cranelift_entity::PrimaryMap;
+use miden_diagnostics::SourceSpan;
+
+use crate::{
+    Block, ConstantData, FunctionIdent, Ident, Inline, Linkage, MasmBlock, MasmBlockId, Opcode,
+    Overflow, Signature, Type, Value,
+};
+
+/// The syntax tree of a module in textual IR form
+pub(super) struct ModuleAst {
+    pub name: Ident,
+    pub is_kernel: bool,
+    pub segments: Vec<SegmentAst>,
+    pub globals: Vec<GlobalVarAst>,
+    pub functions: Vec<FunctionAst>,
+    pub externals: Vec<ExternalAst>,
+}
+
+/// A data segment declaration, e.g. `segment @0x1000 x 8 = 0x...;`
+pub(super) struct SegmentAst {
+    pub span: SourceSpan,
+    pub offset: u32,
+    pub size: u32,
+    pub init: ConstantData,
+    pub readonly: bool,
+}
+
+/// A global variable declaration, e.g. `global external foo : u32 = 0x... { id = gvar0 };`
+pub(super) struct GlobalVarAst {
+    pub span: SourceSpan,
+    pub name: Ident,
+    pub linkage: Linkage,
+    pub ty: Type,
+    pub init: Option<ConstantData>,
+}
+
+/// A declaration of a function defined in another module
+pub(super) struct ExternalAst {
+    pub span: SourceSpan,
+    pub id: FunctionIdent,
+    pub signature: Signature,
+}
+
+/// A function definition
+pub(super) struct FunctionAst {
+    pub span: SourceSpan,
+    pub name: Ident,
+    pub signature: Signature,
+    pub inline: Inline,
+    pub blocks: Vec<BlockAst>,
+}
+
+pub(super) struct BlockAst {
+    pub span: SourceSpan,
+    pub id: BlockRef,
+    pub params: Vec<(ValueRef, Type)>,
+    pub insts: Vec<InstAst>,
+}
+
+/// A reference to a value by the name it was given in the source text, e.g. `v3`
+#[derive(Copy, Clone)]
+pub(super) struct ValueRef {
+    pub span: SourceSpan,
+    pub value: Value,
+}
+
+/// A reference to a block by the name it was given in the source text, e.g. `block3`
+#[derive(Copy, Clone)]
+pub(super) struct BlockRef {
+    pub span: SourceSpan,
+    pub block: Block,
+}
+
+pub(super) struct InstAst {
+    pub span: SourceSpan,
+    pub results: Vec<ValueRef>,
+    pub types: Vec<Type>,
+    pub op: OpAst,
+}
+
+/// The untyped form of an instruction, as parsed from the source text.
+///
+/// Immediates are typed, and the concrete [crate::Instruction] variant selected,
+/// when the instruction is lowered.
+pub(super) enum OpAst {
+    GlobalValue(GlobalValueAst),
+    Binary {
+        op: Opcode,
+        overflow: Overflow,
+        lhs: ValueRef,
+        rhs: Operand,
+    },
+    Unary {
+        op: Opcode,
+        overflow: Overflow,
+        arg: Operand,
+    },
+    Call {
+        op: Opcode,
+        callee: FunctionIdent,
+        args: Vec<ValueRef>,
+    },
+    Br {
+        destination: BlockRef,
+        args: Vec<ValueRef>,
+    },
+    CondBr {
+        cond: ValueRef,
+        then_dest: (BlockRef, Vec<ValueRef>),
+        else_dest: (BlockRef, Vec<ValueRef>),
+    },
+    Switch {
+        arg: ValueRef,
+        arms: Vec<(u32, BlockRef, Vec<ValueRef>)>,
+        default: (BlockRef, Vec<ValueRef>),
+    },
+    Ret {
+        args: Vec<Operand>,
+    },
+    Test {
+        arg: ValueRef,
+        ty: Type,
+    },
+    Load {
+        addr: ValueRef,
+    },
+    PrimOp {
+        op: Opcode,
+        args: Vec<Operand>,
+    },
+    InlineAsm {
+        args: Vec<ValueRef>,
+        body: MasmBlockId,
+        blocks: PrimaryMap<MasmBlockId, MasmBlock>,
+    },
+}
+
+/// An instruction operand, which is either a value or an (untyped) immediate
+#[derive(Copy, Clone)]
+pub(super) enum Operand {
+    Value(ValueRef),
+    Immediate(Literal, SourceSpan),
+}
+
+/// A literal value whose type has not yet been determined
+#[derive(Debug, Copy, Clone)]
+pub(super) enum Literal {
+    Bool(bool),
+    Int(i128),
+    Float(f64),
+}
+
+pub(super) enum GlobalValueAst {
+    Symbol {
+        name: Ident,
+        offset: i32,
+    },
+    Load {
+        base: Box<GlobalValueAst>,
+        offset: i32,
+        ty: Option<Type>,
+        span: SourceSpan,
+    },
+    IAddImm {
+        base: Box<GlobalValueAst>,
+        offset: i32,
+        ty: Type,
+    },
+}
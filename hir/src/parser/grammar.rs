@@ -0,0 +1,999 @@
+use std::{num::NonZeroU16, str::FromStr};
+
+use miden_diagnostics::SourceSpan;
+
+use super::{ast::*, masm, scanner::Scanner, ParseResult, SyntaxError};
+use crate::{
+    AbiParam, AddressSpace, ArgumentExtension, ArgumentPurpose, Block, CallConv, ConstantData,
+    FunctionIdent, Ident, Inline, Linkage, Opcode, Overflow, Signature, StructType, Symbol, Type,
+    Value,
+};
+use miden_hir_type::TypeRepr;
+
+/// A recursive-descent parser for the textual IR format, as emitted by the
+/// `Display` implementation of [crate::Module].
+pub(super) struct Grammar<'a> {
+    scanner: Scanner<'a>,
+    module: Ident,
+}
+impl<'a> Grammar<'a> {
+    pub fn new(scanner: Scanner<'a>) -> Self {
+        Self {
+            scanner,
+            module: Ident::default(),
+        }
+    }
+
+    /// module ::= ('module' | 'kernel') NAME memory? global* (function | external)*
+    pub fn parse_module(&mut self) -> ParseResult<ModuleAst> {
+        let is_kernel = if self.scanner.eat_keyword("kernel") {
+            true
+        } else {
+            self.scanner.expect_keyword("module")?;
+            false
+        };
+        let name = self.ident()?;
+        self.module = name;
+
+        let mut segments = vec![];
+        if self.scanner.eat_keyword("memory") {
+            self.scanner.expect("{")?;
+            while !self.scanner.eat("}") {
+                segments.push(self.parse_segment()?);
+            }
+        }
+
+        let mut globals = vec![];
+        while self.scanner.is_next_keyword("global") {
+            globals.push(self.parse_global()?);
+        }
+
+        let mut functions = vec![];
+        let mut externals = vec![];
+        while !self.scanner.is_eof() {
+            let start = self.scanner.pos();
+            let (name, signature, inline) = self.parse_signature()?;
+            if self.scanner.eat(";") {
+                if inline != Inline::Auto {
+                    return Err(SyntaxError::new(
+                        self.scanner.span_from(start),
+                        "invalid external function declaration",
+                        "inline hints may only be given for function definitions",
+                    ));
+                }
+                let span = self.scanner.span_from(start);
+                let id = FunctionIdent::from_str(name.as_str()).map_err(|_| {
+                    SyntaxError::new(
+                        name.span,
+                        "invalid external function declaration",
+                        "expected a fully-qualified name, e.g. 'std::math::u64::checked_add'",
+                    )
+                })?;
+                let id = FunctionIdent {
+                    module: Ident::new(id.module.as_symbol(), name.span),
+                    function: Ident::new(id.function.as_symbol(), name.span),
+                };
+                externals.push(ExternalAst {
+                    span,
+                    id,
+                    signature,
+                });
+                continue;
+            }
+
+            if name.as_str().contains("::") {
+                return Err(SyntaxError::new(
+                    name.span,
+                    "invalid function definition",
+                    "functions defined in a module must not use a qualified name",
+                ));
+            }
+            self.scanner.expect("{")?;
+            let mut blocks = vec![];
+            while !self.scanner.eat("}") {
+                blocks.push(self.parse_block()?);
+            }
+            let span = self.scanner.span_from(start);
+            functions.push(FunctionAst {
+                span,
+                name,
+                signature,
+                inline,
+                blocks,
+            });
+        }
+
+        Ok(ModuleAst {
+            name,
+            is_kernel,
+            segments,
+            globals,
+            functions,
+            externals,
+        })
+    }
+
+    /// segment ::= 'segment' 'readonly'? '@' INT 'x' INT '=' CONSTANT? ';'
+    fn parse_segment(&mut self) -> ParseResult<SegmentAst> {
+        let start = self.scanner.pos();
+        self.scanner.expect_keyword("segment")?;
+        let readonly = self.scanner.eat_keyword("readonly");
+        self.scanner.expect("@")?;
+        let (offset, _) = self.scanner.integer_of::<u32>("a 32-bit offset")?;
+        self.scanner.expect_keyword("x")?;
+        let (size, _) = self.scanner.integer_of::<u32>("a 32-bit size")?;
+        self.scanner.expect("=")?;
+        let init = if self.scanner.is_next(";") {
+            ConstantData::default()
+        } else {
+            self.parse_constant()?
+        };
+        self.scanner.expect(";")?;
+        Ok(SegmentAst {
+            span: self.scanner.span_from(start),
+            offset,
+            size,
+            init,
+            readonly,
+        })
+    }
+
+    /// global ::= 'global' LINKAGE NAME ':' TYPE ('=' CONSTANT)? '{' 'id' '=' GVAR '}' ';'
+    fn parse_global(&mut self) -> ParseResult<GlobalVarAst> {
+        let start = self.scanner.pos();
+        self.scanner.expect_keyword("global")?;
+        let linkage = if self.scanner.eat_keyword("internal") {
+            Linkage::Internal
+        } else if self.scanner.eat_keyword("odr") {
+            Linkage::Odr
+        } else if self.scanner.eat_keyword("external") {
+            Linkage::External
+        } else {
+            return Err(self.scanner.unexpected("linkage"));
+        };
+        let name = self.ident()?;
+        self.scanner.expect(":")?;
+        let ty = self.parse_type()?;
+        let init = if self.scanner.eat("=") {
+            if self.scanner.is_next("{") {
+                Some(ConstantData::default())
+            } else {
+                Some(self.parse_constant()?)
+            }
+        } else {
+            None
+        };
+        // The global variable id is informational only, ids are assigned in declaration order
+        self.scanner.expect("{")?;
+        self.scanner.expect_keyword("id")?;
+        self.scanner.expect("=")?;
+        self.scanner.ident()?;
+        self.scanner.expect("}")?;
+        self.scanner.expect(";")?;
+        Ok(GlobalVarAst {
+            span: self.scanner.span_from(start),
+            name,
+            linkage,
+            ty,
+            init,
+        })
+    }
+
+    fn parse_constant(&mut self) -> ParseResult<ConstantData> {
+        let (token, span) = self.scanner.word()?;
+        ConstantData::from_str(token).map_err(|_| {
+            SyntaxError::new(
+                span,
+                "invalid constant",
+                "expected constant data in hexadecimal format, e.g. 0x0001",
+            )
+        })
+    }
+
+    /// signature ::= 'pub'? ('cc' '(' CC ')')? ('inline' '(' INLINE ')')?
+    ///               'fn' NAME '(' params ')' ('->' results)?
+    fn parse_signature(&mut self) -> ParseResult<(Ident, Signature, Inline)> {
+        let linkage = if self.scanner.eat_keyword("pub") {
+            Linkage::External
+        } else {
+            Linkage::Internal
+        };
+        let cc = if self.scanner.eat_keyword("cc") {
+            self.scanner.expect("(")?;
+            let cc = if self.scanner.eat_keyword("fast") {
+                CallConv::Fast
+            } else if self.scanner.eat_keyword("kernel") {
+                CallConv::Kernel
+            } else if self.scanner.eat_keyword("C") {
+                CallConv::SystemV
+            } else {
+                return Err(self.scanner.unexpected("calling convention"));
+            };
+            self.scanner.expect(")")?;
+            cc
+        } else {
+            CallConv::SystemV
+        };
+        let inline = if self.scanner.eat_keyword("inline") {
+            self.scanner.expect("(")?;
+            let inline = if self.scanner.eat_keyword("auto") {
+                Inline::Auto
+            } else if self.scanner.eat_keyword("always") {
+                Inline::Always
+            } else if self.scanner.eat_keyword("never") {
+                Inline::Never
+            } else {
+                return Err(self.scanner.unexpected("inline hint"));
+            };
+            self.scanner.expect(")")?;
+            inline
+        } else {
+            Inline::Auto
+        };
+        self.scanner.expect_keyword("fn")?;
+        let name = self.ident()?;
+
+        self.scanner.expect("(")?;
+        let mut params = vec![];
+        if !self.scanner.eat(")") {
+            loop {
+                let purpose = if self.scanner.eat_keyword("sret") {
+                    ArgumentPurpose::StructReturn
+                } else {
+                    ArgumentPurpose::Default
+                };
+                let extension = self.parse_extension();
+                let ty = self.parse_type()?;
+                params.push(AbiParam {
+                    ty,
+                    purpose,
+                    extension,
+                });
+                if self.scanner.eat(")") {
+                    break;
+                }
+                self.scanner.expect(",")?;
+            }
+        }
+
+        let mut results = vec![];
+        if self.scanner.eat("->") {
+            loop {
+                let extension = self.parse_extension();
+                let ty = self.parse_type()?;
+                results.push(AbiParam {
+                    ty,
+                    purpose: ArgumentPurpose::Default,
+                    extension,
+                });
+                if !self.scanner.eat(",") {
+                    break;
+                }
+            }
+        }
+
+        let signature = Signature {
+            params,
+            results,
+            cc,
+            linkage,
+        };
+        Ok((name, signature, inline))
+    }
+
+    fn parse_extension(&mut self) -> ArgumentExtension {
+        if self.scanner.eat_keyword("zext") {
+            ArgumentExtension::Zext
+        } else if self.scanner.eat_keyword("sext") {
+            ArgumentExtension::Sext
+        } else {
+            ArgumentExtension::None
+        }
+    }
+
+    /// block ::= BLOCK ('(' (VALUE ':' TYPE),* ')')? ':' inst*
+    fn parse_block(&mut self) -> ParseResult<BlockAst> {
+        let start = self.scanner.pos();
+        let id = self.block_ref()?;
+        let mut params = vec![];
+        if self.scanner.eat("(") {
+            loop {
+                let value = self.value_ref()?;
+                self.scanner.expect(":")?;
+                let ty = self.parse_type()?;
+                params.push((value, ty));
+                if self.scanner.eat(")") {
+                    break;
+                }
+                self.scanner.expect(",")?;
+            }
+        }
+        self.scanner.expect(":")?;
+        let span = self.scanner.span_from(start);
+
+        let mut insts = vec![];
+        while !self.scanner.is_next("}") && !self.is_next_block() {
+            insts.push(self.parse_inst()?);
+        }
+
+        Ok(BlockAst {
+            span,
+            id,
+            params,
+            insts,
+        })
+    }
+
+    /// inst ::= ((VALUE,)+ '=')? OPCODE operands (':' TYPE,+)?
+    fn parse_inst(&mut self) -> ParseResult<InstAst> {
+        self.scanner.skip_trivia();
+        let start = self.scanner.pos();
+        let mut results = vec![];
+        if self.is_next_value() {
+            loop {
+                results.push(self.value_ref()?);
+                if self.scanner.eat("=") {
+                    break;
+                }
+                self.scanner.expect(",")?;
+            }
+        }
+
+        let op = self.parse_op()?;
+        let span = self.scanner.span_from(start);
+
+        let mut types = vec![];
+        if !results.is_empty() {
+            self.scanner.expect(":")?;
+            loop {
+                types.push(self.parse_type()?);
+                if !self.scanner.eat(",") {
+                    break;
+                }
+            }
+            if types.len() != results.len() {
+                return Err(SyntaxError::new(
+                    self.scanner.span_from(start),
+                    "invalid instruction",
+                    format!(
+                        "expected {} result types, but got {}",
+                        results.len(),
+                        types.len()
+                    ),
+                ));
+            }
+        }
+
+        Ok(InstAst {
+            span,
+            results,
+            types,
+            op,
+        })
+    }
+
+    fn parse_op(&mut self) -> ParseResult<OpAst> {
+        let (word, span) = self.scanner.word()?;
+        let start = self.scanner.pos() - word.len();
+
+        // These opcodes embed a type or expression immediately following the opcode
+        if word == "global" || word.starts_with("global.") {
+            self.scanner.reset(start + "global".len());
+            return self.parse_global_value(span);
+        }
+        if word == "test" || word.starts_with("test.") {
+            self.scanner.reset(start + "test".len());
+            if self.scanner.peek_char() != Some('.') {
+                return Err(self.scanner.unexpected("'.'"));
+            }
+            self.scanner.reset(start + "test.".len());
+            let ty = self.parse_type()?;
+            let arg = self.value_ref()?;
+            return Ok(OpAst::Test { arg, ty });
+        }
+
+        let (opcode, overflow) = parse_opcode(word).ok_or_else(|| {
+            SyntaxError::new(
+                span,
+                format!("invalid opcode '{word}'"),
+                "this is not a recognized opcode",
+            )
+        })?;
+
+        let op = match opcode {
+            Opcode::Call | Opcode::Syscall => {
+                let callee = self.ident()?;
+                let callee = FunctionIdent::from_str(callee.as_str())
+                    .map(|id| FunctionIdent {
+                        module: Ident::new(id.module.as_symbol(), callee.span),
+                        function: Ident::new(id.function.as_symbol(), callee.span),
+                    })
+                    .map_err(|_| {
+                        SyntaxError::new(
+                            callee.span,
+                            "invalid callee",
+                            "expected a fully-qualified function name",
+                        )
+                    })?;
+                self.scanner.expect("(")?;
+                let args = self.value_list(")")?;
+                OpAst::Call {
+                    op: opcode,
+                    callee,
+                    args,
+                }
+            }
+            Opcode::Br => {
+                let (destination, args) = self.successor()?;
+                OpAst::Br { destination, args }
+            }
+            Opcode::CondBr => {
+                let cond = self.value_ref()?;
+                self.scanner.expect(",")?;
+                let then_dest = self.successor()?;
+                self.scanner.expect(",")?;
+                let else_dest = self.successor()?;
+                OpAst::CondBr {
+                    cond,
+                    then_dest,
+                    else_dest,
+                }
+            }
+            Opcode::Switch => {
+                let arg = self.value_ref()?;
+                let mut arms = vec![];
+                loop {
+                    self.scanner.expect(",")?;
+                    if self.is_next_block() {
+                        break;
+                    }
+                    let (value, _) = self.scanner.integer_of::<u32>("a 32-bit integer")?;
+                    self.scanner.expect("=>")?;
+                    let (dest, args) = self.successor()?;
+                    arms.push((value, dest, args));
+                }
+                let default = self.successor()?;
+                OpAst::Switch { arg, arms, default }
+            }
+            Opcode::Ret => OpAst::Ret {
+                args: self.operand_list()?,
+            },
+            Opcode::Load => OpAst::Load {
+                addr: self.value_ref()?,
+            },
+            Opcode::InlineAsm => {
+                self.scanner.expect("(")?;
+                let args = self.value_list(")")?;
+                self.scanner.expect("{")?;
+                let (body, blocks) = masm::parse_body(&mut self.scanner, self.module)?;
+                self.scanner.expect("}")?;
+                OpAst::InlineAsm { args, body, blocks }
+            }
+            Opcode::ImmI1
+            | Opcode::ImmU8
+            | Opcode::ImmI8
+            | Opcode::ImmU16
+            | Opcode::ImmI16
+            | Opcode::ImmU32
+            | Opcode::ImmI32
+            | Opcode::ImmU64
+            | Opcode::ImmI64
+            | Opcode::ImmFelt
+            | Opcode::ImmF64 => OpAst::Unary {
+                op: opcode,
+                overflow,
+                arg: self.operand()?,
+            },
+            Opcode::Alloca
+            | Opcode::Store
+            | Opcode::MemCpy
+            | Opcode::Select
+            | Opcode::Assert
+            | Opcode::Assertz
+            | Opcode::AssertEq
+            | Opcode::Unreachable => OpAst::PrimOp {
+                op: opcode,
+                args: self.operand_list()?,
+            },
+            op if is_binary(op) => {
+                let lhs = self.value_ref()?;
+                self.scanner.expect(",")?;
+                let rhs = self.operand()?;
+                OpAst::Binary {
+                    op,
+                    overflow,
+                    lhs,
+                    rhs,
+                }
+            }
+            op => OpAst::Unary {
+                op,
+                overflow,
+                arg: self.operand()?,
+            },
+        };
+
+        Ok(op)
+    }
+
+    /// global ::= '.symbol' SYMBOL
+    ///          | '.load' ('(' gv ')' OFFSET? ('as' TYPE)? | gv)
+    ///          | '.iadd.' INT '.' TYPE gv
+    fn parse_global_value(&mut self, span: SourceSpan) -> ParseResult<OpAst> {
+        let start = self.scanner.pos();
+        let gv = if self.scanner.eat(".symbol") {
+            self.parse_symbol()?
+        } else if self.scanner.eat(".load") {
+            if self.scanner.eat("(") {
+                let base = self.parse_nested_global_value()?;
+                self.scanner.expect(")")?;
+                self.parse_global_load_suffix(base, start)?
+            } else {
+                let base = self.parse_nested_global_value()?;
+                GlobalValueAst::Load {
+                    base: Box::new(base),
+                    offset: 0,
+                    ty: None,
+                    span: self.scanner.span_from(start),
+                }
+            }
+        } else if self.scanner.eat(".iadd.") {
+            self.parse_global_iadd()?
+        } else {
+            return Err(SyntaxError::new(
+                span,
+                "invalid global value",
+                "expected one of 'global.symbol', 'global.load' or 'global.iadd'",
+            ));
+        };
+        Ok(OpAst::GlobalValue(gv))
+    }
+
+    /// gv ::= SYMBOL | '*' gv | '*(' gv ')' OFFSET? ('as' TYPE)? | 'iadd.' INT '.' TYPE gv
+    fn parse_nested_global_value(&mut self) -> ParseResult<GlobalValueAst> {
+        let start = {
+            self.scanner.skip_trivia();
+            self.scanner.pos()
+        };
+        if self.scanner.is_next("@") {
+            self.parse_symbol()
+        } else if self.scanner.eat("*") {
+            if self.scanner.peek_char() == Some('(') {
+                self.scanner.expect("(")?;
+                let base = self.parse_nested_global_value()?;
+                self.scanner.expect(")")?;
+                self.parse_global_load_suffix(base, start)
+            } else {
+                let base = self.parse_nested_global_value()?;
+                Ok(GlobalValueAst::Load {
+                    base: Box::new(base),
+                    offset: 0,
+                    ty: None,
+                    span: self.scanner.span_from(start),
+                })
+            }
+        } else if self.scanner.eat("iadd.") {
+            self.parse_global_iadd()
+        } else {
+            Err(self.scanner.unexpected("global value expression"))
+        }
+    }
+
+    fn parse_global_load_suffix(
+        &mut self,
+        base: GlobalValueAst,
+        start: usize,
+    ) -> ParseResult<GlobalValueAst> {
+        let offset = self.parse_offset()?;
+        let ty = if self.scanner.eat_keyword("as") {
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+        Ok(GlobalValueAst::Load {
+            base: Box::new(base),
+            offset,
+            ty,
+            span: self.scanner.span_from(start),
+        })
+    }
+
+    fn parse_global_iadd(&mut self) -> ParseResult<GlobalValueAst> {
+        let (offset, _) = self.scanner.integer_of::<i32>("a 32-bit offset")?;
+        self.scanner.expect(".")?;
+        let ty = self.parse_type()?;
+        let base = self.parse_nested_global_value()?;
+        Ok(GlobalValueAst::IAddImm {
+            base: Box::new(base),
+            offset,
+            ty,
+        })
+    }
+
+    fn parse_symbol(&mut self) -> ParseResult<GlobalValueAst> {
+        self.scanner.expect("@")?;
+        let name = self.ident()?;
+        let offset = self.parse_offset()?;
+        Ok(GlobalValueAst::Symbol { name, offset })
+    }
+
+    /// Parses an optional offset, i.e. `+N` or `-N`, immediately following the previous lexeme
+    fn parse_offset(&mut self) -> ParseResult<i32> {
+        match self.scanner.peek_char() {
+            Some('+') => {
+                self.scanner.reset(self.scanner.pos() + 1);
+                self.scanner
+                    .integer_of::<i32>("a 32-bit offset")
+                    .map(|(i, _)| i)
+            }
+            Some('-') => self
+                .scanner
+                .integer_of::<i32>("a 32-bit offset")
+                .map(|(i, _)| i),
+            _ => Ok(0),
+        }
+    }
+
+    /// type ::= '?' | '()' | '!' | PRIMITIVE | '*mut' ('(' 'addrspace' ADDRSPACE ')')? type
+    ///        | '[' type ';' INT ']' | 'struct' ('#[repr(' REPR ')]')? '{' type,* '}'
+    fn parse_type(&mut self) -> ParseResult<Type> {
+        if self.scanner.eat("?") {
+            return Ok(Type::Unknown);
+        }
+        if self.scanner.eat("()") {
+            return Ok(Type::Unit);
+        }
+        if self.scanner.eat("!") {
+            return Ok(Type::Never);
+        }
+        if self.scanner.eat("*mut") {
+            if self.scanner.peek_char() == Some('(') {
+                self.scanner.expect("(")?;
+                self.scanner.expect_keyword("addrspace")?;
+                let addrspace = if self.scanner.eat("?") {
+                    AddressSpace::Unknown
+                } else {
+                    match self.scanner.integer_of::<u16>("an address space identifier")? {
+                        (0, _) => AddressSpace::Root,
+                        (id, _) => AddressSpace::Id(NonZeroU16::new(id).unwrap()),
+                    }
+                };
+                self.scanner.expect(")")?;
+                let pointee = self.parse_type()?;
+                return Ok(Type::NativePtr(Box::new(pointee), addrspace));
+            }
+            let pointee = self.parse_type()?;
+            return Ok(Type::Ptr(Box::new(pointee)));
+        }
+        if self.scanner.eat("[") {
+            let element_ty = self.parse_type()?;
+            self.scanner.expect(";")?;
+            let (arity, _) = self.scanner.integer_of::<usize>("an array length")?;
+            self.scanner.expect("]")?;
+            return Ok(Type::Array(Box::new(element_ty), arity));
+        }
+        if self.scanner.eat_keyword("struct") {
+            let repr = if self.scanner.eat("#[") {
+                self.scanner.expect_keyword("repr")?;
+                self.scanner.expect("(")?;
+                let repr = if self.scanner.eat_keyword("transparent") {
+                    TypeRepr::Transparent
+                } else if self.scanner.eat_keyword("align") {
+                    self.scanner.expect("(")?;
+                    let (n, _) = self.parse_alignment()?;
+                    self.scanner.expect(")")?;
+                    TypeRepr::align(n)
+                } else if self.scanner.eat_keyword("packed") {
+                    self.scanner.expect("(")?;
+                    let (n, _) = self.parse_alignment()?;
+                    self.scanner.expect(")")?;
+                    TypeRepr::packed(n)
+                } else {
+                    return Err(self.scanner.unexpected("type representation"));
+                };
+                self.scanner.expect(")")?;
+                self.scanner.expect("]")?;
+                repr
+            } else {
+                TypeRepr::Default
+            };
+            self.scanner.expect("{")?;
+            let mut fields = vec![];
+            if !self.scanner.eat("}") {
+                loop {
+                    fields.push(self.parse_type()?);
+                    if self.scanner.eat("}") {
+                        break;
+                    }
+                    self.scanner.expect(",")?;
+                }
+            }
+            return Ok(Type::Struct(StructType::new_with_repr(repr, fields)));
+        }
+
+        let start = self.scanner.pos();
+        let (name, span) = self.scanner.ident()?;
+        let ty = match name {
+            "i1" => Type::I1,
+            "i8" => Type::I8,
+            "u8" => Type::U8,
+            "i16" => Type::I16,
+            "u16" => Type::U16,
+            "i32" => Type::I32,
+            "u32" => Type::U32,
+            "i64" => Type::I64,
+            "u64" => Type::U64,
+            "i128" => Type::I128,
+            "u128" => Type::U128,
+            "u256" => Type::U256,
+            "f64" => Type::F64,
+            "felt" => Type::Felt,
+            _ => {
+                self.scanner.reset(start);
+                return Err(SyntaxError::new(
+                    span,
+                    format!("unknown type '{name}'"),
+                    "expected a type",
+                ));
+            }
+        };
+        Ok(ty)
+    }
+
+    fn parse_alignment(&mut self) -> ParseResult<(u16, SourceSpan)> {
+        let (n, span) = self.scanner.integer_of::<u16>("an alignment")?;
+        if n == 0 {
+            return Err(SyntaxError::new(
+                span,
+                "invalid alignment",
+                "alignment must be non-zero",
+            ));
+        }
+        Ok((n, span))
+    }
+
+    fn ident(&mut self) -> ParseResult<Ident> {
+        let (name, span) = self.scanner.ident()?;
+        Ok(Ident::new(Symbol::intern(name), span))
+    }
+
+    fn is_next_value(&mut self) -> bool {
+        self.scanner.skip_trivia();
+        let start = self.scanner.pos();
+        let is_value = self.value_ref().is_ok();
+        self.scanner.reset(start);
+        is_value
+    }
+
+    fn is_next_block(&mut self) -> bool {
+        self.scanner.skip_trivia();
+        let start = self.scanner.pos();
+        let is_block = self.block_ref().is_ok();
+        self.scanner.reset(start);
+        is_block
+    }
+
+    fn value_ref(&mut self) -> ParseResult<ValueRef> {
+        self.scanner.skip_trivia();
+        let start = self.scanner.pos();
+        let (name, span) = self.scanner.ident()?;
+        match parse_entity_name(name, "v") {
+            Some(index) => Ok(ValueRef {
+                span,
+                value: Value::from_u32(index),
+            }),
+            None => {
+                self.scanner.reset(start);
+                Err(self.scanner.unexpected("value"))
+            }
+        }
+    }
+
+    fn block_ref(&mut self) -> ParseResult<BlockRef> {
+        self.scanner.skip_trivia();
+        let start = self.scanner.pos();
+        let (name, span) = self.scanner.ident()?;
+        match parse_entity_name(name, "block") {
+            Some(index) => Ok(BlockRef {
+                span,
+                block: Block::from_u32(index),
+            }),
+            None => {
+                self.scanner.reset(start);
+                Err(self.scanner.unexpected("block"))
+            }
+        }
+    }
+
+    /// successor ::= BLOCK ('(' VALUE,* ')')?
+    fn successor(&mut self) -> ParseResult<(BlockRef, Vec<ValueRef>)> {
+        let block = self.block_ref()?;
+        let args = if self.scanner.peek_char() == Some('(') {
+            self.scanner.expect("(")?;
+            self.value_list(")")?
+        } else {
+            vec![]
+        };
+        Ok((block, args))
+    }
+
+    /// Parses a comma-separated list of values, terminated by `end`
+    fn value_list(&mut self, end: &str) -> ParseResult<Vec<ValueRef>> {
+        let mut values = vec![];
+        if self.scanner.eat(end) {
+            return Ok(values);
+        }
+        loop {
+            values.push(self.value_ref()?);
+            if self.scanner.eat(end) {
+                break Ok(values);
+            }
+            self.scanner.expect(",")?;
+        }
+    }
+
+    /// Parses a possibly-empty, comma-separated list of operands.
+    ///
+    /// The list is only considered non-empty if the first operand is on the same line
+    /// as the preceding opcode, so that an instruction with no operands is not confused
+    /// with the results of the next instruction.
+    fn operand_list(&mut self) -> ParseResult<Vec<Operand>> {
+        let mut operands = vec![];
+        if !self.scanner.skip_inline_whitespace() {
+            return Ok(operands);
+        }
+        if !self.is_next_value() && !self.scanner.is_next_literal() {
+            return Ok(operands);
+        }
+        loop {
+            operands.push(self.operand()?);
+            if !self.scanner.eat(",") {
+                break Ok(operands);
+            }
+        }
+    }
+
+    fn operand(&mut self) -> ParseResult<Operand> {
+        if self.is_next_value() {
+            return self.value_ref().map(Operand::Value);
+        }
+        if self.scanner.is_next_literal() {
+            let (lit, span) = self.scanner.literal()?;
+            return Ok(Operand::Immediate(lit, span));
+        }
+        Err(self.scanner.unexpected("value or immediate"))
+    }
+}
+
+/// Parses the numeric suffix of an entity name like `v1` or `block1`
+fn parse_entity_name(name: &str, prefix: &str) -> Option<u32> {
+    let digits = name.strip_prefix(prefix)?;
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+/// Returns true if `op` is a binary operator, i.e. it takes two operands, the second of
+/// which may be given as an immediate.
+fn is_binary(op: Opcode) -> bool {
+    matches!(
+        op,
+        Opcode::Add
+            | Opcode::Sub
+            | Opcode::Mul
+            | Opcode::Div
+            | Opcode::Mod
+            | Opcode::DivMod
+            | Opcode::Exp
+            | Opcode::And
+            | Opcode::Band
+            | Opcode::Or
+            | Opcode::Bor
+            | Opcode::Xor
+            | Opcode::Bxor
+            | Opcode::Shl
+            | Opcode::Shr
+            | Opcode::Rotl
+            | Opcode::Rotr
+            | Opcode::Eq
+            | Opcode::Neq
+            | Opcode::Gt
+            | Opcode::Gte
+            | Opcode::Lt
+            | Opcode::Lte
+            | Opcode::Min
+            | Opcode::Max
+    )
+}
+
+/// Parses an opcode, along with its optional overflow suffix, e.g. `add.checked`
+fn parse_opcode(word: &str) -> Option<(Opcode, Overflow)> {
+    if let Some(opcode) = opcode_from_name(word) {
+        return Some((opcode, Overflow::Unchecked));
+    }
+    let (name, suffix) = word.rsplit_once('.')?;
+    let overflow = match suffix {
+        "unchecked" => Overflow::Unchecked,
+        "checked" => Overflow::Checked,
+        "wrapping" => Overflow::Wrapping,
+        "overflowing" => Overflow::Overflowing,
+        _ => return None,
+    };
+    opcode_from_name(name).map(|opcode| (opcode, overflow))
+}
+
+/// The inverse of the `Display` implementation for [Opcode]
+fn opcode_from_name(name: &str) -> Option<Opcode> {
+    let opcode = match name {
+        "assert" => Opcode::Assert,
+        "assertz" => Opcode::Assertz,
+        "assert.eq" => Opcode::AssertEq,
+        "const.i1" => Opcode::ImmI1,
+        "const.u8" => Opcode::ImmU8,
+        "const.i8" => Opcode::ImmI8,
+        "const.u16" => Opcode::ImmU16,
+        "const.i16" => Opcode::ImmI16,
+        "const.u32" => Opcode::ImmU32,
+        "const.i32" => Opcode::ImmI32,
+        "const.u64" => Opcode::ImmU64,
+        "const.i64" => Opcode::ImmI64,
+        "const.felt" => Opcode::ImmFelt,
+        "const.f64" => Opcode::ImmF64,
+        "alloca" => Opcode::Alloca,
+        "memory.grow" => Opcode::MemGrow,
+        "load" => Opcode::Load,
+        "store" => Opcode::Store,
+        "memcpy" => Opcode::MemCpy,
+        "ptrtoint" => Opcode::PtrToInt,
+        "inttoptr" => Opcode::IntToPtr,
+        "cast" => Opcode::Cast,
+        "trunc" => Opcode::Trunc,
+        "zext" => Opcode::Zext,
+        "sext" => Opcode::Sext,
+        "br" => Opcode::Br,
+        "condbr" => Opcode::CondBr,
+        "switch" => Opcode::Switch,
+        "call" => Opcode::Call,
+        "syscall" => Opcode::Syscall,
+        "ret" => Opcode::Ret,
+        "select" => Opcode::Select,
+        "add" => Opcode::Add,
+        "sub" => Opcode::Sub,
+        "mul" => Opcode::Mul,
+        "div" => Opcode::Div,
+        "mod" => Opcode::Mod,
+        "divmod" => Opcode::DivMod,
+        "exp" => Opcode::Exp,
+        "neg" => Opcode::Neg,
+        "inv" => Opcode::Inv,
+        "incr" => Opcode::Incr,
+        "pow2" => Opcode::Pow2,
+        "not" => Opcode::Not,
+        "bnot" => Opcode::Bnot,
+        "and" => Opcode::And,
+        "band" => Opcode::Band,
+        "or" => Opcode::Or,
+        "bor" => Opcode::Bor,
+        "xor" => Opcode::Xor,
+        "bxor" => Opcode::Bxor,
+        "shl" => Opcode::Shl,
+        "shr" => Opcode::Shr,
+        "rotl" => Opcode::Rotl,
+        "rotr" => Opcode::Rotr,
+        "popcnt" => Opcode::Popcnt,
+        "eq" => Opcode::Eq,
+        "neq" => Opcode::Neq,
+        "gt" => Opcode::Gt,
+        "gte" => Opcode::Gte,
+        "lt" => Opcode::Lt,
+        "lte" => Opcode::Lte,
+        "is_odd" => Opcode::IsOdd,
+        "min" => Opcode::Min,
+        "max" => Opcode::Max,
+        "unreachable" => Opcode::Unreachable,
+        "asm" => Opcode::InlineAsm,
+        _ => return None,
+    };
+    Some(opcode)
+}
@@ -0,0 +1,620 @@
+use std::collections::BTreeMap;
+
+use cranelift_entity::EntityRef;
+use miden_diagnostics::{DiagnosticsHandler, SourceSpan, Spanned};
+use rustc_hash::FxHashMap;
+
+use super::{ast::*, ParseError, ParseResult, SyntaxError};
+use crate::*;
+
+/// Lowers the syntax tree of a module to a [Module], using [ModuleBuilder].
+///
+/// Blocks and values are numbered in the same order as the names they were given in the
+/// source text, but any gaps in that numbering are closed up, as every block and value of
+/// a function must be defined. Printing the resulting module reproduces the original text
+/// exactly if there were no gaps.
+pub(super) fn lower_module(
+    ast: ModuleAst,
+    diagnostics: &DiagnosticsHandler,
+) -> Result<Box<Module>, ParseError> {
+    let mut builder = if ast.is_kernel {
+        ModuleBuilder::new_kernel(ast.name)
+    } else {
+        ModuleBuilder::new(ast.name)
+    };
+
+    let functions = declare_items(&ast, &mut builder).map_err(|err| err.emit(diagnostics))?;
+
+    for function in ast.functions.iter() {
+        let mut fb = builder
+            .function(function.name, function.signature.clone())
+            .map_err(|_| {
+                SyntaxError::new(
+                    function.name.span,
+                    "invalid function definition",
+                    "a function with this name has already been defined",
+                )
+                .emit(diagnostics)
+            })?;
+        fb.with_inline(function.inline);
+        let mut lowering = FunctionLowering {
+            functions: &functions,
+            function,
+            values: BTreeMap::new(),
+            blocks: BTreeMap::new(),
+            value_ids: FxHashMap::default(),
+            block_ids: FxHashMap::default(),
+        };
+        lowering
+            .lower(fb.data_flow_graph_mut())
+            .map_err(|err| err.emit(diagnostics))?;
+        fb.build(diagnostics).map_err(|_| ParseError::Failed)?;
+    }
+
+    Ok(builder.build())
+}
+
+/// Declares the data segments and global variables of the module, and returns the
+/// signatures of all functions which are defined or declared by it.
+fn declare_items(
+    ast: &ModuleAst,
+    builder: &mut ModuleBuilder,
+) -> ParseResult<FxHashMap<FunctionIdent, Signature>> {
+    for segment in ast.segments.iter() {
+        builder
+            .declare_data_segment(
+                segment.offset,
+                segment.size,
+                segment.init.clone(),
+                segment.readonly,
+            )
+            .map_err(|err| SyntaxError::new(segment.span, "invalid data segment", err.to_string()))?;
+    }
+
+    for global in ast.globals.iter() {
+        builder
+            .declare_global_variable(
+                global.name.as_str(),
+                global.ty.clone(),
+                global.linkage,
+                global.init.clone(),
+                global.name.span,
+            )
+            .map_err(|err| {
+                SyntaxError::new(global.span, "invalid global variable", err.to_string())
+            })?;
+    }
+
+    let mut functions = FxHashMap::<FunctionIdent, Signature>::default();
+    for function in ast.functions.iter() {
+        let id = FunctionIdent {
+            module: ast.name,
+            function: function.name,
+        };
+        if functions.insert(id, function.signature.clone()).is_some() {
+            return Err(SyntaxError::new(
+                function.name.span,
+                "invalid function definition",
+                "a function with this name has already been defined",
+            ));
+        }
+    }
+    for external in ast.externals.iter() {
+        match functions.get(&external.id) {
+            None => {
+                functions.insert(external.id, external.signature.clone());
+            }
+            Some(signature) if signature == &external.signature => continue,
+            Some(_) => {
+                return Err(SyntaxError::new(
+                    external.span,
+                    "invalid external function declaration",
+                    "this declaration conflicts with a previous declaration of the same function",
+                ));
+            }
+        }
+    }
+
+    Ok(functions)
+}
+
+/// Where a value is defined
+enum ValueDef {
+    Param { block: Block, num: u16, ty: Type, span: SourceSpan },
+    Result { inst: Inst, num: u16, ty: Type },
+}
+
+struct FunctionLowering<'a> {
+    functions: &'a FxHashMap<FunctionIdent, Signature>,
+    function: &'a FunctionAst,
+    /// The definition of each value, by the name it was given in the source text
+    values: BTreeMap<Value, ValueDef>,
+    /// The blocks of the function, by the name they were given in the source text
+    blocks: BTreeMap<Block, SourceSpan>,
+    /// The value allocated for each value name
+    value_ids: FxHashMap<Value, Value>,
+    /// The block allocated for each block name
+    block_ids: FxHashMap<Block, Block>,
+}
+impl<'a> FunctionLowering<'a> {
+    fn lower(&mut self, dfg: &mut DataFlowGraph) -> ParseResult<()> {
+        let function = self.function;
+        let entry = dfg.entry_block();
+
+        // The entry block is created with the function, and must be listed first
+        let entry_block = match function.blocks.first() {
+            Some(block) => block,
+            None => {
+                return Err(SyntaxError::new(
+                    function.span,
+                    "invalid function definition",
+                    "a function must have at least one block",
+                ))
+            }
+        };
+        if entry_block.id.block != entry {
+            return Err(SyntaxError::new(
+                entry_block.id.span,
+                "invalid entry block",
+                format!("the first block of a function must be {entry}"),
+            ));
+        }
+        let params = function.signature.params();
+        let entry_param_tys = entry_block.params.iter().map(|(_, ty)| ty);
+        if params.len() != entry_block.params.len()
+            || !params.iter().map(|p| &p.ty).eq(entry_param_tys)
+        {
+            return Err(SyntaxError::new(
+                entry_block.span,
+                "invalid entry block",
+                "the entry block parameters must match the function signature",
+            ));
+        }
+
+        // Collect all of the entities defined in the function body before lowering any
+        // instructions, as instructions may refer to blocks and values defined later on
+        let mut insts = Vec::new();
+        for block in function.blocks.iter() {
+            let id = block.id.block;
+            if self.blocks.insert(id, block.id.span).is_some() {
+                return Err(SyntaxError::new(
+                    block.id.span,
+                    "invalid block",
+                    format!("{id} has already been defined"),
+                ));
+            }
+            for (num, (param, ty)) in block.params.iter().enumerate() {
+                self.define(
+                    *param,
+                    ValueDef::Param {
+                        block: id,
+                        num: num as u16,
+                        ty: ty.clone(),
+                        span: param.span,
+                    },
+                )?;
+            }
+            for inst in block.insts.iter() {
+                let key = dfg.insts.alloc_key();
+                insts.push(key);
+                for (num, (result, ty)) in inst.results.iter().zip(inst.types.iter()).enumerate() {
+                    self.define(
+                        *result,
+                        ValueDef::Result {
+                            inst: key,
+                            num: num as u16,
+                            ty: ty.clone(),
+                        },
+                    )?;
+                }
+            }
+        }
+
+        // Blocks are allocated in the order of their names, and laid out in the order they
+        // appear in the source text. The entry block is created with the function, and has
+        // the lowest name, as it must be named `block0`.
+        for name in self.blocks.keys().copied() {
+            let block = if name == entry {
+                entry
+            } else {
+                dfg.blocks.create()
+            };
+            self.block_ids.insert(name, block);
+        }
+        for block in function.blocks.iter().skip(1) {
+            let id = self.block_ids[&block.id.block];
+            dfg.blocks.append(id, BlockData::new(id));
+        }
+
+        // Likewise, values are allocated in the order of their names. The entry block
+        // parameters were allocated when the function was created, so we redefine them here.
+        dfg.blocks[entry].params.clear(&mut dfg.value_lists);
+        for (index, (name, def)) in self.values.iter().enumerate() {
+            let value = Value::new(index);
+            let data = match def {
+                ValueDef::Param {
+                    block,
+                    num,
+                    ty,
+                    span,
+                } => ValueData::Param {
+                    ty: ty.clone(),
+                    num: *num,
+                    block: self.block_ids[block],
+                    span: *span,
+                },
+                ValueDef::Result { inst, num, ty } => ValueData::Inst {
+                    ty: ty.clone(),
+                    num: *num,
+                    inst: *inst,
+                },
+            };
+            if index < dfg.values.len() {
+                dfg.values[value] = data;
+            } else {
+                let allocated = dfg.make_value(data);
+                debug_assert_eq!(allocated, value);
+            }
+            self.value_ids.insert(*name, value);
+        }
+        for block in function.blocks.iter() {
+            let id = self.block_ids[&block.id.block];
+            for (param, _) in block.params.iter() {
+                let value = self.value_ids[&param.value];
+                dfg.blocks[id].params.push(value, &mut dfg.value_lists);
+            }
+        }
+
+        // Now we can lower the instructions themselves
+        let mut keys = insts.into_iter();
+        for block in function.blocks.iter() {
+            let id = self.block_ids[&block.id.block];
+            for inst in block.insts.iter() {
+                let key = keys.next().unwrap();
+                let data = self.lower_inst(dfg, inst)?;
+                let results = inst
+                    .results
+                    .iter()
+                    .map(|result| self.value_ids[&result.value])
+                    .collect::<Vec<_>>();
+                dfg.append_allocated_inst(key, id, data, &results, inst.span);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn define(&mut self, value: ValueRef, def: ValueDef) -> ParseResult<()> {
+        if self.values.insert(value.value, def).is_some() {
+            return Err(SyntaxError::new(
+                value.span,
+                "invalid value",
+                format!("{} has already been defined", value.value),
+            ));
+        }
+        Ok(())
+    }
+
+    fn lower_inst(&self, dfg: &mut DataFlowGraph, inst: &InstAst) -> ParseResult<Instruction> {
+        let data = match &inst.op {
+            OpAst::GlobalValue(gv) => Instruction::GlobalValue(GlobalValueOp {
+                op: Opcode::GlobalValue,
+                global: self.global_value(dfg, gv)?,
+            }),
+            OpAst::Binary {
+                op,
+                overflow,
+                lhs,
+                rhs,
+            } => {
+                let lhs = self.value(lhs)?;
+                match rhs {
+                    Operand::Value(rhs) => Instruction::BinaryOp(BinaryOp {
+                        op: *op,
+                        overflow: *overflow,
+                        args: [lhs, self.value(rhs)?],
+                    }),
+                    Operand::Immediate(lit, span) => {
+                        let ty = dfg.value_type(lhs).clone();
+                        Instruction::BinaryOpImm(BinaryOpImm {
+                            op: *op,
+                            overflow: *overflow,
+                            arg: lhs,
+                            imm: immediate(*lit, &ty, *span)?,
+                        })
+                    }
+                }
+            }
+            OpAst::Unary { op, overflow, arg } => match arg {
+                Operand::Value(arg) => Instruction::UnaryOp(UnaryOp {
+                    op: *op,
+                    overflow: *overflow,
+                    arg: self.value(arg)?,
+                }),
+                Operand::Immediate(lit, span) => {
+                    let ty = match immediate_type(*op) {
+                        Some(ty) => ty,
+                        None => self.result_type(inst)?.clone(),
+                    };
+                    Instruction::UnaryOpImm(UnaryOpImm {
+                        op: *op,
+                        overflow: *overflow,
+                        imm: immediate(*lit, &ty, *span)?,
+                    })
+                }
+            },
+            OpAst::Call { op, callee, args } => {
+                let signature = self.functions.get(callee).ok_or_else(|| {
+                    SyntaxError::new(
+                        callee.span(),
+                        "undefined function",
+                        "this function is not defined or declared in this module",
+                    )
+                })?;
+                let callee = dfg
+                    .import_function(callee.module, callee.function, signature.clone())
+                    .map_err(|_| {
+                        SyntaxError::new(
+                            callee.span(),
+                            "invalid callee",
+                            "this function conflicts with a previous import",
+                        )
+                    })?;
+                let args = self.value_list(dfg, args)?;
+                Instruction::Call(Call {
+                    op: *op,
+                    callee,
+                    args,
+                })
+            }
+            OpAst::Br { destination, args } => Instruction::Br(Br {
+                op: Opcode::Br,
+                destination: self.block(destination)?,
+                args: self.value_list(dfg, args)?,
+            }),
+            OpAst::CondBr {
+                cond,
+                then_dest,
+                else_dest,
+            } => Instruction::CondBr(CondBr {
+                op: Opcode::CondBr,
+                cond: self.value(cond)?,
+                then_dest: (
+                    self.block(&then_dest.0)?,
+                    self.value_list(dfg, &then_dest.1)?,
+                ),
+                else_dest: (
+                    self.block(&else_dest.0)?,
+                    self.value_list(dfg, &else_dest.1)?,
+                ),
+            }),
+            OpAst::Switch { arg, arms, default } => Instruction::Switch(Switch {
+                op: Opcode::Switch,
+                arg: self.value(arg)?,
+                arms: arms
+                    .iter()
+                    .map(|(value, dest, args)| {
+                        Ok((*value, self.block(dest)?, self.value_list(dfg, args)?))
+                    })
+                    .collect::<ParseResult<Vec<_>>>()?,
+                default: (self.block(&default.0)?, self.value_list(dfg, &default.1)?),
+            }),
+            OpAst::Ret { args } => match args.as_slice() {
+                [Operand::Immediate(lit, span)] => {
+                    let ty = match self.function.signature.results().first() {
+                        Some(result) => result.ty.clone(),
+                        None => {
+                            return Err(SyntaxError::new(
+                                *span,
+                                "invalid return",
+                                "this function does not return any values",
+                            ))
+                        }
+                    };
+                    Instruction::RetImm(RetImm {
+                        op: Opcode::Ret,
+                        arg: immediate(*lit, &ty, *span)?,
+                    })
+                }
+                args => {
+                    let args = self.operand_values(args)?;
+                    Instruction::Ret(Ret {
+                        op: Opcode::Ret,
+                        args: self.value_list(dfg, &args)?,
+                    })
+                }
+            },
+            OpAst::Test { arg, ty } => Instruction::Test(Test {
+                op: Opcode::Test,
+                arg: self.value(arg)?,
+                ty: ty.clone(),
+            }),
+            OpAst::Load { addr } => Instruction::Load(LoadOp {
+                op: Opcode::Load,
+                addr: self.value(addr)?,
+                ty: self.result_type(inst)?.clone(),
+            }),
+            OpAst::PrimOp { op, args } => match args.split_first() {
+                Some((Operand::Immediate(lit, span), rest)) => {
+                    let rest = self.operand_values(rest)?;
+                    let ty = match rest.first() {
+                        Some(value) => dfg.value_type(self.value(value)?).clone(),
+                        None => {
+                            return Err(SyntaxError::new(
+                                *span,
+                                "invalid immediate",
+                                "unable to infer the type of this immediate",
+                            ))
+                        }
+                    };
+                    Instruction::PrimOpImm(PrimOpImm {
+                        op: *op,
+                        imm: immediate(*lit, &ty, *span)?,
+                        args: self.value_list(dfg, &rest)?,
+                    })
+                }
+                _ => {
+                    let args = self.operand_values(args)?;
+                    Instruction::PrimOp(PrimOp {
+                        op: *op,
+                        args: self.value_list(dfg, &args)?,
+                    })
+                }
+            },
+            OpAst::InlineAsm { args, body, blocks } => Instruction::InlineAsm(InlineAsm {
+                op: Opcode::InlineAsm,
+                args: self.value_list(dfg, args)?,
+                results: inst.types.clone(),
+                body: *body,
+                blocks: blocks.clone(),
+            }),
+        };
+
+        Ok(data)
+    }
+
+    fn global_value(
+        &self,
+        dfg: &mut DataFlowGraph,
+        gv: &GlobalValueAst,
+    ) -> ParseResult<GlobalValue> {
+        let data = match gv {
+            GlobalValueAst::Symbol { name, offset } => GlobalValueData::Symbol {
+                name: *name,
+                offset: *offset,
+            },
+            GlobalValueAst::Load {
+                base,
+                offset,
+                ty,
+                span,
+            } => {
+                let base = self.global_value(dfg, base)?;
+                let ty = match ty {
+                    Some(ty) => ty.clone(),
+                    None => dfg.global_type(base).pointee().cloned().ok_or_else(|| {
+                        SyntaxError::new(
+                            *span,
+                            "invalid global value",
+                            "the base of a global load must be a pointer",
+                        )
+                    })?,
+                };
+                GlobalValueData::Load {
+                    base,
+                    offset: *offset,
+                    ty,
+                }
+            }
+            GlobalValueAst::IAddImm { base, offset, ty } => GlobalValueData::IAddImm {
+                base: self.global_value(dfg, base)?,
+                offset: *offset,
+                ty: ty.clone(),
+            },
+        };
+        Ok(dfg.create_global_value(data))
+    }
+
+    fn value(&self, value: &ValueRef) -> ParseResult<Value> {
+        self.value_ids.get(&value.value).copied().ok_or_else(|| {
+            SyntaxError::new(
+                value.span,
+                "undefined value",
+                format!("{} is not defined in this function", value.value),
+            )
+        })
+    }
+
+    fn value_list(&self, dfg: &mut DataFlowGraph, values: &[ValueRef]) -> ParseResult<ValueList> {
+        let mut vlist = ValueList::default();
+        for value in values.iter() {
+            vlist.push(self.value(value)?, &mut dfg.value_lists);
+        }
+        Ok(vlist)
+    }
+
+    fn operand_values(&self, operands: &[Operand]) -> ParseResult<Vec<ValueRef>> {
+        operands
+            .iter()
+            .map(|operand| match operand {
+                Operand::Value(value) => Ok(*value),
+                Operand::Immediate(_, span) => Err(SyntaxError::new(
+                    *span,
+                    "invalid operand",
+                    "expected a value here, immediates are not permitted in this position",
+                )),
+            })
+            .collect()
+    }
+
+    fn block(&self, block: &BlockRef) -> ParseResult<Block> {
+        self.block_ids.get(&block.block).copied().ok_or_else(|| {
+            SyntaxError::new(
+                block.span,
+                "undefined block",
+                format!("{} is not defined in this function", block.block),
+            )
+        })
+    }
+
+    fn result_type<'i>(&self, inst: &'i InstAst) -> ParseResult<&'i Type> {
+        inst.types.first().ok_or_else(|| {
+            SyntaxError::new(
+                inst.span,
+                "invalid instruction",
+                "expected this instruction to have a result",
+            )
+        })
+    }
+}
+
+/// Returns the type of the immediate operand for opcodes which define constants
+fn immediate_type(op: Opcode) -> Option<Type> {
+    let ty = match op {
+        Opcode::ImmI1 => Type::I1,
+        Opcode::ImmU8 => Type::U8,
+        Opcode::ImmI8 => Type::I8,
+        Opcode::ImmU16 => Type::U16,
+        Opcode::ImmI16 => Type::I16,
+        Opcode::ImmU32 => Type::U32,
+        Opcode::ImmI32 => Type::I32,
+        Opcode::ImmU64 => Type::U64,
+        Opcode::ImmI64 => Type::I64,
+        Opcode::ImmFelt => Type::Felt,
+        Opcode::ImmF64 => Type::F64,
+        _ => return None,
+    };
+    Some(ty)
+}
+
+/// Converts a literal to an [Immediate] of type `ty`
+fn immediate(lit: Literal, ty: &Type, span: SourceSpan) -> ParseResult<Immediate> {
+    let imm = match (ty, lit) {
+        (Type::I1, Literal::Bool(b)) => Some(Immediate::I1(b)),
+        (Type::I1, Literal::Int(0)) => Some(Immediate::I1(false)),
+        (Type::I1, Literal::Int(1)) => Some(Immediate::I1(true)),
+        (Type::U8, Literal::Int(i)) => u8::try_from(i).ok().map(Immediate::U8),
+        (Type::I8, Literal::Int(i)) => i8::try_from(i).ok().map(Immediate::I8),
+        (Type::U16, Literal::Int(i)) => u16::try_from(i).ok().map(Immediate::U16),
+        (Type::I16, Literal::Int(i)) => i16::try_from(i).ok().map(Immediate::I16),
+        (Type::U32, Literal::Int(i)) => u32::try_from(i).ok().map(Immediate::U32),
+        (Type::I32, Literal::Int(i)) => i32::try_from(i).ok().map(Immediate::I32),
+        (Type::U64, Literal::Int(i)) => u64::try_from(i).ok().map(Immediate::U64),
+        (Type::I64, Literal::Int(i)) => i64::try_from(i).ok().map(Immediate::I64),
+        (Type::I128, Literal::Int(i)) => Some(Immediate::I128(i)),
+        (Type::F64, Literal::Float(n)) => Some(Immediate::F64(n)),
+        (Type::F64, Literal::Int(i)) => Some(Immediate::F64(i as f64)),
+        (Type::Felt, Literal::Int(i)) => u64::try_from(i)
+            .ok()
+            .map(|i| Immediate::Felt(Felt::new(i))),
+        (ty, Literal::Int(i)) if ty.is_pointer() => u32::try_from(i).ok().map(Immediate::U32),
+        _ => None,
+    };
+    imm.ok_or_else(|| {
+        SyntaxError::new(
+            span,
+            "invalid immediate",
+            format!("expected an immediate of type '{ty}'"),
+        )
+    })
+}
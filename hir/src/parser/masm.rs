@@ -0,0 +1,357 @@
+use cranelift_entity::PrimaryMap;
+use miden_diagnostics::SourceSpan;
+use smallvec::smallvec;
+
+use super::{scanner::Scanner, ParseResult, SyntaxError};
+use crate::{Felt, FunctionIdent, Ident, LocalId, MasmBlock, MasmBlockId, MasmOp, Symbol};
+
+/// Parses the body of an inline assembly block, up to (but not including) the closing `}`.
+///
+/// Returns the id of the top-level code block, along with all of the code blocks that were
+/// parsed, in the form expected by [crate::InlineAsm].
+pub(super) fn parse_body(
+    scanner: &mut Scanner,
+    current_module: Ident,
+) -> ParseResult<(MasmBlockId, PrimaryMap<MasmBlockId, MasmBlock>)> {
+    let mut parser = MasmParser {
+        current_module,
+        blocks: PrimaryMap::new(),
+    };
+    let body = parser.create_block();
+    match parser.parse_block(scanner, body, &[])? {
+        None => Ok((body, parser.blocks)),
+        Some((_, span)) => Err(SyntaxError::new(
+            span,
+            "unexpected token",
+            "this does not terminate any enclosing block",
+        )),
+    }
+}
+
+struct MasmParser {
+    current_module: Ident,
+    blocks: PrimaryMap<MasmBlockId, MasmBlock>,
+}
+impl MasmParser {
+    fn create_block(&mut self) -> MasmBlockId {
+        let id = self.blocks.next_key();
+        self.blocks.push(MasmBlock {
+            id,
+            ops: smallvec![],
+        })
+    }
+
+    /// Parse ops into `block` until one of `terminators` is reached, or a `}` is found.
+    ///
+    /// Returns the terminator which was found, if any.
+    fn parse_block<'a>(
+        &mut self,
+        scanner: &mut Scanner<'a>,
+        block: MasmBlockId,
+        terminators: &[&str],
+    ) -> ParseResult<Option<(&'a str, SourceSpan)>> {
+        loop {
+            if scanner.is_next("}") || scanner.is_eof() {
+                if terminators.is_empty() {
+                    return Ok(None);
+                }
+                return Err(scanner.unexpected(&format!("'{}'", terminators.join("' or '"))));
+            }
+            let (token, span) = scanner.token();
+            if terminators.contains(&token) {
+                return Ok(Some((token, span)));
+            }
+            let op = match token {
+                "if.true" => {
+                    let then_blk = self.create_block();
+                    let else_blk = self.create_block();
+                    if let Some(("else", _)) =
+                        self.parse_block(scanner, then_blk, &["else", "end"])?
+                    {
+                        self.parse_block(scanner, else_blk, &["end"])?;
+                    }
+                    MasmOp::If(then_blk, else_blk)
+                }
+                "while.true" => {
+                    let body = self.create_block();
+                    self.parse_block(scanner, body, &["end"])?;
+                    MasmOp::While(body)
+                }
+                _ => match token.strip_prefix("repeat.") {
+                    Some(n) => {
+                        let n = n.parse::<u8>().map_err(|_| {
+                            SyntaxError::new(
+                                span,
+                                "invalid repeat count",
+                                "expected an integer in the range 0..=255",
+                            )
+                        })?;
+                        let body = self.create_block();
+                        self.parse_block(scanner, body, &["end"])?;
+                        MasmOp::Repeat(n, body)
+                    }
+                    None => parse_op(token, span, self.current_module)?,
+                },
+            };
+            self.blocks[block].push(op);
+        }
+    }
+}
+
+/// Parse a single, non-control flow, Miden Assembly instruction, e.g. `u32.add.checked.1`
+///
+/// Unqualified `exec` and `syscall` targets are resolved relative to `current_module`.
+pub(super) fn parse_op(token: &str, span: SourceSpan, current_module: Ident) -> ParseResult<MasmOp> {
+    if let Some(callee) = token.strip_prefix("exec.") {
+        return parse_callee(callee, span, current_module).map(MasmOp::Exec);
+    }
+    if let Some(callee) = token.strip_prefix("syscall.") {
+        return parse_callee(callee, span, current_module).map(MasmOp::Syscall);
+    }
+
+    let parts = token.split('.').collect::<Vec<_>>();
+    let imm = ImmParser { span };
+    let op = match parts.as_slice() {
+        ["padw"] => MasmOp::Padw,
+        ["push", a] => MasmOp::Push(imm.felt(a)?),
+        ["push", a, b] => MasmOp::Push2([imm.felt(a)?, imm.felt(b)?]),
+        ["push", a, b, c, d] => {
+            MasmOp::Pushw([imm.felt(a)?, imm.felt(b)?, imm.felt(c)?, imm.felt(d)?])
+        }
+        ["drop"] => MasmOp::Drop,
+        ["dropw"] => MasmOp::Dropw,
+        ["dup"] => MasmOp::Dup(0),
+        ["dup", n] => MasmOp::Dup(imm.int(n)?),
+        ["dupw"] => MasmOp::Dupw(0),
+        ["dupw", n] => MasmOp::Dupw(imm.int(n)?),
+        ["swap"] => MasmOp::Swap(1),
+        ["swap", n] => MasmOp::Swap(imm.int(n)?),
+        ["swapw"] => MasmOp::Swapw(1),
+        ["swapw", n] => MasmOp::Swapw(imm.int(n)?),
+        ["movup", n] => MasmOp::Movup(imm.int(n)?),
+        ["movupw", n] => MasmOp::Movupw(imm.int(n)?),
+        ["movdn", n] => MasmOp::Movdn(imm.int(n)?),
+        ["movdnw", n] => MasmOp::Movdnw(imm.int(n)?),
+        ["cswap"] => MasmOp::Cswap,
+        ["cswapw"] => MasmOp::Cswapw,
+        ["cdrop"] => MasmOp::Cdrop,
+        ["cdropw"] => MasmOp::Cdropw,
+        ["assert"] => MasmOp::Assert,
+        ["assertz"] => MasmOp::Assertz,
+        ["assert_eq"] => MasmOp::AssertEq,
+        ["assert_eqw"] => MasmOp::AssertEqw,
+        ["locaddr", id] => MasmOp::LocAddr(LocalId::from_u8(imm.int(id)?)),
+        ["mem_load"] => MasmOp::MemLoad,
+        ["mem_load", addr] => MasmOp::MemLoadImm(imm.int(addr)?),
+        ["mem_load", addr, offset] => MasmOp::MemLoadOffsetImm(imm.int(addr)?, imm.int(offset)?),
+        ["mem_loadw"] => MasmOp::MemLoadw,
+        ["mem_loadw", addr] => MasmOp::MemLoadwImm(imm.int(addr)?),
+        ["mem_store"] => MasmOp::MemStore,
+        ["mem_store", addr] => MasmOp::MemStoreImm(imm.int(addr)?),
+        ["mem_store", addr, offset] => {
+            MasmOp::MemStoreOffsetImm(imm.int(addr)?, imm.int(offset)?)
+        }
+        ["mem_storew"] => MasmOp::MemStorew,
+        ["mem_storew", addr] => MasmOp::MemStorewImm(imm.int(addr)?),
+        ["add"] => MasmOp::Add,
+        ["add", a] => MasmOp::AddImm(imm.felt(a)?),
+        ["sub"] => MasmOp::Sub,
+        ["sub", a] => MasmOp::SubImm(imm.felt(a)?),
+        ["mul"] => MasmOp::Mul,
+        ["mul", a] => MasmOp::MulImm(imm.felt(a)?),
+        ["div"] => MasmOp::Div,
+        ["div", a] => MasmOp::DivImm(imm.felt(a)?),
+        ["neg"] => MasmOp::Neg,
+        ["inv"] => MasmOp::Inv,
+        ["incr"] => MasmOp::Incr,
+        ["pow2"] => MasmOp::Pow2,
+        ["exp"] | ["exp", "u64"] => MasmOp::Exp,
+        ["exp", n] => MasmOp::ExpImm(imm.int(n)?),
+        ["not"] => MasmOp::Not,
+        ["and"] => MasmOp::And,
+        ["and", b] => MasmOp::AndImm(imm.bool(b)?),
+        ["or"] => MasmOp::Or,
+        ["or", b] => MasmOp::OrImm(imm.bool(b)?),
+        ["xor"] => MasmOp::Xor,
+        ["xor", b] => MasmOp::XorImm(imm.bool(b)?),
+        ["eq"] => MasmOp::Eq,
+        ["eq", a] => MasmOp::EqImm(imm.felt(a)?),
+        ["neq"] => MasmOp::Neq,
+        ["neq", a] => MasmOp::NeqImm(imm.felt(a)?),
+        ["gt"] => MasmOp::Gt,
+        ["gt", a] => MasmOp::GtImm(imm.felt(a)?),
+        ["gte"] => MasmOp::Gte,
+        ["gte", a] => MasmOp::GteImm(imm.felt(a)?),
+        ["lt"] => MasmOp::Lt,
+        ["lt", a] => MasmOp::LtImm(imm.felt(a)?),
+        ["lte"] => MasmOp::Lte,
+        ["lte", a] => MasmOp::LteImm(imm.felt(a)?),
+        ["is_odd"] => MasmOp::IsOdd,
+        ["eqw"] => MasmOp::Eqw,
+        ["clk"] => MasmOp::Clk,
+        ["u32", rest @ ..] => parse_u32_op(rest, &imm).ok_or_else(|| invalid_op(token, span))??,
+        _ => return Err(invalid_op(token, span)),
+    };
+
+    Ok(op)
+}
+
+/// Parse the remainder of a `u32.*` instruction.
+///
+/// Returns `None` if the instruction is unrecognized.
+fn parse_u32_op(parts: &[&str], imm: &ImmParser) -> Option<ParseResult<MasmOp>> {
+    macro_rules! with_imm {
+        ($variant:ident, $value:ident) => {
+            Some(imm.int($value).map(MasmOp::$variant))
+        };
+    }
+
+    let op = match parts {
+        ["test"] => MasmOp::U32Test,
+        ["testw"] => MasmOp::U32Testw,
+        ["assert"] => MasmOp::U32Assert,
+        ["assert2"] => MasmOp::U32Assert2,
+        ["assertw"] => MasmOp::U32Assertw,
+        ["cast"] => MasmOp::U32Cast,
+        ["split"] => MasmOp::U32Split,
+        ["add", "checked"] => MasmOp::U32CheckedAdd,
+        ["add", "checked", n] => return with_imm!(U32CheckedAddImm, n),
+        ["add", "overflowing"] => MasmOp::U32OverflowingAdd,
+        ["add", "overflowing", n] => return with_imm!(U32OverflowingAddImm, n),
+        ["add", "wrapping"] => MasmOp::U32WrappingAdd,
+        ["add", "wrapping", n] => return with_imm!(U32WrappingAddImm, n),
+        ["add3", "overflowing"] => MasmOp::U32OverflowingAdd3,
+        ["add3", "wrapping"] => MasmOp::U32WrappingAdd3,
+        ["sub", "checked"] => MasmOp::U32CheckedSub,
+        ["sub", "checked", n] => return with_imm!(U32CheckedSubImm, n),
+        ["sub", "overflowing"] => MasmOp::U32OverflowingSub,
+        ["sub", "overflowing", n] => return with_imm!(U32OverflowingSubImm, n),
+        ["sub", "wrapping"] => MasmOp::U32WrappingSub,
+        ["sub", "wrapping", n] => return with_imm!(U32WrappingSubImm, n),
+        ["mul", "checked"] => MasmOp::U32CheckedMul,
+        ["mul", "checked", n] => return with_imm!(U32CheckedMulImm, n),
+        ["mul", "overflowing"] => MasmOp::U32OverflowingMul,
+        ["mul", "overflowing", n] => return with_imm!(U32OverflowingMulImm, n),
+        ["mul", "wrapping"] => MasmOp::U32WrappingMul,
+        ["mul", "wrapping", n] => return with_imm!(U32WrappingMulImm, n),
+        ["madd", "overflowing"] => MasmOp::U32OverflowingMadd,
+        ["madd", "wrapping"] => MasmOp::U32WrappingMadd,
+        ["div", "checked"] => MasmOp::U32CheckedDiv,
+        ["div", "checked", n] => return with_imm!(U32CheckedDivImm, n),
+        ["div", "unchecked"] => MasmOp::U32UncheckedDiv,
+        ["div", "unchecked", n] => return with_imm!(U32UncheckedDivImm, n),
+        ["mod", "checked"] => MasmOp::U32CheckedMod,
+        ["mod", "checked", n] => return with_imm!(U32CheckedModImm, n),
+        ["mod", "unchecked"] => MasmOp::U32UncheckedMod,
+        ["mod", "unchecked", n] => return with_imm!(U32UncheckedModImm, n),
+        ["divmod", "checked"] => MasmOp::U32CheckedDivMod,
+        ["divmod", "checked", n] => return with_imm!(U32CheckedDivModImm, n),
+        ["divmod", "unchecked"] => MasmOp::U32UncheckedDivMod,
+        ["divmod", "unchecked", n] => return with_imm!(U32UncheckedDivModImm, n),
+        ["and"] => MasmOp::U32And,
+        ["or"] => MasmOp::U32Or,
+        ["xor"] => MasmOp::U32Xor,
+        ["not"] => MasmOp::U32Not,
+        ["shl", "checked"] => MasmOp::U32CheckedShl,
+        ["shl", "checked", n] => return with_imm!(U32CheckedShlImm, n),
+        ["shl", "unchecked"] => MasmOp::U32UncheckedShl,
+        ["shl", "unchecked", n] => return with_imm!(U32UncheckedShlImm, n),
+        ["shr", "checked"] => MasmOp::U32CheckedShr,
+        ["shr", "checked", n] => return with_imm!(U32CheckedShrImm, n),
+        ["shr", "unchecked"] => MasmOp::U32UncheckedShr,
+        ["shr", "unchecked", n] => return with_imm!(U32UncheckedShrImm, n),
+        ["rotl", "checked"] => MasmOp::U32CheckedRotl,
+        ["rotl", "checked", n] => return with_imm!(U32CheckedRotlImm, n),
+        ["rotl", "unchecked"] => MasmOp::U32UncheckedRotl,
+        ["rotl", "unchecked", n] => return with_imm!(U32UncheckedRotlImm, n),
+        ["rotr", "checked"] => MasmOp::U32CheckedRotr,
+        ["rotr", "checked", n] => return with_imm!(U32CheckedRotrImm, n),
+        ["rotr", "unchecked"] => MasmOp::U32UncheckedRotr,
+        ["rotr", "unchecked", n] => return with_imm!(U32UncheckedRotrImm, n),
+        ["popcnt", "checked"] => MasmOp::U32CheckedPopcnt,
+        ["popcnt", "unchecked"] => MasmOp::U32UncheckedPopcnt,
+        ["eq"] => MasmOp::U32Eq,
+        ["eq", n] => return with_imm!(U32EqImm, n),
+        ["neq"] => MasmOp::U32Neq,
+        ["neq", n] => return with_imm!(U32NeqImm, n),
+        ["lt", "checked"] => MasmOp::U32CheckedLt,
+        ["lt", "unchecked"] => MasmOp::U32UncheckedLt,
+        ["lte", "checked"] => MasmOp::U32CheckedLte,
+        ["lte", "unchecked"] => MasmOp::U32UncheckedLte,
+        ["gt", "checked"] => MasmOp::U32CheckedGt,
+        ["gt", "unchecked"] => MasmOp::U32UncheckedGt,
+        ["gte", "checked"] => MasmOp::U32CheckedGte,
+        ["gte", "unchecked"] => MasmOp::U32UncheckedGte,
+        ["min", "checked"] => MasmOp::U32CheckedMin,
+        ["min", "unchecked"] => MasmOp::U32UncheckedMin,
+        ["max", "checked"] => MasmOp::U32CheckedMax,
+        ["max", "unchecked"] => MasmOp::U32UncheckedMax,
+        _ => return None,
+    };
+
+    Some(Ok(op))
+}
+
+fn parse_callee(callee: &str, span: SourceSpan, current_module: Ident) -> ParseResult<FunctionIdent> {
+    if callee.is_empty() {
+        return Err(SyntaxError::new(
+            span,
+            "invalid callee",
+            "expected a function name",
+        ));
+    }
+    Ok(match callee.rsplit_once("::") {
+        Some((module, function)) => FunctionIdent {
+            module: Ident::new(Symbol::intern(module), span),
+            function: Ident::new(Symbol::intern(function), span),
+        },
+        None => FunctionIdent {
+            module: current_module,
+            function: Ident::new(Symbol::intern(callee), span),
+        },
+    })
+}
+
+fn invalid_op(token: &str, span: SourceSpan) -> SyntaxError {
+    SyntaxError::new(
+        span,
+        format!("invalid instruction '{token}'"),
+        "this is not a recognized Miden Assembly instruction",
+    )
+}
+
+/// Parses the immediate operands of an instruction, which may be given in decimal or hexadecimal
+struct ImmParser {
+    span: SourceSpan,
+}
+impl ImmParser {
+    fn int<T: TryFrom<u64>>(&self, s: &str) -> ParseResult<T> {
+        let value = match s.strip_prefix("0x") {
+            Some(hex) => u64::from_str_radix(hex, 16).ok(),
+            None => s.parse::<u64>().ok(),
+        };
+        value.and_then(|v| T::try_from(v).ok()).ok_or_else(|| {
+            SyntaxError::new(
+                self.span,
+                "invalid immediate",
+                format!("'{s}' is not a valid integer for this instruction"),
+            )
+        })
+    }
+
+    fn felt(&self, s: &str) -> ParseResult<Felt> {
+        self.int::<u64>(s).map(Felt::new)
+    }
+
+    fn bool(&self, s: &str) -> ParseResult<bool> {
+        match s {
+            "true" => Ok(true),
+            "false" => Ok(false),
+            _ => Err(SyntaxError::new(
+                self.span,
+                "invalid immediate",
+                format!("expected 'true' or 'false', got '{s}'"),
+            )),
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! This module provides a parser for the textual form of the IR, i.e. the format
+//! produced by the [std::fmt::Display] implementation of [crate::Module].
+//!
+//! The parser is a hand-written recursive descent parser, which produces a syntax tree
+//! that is then lowered to a [crate::Module] using [crate::ModuleBuilder]. Blocks and
+//! values are numbered in the order of the names they were given in the source text, so
+//! a module which is printed and then parsed again will print identically, unless there
+//! were gaps in its numbering, e.g. after dead code elimination, which are closed up.
+
+mod ast;
+mod grammar;
+mod lower;
+mod masm;
+mod scanner;
+#[cfg(test)]
+mod tests;
+
+use std::{path::Path, sync::Arc};
+
+use miden_diagnostics::{CodeMap, DiagnosticsHandler, Severity, SourceId, SourceSpan};
+
+use self::{grammar::Grammar, scanner::Scanner};
+use crate::Module;
+
+/// This error is returned when a module could not be parsed.
+///
+/// Any syntax or semantic errors encountered during parsing are emitted as
+/// diagnostics, so this error only indicates that parsing failed.
+#[derive(Debug, thiserror::Error)]
+pub enum ParseError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("parsing failed, see diagnostics for details")]
+    Failed,
+}
+
+/// An error raised while parsing, which has not yet been emitted as a diagnostic
+#[derive(Debug)]
+pub(super) struct SyntaxError {
+    span: SourceSpan,
+    message: String,
+    label: String,
+}
+impl SyntaxError {
+    pub fn new(span: SourceSpan, message: impl Into<String>, label: impl Into<String>) -> Self {
+        Self {
+            span,
+            message: message.into(),
+            label: label.into(),
+        }
+    }
+
+    /// Emit this error as a diagnostic, returning [ParseError::Failed]
+    pub fn emit(self, diagnostics: &DiagnosticsHandler) -> ParseError {
+        diagnostics
+            .diagnostic(Severity::Error)
+            .with_message(self.message)
+            .with_primary_label(self.span, self.label)
+            .emit();
+        ParseError::Failed
+    }
+}
+
+type ParseResult<T> = Result<T, SyntaxError>;
+
+/// The [Parser] is used to parse a [Module] from its textual representation.
+pub struct Parser<'a> {
+    diagnostics: &'a DiagnosticsHandler,
+    codemap: Arc<CodeMap>,
+}
+impl<'a> Parser<'a> {
+    pub fn new(diagnostics: &'a DiagnosticsHandler, codemap: Arc<CodeMap>) -> Self {
+        Self {
+            diagnostics,
+            codemap,
+        }
+    }
+
+    /// Parse a [Module] from the file at `path`
+    pub fn parse_file<P: AsRef<Path>>(&self, path: P) -> Result<Box<Module>, ParseError> {
+        let id = self.codemap.add_file(path.as_ref())?;
+        self.parse(id)
+    }
+
+    /// Parse a [Module] from `source`
+    pub fn parse_str<S: AsRef<str>>(&self, source: S) -> Result<Box<Module>, ParseError> {
+        let id = self.codemap.add("nofile", source.as_ref().to_string());
+        self.parse(id)
+    }
+
+    fn parse(&self, id: SourceId) -> Result<Box<Module>, ParseError> {
+        let file = self.codemap.get(id).expect("expected source file to be in the codemap");
+        let scanner = Scanner::new(file.source(), file.source_span().start());
+        let ast = Grammar::new(scanner)
+            .parse_module()
+            .map_err(|err| err.emit(self.diagnostics))?;
+        lower::lower_module(ast, self.diagnostics)
+    }
+}
@@ -0,0 +1,366 @@
+use miden_diagnostics::{SourceIndex, SourceSpan};
+
+use super::{ast::Literal, ParseResult, SyntaxError};
+
+/// A [Scanner] is a cursor over the raw source text being parsed.
+///
+/// The textual IR format is not particularly regular (e.g. types and immediates are
+/// embedded directly in opcodes), so rather than tokenizing the input up front, the
+/// parser pulls lexemes from the scanner on demand, based on what it expects to see next.
+pub(super) struct Scanner<'a> {
+    source: &'a str,
+    pos: usize,
+    start: SourceIndex,
+}
+impl<'a> Scanner<'a> {
+    pub fn new(source: &'a str, start: SourceIndex) -> Self {
+        Self {
+            source,
+            pos: 0,
+            start,
+        }
+    }
+
+    /// Returns the current byte offset in the source text
+    #[inline(always)]
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+
+    /// Moves the cursor back to `pos`, which must have been obtained from [Scanner::pos]
+    #[inline(always)]
+    pub fn reset(&mut self, pos: usize) {
+        self.pos = pos;
+    }
+
+    /// Returns a [SourceSpan] for the given byte range
+    pub fn span(&self, start: usize, end: usize) -> SourceSpan {
+        SourceSpan::new(self.start + start, self.start + end)
+    }
+
+    /// Returns a [SourceSpan] from `start` to the current position
+    pub fn span_from(&self, start: usize) -> SourceSpan {
+        self.span(start, self.pos)
+    }
+
+    #[inline]
+    fn rest(&self) -> &'a str {
+        &self.source[self.pos..]
+    }
+
+    /// Peek at the next character, without skipping whitespace
+    #[inline]
+    pub fn peek_char(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    /// Skip over any whitespace and comments
+    pub fn skip_trivia(&mut self) {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.pos += rest.len() - trimmed.len();
+            if trimmed.starts_with("//") {
+                match trimmed.find('\n') {
+                    Some(offset) => self.pos += offset,
+                    None => self.pos = self.source.len(),
+                }
+                continue;
+            }
+            break;
+        }
+    }
+
+    /// Skip over whitespace on the current line only.
+    ///
+    /// Returns true if there is more content on the current line.
+    pub fn skip_inline_whitespace(&mut self) -> bool {
+        let rest = self.rest();
+        let trimmed = rest.trim_start_matches([' ', '\t']);
+        self.pos += rest.len() - trimmed.len();
+        !(trimmed.is_empty()
+            || trimmed.starts_with('\n')
+            || trimmed.starts_with('\r')
+            || trimmed.starts_with("//"))
+    }
+
+    /// Returns true if the end of the input has been reached
+    pub fn is_eof(&mut self) -> bool {
+        self.skip_trivia();
+        self.pos >= self.source.len()
+    }
+
+    /// Returns true if the next lexeme starts with `s`
+    pub fn is_next(&mut self, s: &str) -> bool {
+        self.skip_trivia();
+        self.rest().starts_with(s)
+    }
+
+    /// Returns true if the next lexeme is the keyword `kw`
+    pub fn is_next_keyword(&mut self, kw: &str) -> bool {
+        self.skip_trivia();
+        let rest = self.rest();
+        rest.starts_with(kw)
+            && !rest[kw.len()..]
+                .chars()
+                .next()
+                .map(is_ident_char)
+                .unwrap_or(false)
+    }
+
+    /// Consumes `s` if it is the next lexeme, returning true if successful
+    pub fn eat(&mut self, s: &str) -> bool {
+        if self.is_next(s) {
+            self.pos += s.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Consumes the keyword `kw` if it is the next lexeme, returning true if successful
+    pub fn eat_keyword(&mut self, kw: &str) -> bool {
+        if self.is_next_keyword(kw) {
+            self.pos += kw.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Consumes `s`, or raises an error if the next lexeme is something else
+    pub fn expect(&mut self, s: &str) -> ParseResult<SourceSpan> {
+        let start = self.pos();
+        if self.eat(s) {
+            Ok(self.span(self.pos - s.len(), self.pos))
+        } else {
+            self.reset(start);
+            Err(self.unexpected(&format!("'{s}'")))
+        }
+    }
+
+    /// Consumes the keyword `kw`, or raises an error if the next lexeme is something else
+    pub fn expect_keyword(&mut self, kw: &str) -> ParseResult<SourceSpan> {
+        if self.eat_keyword(kw) {
+            Ok(self.span(self.pos - kw.len(), self.pos))
+        } else {
+            Err(self.unexpected(&format!("'{kw}'")))
+        }
+    }
+
+    /// Parse an identifier, i.e. `[A-Za-z_$][A-Za-z0-9_$.]*`, which may be namespaced using `::`
+    pub fn ident(&mut self) -> ParseResult<(&'a str, SourceSpan)> {
+        self.skip_trivia();
+        let start = self.pos;
+        match self.peek_char() {
+            Some(c) if c.is_ascii_alphabetic() || c == '_' || c == '$' => (),
+            _ => return Err(self.unexpected("identifier")),
+        }
+        loop {
+            let rest = self.rest();
+            match rest.chars().next() {
+                Some(c) if is_ident_char(c) || c == '.' => self.pos += c.len_utf8(),
+                Some(':') if rest.starts_with("::") => self.pos += 2,
+                _ => break,
+            }
+        }
+        Ok((&self.source[start..self.pos], self.span_from(start)))
+    }
+
+    /// Parse an opcode-like word, i.e. one or more identifiers separated by `.`
+    pub fn word(&mut self) -> ParseResult<(&'a str, SourceSpan)> {
+        self.skip_trivia();
+        let start = self.pos;
+        loop {
+            let rest = self.rest();
+            match rest.chars().next() {
+                Some(c) if is_ident_char(c) => self.pos += c.len_utf8(),
+                Some('.') if rest[1..].chars().next().map(is_ident_char).unwrap_or(false) => {
+                    self.pos += 1
+                }
+                _ => break,
+            }
+        }
+        if self.pos == start {
+            return Err(self.unexpected("opcode"));
+        }
+        Ok((&self.source[start..self.pos], self.span_from(start)))
+    }
+
+    /// Parse a whitespace-delimited token, stopping early at `}`
+    pub fn token(&mut self) -> (&'a str, SourceSpan) {
+        self.skip_trivia();
+        let start = self.pos;
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| c.is_whitespace() || c == '}')
+            .unwrap_or(rest.len());
+        self.pos += len;
+        (&self.source[start..self.pos], self.span_from(start))
+    }
+
+    /// Parse an integer literal, either decimal or hexadecimal, with an optional sign
+    pub fn integer(&mut self) -> ParseResult<(i128, SourceSpan)> {
+        self.skip_trivia();
+        let start = self.pos;
+        match self.literal()? {
+            (Literal::Int(i), span) => Ok((i, span)),
+            _ => {
+                self.reset(start);
+                Err(self.unexpected("integer literal"))
+            }
+        }
+    }
+
+    /// Parse an integer literal which must be representable as `T`
+    pub fn integer_of<T: TryFrom<i128>>(&mut self, expected: &str) -> ParseResult<(T, SourceSpan)> {
+        let (i, span) = self.integer()?;
+        match T::try_from(i) {
+            Ok(i) => Ok((i, span)),
+            Err(_) => Err(SyntaxError::new(
+                span,
+                "invalid integer literal",
+                format!("expected {expected}"),
+            )),
+        }
+    }
+
+    /// Parse a literal value, i.e. a boolean, integer or floating-point number
+    pub fn literal(&mut self) -> ParseResult<(Literal, SourceSpan)> {
+        self.skip_trivia();
+        let start = self.pos;
+        if self.eat_keyword("true") {
+            return Ok((Literal::Bool(true), self.span_from(start)));
+        }
+        if self.eat_keyword("false") {
+            return Ok((Literal::Bool(false), self.span_from(start)));
+        }
+        let negative = self.rest().starts_with('-');
+        if negative {
+            self.pos += 1;
+        }
+        if self.eat_keyword("inf") {
+            let n = if negative {
+                f64::NEG_INFINITY
+            } else {
+                f64::INFINITY
+            };
+            return Ok((Literal::Float(n), self.span_from(start)));
+        }
+        if self.eat_keyword("NaN") {
+            return Ok((Literal::Float(f64::NAN), self.span_from(start)));
+        }
+        let rest = self.rest();
+        if let Some(hex) = rest.strip_prefix("0x") {
+            let len = hex
+                .find(|c: char| !c.is_ascii_hexdigit())
+                .unwrap_or(hex.len());
+            self.pos += 2 + len;
+            let digits = &hex[..len];
+            let span = self.span_from(start);
+            return match i128::from_str_radix(digits, 16) {
+                Ok(i) if negative => Ok((Literal::Int(-i), span)),
+                Ok(i) => Ok((Literal::Int(i), span)),
+                Err(_) => Err(SyntaxError::new(
+                    span,
+                    "invalid integer literal",
+                    "expected hexadecimal integer",
+                )),
+            };
+        }
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        if digits == 0 {
+            self.reset(start);
+            return Err(self.unexpected("literal"));
+        }
+        self.pos += digits;
+        let mut is_float = false;
+        let rest = self.rest();
+        if rest.starts_with('.') && rest[1..].starts_with(|c: char| c.is_ascii_digit()) {
+            is_float = true;
+            self.pos += 1;
+            let rest = self.rest();
+            self.pos += rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+        }
+        let rest = self.rest();
+        if rest.starts_with(['e', 'E']) {
+            let exp = rest[1..].trim_start_matches(['+', '-']);
+            if exp.starts_with(|c: char| c.is_ascii_digit()) {
+                is_float = true;
+                self.pos += rest.len() - exp.len();
+                let rest = self.rest();
+                self.pos += rest
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(rest.len());
+            }
+        }
+        let text = &self.source[start..self.pos];
+        let span = self.span_from(start);
+        if is_float {
+            text.parse::<f64>()
+                .map(|n| (Literal::Float(n), span))
+                .map_err(|_| {
+                    SyntaxError::new(span, "invalid float literal", "could not parse this value")
+                })
+        } else {
+            text.parse::<i128>()
+                .map(|i| (Literal::Int(i), span))
+                .map_err(|_| {
+                    SyntaxError::new(
+                        span,
+                        "invalid integer literal",
+                        "this value is out of range",
+                    )
+                })
+        }
+    }
+
+    /// Returns true if the next lexeme looks like the start of a literal
+    pub fn is_next_literal(&mut self) -> bool {
+        self.skip_trivia();
+        let rest = self.rest();
+        match rest.chars().next() {
+            Some(c) if c.is_ascii_digit() || c == '-' => true,
+            Some(_) => {
+                self.is_next_keyword("true")
+                    || self.is_next_keyword("false")
+                    || self.is_next_keyword("inf")
+                    || self.is_next_keyword("NaN")
+            }
+            None => false,
+        }
+    }
+
+    /// Construct an error indicating that `expected` was expected, but something else was found
+    pub fn unexpected(&mut self, expected: &str) -> SyntaxError {
+        self.skip_trivia();
+        let rest = self.rest();
+        if rest.is_empty() {
+            let span = self.span(self.pos, self.pos);
+            return SyntaxError::new(
+                span,
+                "unexpected end of file",
+                format!("expected {expected} here"),
+            );
+        }
+        let len = rest
+            .find(char::is_whitespace)
+            .unwrap_or(rest.len())
+            .max(rest.chars().next().unwrap().len_utf8());
+        let span = self.span(self.pos, self.pos + len);
+        SyntaxError::new(
+            span,
+            format!("unexpected token '{}'", &rest[..len]),
+            format!("expected {expected}"),
+        )
+    }
+}
+
+#[inline]
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '$'
+}
@@ -0,0 +1,233 @@
+use super::*;
+use crate::{
+    testing::{self, TestContext},
+    BranchInfo, Inline, ModuleBuilder, ProgramBuilder,
+};
+
+/// Parse `source`, and assert that printing the resulting module reproduces it exactly
+fn assert_roundtrip(context: &TestContext, source: &str) {
+    let parser = Parser::new(&context.diagnostics, context.codemap.clone());
+    let module = parser
+        .parse_str(source)
+        .expect("unexpected parse error, see diagnostics output");
+    assert_eq!(module.to_string(), source);
+}
+
+/// Test that a single-function module roundtrips through the parser
+#[test]
+fn parser_roundtrip_fib() {
+    let context = TestContext::default();
+
+    let mut builder = ModuleBuilder::new("test");
+    testing::fib1(&mut builder, &context);
+    let module = builder.build();
+
+    assert_roundtrip(&context, &module.to_string());
+}
+
+/// Test that a module containing memory operations roundtrips through the parser
+#[test]
+fn parser_roundtrip_sum_matrix() {
+    let context = TestContext::default();
+
+    let mut builder = ModuleBuilder::new("test");
+    testing::sum_matrix(&mut builder, &context);
+    let module = builder.build();
+
+    assert_roundtrip(&context, &module.to_string());
+}
+
+/// Test that every module of a linked program, including data segments, globals,
+/// external functions and global value expressions, roundtrips through the parser
+#[test]
+fn parser_roundtrip_program() {
+    let context = TestContext::default();
+
+    let mut builder = ProgramBuilder::new(&context.diagnostics);
+    testing::intrinsics(&mut builder, &context).expect("unexpected error building intrinsics");
+    testing::hello_world(&mut builder, &context).expect("unexpected error building test module");
+    let program = builder.link().expect("failed to link program");
+
+    for module in program.modules().iter() {
+        assert_roundtrip(&context, &module.to_string());
+    }
+}
+
+/// Test that hand-written IR, with comments and irregular whitespace, can be parsed
+#[test]
+fn parser_handwritten_module() {
+    let context = TestContext::default();
+    let parser = Parser::new(&context.diagnostics, context.codemap.clone());
+
+    let source = r#"
+// A simple module
+module test
+
+pub fn sum(u32, u32) -> u32 {
+block0(v0: u32, v1: u32):
+    v2 = add.checked v0, v1  : u32   // add the arguments
+    v3 = mul.wrapping v2, 2  : u32
+    br block1(v3)
+
+block1(v4: u32):
+    ret v4
+}
+"#;
+    let module = parser
+        .parse_str(source)
+        .expect("unexpected parse error, see diagnostics output");
+    let function = module
+        .function("sum".into())
+        .expect("expected 'sum' to be defined");
+    assert_eq!(function.dfg.blocks().count(), 2);
+    assert_eq!(function.signature.params().len(), 2);
+
+    // The printed form of the parsed module must itself roundtrip
+    assert_roundtrip(&context, &module.to_string());
+}
+
+/// Test that the arms and default of a switch may pass block arguments
+#[test]
+fn parser_switch_with_block_arguments() {
+    let context = TestContext::default();
+    let parser = Parser::new(&context.diagnostics, context.codemap.clone());
+
+    let source = r#"
+module test
+
+pub fn select(u32, u32) -> u32 {
+block0(v0: u32, v1: u32):
+    switch v0, 0 => block1(v1), 3 => block2, block1(v0)
+
+block1(v2: u32):
+    ret v2
+
+block2:
+    ret v1
+}
+"#;
+    let module = parser
+        .parse_str(source)
+        .expect("unexpected parse error, see diagnostics output");
+    let function = module
+        .function("select".into())
+        .expect("expected 'select' to be defined");
+    let entry = function.dfg.entry_block();
+    let switch = function.dfg.last_inst(entry).unwrap();
+    let BranchInfo::MultiDest(jts) = function.dfg.analyze_branch(switch) else {
+        panic!("expected switch to be a multi-way branch");
+    };
+    let args = jts.iter().map(|jt| jt.args.len()).collect::<Vec<_>>();
+    assert_eq!(args, vec![1, 0, 1]);
+
+    assert_roundtrip(&context, &module.to_string());
+}
+
+/// Test that gaps in the numbering of blocks and values, e.g. as left by dead code
+/// elimination, are closed up, rather than filled with values that are never defined
+#[test]
+fn parser_renumbers_gaps() {
+    let context = TestContext::default();
+    let parser = Parser::new(&context.diagnostics, context.codemap.clone());
+
+    let source = r#"
+module test
+
+pub fn inc(u32) -> u32 {
+block0(v0: u32):
+    v3 = add.checked v0, 1  : u32
+    br block2(v3)
+
+block2(v5: u32):
+    ret v5
+}
+"#;
+    let module = parser
+        .parse_str(source)
+        .expect("unexpected parse error, see diagnostics output");
+    let function = module
+        .function("inc".into())
+        .expect("expected 'inc' to be defined");
+    assert_eq!(function.dfg.values.len(), 3);
+    assert_eq!(function.dfg.num_blocks(), 2);
+
+    let printed = module.to_string();
+    assert!(printed.contains("v1 = add.checked v0, 1  : u32"));
+    assert!(printed.contains("br block1(v1)"));
+    assert!(printed.contains("block1(v2: u32):"));
+    assert!(printed.contains("ret v2"));
+
+    assert_roundtrip(&context, &printed);
+}
+
+/// Test that inline hints are parsed from, and printed in, the function header
+#[test]
+fn parser_roundtrip_inline_hints() {
+    let context = TestContext::default();
+    let parser = Parser::new(&context.diagnostics, context.codemap.clone());
+
+    let source = r#"
+module test
+
+pub inline(never) fn inc(u32) -> u32 {
+block0(v0: u32):
+    v1 = add v0, 1  : u32
+    ret v1
+}
+
+cc(fast) inline(always) fn double(u32) -> u32 {
+block0(v0: u32):
+    v1 = mul v0, 2  : u32
+    ret v1
+}
+"#;
+    let module = parser
+        .parse_str(source)
+        .expect("unexpected parse error, see diagnostics output");
+    for (name, inline) in [("inc", Inline::Never), ("double", Inline::Always)] {
+        let function = module
+            .function(name.into())
+            .unwrap_or_else(|| panic!("expected '{name}' to be defined"));
+        assert_eq!(function.inline(), inline);
+    }
+
+    let printed = module.to_string();
+    assert!(printed.contains("pub inline(never) fn inc(u32) -> u32 {"));
+    assert!(printed.contains("cc(fast) inline(always) fn double(u32) -> u32 {"));
+    assert_roundtrip(&context, &printed);
+}
+
+/// Test that references to undefined values are rejected
+#[test]
+fn parser_rejects_undefined_value() {
+    let context = TestContext::default();
+    let parser = Parser::new(&context.diagnostics, context.codemap.clone());
+
+    let source = r#"
+module test
+
+pub fn foo(u32) -> u32 {
+block0(v0: u32):
+    v1 = add.checked v0, v7  : u32
+    ret v1
+}
+"#;
+    assert!(matches!(parser.parse_str(source), Err(ParseError::Failed)));
+}
+
+/// Test that malformed input is rejected
+#[test]
+fn parser_rejects_syntax_error() {
+    let context = TestContext::default();
+    let parser = Parser::new(&context.diagnostics, context.codemap.clone());
+
+    let source = r#"
+module test
+
+pub fn foo(u32 -> u32 {
+block0(v0: u32):
+    ret v0
+}
+"#;
+    assert!(matches!(parser.parse_str(source), Err(ParseError::Failed)));
+}
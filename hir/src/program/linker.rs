@@ -75,6 +75,14 @@ pub enum LinkerError {
     #[error(transparent)]
     GlobalVariableError(#[from] GlobalVariableError),
 }
+impl From<CallGraphCycleError> for LinkerError {
+    fn from(err: CallGraphCycleError) -> Self {
+        Self::InvalidCycle {
+            caller: err.caller,
+            callee: err.callee,
+        }
+    }
+}
 
 /// Represents a node in the global variable dependency graph
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
@@ -136,20 +144,13 @@ pub struct Linker {
     pending: FxHashMap<Ident, Box<Module>>,
     /// This is the dependency graph for all functions in the program.
     ///
-    /// This graph is used to obtain a topological ordering of the
-    /// functions in the program, so that we may emit Miden Assembly
-    /// such that all procedure definitions occur before their uses.
+    /// This graph is used to verify that all referenced functions are defined,
+    /// with signatures matching those of their references.
     ///
     /// It is allowed for there to be cyclical module dependencies, but
     /// we do not permit cyclical function dependencies (i.e. recursive
     /// function calls).
-    ///
-    /// The edge weight is unused.
-    callgraph: DiGraphMap<FunctionIdent, ()>,
-    /// This is a subset of `callgraph` for a single module.
-    ///
-    /// This is only used when preprocessing a module, and is reset on each call to `add`
-    local_callgraph: DiGraphMap<FunctionIdent, ()>,
+    callgraph: CallGraph,
     /// This is the dependency graph for all globals in the program.
     ///
     /// This graph is used to identify what global symbols are used, from where,
@@ -174,8 +175,7 @@ impl Default for Linker {
         Self {
             program,
             pending: Default::default(),
-            callgraph: DiGraphMap::new(),
-            local_callgraph: DiGraphMap::new(),
+            callgraph: CallGraph::new(),
             globals: DiGraphMap::new(),
             renamed: Default::default(),
         }
@@ -216,7 +216,6 @@ impl Linker {
         let id = module.name;
 
         // Reset the auxiliary data structures used for preprocessing
-        self.local_callgraph.clear();
         self.renamed.clear();
 
         // Raise an error if we've already got a module by this name pending
@@ -248,31 +247,19 @@ impl Linker {
             self.globals.add_node(Node::Global(name));
         }
 
-        // Compute a subset of the call graph just for this module
-        for function in module.functions.iter() {
-            // While we're here, update the global call graph as well
-            let caller = self.callgraph.add_node(function.id);
-            let caller = self.local_callgraph.add_node(caller);
-            for import in function.imports() {
-                let callee = self.callgraph.add_node(import.id);
-                self.callgraph.add_edge(caller, callee, ());
-                // For the toposort, we only care about functions in the same module
-                if import.id.module == module.name {
-                    let callee = self.local_callgraph.add_node(callee);
-                    self.local_callgraph.add_edge(caller, callee, ());
-                }
-            }
-        }
-
-        // Compute the topographical ordering of functions in this module
-        let topography = petgraph::algo::toposort(&self.local_callgraph, None).map_err(|_| {
-            // The error here only gives us the caller, but we'd like to know
-            // the callee for our error diagnostics. To get it, we call the
-            // call graph validation routine which does a traversal specifically
-            // designed to obtain that information.
-            validate_callgraph(&self.local_callgraph)
-                .expect_err("expected call graph to contain a cycle")
-        })?;
+        // Compute the topographical ordering of functions in this module.
+        //
+        // The call graph of a single module has no edges out of functions defined elsewhere,
+        // so any cycle found here is due to recursion within this module. For the ordering,
+        // we only care about functions in the same module.
+        let topography = CallGraph::with_module(&module)
+            .toposort()?
+            .into_iter()
+            .filter(|function| function.module == id)
+            .collect::<Vec<_>>();
+
+        // Update the global call graph as well
+        self.callgraph.add_module(&module);
 
         // Preprocess all functions in this module by:
         //
@@ -346,7 +333,7 @@ impl Linker {
         self.populate_builtins();
 
         // Look for cycles in the call graph
-        validate_callgraph(&self.callgraph)?;
+        self.callgraph.toposort()?;
 
         // Verify the entrypoint, if declared
         if let Some(entry) = self.program.entrypoint {
@@ -365,7 +352,7 @@ impl Linker {
         }
 
         // Verify module/function references
-        for node in self.callgraph.nodes() {
+        for node in self.callgraph.functions() {
             // If the module is pending, it is being linked
             let is_linked = self.pending.contains_key(&node.module);
             let is_stdlib = node.module.as_str().starts_with("std::");
@@ -393,7 +380,7 @@ impl Linker {
             let is_externally_linkable = function.is_public();
 
             // Next, visit all of the dependent functions, and ensure their signatures match
-            for dependent_id in self.callgraph.neighbors_directed(node, Direction::Incoming) {
+            for dependent_id in self.callgraph.callers(node) {
                 // If the dependent is in another module, but the function has internal linkage, raise an error
                 if dependent_id.module != node.module && !is_externally_linkable {
                     return Err(LinkerError::LinkageMismatch(node));
@@ -565,19 +552,3 @@ fn is_matching_param(expected: &AbiParam, actual: &AbiParam) -> bool {
         ArgumentExtension::Zext | ArgumentExtension::Sext => expected.ty == actual.ty,
     }
 }
-
-/// Validate the given call graph by looking for cycles caused by recursion.
-///
-/// Returns a [LinkerError] if a cycle is found.
-fn validate_callgraph(callgraph: &DiGraphMap<FunctionIdent, ()>) -> Result<(), LinkerError> {
-    use petgraph::visit::{depth_first_search, DfsEvent, IntoNodeIdentifiers};
-
-    depth_first_search(
-        callgraph,
-        callgraph.node_identifiers(),
-        |event| match event {
-            DfsEvent::BackEdge(caller, callee) => Err(LinkerError::InvalidCycle { caller, callee }),
-            _ => Ok(()),
-        },
-    )
-}
@@ -0,0 +1,213 @@
+use cranelift_entity::{entity_impl, PrimaryMap};
+use rustc_hash::FxHashMap;
+use smallvec::SmallVec;
+
+use miden_diagnostics::SourceSpan;
+
+use super::*;
+
+/// A handle to a variable declared with [FunctionBuilder::declare_var]
+///
+/// Variables are a frontend convenience for values which are mutated, e.g. the locals
+/// of a source language. They are not present in the IR: each use of a variable is
+/// resolved to the SSA value which reaches that point, inserting block parameters,
+/// and the corresponding branch arguments, where definitions from different
+/// predecessors meet.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Variable(u32);
+entity_impl!(Variable, "var");
+
+/// This structure implements on-the-fly construction of SSA form for variables,
+/// as described in "Simple and Efficient Construction of Static Single Assignment
+/// Form", Braun et al., 2013.
+///
+/// The value of a variable in a block is either the last definition of it in that
+/// block, or is determined by looking it up in the predecessors of the block. When
+/// a block is not yet sealed, i.e. not all of its predecessors are known, a block
+/// parameter is added for the variable, and the arguments for it are provided once
+/// the block is sealed.
+///
+/// A block parameter whose arguments turn out to all be the same value, ignoring
+/// the parameter itself, is removed again, and all uses of it are replaced with
+/// that value.
+#[derive(Default)]
+pub(crate) struct SsaBuilder {
+    /// The type of each declared variable
+    types: PrimaryMap<Variable, Type>,
+    /// The last definition of each variable in each block
+    defs: FxHashMap<(Variable, Block), Value>,
+    /// The predecessors of each sealed block
+    preds: FxHashMap<Block, SmallVec<[Inst; 2]>>,
+    /// The block parameters added to each unsealed block, whose arguments
+    /// must be provided when the block is sealed
+    incomplete: FxHashMap<Block, Vec<(Variable, Value)>>,
+}
+impl SsaBuilder {
+    pub fn declare_var(&mut self, ty: Type) -> Variable {
+        self.types.push(ty)
+    }
+
+    pub fn def_var(&mut self, dfg: &DataFlowGraph, var: Variable, value: Value, block: Block) {
+        let ty = &self.types[var];
+        assert_eq!(
+            dfg.value_type(value),
+            ty,
+            "cannot define {var} with a value of type {}, expected {ty}",
+            dfg.value_type(value),
+        );
+        self.defs.insert((var, block), value);
+    }
+
+    pub fn use_var(&mut self, dfg: &mut DataFlowGraph, var: Variable, block: Block) -> Value {
+        match self.defs.get(&(var, block)) {
+            Some(value) => *value,
+            None => self.use_var_recursive(dfg, var, block),
+        }
+    }
+
+    fn is_sealed(&self, dfg: &DataFlowGraph, block: Block) -> bool {
+        block == dfg.entry_block() || self.preds.contains_key(&block)
+    }
+
+    /// Seal `block`, indicating that all of its predecessors have been built
+    ///
+    /// Branches to `block` must not be added once it has been sealed.
+    pub fn seal_block(&mut self, dfg: &mut DataFlowGraph, block: Block) {
+        if self.is_sealed(dfg, block) {
+            return;
+        }
+        self.preds.insert(block, predecessors(dfg, block));
+        for (var, param) in self.incomplete.remove(&block).unwrap_or_default() {
+            self.add_param_args(dfg, var, block, param);
+        }
+    }
+
+    fn use_var_recursive(&mut self, dfg: &mut DataFlowGraph, var: Variable, block: Block) -> Value {
+        let value = if !self.is_sealed(dfg, block) {
+            // The predecessors are not yet known, so provide the arguments later
+            let param = dfg.append_block_param(block, self.types[var].clone(), SourceSpan::UNKNOWN);
+            self.incomplete.entry(block).or_default().push((var, param));
+            param
+        } else {
+            let preds = self.preds.get(&block).cloned().unwrap_or_default();
+            match preds.as_slice() {
+                [] => panic!("{var} is used in {block} before it is defined"),
+                [pred] => {
+                    let pred = dfg.inst_block(*pred).unwrap();
+                    self.use_var(dfg, var, pred)
+                }
+                _ => {
+                    let param =
+                        dfg.append_block_param(block, self.types[var].clone(), SourceSpan::UNKNOWN);
+                    // Define the parameter before visiting the predecessors, to break cycles
+                    self.defs.insert((var, block), param);
+                    self.add_param_args(dfg, var, block, param)
+                }
+            }
+        };
+        self.defs.insert((var, block), value);
+        value
+    }
+
+    /// Pass the value of `var` along every edge to `block` as the argument for `param`,
+    /// removing `param` again if it turns out to be trivial.
+    fn add_param_args(
+        &mut self,
+        dfg: &mut DataFlowGraph,
+        var: Variable,
+        block: Block,
+        param: Value,
+    ) -> Value {
+        let preds = self.preds[&block].clone();
+        for pred in preds.into_iter() {
+            let pred_block = dfg.inst_block(pred).unwrap();
+            let value = self.use_var(dfg, var, pred_block);
+            dfg.visit_edges_to(pred, block, |_, args, pool| {
+                args.push(value, pool);
+            });
+        }
+        self.try_remove_trivial_param(dfg, block, param)
+    }
+
+    /// If all of the arguments for `param` are the same value, other than `param` itself,
+    /// remove `param` and replace all uses of it with that value, which is returned.
+    fn try_remove_trivial_param(
+        &mut self,
+        dfg: &mut DataFlowGraph,
+        block: Block,
+        param: Value,
+    ) -> Value {
+        let index = dfg
+            .block_params(block)
+            .iter()
+            .position(|p| *p == param)
+            .unwrap();
+        let preds = &self.preds[&block];
+
+        let mut same = None;
+        for pred in preds.iter().copied() {
+            let args = match dfg.analyze_branch(pred) {
+                BranchInfo::NotABranch => unreachable!(),
+                BranchInfo::SingleDest(_, args) => {
+                    SmallVec::<[Value; 2]>::from_slice(&[args[index]])
+                }
+                BranchInfo::MultiDest(jts) => jts
+                    .into_iter()
+                    .filter(|jt| jt.destination == block)
+                    .map(|jt| jt.args[index])
+                    .collect(),
+            };
+            for arg in args.into_iter() {
+                if arg == param || Some(arg) == same {
+                    continue;
+                }
+                if same.is_some() {
+                    return param;
+                }
+                same = Some(arg);
+            }
+        }
+        let Some(same) = same else {
+            return param;
+        };
+
+        for pred in preds.iter().copied() {
+            dfg.visit_edges_to(pred, block, |_, args, pool| {
+                args.remove(index, pool);
+            });
+        }
+        dfg.remove_block_param(block, index);
+        let blocks = dfg.blocks().map(|(b, _)| b).collect::<Vec<_>>();
+        for b in blocks.into_iter() {
+            let insts = dfg.block_insts(b).collect::<SmallVec<[Inst; 16]>>();
+            for inst in insts.into_iter() {
+                dfg.replace_uses(inst, param, same);
+            }
+        }
+        for value in self.defs.values_mut() {
+            if *value == param {
+                *value = same;
+            }
+        }
+        same
+    }
+}
+
+/// Get the set of branch instructions with an edge to `block`
+fn predecessors(dfg: &DataFlowGraph, block: Block) -> SmallVec<[Inst; 2]> {
+    let mut preds = SmallVec::new();
+    for (b, _) in dfg.blocks() {
+        let Some(inst) = dfg.last_inst(b) else {
+            continue;
+        };
+        let is_pred = match dfg.analyze_branch(inst) {
+            BranchInfo::NotABranch => false,
+            BranchInfo::SingleDest(dest, _) => dest == block,
+            BranchInfo::MultiDest(jts) => jts.iter().any(|jt| jt.destination == block),
+        };
+        if is_pred {
+            preds.push(inst);
+        }
+    }
+    preds
+}
@@ -154,3 +154,119 @@ fn linker_test() {
         .link()
         .expect("failed to link program");
 }
+
+/// Test that the linker rejects recursion which spans multiple modules
+#[test]
+fn linker_rejects_mutual_recursion_test() {
+    let context = TestContext::default();
+
+    let signature = Signature::new([AbiParam::new(Type::U32)], [AbiParam::new(Type::U32)]);
+
+    // Define `a::f` and `b::g`, each of which calls the other
+    let mut builder = ProgramBuilder::new(&context.diagnostics);
+    for (module, function, callee) in [("a", "f", ("b", "g")), ("b", "g", ("a", "f"))] {
+        let mut mb = ModuleBuilder::new(module);
+        let mut fb = mb
+            .function(function, signature.clone())
+            .expect("unexpected symbol conflict");
+        let v0 = {
+            let entry = fb.entry_block();
+            fb.block_params(entry)[0]
+        };
+        let callee = fb
+            .import_function(callee.0, callee.1, signature.clone())
+            .unwrap();
+        let call = fb.ins().call(callee, &[v0], SourceSpan::UNKNOWN);
+        let v1 = fb.first_result(call);
+        fb.ins().ret(Some(v1), SourceSpan::UNKNOWN);
+        fb.build(&context.diagnostics)
+            .expect("unexpected validation error, see diagnostics output");
+        builder.add_module(mb.build()).unwrap();
+    }
+
+    // Each module is free of recursion on its own, so the cycle is only found when linking
+    match builder.link() {
+        Err(err) => assert_matches!(err, LinkerError::InvalidCycle { .. }),
+        Ok(_) => panic!("expected recursion to be rejected"),
+    }
+}
+
+/// Test that variables are resolved to SSA values, with block parameters inserted
+/// where definitions meet, and redundant block parameters removed again
+#[test]
+fn function_builder_variables_test() {
+    let id = "test::sum".parse().unwrap();
+    let mut function = Function::new(
+        id,
+        Signature::new([AbiParam::new(Type::U32)], [AbiParam::new(Type::U32)]),
+    );
+
+    {
+        let mut fb = FunctionBuilder::new(&mut function);
+        let entry = fb.current_block();
+        let v0 = fb.block_params(entry)[0];
+        let header = fb.create_block();
+        let body = fb.create_block();
+        let exit = fb.create_block();
+
+        let n = fb.declare_var(Type::U32);
+        let i = fb.declare_var(Type::U32);
+        let sum = fb.declare_var(Type::U32);
+
+        // entry
+        let zero = fb.ins().u32(0, SourceSpan::UNKNOWN);
+        fb.def_var(n, v0);
+        fb.def_var(i, zero);
+        fb.def_var(sum, zero);
+        fb.ins().br(header, &[], SourceSpan::UNKNOWN);
+
+        // block1, the loop header, which can't be sealed until the body is built
+        fb.switch_to_block(header);
+        let iv = fb.use_var(i);
+        let nv = fb.use_var(n);
+        let cond = fb.ins().lt(iv, nv, SourceSpan::UNKNOWN);
+        fb.ins()
+            .cond_br(cond, body, &[], exit, &[], SourceSpan::UNKNOWN);
+
+        // block2, the loop body
+        fb.switch_to_block(body);
+        fb.seal_block(body);
+        let sv = fb.use_var(sum);
+        let iv = fb.use_var(i);
+        let sv = fb.ins().add(sv, iv, SourceSpan::UNKNOWN);
+        fb.def_var(sum, sv);
+        let iv = fb.ins().add_imm(iv, Immediate::U32(1), SourceSpan::UNKNOWN);
+        fb.def_var(i, iv);
+        fb.ins().br(header, &[], SourceSpan::UNKNOWN);
+        fb.seal_block(header);
+
+        // block3
+        fb.switch_to_block(exit);
+        fb.seal_block(exit);
+        let sv = fb.use_var(sum);
+        fb.ins().ret(Some(sv), SourceSpan::UNKNOWN);
+    }
+
+    // The parameter added to the loop header for `n` is redundant, as `n` is never
+    // redefined in the loop, so it should have been removed, and its use replaced
+    let expected = "pub fn sum(u32) -> u32 {
+block0(v0: u32):
+    v1 = const.u32 0  : u32
+    br block1(v1, v1)
+
+block1(v2: u32, v5: u32):
+    v4 = lt v2, v0  : i1
+    condbr v4, block2, block3
+
+block2:
+    v6 = add v5, v2  : u32
+    v7 = add v2, 1  : u32
+    br block1(v7, v6)
+
+block3:
+    ret v5
+}
+";
+
+    assert_eq!(function.to_string().as_str(), expected);
+}
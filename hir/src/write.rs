@@ -3,7 +3,7 @@ use std::fmt::{self, Write};
 use super::*;
 
 pub fn write_function(w: &mut dyn Write, func: &Function) -> fmt::Result {
-    write_signature(w, None, func.id.function, &func.signature)?;
+    write_signature(w, None, func.id.function, &func.signature, func.inline)?;
     writeln!(w, " {{")?;
     for (i, (block, block_data)) in func.dfg.blocks().enumerate() {
         if i > 0 {
@@ -23,7 +23,7 @@ pub fn write_external_function(
     name: &FunctionIdent,
     signature: &Signature,
 ) -> fmt::Result {
-    write_signature(w, Some(name.module), name.function, signature)?;
+    write_signature(w, Some(name.module), name.function, signature, Inline::Auto)?;
     writeln!(w, ";")
 }
 
@@ -32,15 +32,21 @@ fn write_signature(
     module: Option<Ident>,
     name: Ident,
     signature: &Signature,
+    inline: Inline,
 ) -> fmt::Result {
     if signature.is_public() {
         write!(w, "pub ")?;
     }
     match signature.cc {
-        CallConv::Fast => w.write_str("cc(fast) fn ")?,
-        CallConv::SystemV => w.write_str("fn ")?,
-        CallConv::Kernel => w.write_str("cc(kernel) fn ")?,
+        CallConv::Fast => w.write_str("cc(fast) ")?,
+        CallConv::SystemV => (),
+        CallConv::Kernel => w.write_str("cc(kernel) ")?,
     }
+    match inline {
+        Inline::Auto => (),
+        hint => write!(w, "inline({}) ", hint)?,
+    }
+    w.write_str("fn ")?;
     match module {
         None => write!(w, "{}(", name)?,
         Some(module) => write!(
@@ -134,6 +140,10 @@ fn write_instruction(w: &mut dyn Write, func: &Function, inst: Inst, indent: usi
 
     let opcode = func.dfg[inst].opcode();
     write!(w, "{}", opcode)?;
+    match func.dfg[inst].overflow() {
+        None | Some(Overflow::Unchecked) => (),
+        Some(overflow) => write!(w, ".{}", overflow)?,
+    }
     write_operands(w, &func.dfg, inst, indent)?;
 
     if has_results {
@@ -202,10 +212,12 @@ fn write_operands(
             arg, arms, default, ..
         }) => {
             write!(w, " {}", arg)?;
-            for (value, dest) in arms.iter() {
+            for (value, dest, args) in arms.iter() {
                 write!(w, ", {} => {}", value, dest)?;
+                write_block_args(w, args.as_slice(pool))?;
             }
-            write!(w, ", {}", default)
+            write!(w, ", {}", default.0)?;
+            write_block_args(w, default.1.as_slice(pool))
         }
         Instruction::Test(Test { arg, ref ty, .. }) => {
             write!(w, ".{} {}", ty, arg)
@@ -254,7 +266,7 @@ fn write_global_value(
             }
             write_global_value(w, dfg, *base, true)?;
             if is_cast {
-                write!(w, "){} as {}", offset, pointer_ty)
+                write!(w, "){} as {}", offset, ty)
             } else if has_offset {
                 write!(w, "){}", offset)
             } else {
@@ -272,7 +284,7 @@ fn write_global_value(
             }
             write_global_value(w, dfg, *base, true)?;
             if is_cast {
-                write!(w, "){} as {}", offset, pointer_ty)
+                write!(w, "){} as {}", offset, ty)
             } else if has_offset {
                 write!(w, "){}", offset)
             } else {
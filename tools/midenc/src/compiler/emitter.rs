@@ -1,23 +1,52 @@
-use std::{fs::File, io::prelude::*, path::Path};
-use miden_assembly::ast::{self as masm};
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
 
-/// The emitter for MASM output.
+use miden_codegen_masm as masm;
+use miden_diagnostics::CodeMap;
+use miden_hir::{self as hir, Ident};
 
-pub enum MASMAst {
-    Program(masm::ProgramAst),
-    Module(masm::ModuleAst),
+/// Returns the path of the artifact for the module `name`, rooted at `dir`.
+///
+/// Like Miden Assembly modules, the path is derived from the module name, e.g.
+/// the `.hir` artifact for `std::math::u64` is written to `<dir>/std/math/u64.hir`
+fn module_artifact_path(dir: &Path, name: Ident, extension: &str) -> io::Result<PathBuf> {
+    let mut path = dir.to_path_buf();
+    for component in name.as_str().split("::") {
+        path.push(component);
+    }
+    path.set_extension(extension);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    Ok(path)
 }
 
-impl fmt::Display for MASMAst {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Program(p) => write!("{}", p),
-            Module(m) => write!("{}", m),
-        }
+/// Write the IR of every module in `program` to `dir`, using `extension` for the files written
+pub fn write_hir(dir: &Path, program: &hir::Program, extension: &str) -> io::Result<()> {
+    for module in program.modules().iter() {
+        let path = module_artifact_path(dir, module.name, extension)?;
+        let mut file = File::create(path)?;
+        write!(&mut file, "{}", module)?;
     }
+
+    Ok(())
 }
 
-fn write_ast_to_file<P: AsRef<Path>>(ast: &MASMAst, path: P) -> io::Result<()> {
-    let mut file = File::create(path);
-    file.write_fmt(format_args!("{}", ast))
+/// Write the `miden_assembly` syntax tree of every module in `program` to `dir`
+pub fn write_masm_ast(dir: &Path, codemap: &CodeMap, program: &masm::Program) -> io::Result<()> {
+    if program.is_executable() {
+        let mut file = File::create(dir.join("exec.ast"))?;
+        write!(&mut file, "{:#?}", program.to_program_ast())?;
+    }
+
+    for module in program.modules.iter() {
+        let path = module_artifact_path(dir, module.name, "ast")?;
+        let mut file = File::create(path)?;
+        write!(&mut file, "{:#?}", module.to_module_ast(codemap).ast)?;
+    }
+
+    Ok(())
 }
@@ -1,20 +1,117 @@
+mod emitter;
 mod options;
+mod run;
 
-pub use self::options::Options;
+pub use self::options::{Options, OutputTypes};
+pub use self::run::{run, RunOptions};
 
+use std::io::Read;
 use std::sync::Arc;
 use std::time::Instant;
 
+use anyhow::{bail, Context};
+use miden_codegen_masm::{FunctionCost, MasmCompiler, Pipeline};
 use miden_diagnostics::term::termcolor::ColorChoice;
 use miden_diagnostics::*;
+use miden_hir::{Module, ParseError, Parser, Program, ProgramBuilder};
+use miden_hir_analysis::ModuleValidator;
+use miden_hir_transform::PrintOptions;
 
 use crate::utils::HumanDuration;
 
+/// Options which are specific to `midenc compile`
+#[derive(Debug)]
+pub struct CompileOptions {
+    /// Whether to print the estimated cycle cost of each compiled function
+    pub print_cycles: bool,
+    /// The rewrites to apply to each function before it is lowered to Miden Assembly
+    pub pipeline: Pipeline,
+    /// When to print the IR of functions during compilation
+    pub print: PrintOptions,
+    /// Whether to report the time spent in, and changes made by, each pass
+    pub time_passes: bool,
+}
+
 pub fn compile(
     options: Arc<Options>,
+    compile_options: CompileOptions,
     codemap: Arc<CodeMap>,
     emitter: Option<Arc<dyn Emitter>>,
 ) -> anyhow::Result<()> {
+    let diagnostics = diagnostics_handler(&options, codemap.clone(), emitter);
+
+    // Track when compilation began
+    let start = Instant::now();
+
+    let mut program = link_inputs(&options, &codemap, &diagnostics)?;
+
+    let output_dir = options.get_output_dir();
+    std::fs::create_dir_all(&output_dir).with_context(|| {
+        format!(
+            "unable to create output directory '{}'",
+            output_dir.display()
+        )
+    })?;
+
+    if options.output_types.contains(OutputTypes::IR) {
+        emitter::write_hir(&output_dir, &program, "hir")
+            .context("failed to write linked program")?;
+    }
+
+    // Compile the program to Miden Assembly
+    let mut compiler = MasmCompiler::new(&diagnostics)
+        .with_pipeline(compile_options.pipeline)
+        .with_print_options(compile_options.print)
+        .with_statistics(compile_options.time_passes);
+    let output = compiler.compile(&mut program)?;
+
+    // Emit the requested artifacts
+    if options.output_types.contains(OutputTypes::IR) {
+        // The compiler applies its rewrites to the program in-place
+        emitter::write_hir(&output_dir, &program, "rewritten.hir")
+            .context("failed to write rewritten program")?;
+    }
+    if options.output_types.contains(OutputTypes::ASM) {
+        output
+            .write_to_directory(&codemap, &output_dir)
+            .with_context(|| format!("failed to write program to '{}'", output_dir.display()))?;
+    }
+    if options.output_types.contains(OutputTypes::AST) {
+        emitter::write_masm_ast(&output_dir, &codemap, &output)
+            .context("failed to write syntax tree")?;
+    }
+
+    if compile_options.print_cycles {
+        for cost in FunctionCost::compute_program(&output) {
+            diagnostics
+                .diagnostic(Severity::Note)
+                .with_message(format!("estimated cycles for {}", &cost.name))
+                .with_note(cost.to_string().trim_end())
+                .emit();
+        }
+    }
+
+    if let Some(statistics) = compiler.statistics() {
+        diagnostics
+            .diagnostic(Severity::Note)
+            .with_message("pass statistics")
+            .with_note(statistics.to_string().trim_end())
+            .emit();
+    }
+
+    let duration = HumanDuration::since(start);
+    diagnostics.success(
+        "Finished",
+        &format!("built {} in {:#}", options.name, duration),
+    );
+    Ok(())
+}
+
+fn diagnostics_handler(
+    options: &Options,
+    codemap: Arc<CodeMap>,
+    emitter: Option<Arc<dyn Emitter>>,
+) -> Arc<DiagnosticsHandler> {
     let diagnostics = Arc::new(DiagnosticsHandler::new(
         DiagnosticsConfig {
             verbosity: options.verbosity,
@@ -22,7 +119,7 @@ pub fn compile(
             no_warn: options.no_warn,
             display: Default::default(),
         },
-        codemap.clone(),
+        codemap,
         emitter.unwrap_or_else(|| default_emitter(options.verbosity, ColorChoice::Auto)),
     ));
 
@@ -30,26 +127,82 @@ pub fn compile(
         diagnostics.fatal("No inputs found!").raise();
     }
 
-    // Track when compilation began
-    let start = Instant::now();
+    diagnostics
+}
 
-    // let files = options.input_files.clone();
-    // let artifacts = compile()?;
-    // diagnostics.abort_if_errors();
-
-//    for x in artifacts {
-//        let dir = options.get_output_dir();
-//        dir.push(x.name);
-//        dir.set_extension("masm");
-//        self::emitter::write_ast_to_file(x.ast, dir.as_path());
-//    }
-    
-    let duration = HumanDuration::since(start);
-    diagnostics.success(
-        "Finished",
-        &format!("built {} in {:#}", options.name, duration),
-    );
-    Ok(())
+/// Parse, link, and validate the inputs given to the compiler, producing a [Program]
+fn link_inputs(
+    options: &Options,
+    codemap: &Arc<CodeMap>,
+    diagnostics: &DiagnosticsHandler,
+) -> anyhow::Result<Box<Program>> {
+    // Parse all of the inputs
+    let modules = parse_inputs(options, codemap, diagnostics)?;
+
+    // Link the modules into a program
+    let mut builder = ProgramBuilder::new(diagnostics);
+    if let Some(entry) = options.entrypoint {
+        builder = builder.with_entrypoint(entry);
+    }
+    for module in modules.into_iter() {
+        builder
+            .add_module(module)
+            .context("failed to link program")?;
+    }
+    let program = builder.link().context("failed to link program")?;
+
+    // Validate the linked modules
+    let mut validator = ModuleValidator::new(diagnostics);
+    for module in program.modules().iter() {
+        validator
+            .validate(module)
+            .context("validation failed, see diagnostics for details")?;
+    }
+
+    Ok(program)
+}
+
+/// Parse all of the input files given to the compiler.
+///
+/// Every input is parsed, even if an earlier one fails, so that all errors are reported at once.
+fn parse_inputs(
+    options: &Options,
+    codemap: &Arc<CodeMap>,
+    diagnostics: &DiagnosticsHandler,
+) -> anyhow::Result<Vec<Box<Module>>> {
+    let parser = Parser::new(diagnostics, codemap.clone());
+
+    let mut modules = Vec::with_capacity(options.input_files.len());
+    let mut failed = false;
+    for input in options.input_files.iter() {
+        let result = match input {
+            FileName::Real(path) => parser.parse_file(path),
+            FileName::Virtual(_) => {
+                let mut source = String::new();
+                std::io::stdin()
+                    .read_to_string(&mut source)
+                    .context("unable to read from stdin")?;
+                parser.parse_str(source)
+            }
+        };
+        match result {
+            Ok(module) => modules.push(module),
+            Err(ParseError::Failed) => failed = true,
+            Err(err) => {
+                diagnostics
+                    .diagnostic(Severity::Error)
+                    .with_message(format!("unable to read '{}': {}", input, err))
+                    .emit();
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        bail!("compilation failed due to previous errors");
+    }
+
+    Ok(modules)
 }
 
 fn default_emitter(verbosity: Verbosity, color: ColorChoice) -> Arc<dyn Emitter> {
@@ -5,6 +5,7 @@ use std::sync::Arc;
 use anyhow::bail;
 use clap::ColorChoice;
 use miden_diagnostics::{FileName, Verbosity};
+use miden_hir::FunctionIdent;
 
 use crate::driver::Warnings;
 
@@ -28,12 +29,16 @@ pub struct Options {
     pub input_files: Vec<FileName>,
     /// The directory in which compiler artifacts will be emitted
     pub output_dir: Option<PathBuf>,
+    /// The function to use as the program entrypoint, if this is an executable program
+    pub entrypoint: Option<FunctionIdent>,
 }
 impl Options {
     pub fn new(
         cwd: PathBuf,
         inputs: Vec<PathBuf>,
         output_dir: Option<PathBuf>,
+        entrypoint: Option<String>,
+        emit: Vec<String>,
         warn: Warnings,
         verbosity: Verbosity,
     ) -> anyhow::Result<Arc<Self>> {
@@ -75,15 +80,14 @@ impl Options {
                         .map(PathBuf::from)
                         .unwrap_or_else(|| PathBuf::from(first_os))
                         .into();
-                    let mut files = vec![];
                     if first_filename.is_dir() {
                         bail!(
                             "must specify a file path, '{}' is a directory",
                             &first_filename
                         );
                     }
-                    files.push(first_filename);
 
+                    let mut files = Vec::with_capacity(num_inputs);
                     for input in inputs {
                         let path = PathBuf::from(input);
                         if !path.exists() {
@@ -106,7 +110,32 @@ impl Options {
             }
         };
 
-        let output_types = OutputTypes::default();
+        let entrypoint = match entrypoint {
+            None => None,
+            Some(name) => match FunctionIdent::from_str(&name) {
+                Ok(id) => Some(id),
+                Err(_) => bail!(
+                    "invalid entrypoint '{}', expected a fully-qualified function name, e.g. 'foo::main'",
+                    &name
+                ),
+            },
+        };
+
+        let output_types = if emit.is_empty() {
+            OutputTypes::default()
+        } else {
+            let mut output_types = OutputTypes::empty();
+            for kind in emit.iter() {
+                match kind.parse::<OutputTypes>() {
+                    Ok(ty) => output_types |= ty,
+                    Err(_) => bail!(
+                        "invalid output type '{}', expected one of [ir, masm, ast, all]",
+                        kind
+                    ),
+                }
+            }
+            output_types
+        };
 
         Ok(Arc::new(Self {
             name,
@@ -118,10 +147,11 @@ impl Options {
             current_dir: cwd,
             input_files,
             output_dir,
+            entrypoint,
         }))
     }
 
-    fn get_output_dir(&self) -> PathBuf {
+    pub fn get_output_dir(&self) -> PathBuf {
         match self.output_dir {
             Some(ref dir) => dir.clone(),
             None => self.current_dir.clone(),
@@ -131,9 +161,9 @@ impl Options {
 
 bitflags::bitflags! {
     pub struct OutputTypes: u32 {
-        /// The abstract syntax tree produced by parsing Miden IR
+        /// The Miden Assembly syntax tree, as constructed by `miden_assembly`
         const AST = 1;
-        /// The IR produced by lowering from another frontend (e.g. Sway)
+        /// The IR of the linked program, both before and after rewrites are applied
         const IR = 1 << 1;
         /// The assembly produced by lowering Miden IR through the compiler
         const ASM = 1 << 2;
@@ -153,7 +183,7 @@ impl FromStr for OutputTypes {
         match s {
             "ast" => Ok(Self::AST),
             "ir" => Ok(Self::IR),
-            "asm" => Ok(Self::ASM),
+            "asm" | "masm" => Ok(Self::ASM),
             "all" => Ok(Self::ALL),
             _ => Err(()),
         }
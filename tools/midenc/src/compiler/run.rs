@@ -0,0 +1,236 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Context};
+use miden_codegen_masm::{Emulator, MasmCompiler, Module, Pipeline, Program};
+use miden_diagnostics::{CodeMap, Emitter};
+use miden_hir::{Felt, FunctionIdent, Immediate, OperandStack, Stack, StarkField, Type};
+
+use super::Options;
+
+/// Options which are specific to `midenc run`
+#[derive(Debug)]
+pub struct RunOptions {
+    /// The function to invoke, if not the program entrypoint
+    pub invoke: Option<FunctionIdent>,
+    /// The maximum number of cycles to execute before aborting
+    pub max_cycles: Option<usize>,
+    /// The rewrites to apply to each function before it is lowered to Miden Assembly
+    pub pipeline: Pipeline,
+    /// The arguments to pass to the invoked function, as parsed from the command line
+    pub args: Vec<String>,
+}
+
+/// Compile the inputs described by `options`, and execute the resulting program in the [Emulator]
+///
+/// The function invoked is either the one given by `--invoke`, or the program entrypoint. Once
+/// it returns, the contents of the operand stack, and the number of cycles executed, are printed.
+pub fn run(
+    options: Arc<Options>,
+    run_options: RunOptions,
+    codemap: Arc<CodeMap>,
+    emitter: Option<Arc<dyn Emitter>>,
+) -> anyhow::Result<()> {
+    let diagnostics = super::diagnostics_handler(&options, codemap.clone(), emitter);
+
+    let mut program = super::link_inputs(&options, &codemap, &diagnostics)?;
+
+    let callee = run_options
+        .invoke
+        .or(options.entrypoint)
+        .ok_or_else(|| anyhow!("nothing to run: specify either --invoke or --entrypoint"))?;
+    let signature = program
+        .signature(&callee)
+        .cloned()
+        .ok_or_else(|| anyhow!("unable to invoke '{callee}': no such function"))?;
+
+    // Convert the arguments to their representation on the operand stack
+    if signature.arity() != run_options.args.len() {
+        bail!(
+            "'{callee}' expects {} arguments, but {} were given",
+            signature.arity(),
+            run_options.args.len()
+        );
+    }
+    let mut args = vec![];
+    for (param, arg) in signature.params().iter().zip(run_options.args.iter()) {
+        let imm = parse_argument(&param.ty, arg).with_context(|| {
+            format!(
+                "invalid argument '{arg}' for parameter of type '{}'",
+                &param.ty
+            )
+        })?;
+        args.extend(immediate_to_felts(imm)?);
+    }
+
+    // Compile the program, and execute it
+    let mut compiler = MasmCompiler::new(&diagnostics).with_pipeline(run_options.pipeline);
+    let output = compiler.compile(&mut program)?;
+
+    let (stack, cycles) = execute(output, callee, &args, run_options.max_cycles)?;
+
+    // The top of the operand stack is printed first
+    let elements = stack
+        .stack()
+        .iter()
+        .rev()
+        .map(|elem| elem.as_int().to_string())
+        .collect::<Vec<_>>();
+    println!("stack: [{}]", elements.join(", "));
+    println!("cycles: {cycles}");
+
+    Ok(())
+}
+
+/// Load `program` into a new [Emulator], and invoke `callee` with `args`
+///
+/// The code generated for `program` calls the `intrinsics::mem` module for memory accesses,
+/// and the `std::math::u64` module for 64-bit integer operations, neither of which is part of
+/// the program itself, so these are loaded first, unless the program provides its own.
+///
+/// Returns the operand stack once `callee` returns, and the number of cycles executed.
+fn execute(
+    program: Program,
+    callee: FunctionIdent,
+    args: &[Felt],
+    max_cycles: Option<usize>,
+) -> anyhow::Result<(OperandStack<Felt>, usize)> {
+    let mut emulator = Emulator::default();
+    if let Some(max_cycles) = max_cycles {
+        emulator.set_max_cycles(max_cycles);
+    }
+    for module in [Module::mem_intrinsics(), Module::std_math_u64()] {
+        if program.modules.iter().all(|m| m.name != module.name) {
+            emulator.load_module(module)?;
+        }
+    }
+    emulator.load_program(program)?;
+
+    let stack = emulator.invoke(callee, args).with_context(|| {
+        format!(
+            "execution of '{callee}' failed after {} cycles",
+            emulator.cycles()
+        )
+    })?;
+
+    Ok((stack, emulator.cycles()))
+}
+
+/// Parse `value` as an immediate of type `ty`
+fn parse_argument(ty: &Type, value: &str) -> anyhow::Result<Immediate> {
+    let imm = match ty {
+        Type::I1 => match value {
+            "true" | "1" => Immediate::I1(true),
+            "false" | "0" => Immediate::I1(false),
+            _ => bail!("expected a boolean"),
+        },
+        Type::U8 => Immediate::U8(value.parse()?),
+        Type::I8 => Immediate::I8(value.parse()?),
+        Type::U16 => Immediate::U16(value.parse()?),
+        Type::I16 => Immediate::I16(value.parse()?),
+        Type::U32 => Immediate::U32(value.parse()?),
+        Type::I32 => Immediate::I32(value.parse()?),
+        Type::U64 => Immediate::U64(value.parse()?),
+        Type::I64 => Immediate::I64(value.parse()?),
+        Type::I128 => Immediate::I128(value.parse()?),
+        Type::Felt => Immediate::Felt(Felt::new(value.parse()?)),
+        ty if ty.is_pointer() => Immediate::U32(value.parse()?),
+        ty => bail!("arguments of type '{ty}' are not supported"),
+    };
+    Ok(imm)
+}
+
+/// Convert `imm` to the field elements which represent it on the operand stack,
+/// with the element which is on top of the stack first.
+///
+/// This must match the way the code generator pushes immediates on the operand stack.
+fn immediate_to_felts(imm: Immediate) -> anyhow::Result<Vec<Felt>> {
+    let felts = match imm {
+        Immediate::I1(i) => vec![Felt::new(i as u64)],
+        Immediate::U8(i) => vec![Felt::new(i as u64)],
+        Immediate::I8(i) => vec![Felt::new(i as u8 as u64)],
+        Immediate::U16(i) => vec![Felt::new(i as u64)],
+        Immediate::I16(i) => vec![Felt::new(i as u16 as u64)],
+        Immediate::U32(i) => vec![Felt::new(i as u64)],
+        Immediate::I32(i) => vec![Felt::new(i as u32 as u64)],
+        Immediate::U64(i) => u64_to_felts(i).to_vec(),
+        Immediate::I64(i) => u64_to_felts(i as u64).to_vec(),
+        Immediate::I128(i) => {
+            let i = i as u128;
+            let mut felts = u64_to_felts(i as u64).to_vec();
+            felts.extend(u64_to_felts((i >> 64) as u64));
+            felts
+        }
+        Immediate::Felt(i) => vec![i],
+        Immediate::F64(_) => bail!("floating-point arguments are not supported"),
+    };
+    Ok(felts)
+}
+
+/// A 64-bit value is represented as two 32-bit limbs, with the low limb on top
+#[inline]
+fn u64_to_felts(value: u64) -> [Felt; 2] {
+    [Felt::new(value & (u32::MAX as u64)), Felt::new(value >> 32)]
+}
+
+#[cfg(test)]
+mod tests {
+    use miden_hir::{
+        testing::TestContext, AbiParam, InstBuilder, ProgramBuilder, Signature, SourceSpan,
+    };
+
+    use super::*;
+
+    /// Test that a program which accesses memory, and performs 64-bit integer arithmetic,
+    /// can be executed, i.e. that the modules the generated code calls are available
+    #[test]
+    fn run_with_memory_and_u64_operations() {
+        let context = TestContext::default();
+
+        // Store `value` to `ptr`, then load it back, and return it plus one
+        let mut builder = ProgramBuilder::new(&context.diagnostics);
+        let mut mb = builder.module("test");
+        let id = {
+            let mut fb = mb
+                .function(
+                    "main",
+                    Signature::new(
+                        [
+                            AbiParam::new(Type::Ptr(Box::new(Type::U64))),
+                            AbiParam::new(Type::U64),
+                        ],
+                        [AbiParam::new(Type::U64)],
+                    ),
+                )
+                .expect("unexpected symbol conflict");
+            let entry = fb.current_block();
+            let (ptr, value) = {
+                let args = fb.block_params(entry);
+                (args[0], args[1])
+            };
+            fb.ins().store(ptr, value, SourceSpan::UNKNOWN);
+            let loaded = fb.ins().load(ptr, SourceSpan::UNKNOWN);
+            let result = fb
+                .ins()
+                .add_imm(loaded, Immediate::U64(1), SourceSpan::UNKNOWN);
+            fb.ins().ret(Some(result), SourceSpan::UNKNOWN);
+            fb.build().expect("unexpected error building function")
+        };
+        mb.build()
+            .expect("unexpected error constructing test module");
+        let mut program = builder
+            .with_entrypoint(id)
+            .link()
+            .expect("failed to link program");
+
+        let mut compiler = MasmCompiler::new(&context.diagnostics);
+        let output = compiler.compile(&mut program).expect("compilation failed");
+
+        let mut args = immediate_to_felts(Immediate::U32(1024)).unwrap();
+        args.extend(immediate_to_felts(Immediate::U64(41)).unwrap());
+        let (mut stack, _) = execute(output, id, &args, None).expect("execution failed");
+        assert_eq!(stack.len(), 2);
+        for expected in immediate_to_felts(Immediate::U64(42)).unwrap() {
+            assert_eq!(stack.pop(), Some(expected));
+        }
+    }
+}
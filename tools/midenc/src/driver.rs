@@ -3,11 +3,15 @@ use std::fmt;
 use std::path::PathBuf;
 use std::sync::Arc;
 
+use anyhow::anyhow;
 use clap::{Parser, Subcommand, ValueEnum};
 
+use miden_codegen_masm::{OptLevel, Pipeline};
 use miden_diagnostics::{CodeMap, Emitter, Verbosity};
+use miden_hir::FunctionIdent;
+use miden_hir_transform::PrintOptions;
 
-use crate::compiler::{self, Options};
+use crate::compiler::{self, CompileOptions, Options, RunOptions};
 
 #[derive(Debug, Copy, Clone, Default, ValueEnum)]
 pub enum Warnings {
@@ -43,6 +47,21 @@ pub enum Commands {
         /// Write all compiler artifacts to DIR
         #[arg(value_name = "DIR", long = "output-dir")]
         output_dir: Option<PathBuf>,
+        /// The fully-qualified name of the program entrypoint, e.g. `foo::main`
+        ///
+        /// If not specified, the inputs are compiled as a library.
+        #[arg(value_name = "NAME", long = "entrypoint")]
+        entrypoint: Option<String>,
+        /// The type(s) of artifacts to write to the output directory, as a comma-separated list.
+        ///
+        /// * `ir` writes the linked program, and the program after rewrites, as `.hir` files
+        /// * `masm` writes the compiled Miden Assembly as `.masm` files
+        /// * `ast` writes the Miden Assembly syntax tree as `.ast` files
+        /// * `all` writes all of the above
+        ///
+        /// Defaults to `masm`.
+        #[arg(value_name = "KIND", long = "emit", value_delimiter = ',')]
+        emit: Vec<String>,
         /// Modify how warnings are treated by the compiler.
         #[arg(
             value_enum,
@@ -56,12 +75,92 @@ pub enum Commands {
         /// When set, produces more verbose output during compilation
         #[arg(short = 'v', long, default_value_t = false)]
         verbose: bool,
+        /// When set, prints the estimated number of cycles needed to execute each compiled
+        /// function, in the best and worst case, and for each of its blocks
+        #[arg(long = "print-cycles", default_value_t = false)]
+        print_cycles: bool,
+        /// The optimization level to compile at, one of `0`, `1`, `2`, or `s`
+        #[arg(value_name = "LEVEL", short = 'O', default_value = "0")]
+        opt_level: String,
+        /// The rewrites to apply to each function before it is lowered, as a comma-separated
+        /// list, in place of those selected by the optimization level.
+        ///
+        /// * `inline` inlines small functions into their callers
+        /// * `mem2reg` promotes stack slots to SSA values
+        /// * `sccp` propagates constants and removes unreachable code
+        /// * `gvn` eliminates redundant computations
+        /// * `dce` removes dead code
+        /// * `simplify-cfg` simplifies the control flow graph
+        #[arg(value_name = "PASSES", long = "passes", conflicts_with = "opt_level")]
+        passes: Option<String>,
+        /// Print the IR of each function before the given pass(es), e.g. `treeify` or `stackify`
+        #[arg(value_name = "PASS", long = "print-before", value_delimiter = ',')]
+        print_before: Vec<String>,
+        /// Print the IR of each function after the given pass(es), e.g. `treeify` or `stackify`
+        #[arg(value_name = "PASS", long = "print-after", value_delimiter = ',')]
+        print_after: Vec<String>,
+        /// Print the IR of each function after every pass
+        #[arg(long = "print-after-all", default_value_t = false)]
+        print_after_all: bool,
+        /// Only print the IR of the given function(s), by fully-qualified name, e.g. `foo::bar`,
+        /// or by name alone, when using `--print-before` and `--print-after`
+        #[arg(value_name = "NAME", long = "print-filter", value_delimiter = ',')]
+        print_filter: Vec<String>,
+        /// When set, reports the time spent in each pass, and the number of instructions and
+        /// blocks each pass added and removed, once compilation is finished
+        #[arg(long = "time-passes", default_value_t = false)]
+        time_passes: bool,
         /// Path(s) to the source file(s) to compile.
         ///
         /// You may also use `-` as a file name to read a file from stdin.
         #[arg(last(true), value_name = "INPUTS")]
         inputs: Vec<PathBuf>,
     },
+    /// Compile the inputs, and execute the resulting program in the emulator
+    Run {
+        /// The fully-qualified name of the program entrypoint, e.g. `foo::main`
+        #[arg(value_name = "NAME", long = "entrypoint")]
+        entrypoint: Option<String>,
+        /// The fully-qualified name of the function to invoke, e.g. `foo::bar`
+        ///
+        /// If not specified, the program entrypoint is invoked.
+        #[arg(value_name = "NAME", long = "invoke")]
+        invoke: Option<String>,
+        /// Abort execution if the program runs for more than N cycles
+        #[arg(value_name = "N", long = "max-cycles")]
+        max_cycles: Option<usize>,
+        /// Modify how warnings are treated by the compiler.
+        #[arg(
+            value_enum,
+            value_name = "LEVEL",
+            short = 'W',
+            next_line_help(true),
+            default_value_t = Warnings::Auto,
+            default_missing_value = "auto",
+        )]
+        warn: Warnings,
+        /// When set, produces more verbose output during compilation
+        #[arg(short = 'v', long, default_value_t = false)]
+        verbose: bool,
+        /// The optimization level to compile at, one of `0`, `1`, `2`, or `s`
+        #[arg(value_name = "LEVEL", short = 'O', default_value = "0")]
+        opt_level: String,
+        /// The rewrites to apply to each function before it is lowered, as a comma-separated
+        /// list, in place of those selected by the optimization level.
+        ///
+        /// See `midenc compile --help` for the available rewrites.
+        #[arg(value_name = "PASSES", long = "passes", conflicts_with = "opt_level")]
+        passes: Option<String>,
+        /// Path(s) to the source file(s) to compile.
+        #[arg(required(true), value_name = "INPUTS")]
+        inputs: Vec<PathBuf>,
+        /// The arguments to pass to the invoked function.
+        ///
+        /// Each argument is parsed according to the type of the corresponding
+        /// function parameter, e.g. `true`, `-1`, or `42`.
+        #[arg(last(true), value_name = "ARGS", allow_hyphen_values(true))]
+        args: Vec<String>,
+    },
 }
 
 pub fn run_compiler(cwd: PathBuf, args: impl Iterator<Item = OsString>) -> anyhow::Result<i32> {
@@ -78,8 +177,18 @@ pub fn run_compiler_with_emitter(
         Commands::Compile {
             inputs,
             output_dir,
+            entrypoint,
+            emit,
             warn,
             verbose,
+            print_cycles,
+            opt_level,
+            passes,
+            print_before,
+            print_after,
+            print_after_all,
+            print_filter,
+            time_passes,
         } => {
             let codemap = Arc::new(CodeMap::new());
             let verbosity = if verbose {
@@ -87,8 +196,76 @@ pub fn run_compiler_with_emitter(
             } else {
                 Verbosity::Info
             };
-            let options = Options::new(cwd, inputs, output_dir, warn, verbosity)?;
-            compiler::compile(options, codemap, emitter).map(|_| 0)
+            let options = Options::new(
+                cwd, inputs, output_dir, entrypoint, emit, warn, verbosity,
+            )?;
+            let pipeline = parse_pipeline(&opt_level, passes.as_deref())?;
+            let print = PrintOptions {
+                before: print_before,
+                after: print_after,
+                after_all: print_after_all,
+                functions: print_filter,
+            };
+            let compile_options = CompileOptions {
+                print_cycles,
+                pipeline,
+                print,
+                time_passes,
+            };
+            compiler::compile(options, compile_options, codemap, emitter).map(|_| 0)
+        }
+        Commands::Run {
+            inputs,
+            entrypoint,
+            invoke,
+            max_cycles,
+            warn,
+            verbose,
+            opt_level,
+            passes,
+            args,
+        } => {
+            let codemap = Arc::new(CodeMap::new());
+            let verbosity = if verbose {
+                Verbosity::Debug
+            } else {
+                Verbosity::Info
+            };
+            let invoke = match invoke {
+                None => None,
+                Some(name) => Some(name.parse::<FunctionIdent>().map_err(|_| {
+                    anyhow!(
+                        "invalid function '{}', expected a fully-qualified function name, e.g. 'foo::bar'",
+                        &name
+                    )
+                })?),
+            };
+            let options =
+                Options::new(cwd, inputs, None, entrypoint, vec![], warn, verbosity)?;
+            let pipeline = parse_pipeline(&opt_level, passes.as_deref())?;
+            let run_options = RunOptions {
+                invoke,
+                max_cycles,
+                pipeline,
+                args,
+            };
+            compiler::run(options, run_options, codemap, emitter).map(|_| 0)
+        }
+    }
+}
+
+/// Select the rewrites to apply from `--passes`, if given, or from the optimization level
+fn parse_pipeline(opt_level: &str, passes: Option<&str>) -> anyhow::Result<Pipeline> {
+    match passes {
+        Some(passes) => passes.parse::<Pipeline>(),
+        None => {
+            let level = opt_level.parse::<OptLevel>().map_err(|_| {
+                anyhow!(
+                    "invalid optimization level '{}', expected one of [0, 1, 2, s]",
+                    opt_level
+                )
+            })?;
+            Ok(Pipeline::with_opt_level(level))
         }
     }
 }